@@ -0,0 +1,62 @@
+use chrono::NaiveDate;
+use compact_calendar_cli::models::{Calendar, CalendarOptions, Event, EventKind};
+use compact_calendar_cli::rendering::CalendarRenderer;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A busy year with a range starting every few days
+fn calendar_with(extra: Option<Event>) -> Calendar {
+    let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let mut events: Vec<Event> = (0..120)
+        .map(|i| {
+            let start = first + chrono::Days::new(i * 3);
+            Event::builder(EventKind::Range, start)
+                .end(start + chrono::Days::new(1))
+                .color("blue")
+                .description(format!("Range {}", i))
+                .build()
+                .unwrap()
+        })
+        .collect();
+    events.extend(extra);
+    let options = CalendarOptions::builder()
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    Calendar::new(2024, options, events)
+}
+
+fn incremental_render(c: &mut Criterion) {
+    let before = calendar_with(None);
+    let date = NaiveDate::from_ymd_opt(2024, 6, 14).unwrap();
+    let after = calendar_with(Some(
+        Event::builder(EventKind::Date, date)
+            .color("red")
+            .description("Demo")
+            .build()
+            .unwrap(),
+    ));
+    let changes = after.changes_since(&before);
+    let layout = CalendarRenderer::new(&before).with_colors(true).layout();
+
+    // What `--watch` did before: the whole year for one new date
+    c.bench_function("full re-render", |b| {
+        b.iter(|| {
+            let mut output = Vec::new();
+            CalendarRenderer::new(&after)
+                .with_colors(true)
+                .render_to(&mut output)
+                .unwrap();
+            black_box(output)
+        })
+    });
+    c.bench_function("layout update", |b| {
+        let renderer = CalendarRenderer::new(&after).with_colors(true);
+        b.iter(|| {
+            let mut layout = layout.clone();
+            black_box(layout.update(&renderer, &changes));
+            black_box(layout)
+        })
+    });
+}
+
+criterion_group!(benches, incremental_render);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+use chrono::{Duration, NaiveDate};
+use compact_calendar_cli::models::{Calendar, CalendarOptions, Event, EventKind};
+use compact_calendar_cli::rendering::CalendarRenderer;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const COLORS: [&str; 4] = ["blue", "green", "orange", "purple"];
+
+/// Weekly ranges leading up to the end of 2024, like years of an imported
+/// team calendar
+fn imported_calendar(ranges: usize) -> Calendar {
+    let first = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap() - Duration::weeks(ranges as i64);
+    let events = (0..ranges)
+        .map(|i| {
+            let start = first + Duration::weeks(i as i64);
+            Event::builder(EventKind::Range, start)
+                .end(start + Duration::days((i % 5) as i64 + 1))
+                .color(COLORS[i % COLORS.len()])
+                .description(format!("Range {}", i))
+                .build()
+                .unwrap()
+        })
+        .collect();
+    Calendar::new(2024, CalendarOptions::default(), events)
+}
+
+fn days_of_year() -> Vec<NaiveDate> {
+    NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .iter_days()
+        .take(366)
+        .collect()
+}
+
+fn range_lookup(c: &mut Criterion) {
+    let days = days_of_year();
+    for ranges in [10, 500] {
+        let calendar = imported_calendar(ranges);
+
+        // The scan every lookup used to do
+        c.bench_function(&format!("linear scan, {} ranges", ranges), |b| {
+            b.iter(|| {
+                for &date in &days {
+                    black_box(
+                        calendar
+                            .ranges
+                            .iter()
+                            .find(|range| range.contains(date) && range.color.is_some()),
+                    );
+                }
+            })
+        });
+        c.bench_function(&format!("indexed lookup, {} ranges", ranges), |b| {
+            b.iter(|| {
+                for &date in &days {
+                    black_box(calendar.coloring_event(date));
+                }
+            })
+        });
+        c.bench_function(&format!("render, {} ranges", ranges), |b| {
+            b.iter(|| black_box(CalendarRenderer::new(&calendar).render_to_string()))
+        });
+    }
+}
+
+criterion_group!(benches, range_lookup);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use crate::models::Calendar;
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+
+/// Events with this tag (vacations, holidays) free up the days they cover
+pub const TIME_OFF_TAG: &str = "off";
+
+/// Time from which a day counts as evening
+pub const EVENING_START: NaiveTime = match NaiveTime::from_hms_opt(18, 0, 0) {
+    Some(time) => time,
+    None => panic!("invalid evening start"),
+};
+
+/// A weekly recurring busy period, e.g. Mon–Fri 09:00–17:00
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailabilityBlock {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayAvailability {
+    /// No busy blocks
+    Free,
+    /// Busy, but every block ends by [`EVENING_START`]
+    FreeEvening,
+    Busy,
+}
+
+impl Calendar {
+    /// How much of `date` is free, given the weekly availability blocks
+    pub fn availability(&self, date: NaiveDate) -> DayAvailability {
+        if self.is_time_off(date) || self.is_non_working(date) {
+            return DayAvailability::Free;
+        }
+
+        let latest_end = self
+            .availability
+            .iter()
+            .filter(|block| block.days.contains(&date.weekday()))
+            .map(|block| block.end)
+            .max();
+        match latest_end {
+            None => DayAvailability::Free,
+            Some(end) if end <= EVENING_START => DayAvailability::FreeEvening,
+            Some(_) => DayAvailability::Busy,
+        }
+    }
+
+    /// Whether a date or range tagged [`TIME_OFF_TAG`] covers `date`
+    pub fn is_time_off(&self, date: NaiveDate) -> bool {
+        self.details_on(date)
+            .chain(self.ranges_on(date))
+            .any(|event| event.all_tags().any(|tag| tag == TIME_OFF_TAG))
+    }
+
+    /// Runs of at least `min_days` consecutive free days between `from` and
+    /// `to` (inclusive), as (first, last) pairs
+    pub fn free_runs(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        min_days: usize,
+    ) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        let mut date = from;
+        while date <= to {
+            let free = self.availability(date) == DayAvailability::Free;
+            match (free, run_start) {
+                (true, None) => run_start = Some(date),
+                (false, Some(start)) => {
+                    runs.push((start, date.pred_opt().unwrap()));
+                    run_start = None;
+                }
+                _ => {}
+            }
+            date = date.succ_opt().unwrap();
+        }
+        if let Some(start) = run_start {
+            runs.push((start, to));
+        }
+
+        runs.retain(|(start, end)| (*end - *start).num_days() as usize + 1 >= min_days);
+        runs
+    }
+}
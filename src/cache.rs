@@ -0,0 +1,226 @@
+//! The on-disk cache of fetched calendars and configs, in
+//! `$XDG_CACHE_HOME/compact-calendar`. Each kind of data has a directory,
+//! holding a file per URL or calendar named by its hash, with the URL kept
+//! beside it in a `.key` file for `cache status`.
+
+use crate::paths;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long a fetched copy is used before fetching it again, unless its
+/// source sets a `ttl`
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Extensions of the files kept beside entries: their keys and ETags, and
+/// OAuth tokens, which aren't cached data and survive `cache clear`
+const SIDECARS: [&str; 4] = ["key", "etag", "new", "token"];
+
+/// When cached copies are used instead of fetching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// While younger than their TTL, or when the fetch fails
+    #[default]
+    Fresh,
+    /// Only when the fetch fails (`--refresh`)
+    Refresh,
+    /// Always, without fetching (`--offline`)
+    Offline,
+}
+
+/// Contents from the cache or freshly fetched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cached {
+    pub path: PathBuf,
+    pub contents: String,
+    /// The error of a failed fetch, when an older copy was used instead
+    pub stale: Option<String>,
+}
+
+/// A cached copy, for `cache status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// "sources", "google", "holidays", "weather" or "configs"
+    pub kind: String,
+    /// URL or calendar ID it was fetched from
+    pub key: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// Time since it was fetched
+    pub age: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The user's cache directory. Without `XDG_CACHE_HOME` or `HOME` there
+    /// is none: a shared directory such as the temporary one would let
+    /// anyone on the machine plant the copies that are read back.
+    pub fn user() -> Result<Self, String> {
+        paths::cache_dir()
+            .map(Self::new)
+            .ok_or_else(|| "no cache directory; set XDG_CACHE_HOME or HOME".to_string())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File caching `key`, such as a URL, among the `kind` entries
+    pub fn path(&self, kind: &str, key: &str, extension: &str) -> PathBuf {
+        self.dir
+            .join(kind)
+            .join(format!("{:016x}.{}", fnv1a(key), extension))
+    }
+
+    /// The copy at `path` when younger than `ttl` or `policy` calls for it,
+    /// else the result of `fetch`, which is cached under `key`. When the
+    /// fetch fails, an older copy is used along with the error.
+    pub fn get(
+        &self,
+        path: &Path,
+        key: &str,
+        ttl: Duration,
+        policy: CachePolicy,
+        fetch: impl FnOnce() -> Result<String, String>,
+    ) -> Result<Cached, String> {
+        let age = age(path);
+        let cached = |stale| {
+            Ok(Cached {
+                path: path.to_path_buf(),
+                contents: read(path)?,
+                stale,
+            })
+        };
+        match (age, policy) {
+            (Some(_), CachePolicy::Offline) => cached(None),
+            (None, CachePolicy::Offline) => Err("not cached yet, and offline".to_string()),
+            (Some(age), CachePolicy::Fresh) if age < ttl => cached(None),
+            _ => match fetch() {
+                Ok(contents) => {
+                    write(path, &contents)?;
+                    write(&path.with_extension("key"), key)?;
+                    Ok(Cached {
+                        path: path.to_path_buf(),
+                        contents,
+                        stale: None,
+                    })
+                }
+                Err(e) if age.is_some() => cached(Some(e)),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Every cached copy, by kind and then key
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for kind in read_dir(&self.dir).filter(|path| path.is_dir()) {
+            for path in read_dir(&kind).filter(|path| !is_sidecar(path)) {
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                let key = fs::read_to_string(path.with_extension("key"))
+                    .unwrap_or_else(|_| path.display().to_string());
+                entries.push(Entry {
+                    kind: kind
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    key,
+                    size: metadata.len(),
+                    age: age(&path),
+                    path,
+                });
+            }
+        }
+        entries.sort_by(|a, b| (&a.kind, &a.key).cmp(&(&b.kind, &b.key)));
+        entries
+    }
+
+    /// Remove every cached copy, keeping OAuth tokens; returns how many
+    /// copies were removed
+    pub fn clear(&self) -> io::Result<usize> {
+        let mut removed = 0;
+        for kind in read_dir(&self.dir).filter(|path| path.is_dir()) {
+            for path in read_dir(&kind) {
+                if path.extension().is_some_and(|ext| ext == "token") {
+                    continue;
+                }
+                if !is_sidecar(&path) {
+                    removed += 1;
+                }
+                fs::remove_file(&path)?;
+            }
+            // Left in place when a token is still in it
+            let _ = fs::remove_dir(&kind);
+        }
+        Ok(removed)
+    }
+}
+
+/// Parse a TTL such as "30m", "6h" or "1d"; plain numbers are seconds
+pub fn parse_ttl(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let invalid = || format!("invalid ttl '{}', expected e.g. 30m, 6h or 1d", input);
+    let (number, unit) = input.split_at(input.trim_end_matches(char::is_alphabetic).len());
+    let number: u64 = number.trim().parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_secs(
+        number.checked_mul(seconds).ok_or_else(invalid)?,
+    ))
+}
+
+/// Name of a cache file; FNV-1a, which unlike `DefaultHasher` stays the same
+/// between builds
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn is_sidecar(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SIDECARS.contains(&ext))
+}
+
+fn read_dir(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+}
+
+fn age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    let write = || {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    };
+    write().map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -1,128 +1,2171 @@
-use crate::models::{DateDetail, DateRange};
-use chrono::NaiveDate;
+use crate::availability::AvailabilityBlock;
+use crate::error::{Error, Result};
+use crate::expressions::{Anchors, DateExpr, NthWeekday};
+use crate::formatting::MonthInfo;
+use crate::holidays::Country;
+use crate::models::{parse_icon, parse_timezone, Event, EventKind, Gradient, Status};
+use crate::paths;
+use crate::settings::{Settings, Style};
+use crate::sun::Daylight;
+use crate::weather;
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+/// File format of a config, detected from its extension unless given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+    /// A remind(1) file, translated into entries
+    Remind,
+}
+
+impl ConfigFormat {
+    pub fn parse(input: &str) -> std::result::Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "toml" => Ok(Self::Toml),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "json" => Ok(Self::Json),
+            "remind" | "rem" => Ok(Self::Remind),
+            _ => Err(format!(
+                "Invalid config format: '{}'. Use toml, yaml, json or remind",
+                input
+            )),
+        }
+    }
+
+    /// `.yaml`/`.yml` files are YAML, `.json` files JSON, `.rem` files and
+    /// remind's `.reminders` remind files, anything else TOML
+    pub fn from_path(path: &Path) -> Self {
+        let name = path.file_name().and_then(|name| name.to_str());
+        if matches!(name, Some(".reminders" | "reminders")) {
+            return Self::Remind;
+        }
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| Self::parse(ext).ok())
+            .unwrap_or(Self::Toml)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct CalendarConfig {
+    /// Refuse edits through the CLI, for shared configs
+    #[serde(default)]
+    pub readonly: bool,
+    /// Timezone deciding which day is today, e.g. "Asia/Tokyo"; the local
+    /// one when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Config fragments whose dates, ranges and busy blocks are merged in;
+    /// paths and globs are relative to this file
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// vdir collections, directories of `.ics` files as vdirsyncer and khal
+    /// keep them, whose events are added; paths and globs are relative to
+    /// this file
+    #[serde(default)]
+    pub vdir: Vec<String>,
+    /// CalDAV calendars and subscribed `.ics` files whose events are fetched
+    /// and added, read-only
+    #[serde(default)]
+    pub sources: Vec<RawSource>,
+    /// Google calendars whose events are fetched and added, read-only; needs
+    /// a build with the `google` feature
+    #[serde(default)]
+    pub google: Vec<RawGoogleCalendar>,
+    /// National holidays added every year, built in or from a holiday API
     #[serde(default)]
-    pub dates: HashMap<String, RawDateDetail>,
+    pub holidays: Option<RawHolidays>,
+    /// Defaults for the `show` flags
+    #[serde(default)]
+    pub settings: Settings,
+    /// Defaults for how the calendar looks
+    #[serde(default)]
+    pub style: Style,
+    /// Entries by date; a date given as `[[dates."..."]]` holds several
+    #[serde(default, deserialize_with = "one_or_more_details")]
+    pub dates: HashMap<String, Vec<RawDateDetail>>,
     #[serde(default)]
     pub ranges: Vec<RawDateRange>,
+    /// Standing events repeated every week
+    #[serde(default)]
+    pub weekly: Vec<RawWeekly>,
+    /// Back-to-back sprints generated as ranges
+    #[serde(default)]
+    pub sprints: Option<RawSprints>,
+    /// Paydays, and the pay periods between them
+    #[serde(default)]
+    pub payperiods: Option<RawPayPeriods>,
+    /// A repeating shift rotation, generated as ranges
+    #[serde(default)]
+    pub shifts: Option<RawShifts>,
+    /// Labels shown instead of the week numbers
+    #[serde(default)]
+    pub week_labels: Option<RawWeekLabels>,
+    /// School terms, shaded and with their weeks numbered
+    #[serde(default)]
+    pub terms: Vec<RawTerm>,
+    /// Where the calendar is, for `--daylight`
+    #[serde(default)]
+    pub daylight: Option<RawDaylight>,
+    /// Where and how far ahead to forecast, for `--weather`
+    #[serde(default)]
+    pub weather: Option<RawWeather>,
+    #[serde(default)]
+    pub availability: Vec<RawAvailability>,
+    /// Days the company is closed, as dates or "start..end" ranges; dimmed
+    /// like weekends and left out of working days
+    #[serde(default)]
+    pub non_working: Vec<String>,
+    /// Weekend days worked to make up for a bridge holiday, as dates or
+    /// "start..end" ranges; shown and counted like weekdays
+    #[serde(default)]
+    pub working_weekends: Vec<String>,
+    /// Shared colors and display options for entries with a `tag`, by tag
+    #[serde(default)]
+    pub categories: HashMap<String, RawCategory>,
+    /// What was left out while loading, such as unsupported remind lines
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Settings shared by every date and range with the same `tag`
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+pub struct RawCategory {
+    /// Color for entries of the category that don't set their own
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Leave the category's entries off the calendar
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
 pub struct RawDateDetail {
     #[serde(default)]
     pub description: String,
     #[serde(default)]
     pub color: Option<String>,
+    /// Category from `[categories]`, which supplies the color when unset
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Name that other entries can define their dates relative to, as in
+    /// `date = "launch - 14d"`
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Date expression such as "launch - 14d", instead of the entry's key
+    #[serde(default)]
+    pub date: Option<String>,
+    /// Floating date such as the 4th Thursday of November, instead of the
+    /// date in the entry's key
+    #[serde(default)]
+    pub nth_weekday: Option<RawNthWeekday>,
+    /// "YYYY-MM-DD" of a birthday or other anniversary, shown every year from
+    /// then on with the count of years; `{years}` in the description places
+    /// it, otherwise it is appended like "Mom's birthday (65)"
+    #[serde(default)]
+    pub anniversary: Option<String>,
+    /// Where a February 29th anniversary falls in other years: "02-28" (the
+    /// default) or "03-01"
+    #[serde(default)]
+    pub leap_day: Option<String>,
+    /// `date` or `milestone`; yearly `MM-DD` dates default to `recurring`
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// Higher priorities are annotated first; `--min-priority` hides lower ones
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Glyph such as "✈" marking the day and its annotation
+    #[serde(default)]
+    pub icon: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RawDateRange {
     pub start: String,
     pub end: String,
+    /// Required unless the range's category has a color
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Category from `[categories]`, which supplies the color when unset
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `range` (the default) or `travel`
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Destination timezone of a travel range, e.g. "Asia/Tokyo"
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// Higher priorities are annotated first; `--min-priority` hides lower ones
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Where ranges overlap, the highest layer colors the day (0 when unset);
+    /// on the same layer the range listed first does
+    #[serde(default)]
+    pub layer: Option<i32>,
+    /// `requested`, `approved` or `rejected`, for time off awaiting sign-off
+    #[serde(default)]
+    pub status: Option<String>,
+    /// `shade` (light to dark) or the color the range fades into by its end
+    #[serde(default)]
+    pub gradient: Option<String>,
+    /// Glyph such as "✈" marking the range's days and its annotation
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+/// `nth_weekday = { n = 4, weekday = "thu", month = 11 }`
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawNthWeekday {
+    /// 1 to 5, or -1 for the last
+    pub n: i32,
+    pub weekday: String,
+    pub month: u32,
+}
+
+impl RawNthWeekday {
+    pub fn to_nth_weekday(&self) -> Result<NthWeekday> {
+        let weekday = self
+            .weekday
+            .parse()
+            .map_err(|_| Error::InvalidEntry(format!("invalid weekday '{}'", self.weekday)))?;
+        NthWeekday::new(self.n, weekday, self.month).map_err(Error::InvalidEntry)
+    }
+}
+
+/// A standing event on the same weekdays every week, e.g. a team sync
+#[derive(Debug, Deserialize, Clone)]
+pub struct RawWeekly {
+    /// Weekdays it falls on, e.g. "tuesday", "tue" or "mon,thu"
+    pub every: String,
+    /// First day it can fall on, "YYYY-MM-DD" or yearly "MM-DD"; the start of
+    /// the year when unset
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Last day it can fall on; the end of the year when unset
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Description, color and other details of each occurrence
+    #[serde(flatten)]
+    pub detail: RawDateDetail,
+}
+
+/// Back-to-back sprints of the same length from `start`, generated as
+/// ranges so they needn't be listed one by one
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawSprints {
+    /// First day of the first sprint, e.g. "2025-01-06"
+    pub start: String,
+    /// Length of each sprint in weeks; 2 when unset
+    #[serde(default)]
+    pub weeks: Option<u32>,
+    /// Label of each sprint, with `{n}` replaced by its number; "Sprint {n}"
+    /// when unset
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Number of the first sprint; 1 when unset
+    #[serde(default)]
+    pub first: Option<u32>,
+    /// Colors the sprints take in turn; light blue and light green when unset
+    #[serde(default)]
+    pub colors: Vec<String>,
+    /// Where sprints overlap other ranges; -1 when unset, so any other range
+    /// shows on top
+    #[serde(default)]
+    pub layer: Option<i32>,
+}
+
+/// How often pay comes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayInterval {
+    Weekly,
+    Biweekly,
+    /// Twice a month, on the first payday's day and half a month apart
+    Semimonthly,
+    Monthly,
+}
+
+impl PayInterval {
+    pub fn parse(input: &str) -> std::result::Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "weekly" => Ok(Self::Weekly),
+            "biweekly" | "fortnightly" => Ok(Self::Biweekly),
+            "semimonthly" => Ok(Self::Semimonthly),
+            "monthly" => Ok(Self::Monthly),
+            _ => Err(format!(
+                "invalid pay interval '{}', expected weekly, biweekly, semimonthly or monthly",
+                input
+            )),
+        }
+    }
+
+    /// The `n`th payday counting `first` as the 0th. Semimonthly paydays
+    /// fall 15 days apart, with a second one on the 30th or later moved to
+    /// the month's last day; days past a month's end fall on its last day.
+    pub fn nth_payday(&self, first: NaiveDate, n: u32) -> NaiveDate {
+        let in_month = |months: u32, day: u32| {
+            let months = first.year() * 12 + first.month0() as i32 + months as i32;
+            let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+            let day = day.min(MonthInfo::days_in_month(month, year));
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        };
+        match self {
+            Self::Weekly => first + chrono::Duration::days(7 * n as i64),
+            Self::Biweekly => first + chrono::Duration::days(14 * n as i64),
+            Self::Monthly => in_month(n, first.day()),
+            Self::Semimonthly => {
+                let (early, late) = if first.day() > 15 {
+                    (first.day() - 15, first.day())
+                } else {
+                    (first.day(), first.day() + 15)
+                };
+                let late = if late >= 30 { 31 } else { late };
+                let half = n + u32::from(first.day() > 15);
+                in_month(half / 2, if half.is_multiple_of(2) { early } else { late })
+            }
+        }
+    }
+}
+
+/// Paydays repeating from the first one, marked on the calendar, and
+/// optionally the pay periods between them shaded for budgeting
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawPayPeriods {
+    /// First payday, e.g. "2025-01-10"
+    pub payday: String,
+    /// "weekly", "biweekly", "semimonthly" or "monthly"
+    pub interval: String,
+    /// Label of each payday; "Payday" when unset
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Color of each payday; green when unset
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Shade each period, from a payday to the day before the next
+    #[serde(default)]
+    pub shade: bool,
+    /// Colors the shaded periods take in turn; light green and light yellow
+    /// when unset
+    #[serde(default)]
+    pub colors: Vec<String>,
+}
+
+/// A rotation of shifts repeating every `pattern.len()` days from `start`,
+/// such as "DDNNOOOO" for two days, two nights and four off
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawShifts {
+    /// Day the pattern begins, e.g. "2025-01-06"
+    pub start: String,
+    /// One letter per day; `O` and `-` are days off, and spaces are ignored
+    pub pattern: String,
+    /// Name and color of each letter; day (D), evening (E) and night (N)
+    /// shifts when unset
+    #[serde(default)]
+    pub codes: HashMap<String, RawShiftCode>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawShiftCode {
+    pub name: String,
     pub color: String,
+}
+
+/// A school term, shaded from `start` to `end` except for its breaks, with
+/// its weeks numbered from 1
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawTerm {
+    pub name: String,
+    pub start: String,
+    pub end: String,
+    /// Light blue when unset
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Days off during the term, as dates or "start..end"; they are left
+    /// unshaded and weeks spent entirely on them aren't numbered
+    #[serde(default)]
+    pub breaks: Vec<String>,
+    /// Label of the term's weeks, with `{n}` replaced by the week of term
+    /// and `{term}` by the term's number; "T{term}W{n}" when unset
+    #[serde(default)]
+    pub week_label: Option<String>,
+}
+
+/// A CalDAV calendar to fetch events from. `${VAR}` references in the
+/// credentials are expanded, so they needn't be written in the config.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawSource {
+    /// URL of a CalDAV calendar collection, e.g.
+    /// "https://dav.example.com/calendars/me/work/", or of an `.ics` file to
+    /// subscribe to, e.g. "webcal://example.com/team/basic.ics"
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Color of its events; light blue ranges and uncolored dates when unset
+    #[serde(default)]
+    pub color: Option<String>,
+    /// How long a fetched copy is used, e.g. "30m", "6h" or "1d"; 15 minutes
+    /// when unset
+    #[serde(default)]
+    pub ttl: Option<String>,
+}
+
+/// A Google calendar to fetch events from, with an API key for public
+/// calendars or an OAuth client for private ones. `${VAR}` references in the
+/// credentials are expanded.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawGoogleCalendar {
+    /// Calendar ID, e.g. "en.usa#holiday@group.v.calendar.google.com", or
+    /// "primary" with OAuth
+    pub calendar: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// OAuth client of the "TVs and Limited Input devices" type, authorized
+    /// on first use by visiting a URL and entering a code
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Color of events without a Google color of their own
+    #[serde(default)]
+    pub color: Option<String>,
+    /// How long a fetched copy is used, like a source's `ttl`
+    #[serde(default)]
+    pub ttl: Option<String>,
+}
+
+/// Labels replacing the `W01` week numbers, generated from `format` or given
+/// for the weeks containing some dates
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+pub struct RawWeekLabels {
+    /// Label of every week from `start`, with `{n}` replaced by a counter,
+    /// e.g. "S{n}"
+    #[serde(default)]
+    pub format: Option<String>,
+    /// A day in the first labeled week; January 1st when unset
+    #[serde(default)]
+    pub start: Option<String>,
+    /// Weeks sharing each number, e.g. 2 for two-week sprints; 1 when unset
+    #[serde(default)]
+    pub every: Option<u32>,
+    /// Number of the first labeled week; 1 when unset
+    #[serde(default)]
+    pub first: Option<u32>,
+    /// Labels of the weeks containing these dates, over generated ones
+    #[serde(default)]
+    pub weeks: HashMap<String, String>,
+}
+
+/// A country's public holidays: `holidays = { country = "FR", source = "api" }`
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawHolidays {
+    /// Two-letter country code, e.g. "DE"
+    pub country: String,
+    /// "builtin" for the embedded holidays, "api" to fetch them; the
+    /// embedded ones when the country has them, else the API, when unset
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Holiday API with `{year}` and `{country}` placeholders; Nager.Date
+    /// when unset
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Color of the holidays; red when unset
+    #[serde(default)]
+    pub color: Option<String>,
+    /// How long fetched holidays are used; a week when unset
+    #[serde(default)]
+    pub ttl: Option<String>,
+}
+
+impl RawHolidays {
+    /// Whether the holidays are fetched from the API rather than embedded
+    pub fn use_api(&self) -> std::result::Result<bool, String> {
+        let builtin = Country::parse(&self.country).is_ok();
+        match self.source.as_deref() {
+            Some("api") => Ok(true),
+            Some("builtin") if builtin => Ok(false),
+            Some("builtin") => Err(format!(
+                "no built-in holidays for {}; use source = \"api\"",
+                self.country
+            )),
+            None => Ok(!builtin),
+            Some(other) => Err(format!(
+                "unknown holiday source '{}', expected builtin or api",
+                other
+            )),
+        }
+    }
+}
+
+/// Location for sunrise and sunset, and the times worth a note when they
+/// cross them
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawDaylight {
+    /// Degrees north; negative in the south
+    pub latitude: f64,
+    /// Degrees east; negative in the west
+    pub longitude: f64,
+    /// "HH:MM" times to note sunrise crossing
+    #[serde(default)]
+    pub sunrise: Vec<String>,
+    /// "HH:MM" times to note sunset crossing
+    #[serde(default)]
+    pub sunset: Vec<String>,
+}
+
+impl RawDaylight {
+    pub fn to_daylight(&self) -> Result<Daylight> {
+        let mut daylight =
+            Daylight::new(self.latitude, self.longitude).map_err(Error::InvalidEntry)?;
+        let times = |values: &[String]| {
+            values
+                .iter()
+                .map(|value| {
+                    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+                        Error::InvalidEntry(format!("invalid time '{}', expected HH:MM", value))
+                    })
+                })
+                .collect::<Result<Vec<NaiveTime>>>()
+        };
+        daylight.sunrise = times(&self.sunrise)?;
+        daylight.sunset = times(&self.sunset)?;
+        Ok(daylight)
+    }
+}
+
+/// Forecast location and length for `--weather`
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+pub struct RawWeather {
+    /// Degrees north; the `[daylight]` latitude when unset
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    /// Degrees east; the `[daylight]` longitude when unset
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// Days to forecast, up to 16; 7 when unset
+    #[serde(default)]
+    pub days: Option<u32>,
+    /// Forecast API with `{latitude}`, `{longitude}` and `{days}`
+    /// placeholders; Open-Meteo when unset
+    #[serde(default)]
+    pub url: Option<String>,
+    /// How long a fetched forecast is used; an hour when unset
+    #[serde(default)]
+    pub ttl: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RawAvailability {
+    /// Weekdays the block applies to, e.g. "mon-fri" or "sat,sun"
+    pub days: String,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
     #[serde(default)]
     pub description: Option<String>,
 }
 
+impl RawAvailability {
+    pub fn to_block(&self) -> Result<AvailabilityBlock> {
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+                Error::InvalidEntry(format!("invalid time '{}', expected HH:MM", value))
+            })
+        };
+        let (start, end) = (time(&self.start)?, time(&self.end)?);
+        if end <= start {
+            return Err(Error::InvalidEntry(format!(
+                "busy block ends ({}) before it starts ({})",
+                self.end, self.start
+            )));
+        }
+
+        Ok(AvailabilityBlock {
+            days: parse_weekdays(&self.days)?,
+            start,
+            end,
+            description: self.description.clone(),
+        })
+    }
+}
+
+/// Parse "mon-fri", "sat,sun" or a mix like "mon,wed-fri"
+fn parse_weekdays(input: &str) -> Result<Vec<Weekday>> {
+    let weekday = |name: &str| {
+        name.trim()
+            .parse::<Weekday>()
+            .map_err(|_| Error::InvalidEntry(format!("invalid weekday '{}'", name.trim())))
+    };
+
+    let mut days = Vec::new();
+    for part in input.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut day, last) = (weekday(first)?, weekday(last)?);
+                days.push(day);
+                while day != last {
+                    day = day.succ();
+                    days.push(day);
+                }
+            }
+            None => days.push(weekday(part)?),
+        }
+    }
+    Ok(days)
+}
+
+impl RawDateDetail {
+    /// Build the event for this entry; yearly dates such as `MM-DD` or
+    /// "2nd monday of october" need a `year`, `anchors` resolve dates given
+    /// relative to other entries, and `categories` fill in the color of
+    /// tagged entries
+    pub fn to_event(
+        &self,
+        date_str: &str,
+        year: Option<i32>,
+        anchors: &Anchors,
+        categories: &HashMap<String, RawCategory>,
+    ) -> Result<Event> {
+        let expr = self.expr(date_str, year)?;
+        let date = resolve_date(&expr, date_str, year, anchors)?;
+        let default_kind = if expr.is_yearly() {
+            EventKind::Recurring
+        } else {
+            EventKind::Date
+        };
+        let mut event = self.event_on(date, default_kind, categories)?;
+        if let Some(since) = self.anniversary_date()? {
+            let years = date.year() - since.year();
+            if years < 0 {
+                return Err(Error::InvalidEntry(format!(
+                    "{} is before the anniversary {}",
+                    date, since
+                )));
+            }
+            event.description = Some(if self.description.contains("{years}") {
+                self.description.replace("{years}", &years.to_string())
+            } else if self.description.is_empty() {
+                format!("({})", years)
+            } else {
+                format!("{} ({})", self.description, years)
+            });
+        }
+        Ok(event)
+    }
+
+    /// The entry's date: its `date`, `nth_weekday` or `anniversary` if set,
+    /// else its key. A February 29th anniversary moves to its `leap_day` in
+    /// a `year` without one.
+    fn expr(&self, date_str: &str, year: Option<i32>) -> Result<DateExpr> {
+        if let Some(date) = &self.date {
+            return parse_date(date);
+        }
+        if let Some(nth) = &self.nth_weekday {
+            return Ok(DateExpr::NthWeekday(nth.to_nth_weekday()?));
+        }
+        if let Some(since) = self.anniversary_date()? {
+            let leap_day = self.leap_day()?;
+            let (month, day) = match year {
+                Some(year)
+                    if (since.month(), since.day()) == (2, 29)
+                        && NaiveDate::from_ymd_opt(year, 2, 29).is_none() =>
+                {
+                    leap_day
+                }
+                _ => (since.month(), since.day()),
+            };
+            return Ok(DateExpr::Yearly { month, day });
+        }
+        parse_date(date_str)
+    }
+
+    /// Month and day a February 29th anniversary falls on in other years
+    fn leap_day(&self) -> Result<(u32, u32)> {
+        match self.leap_day.as_deref() {
+            None | Some("02-28") => Ok((2, 28)),
+            Some("03-01") => Ok((3, 1)),
+            Some(leap_day) => Err(Error::InvalidEntry(format!(
+                "invalid leap_day '{}', expected \"02-28\" or \"03-01\"",
+                leap_day
+            ))),
+        }
+    }
+
+    /// The original date of an anniversary entry
+    pub fn anniversary_date(&self) -> Result<Option<NaiveDate>> {
+        self.anniversary
+            .as_deref()
+            .map(|since| {
+                NaiveDate::parse_from_str(since, "%Y-%m-%d").map_err(|_| invalid_date(since))
+            })
+            .transpose()
+    }
+
+    /// Build the event on an already resolved `date`
+    fn event_on(
+        &self,
+        date: NaiveDate,
+        default_kind: EventKind,
+        categories: &HashMap<String, RawCategory>,
+    ) -> Result<Event> {
+        let kind = match &self.kind {
+            Some(kind) => EventKind::parse(kind).map_err(Error::InvalidEntry)?,
+            None => default_kind,
+        };
+
+        let mut builder = Event::builder(kind, date).tags(self.tags.iter().cloned());
+        if !self.description.is_empty() {
+            builder = builder.description(&self.description);
+        }
+        if let Some(color) = category_color(&self.color, &self.tag, categories) {
+            builder = builder.color(color);
+        }
+        if let Some(tag) = &self.tag {
+            builder = builder.category(tag);
+        }
+        if let Some(id) = &self.id {
+            builder = builder.id(id);
+        }
+        if let Some(owner) = &self.owner {
+            builder = builder.owner(owner);
+        }
+        if let Some(cost) = self.cost {
+            builder = builder.cost(cost);
+        }
+        if let Some(priority) = self.priority {
+            builder = builder.priority(priority);
+        }
+        if let Some(icon) = &self.icon {
+            builder = builder.icon(parse_icon(icon).map_err(Error::InvalidEntry)?);
+        }
+        builder.build()
+    }
+}
+
+impl RawDateRange {
+    /// Build the event for this range; yearly `MM-DD` ranges need a `year`,
+    /// `anchors` resolve ends given relative to other entries, and
+    /// `categories` fill in the color of tagged ranges
+    pub fn to_event(
+        &self,
+        year: Option<i32>,
+        anchors: &Anchors,
+        categories: &HashMap<String, RawCategory>,
+    ) -> Result<Event> {
+        let resolve =
+            |date_str: &str| resolve_date(&parse_date(date_str)?, date_str, year, anchors);
+
+        let kind = match &self.kind {
+            Some(kind) => match EventKind::parse(kind).map_err(Error::InvalidEntry)? {
+                kind @ (EventKind::Range | EventKind::Travel) => kind,
+                kind => {
+                    return Err(Error::InvalidEntry(format!(
+                        "a range cannot be a {}",
+                        kind.name()
+                    )))
+                }
+            },
+            None => EventKind::Range,
+        };
+
+        let mut builder = Event::builder(kind, resolve(&self.start)?)
+            .end(resolve(&self.end)?)
+            .tags(self.tags.iter().cloned());
+        if let Some(color) = category_color(&self.color, &self.tag, categories) {
+            builder = builder.color(color);
+        }
+        if let Some(tag) = &self.tag {
+            builder = builder.category(tag);
+        }
+        if let Some(description) = &self.description {
+            builder = builder.description(description);
+        }
+        if let Some(timezone) = &self.timezone {
+            builder = builder.timezone(parse_timezone(timezone).map_err(Error::InvalidEntry)?);
+        }
+        if let Some(id) = &self.id {
+            builder = builder.id(id);
+        }
+        if let Some(owner) = &self.owner {
+            builder = builder.owner(owner);
+        }
+        if let Some(cost) = self.cost {
+            builder = builder.cost(cost);
+        }
+        if let Some(priority) = self.priority {
+            builder = builder.priority(priority);
+        }
+        if let Some(layer) = self.layer {
+            builder = builder.layer(layer);
+        }
+        if let Some(status) = &self.status {
+            builder = builder.status(Status::parse(status).map_err(Error::InvalidEntry)?);
+        }
+        if let Some(gradient) = &self.gradient {
+            builder = builder.gradient(Gradient::parse(gradient).map_err(Error::InvalidEntry)?);
+        }
+        if let Some(icon) = &self.icon {
+            builder = builder.icon(parse_icon(icon).map_err(Error::InvalidEntry)?);
+        }
+        builder.build()
+    }
+}
+
+impl RawWeekly {
+    /// An event for each matching weekday of `year` between `from` and `until`
+    pub fn to_events(
+        &self,
+        year: i32,
+        anchors: &Anchors,
+        categories: &HashMap<String, RawCategory>,
+    ) -> Result<Vec<Event>> {
+        let days = parse_weekdays(&self.every)?;
+        let resolve =
+            |date_str: &str| resolve_date(&parse_date(date_str)?, date_str, Some(year), anchors);
+        let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+        let from = self.from.as_deref().map(resolve).transpose()?;
+        let until = self.until.as_deref().map(resolve).transpose()?;
+        if let (Some(from), Some(until)) = (from, until) {
+            if until < from {
+                return Err(Error::InvalidEntry(
+                    "weekly event ends before it starts".to_string(),
+                ));
+            }
+        }
+
+        let (from, until) = (
+            from.unwrap_or(first).max(first),
+            until.unwrap_or(last).min(last),
+        );
+        from.iter_days()
+            .take_while(|date| *date <= until)
+            .filter(|date| days.contains(&date.weekday()))
+            .map(|date| self.detail.event_on(date, EventKind::Date, categories))
+            .collect()
+    }
+}
+
+impl RawSprints {
+    pub const DEFAULT_WEEKS: u32 = 2;
+    pub const DEFAULT_NAME: &'static str = "Sprint {n}";
+    pub const DEFAULT_COLORS: [&'static str; 2] = ["light_blue", "light_green"];
+    pub const DEFAULT_LAYER: i32 = -1;
+
+    /// A range for each sprint touching `year`, numbered from the first one
+    pub fn to_events(&self, year: i32, anchors: &Anchors) -> Result<Vec<Event>> {
+        let weeks = self.weeks.unwrap_or(Self::DEFAULT_WEEKS);
+        if weeks == 0 {
+            return Err(Error::InvalidEntry(
+                "sprints must last at least a week".to_string(),
+            ));
+        }
+        let colors: Vec<&str> = if self.colors.is_empty() {
+            Self::DEFAULT_COLORS.to_vec()
+        } else {
+            self.colors.iter().map(String::as_str).collect()
+        };
+        let name = self.name.as_deref().unwrap_or(Self::DEFAULT_NAME);
+        let start = resolve_date(&parse_date(&self.start)?, &self.start, Some(year), anchors)?;
+        let length = weeks as i64 * 7;
+        let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+
+        // Skip the sprints over before the year begins
+        let mut index = ((first - start).num_days() / length).max(0);
+        let mut events = Vec::new();
+        loop {
+            let sprint_start = start + chrono::Duration::days(index * length);
+            if sprint_start > last {
+                return Ok(events);
+            }
+            let number = self.first.unwrap_or(1) as i64 + index;
+            let event = Event::builder(EventKind::Range, sprint_start)
+                .end(sprint_start + chrono::Duration::days(length - 1))
+                .description(name.replace("{n}", &number.to_string()))
+                .color(colors[index as usize % colors.len()])
+                .layer(self.layer.unwrap_or(Self::DEFAULT_LAYER))
+                .build()?;
+            events.push(event);
+            index += 1;
+        }
+    }
+}
+
+impl RawPayPeriods {
+    pub const DEFAULT_NAME: &'static str = "Payday";
+    pub const DEFAULT_COLOR: &'static str = "green";
+    pub const DEFAULT_COLORS: [&'static str; 2] = ["light_green", "light_yellow"];
+    /// Under other ranges, like sprints
+    pub const DEFAULT_LAYER: i32 = -1;
+    /// Period annotations give way to the paydays when cut short
+    pub const PERIOD_PRIORITY: i32 = -1;
+
+    /// The paydays in `year`, and the shaded periods touching it
+    pub fn to_events(&self, year: i32, anchors: &Anchors) -> Result<Vec<Event>> {
+        let interval = PayInterval::parse(&self.interval).map_err(Error::InvalidEntry)?;
+        let colors: Vec<&str> = if self.colors.is_empty() {
+            Self::DEFAULT_COLORS.to_vec()
+        } else {
+            self.colors.iter().map(String::as_str).collect()
+        };
+        let first = resolve_date(
+            &parse_date(&self.payday)?,
+            &self.payday,
+            Some(year),
+            anchors,
+        )?;
+        let year_start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let year_end = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+
+        let mut events = Vec::new();
+        for n in 0.. {
+            let payday = interval.nth_payday(first, n);
+            if payday > year_end {
+                break;
+            }
+            let next = interval.nth_payday(first, n + 1);
+            if next <= year_start {
+                continue;
+            }
+            if payday >= year_start {
+                let event = Event::builder(EventKind::Date, payday)
+                    .description(self.name.as_deref().unwrap_or(Self::DEFAULT_NAME))
+                    .color(self.color.as_deref().unwrap_or(Self::DEFAULT_COLOR))
+                    .build()?;
+                events.push(event);
+            }
+            if self.shade {
+                let event = Event::builder(EventKind::Range, payday)
+                    .end(next.pred_opt().unwrap())
+                    .description("Pay period")
+                    .color(colors[n as usize % colors.len()])
+                    .layer(Self::DEFAULT_LAYER)
+                    .priority(Self::PERIOD_PRIORITY)
+                    .build()?;
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+}
+
+impl RawShifts {
+    pub const DEFAULT_CODES: [(&'static str, &'static str, &'static str); 3] = [
+        ("D", "Day shift", "yellow"),
+        ("E", "Evening shift", "orange"),
+        ("N", "Night shift", "blue"),
+    ];
+    pub const OFF: [char; 2] = ['O', '-'];
+    /// Under other ranges, like sprints
+    pub const DEFAULT_LAYER: i32 = -1;
+
+    /// Name and color of each day of the pattern, `None` on days off
+    pub fn days(&self) -> Result<Vec<Option<(String, String)>>> {
+        let days: Vec<char> = self
+            .pattern
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        if days.is_empty() {
+            return Err(Error::InvalidEntry("shift pattern is empty".to_string()));
+        }
+        days.into_iter()
+            .map(|letter| {
+                if Self::OFF.contains(&letter) {
+                    return Ok(None);
+                }
+                let code = letter.to_string();
+                let shift = if self.codes.is_empty() {
+                    Self::DEFAULT_CODES
+                        .iter()
+                        .find(|(default, _, _)| *default == code)
+                        .map(|(_, name, color)| (name.to_string(), color.to_string()))
+                } else {
+                    self.codes
+                        .get(&code)
+                        .map(|shift| (shift.name.clone(), shift.color.clone()))
+                };
+                shift.map(Some).ok_or_else(|| {
+                    Error::InvalidEntry(format!(
+                        "unknown shift '{}' in pattern, add it to [shifts.codes] or use O for days off",
+                        letter
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// A range for each run of days on the same shift touching `year`
+    pub fn to_events(&self, year: i32, anchors: &Anchors) -> Result<Vec<Event>> {
+        let days = self.days()?;
+        let start = resolve_date(&parse_date(&self.start)?, &self.start, Some(year), anchors)?;
+        let cycle = chrono::Duration::days(days.len() as i64);
+        let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+
+        // Start a cycle early, so runs already underway on New Year's Day
+        // come out whole
+        let mut events = Vec::new();
+        let mut run: Option<(NaiveDate, NaiveDate, usize)> = None;
+        for date in start.max(first - cycle).iter_days() {
+            let index = (date - start).num_days() as usize % days.len();
+            // A pattern of one shift never changes, so runs stop a cycle on
+            let same = matches!(run, Some((_, _, run_index)) if days[run_index] == days[index]);
+            if same && date <= last + cycle {
+                if let Some((_, end, _)) = &mut run {
+                    *end = date;
+                }
+                continue;
+            }
+            if let Some((run_start, run_end, run_index)) = run.take() {
+                if let (Some((name, color)), true) = (&days[run_index], run_end >= first) {
+                    let event = Event::builder(EventKind::Range, run_start)
+                        .end(run_end)
+                        .description(name.clone())
+                        .color(color.as_str())
+                        .layer(Self::DEFAULT_LAYER)
+                        .build()?;
+                    events.push(event);
+                }
+            }
+            if date > last {
+                return Ok(events);
+            }
+            run = Some((date, date, index));
+        }
+        Ok(events)
+    }
+}
+
+impl RawTerm {
+    pub const DEFAULT_COLOR: &'static str = "light_blue";
+    pub const DEFAULT_WEEK_LABEL: &'static str = "T{term}W{n}";
+    /// Under other ranges, like sprints
+    pub const DEFAULT_LAYER: i32 = -1;
+
+    /// First and last day of the term in `year`, and of each of its breaks
+    pub fn days(&self, year: i32, anchors: &Anchors) -> Result<(DaySpan, Vec<DaySpan>)> {
+        let term = parse_day_span(&format!("{}..{}", self.start, self.end), year, anchors)?;
+        let mut breaks = Vec::new();
+        for entry in &self.breaks {
+            let (start, end) = parse_day_span(entry, year, anchors)
+                .map_err(|e| Error::InvalidEntry(format!("break {}: {}", entry, e)))?;
+            if start < term.0 || end > term.1 {
+                return Err(Error::InvalidEntry(format!(
+                    "break {} is outside the term",
+                    entry
+                )));
+            }
+            breaks.push((start, end));
+        }
+        breaks.sort();
+        Ok((term, breaks))
+    }
+
+    /// A range for each stretch of the term between its breaks
+    pub fn to_events(&self, year: i32, anchors: &Anchors) -> Result<Vec<Event>> {
+        let ((start, end), breaks) = self.days(year, anchors)?;
+        let color = self.color.as_deref().unwrap_or(Self::DEFAULT_COLOR);
+        let mut events = Vec::new();
+        let mut push = |first: NaiveDate, last: NaiveDate| -> Result<()> {
+            if first <= last {
+                let event = Event::builder(EventKind::Range, first)
+                    .end(last)
+                    .description(self.name.clone())
+                    .color(color)
+                    .layer(Self::DEFAULT_LAYER)
+                    .build()?;
+                events.push(event);
+            }
+            Ok(())
+        };
+        let mut first = start;
+        for (break_start, break_end) in breaks {
+            if break_start > first {
+                push(first, break_start.pred_opt().unwrap())?;
+            }
+            first = first.max(break_end.succ_opt().unwrap());
+        }
+        push(first, end)?;
+        Ok(events)
+    }
+
+    /// Labels of the term's weeks, by their Monday; `number` is the term's
+    /// place in the year
+    pub fn week_labels(
+        &self,
+        number: usize,
+        year: i32,
+        anchors: &Anchors,
+    ) -> Result<Vec<(NaiveDate, String)>> {
+        let ((start, end), breaks) = self.days(year, anchors)?;
+        let format = self
+            .week_label
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_WEEK_LABEL);
+        let in_school = |date: &NaiveDate| {
+            !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+                && (start..=end).contains(date)
+                && !breaks
+                    .iter()
+                    .any(|(first, last)| (first..=last).contains(&date))
+        };
+        let mut labels = Vec::new();
+        let mut week = 1;
+        let monday = start - chrono::Duration::days(start.weekday().num_days_from_monday() as i64);
+        for week_start in monday.iter_weeks().take_while(|date| *date <= end) {
+            if week_start.iter_days().take(7).any(|date| in_school(&date)) {
+                let label = format
+                    .replace("{n}", &week.to_string())
+                    .replace("{term}", &number.to_string());
+                labels.push((week_start, label));
+                week += 1;
+            }
+        }
+        Ok(labels)
+    }
+}
+
+impl RawWeekLabels {
+    /// Labels by a day of their week for `year`, the given ones first
+    pub fn to_labels(&self, year: i32, anchors: &Anchors) -> Result<Vec<(NaiveDate, String)>> {
+        let mut labels = Vec::new();
+        let mut weeks: Vec<(&String, &String)> = self.weeks.iter().collect();
+        weeks.sort_by_key(|(date_str, _)| sort_key(date_str));
+        for (date_str, label) in weeks {
+            let date = resolve_date(&parse_date(date_str)?, date_str, Some(year), anchors)?;
+            labels.push((date, label.clone()));
+        }
+
+        let Some(format) = &self.format else {
+            return Ok(labels);
+        };
+        let every = self.every.unwrap_or(1);
+        if every == 0 {
+            return Err(Error::InvalidEntry(
+                "week labels must last at least a week".to_string(),
+            ));
+        }
+        let start = match &self.start {
+            Some(start) => resolve_date(&parse_date(start)?, start, Some(year), anchors)?,
+            None => NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+        };
+        // The first row can start up to six days before the year
+        let first_day = NaiveDate::from_ymd_opt(year, 1, 1).unwrap() - chrono::Duration::days(6);
+        let last_day = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+        let mut week = ((first_day - start).num_days() / 7).max(0);
+        loop {
+            let date = start + chrono::Duration::weeks(week);
+            if date > last_day {
+                return Ok(labels);
+            }
+            let number = self.first.unwrap_or(1) as i64 + week / every as i64;
+            labels.push((date, format.replace("{n}", &number.to_string())));
+            week += 1;
+        }
+    }
+}
+
 impl CalendarConfig {
-    pub fn parse_dates(&self) -> HashMap<NaiveDate, DateDetail> {
-        self.dates
-            .iter()
+    /// The configured timezone for "today", if any
+    pub fn timezone(&self) -> Result<Option<Tz>> {
+        self.timezone
+            .as_deref()
+            .map(|timezone| parse_timezone(timezone).map_err(Error::InvalidEntry))
+            .transpose()
+    }
+
+    /// The location for `--daylight`, if any
+    pub fn daylight(&self) -> Result<Option<Daylight>> {
+        self.daylight
+            .as_ref()
+            .map(RawDaylight::to_daylight)
+            .transpose()
+    }
+
+    /// The `[weather]` section with its coordinates filled in from
+    /// `[daylight]`, if there are any
+    pub fn weather(&self) -> Result<Option<RawWeather>> {
+        let mut weather = self.weather.clone().unwrap_or_default();
+        if let Some(daylight) = &self.daylight {
+            weather.latitude = weather.latitude.or(Some(daylight.latitude));
+            weather.longitude = weather.longitude.or(Some(daylight.longitude));
+        }
+        let (Some(latitude), Some(longitude)) = (weather.latitude, weather.longitude) else {
+            return Ok(None);
+        };
+        // Checks the coordinates the same way
+        Daylight::new(latitude, longitude).map_err(Error::InvalidEntry)?;
+        if let Some(days) = weather.days {
+            if !(1..=weather::MAX_DAYS).contains(&days) {
+                return Err(Error::InvalidEntry(format!(
+                    "weather days must be 1 to {}, not {}",
+                    weather::MAX_DAYS,
+                    days
+                )));
+            }
+        }
+        Ok(Some(weather))
+    }
+
+    /// Events for entries with a full `YYYY-MM-DD` date
+    pub fn parse_dates(&self) -> Vec<Event> {
+        let anchors = self.anchors(None);
+        self.date_entries()
             .filter_map(|(date_str, detail)| {
-                NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+                detail
+                    .to_event(date_str, None, &anchors, &self.categories)
+                    .ok()
+            })
+            .filter(|event| self.is_shown(event))
+            .collect()
+    }
+
+    /// Events for all dates, with yearly `MM-DD` entries placed in `year`
+    pub fn parse_dates_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.date_entries()
+            .filter_map(|(date_str, detail)| {
+                detail
+                    .to_event(date_str, Some(year), &anchors, &self.categories)
                     .ok()
-                    .map(|date| {
-                        (
-                            date,
-                            DateDetail {
-                                description: detail.description.clone(),
-                                color: detail.color.clone(),
-                            },
-                        )
-                    })
             })
+            .filter(|event| self.is_shown(event))
             .collect()
     }
 
-    pub fn parse_dates_for_year(&self, year: i32) -> HashMap<NaiveDate, DateDetail> {
+    /// Every `[dates]` entry with its key, several per key for dates given
+    /// as `[[dates."..."]]`
+    pub fn date_entries(&self) -> impl Iterator<Item = (&String, &RawDateDetail)> {
         self.dates
             .iter()
-            .flat_map(|(date_str, detail)| {
-                if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-                    return vec![(
-                        date,
-                        DateDetail {
-                            description: detail.description.clone(),
-                            color: detail.color.clone(),
-                        },
-                    )];
-                }
-                if let Ok(md) =
-                    chrono::NaiveDate::parse_from_str(&format!("{}-{}", year, date_str), "%Y-%m-%d")
-                {
-                    return vec![(
-                        md,
-                        DateDetail {
-                            description: detail.description.clone(),
-                            color: detail.color.clone(),
-                        },
-                    )];
-                }
+            .flat_map(|(date_str, details)| details.iter().map(move |detail| (date_str, detail)))
+    }
 
-                vec![]
-            })
+    /// Events for ranges with full `YYYY-MM-DD` dates
+    pub fn parse_ranges(&self) -> Vec<Event> {
+        let anchors = self.anchors(None);
+        self.ranges
+            .iter()
+            .filter_map(|range| range.to_event(None, &anchors, &self.categories).ok())
+            .filter(|event| self.is_shown(event))
             .collect()
     }
 
-    pub fn parse_ranges(&self) -> Vec<DateRange> {
+    /// Events for all ranges, with yearly `MM-DD` ranges placed in `year`
+    pub fn parse_ranges_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
         self.ranges
             .iter()
-            .filter_map(|range| {
-                let start = NaiveDate::parse_from_str(&range.start, "%Y-%m-%d").ok()?;
-                let end = NaiveDate::parse_from_str(&range.end, "%Y-%m-%d").ok()?;
-                Some(DateRange {
-                    start,
-                    end,
-                    color: range.color.clone(),
-                    description: range.description.clone(),
-                })
-            })
+            .filter_map(|range| range.to_event(Some(year), &anchors, &self.categories).ok())
+            .filter(|event| self.is_shown(event))
             .collect()
     }
 
-    pub fn parse_ranges_for_year(&self, year: i32) -> Vec<DateRange> {
-        self.ranges
+    /// Week labels for `year`, if they are valid; `[week_labels]` come
+    /// before those of the terms
+    pub fn week_labels_for_year(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        let anchors = self.anchors(Some(year));
+        let mut labels = self
+            .week_labels
+            .as_ref()
+            .and_then(|labels| labels.to_labels(year, &anchors).ok())
+            .unwrap_or_default();
+        for (i, term) in self.terms.iter().enumerate() {
+            labels.extend(term.week_labels(i + 1, year, &anchors).unwrap_or_default());
+        }
+        labels
+    }
+
+    /// The stretches of every valid term in `year`
+    pub fn parse_terms_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.terms
             .iter()
-            .filter_map(|range| {
-                if let (Ok(start), Ok(end)) = (
-                    NaiveDate::parse_from_str(&range.start, "%Y-%m-%d"),
-                    NaiveDate::parse_from_str(&range.end, "%Y-%m-%d"),
-                ) {
-                    return Some(DateRange {
-                        start,
-                        end,
-                        color: range.color.clone(),
-                        description: range.description.clone(),
-                    });
-                }
-                if let (Ok(start), Ok(end)) = (
-                    NaiveDate::parse_from_str(&format!("{}-{}", year, &range.start), "%Y-%m-%d"),
-                    NaiveDate::parse_from_str(&format!("{}-{}", year, &range.end), "%Y-%m-%d"),
-                ) {
-                    return Some(DateRange {
-                        start,
-                        end,
-                        color: range.color.clone(),
-                        description: range.description.clone(),
-                    });
-                }
-
-                None
-            })
+            .filter_map(|term| term.to_events(year, &anchors).ok())
+            .flatten()
             .collect()
     }
+
+    /// The sprints touching `year`, if they are valid
+    pub fn parse_sprints_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.sprints
+            .iter()
+            .filter_map(|sprints| sprints.to_events(year, &anchors).ok())
+            .flatten()
+            .collect()
+    }
+
+    /// The paydays and pay periods touching `year`, if they are valid
+    pub fn parse_payperiods_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.payperiods
+            .iter()
+            .filter_map(|payperiods| payperiods.to_events(year, &anchors).ok())
+            .flatten()
+            .collect()
+    }
+
+    /// The shifts touching `year`, if they are valid
+    pub fn parse_shifts_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.shifts
+            .iter()
+            .filter_map(|shifts| shifts.to_events(year, &anchors).ok())
+            .flatten()
+            .collect()
+    }
+
+    /// Occurrences of the weekly events in `year`, skipping invalid entries
+    pub fn parse_weekly_for_year(&self, year: i32) -> Vec<Event> {
+        let anchors = self.anchors(Some(year));
+        self.weekly
+            .iter()
+            .filter_map(|weekly| weekly.to_events(year, &anchors, &self.categories).ok())
+            .flatten()
+            .filter(|event| self.is_shown(event))
+            .collect()
+    }
+
+    /// Dates of the entries with an `id`, a range's being its start, with
+    /// yearly ones placed in `year`. Entries defined relative to each other
+    /// resolve in dependency order; unknown or circular references are left
+    /// out.
+    pub fn anchors(&self, year: Option<i32>) -> Anchors {
+        let mut anchors = Anchors::new();
+        loop {
+            let resolved = anchors.len();
+            for (date_str, detail) in self.date_entries() {
+                let Some(id) = detail.id.as_ref().filter(|id| !anchors.contains_key(*id)) else {
+                    continue;
+                };
+                let date = detail
+                    .expr(date_str, year)
+                    .and_then(|expr| resolve_date(&expr, date_str, year, &anchors));
+                if let Ok(date) = date {
+                    anchors.insert(id.clone(), date);
+                }
+            }
+            for range in &self.ranges {
+                let Some(id) = range.id.as_ref().filter(|id| !anchors.contains_key(*id)) else {
+                    continue;
+                };
+                let date = parse_date(&range.start)
+                    .and_then(|expr| resolve_date(&expr, &range.start, year, &anchors));
+                if let Ok(date) = date {
+                    anchors.insert(id.clone(), date);
+                }
+            }
+            if anchors.len() == resolved {
+                return anchors;
+            }
+        }
+    }
+
+    /// Whether the event's category, if any, is not hidden
+    fn is_shown(&self, event: &Event) -> bool {
+        event
+            .category
+            .as_ref()
+            .and_then(|tag| self.categories.get(tag))
+            .is_none_or(|category| !category.hidden)
+    }
+
+    /// The `non_working` days in `year` as (first, last) pairs, skipping
+    /// invalid entries
+    pub fn non_working_for_year(&self, year: i32) -> Vec<(NaiveDate, NaiveDate)> {
+        self.day_spans(&self.non_working, year)
+    }
+
+    /// The `working_weekends` days in `year` as (first, last) pairs,
+    /// skipping invalid entries
+    pub fn working_weekends_for_year(&self, year: i32) -> Vec<(NaiveDate, NaiveDate)> {
+        self.day_spans(&self.working_weekends, year)
+    }
+
+    fn day_spans(&self, entries: &[String], year: i32) -> Vec<(NaiveDate, NaiveDate)> {
+        let anchors = self.anchors(Some(year));
+        entries
+            .iter()
+            .filter_map(|entry| parse_day_span(entry, year, &anchors).ok())
+            .collect()
+    }
+
+    /// Weekly busy blocks, skipping invalid ones, which loading the config
+    /// warns about
+    pub fn parse_availability(&self) -> Vec<AvailabilityBlock> {
+        self.availability
+            .iter()
+            .filter_map(|block| block.to_block().ok())
+            .collect()
+    }
+
+    /// Every date and range event for `year`; dates listed under `[dates]`
+    /// replace the weekly occurrences on their day
+    pub fn events_for_year(&self, year: i32) -> Vec<Event> {
+        let dates = self.parse_dates_for_year(year);
+        let mut events = self.parse_ranges_for_year(year);
+        events.extend(self.parse_sprints_for_year(year));
+        events.extend(self.parse_payperiods_for_year(year));
+        events.extend(self.parse_shifts_for_year(year));
+        events.extend(self.parse_terms_for_year(year));
+        events.extend(
+            self.parse_weekly_for_year(year)
+                .into_iter()
+                .filter(|weekly| dates.iter().all(|date| date.start != weekly.start)),
+        );
+        events.extend(dates);
+        events
+    }
+
+    /// Every event for each of `years`; fully dated entries appear once
+    pub fn events_for_years(&self, years: RangeInclusive<i32>) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+        for year in years {
+            for event in self.events_for_year(year) {
+                if !events.contains(&event) {
+                    events.push(event);
+                }
+            }
+        }
+        events
+    }
+
+    /// Files matched by the `include` entries, relative to `dir` after
+    /// expanding `${VAR}` references. Plain paths must exist; globs may match
+    /// nothing.
+    pub fn include_paths(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        resolve_paths(&self.include, dir, "include")
+    }
+
+    /// Collections matched by the `vdir` entries, resolved like `include`
+    pub fn vdir_paths(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let paths = resolve_paths(&self.vdir, dir, "vdir")?;
+        Ok(paths.into_iter().filter(|path| path.is_dir()).collect())
+    }
+
+    /// Merge an included fragment; entries already present win on the same
+    /// date or category. Settings, `readonly` and `timezone` only apply from
+    /// the main file.
+    pub fn merge(&mut self, fragment: CalendarConfig) {
+        for (date_str, details) in fragment.dates {
+            self.dates.entry(date_str).or_insert(details);
+        }
+        for (tag, category) in fragment.categories {
+            self.categories.entry(tag).or_insert(category);
+        }
+        self.ranges.extend(fragment.ranges);
+        self.weekly.extend(fragment.weekly);
+        self.availability.extend(fragment.availability);
+        self.non_working.extend(fragment.non_working);
+        self.working_weekends.extend(fragment.working_weekends);
+        self.sprints = self.sprints.take().or(fragment.sprints);
+        self.payperiods = self.payperiods.take().or(fragment.payperiods);
+        self.shifts = self.shifts.take().or(fragment.shifts);
+        self.week_labels = self.week_labels.take().or(fragment.week_labels);
+        self.terms.extend(fragment.terms);
+        self.sources.extend(fragment.sources);
+        self.google.extend(fragment.google);
+        self.holidays = self.holidays.take().or(fragment.holidays);
+        self.warnings.extend(fragment.warnings);
+    }
+
+    /// A commented example config for `year`, optionally seeded with dates
+    /// such as national holidays
+    pub fn starter(year: i32, seed: &[(NaiveDate, &str)]) -> String {
+        let mut output = format!(
+            r#"# compact-calendar-cli configuration
+#
+# Dates are "YYYY-MM-DD", or "MM-DD" to repeat every year.
+# Colors: orange, yellow, green, blue, purple, red, cyan, gray, and their
+# light_ variants (light_blue, ...).
+
+# A colored range of days
+[[ranges]]
+start = "{year}-07-01"
+end = "{year}-07-14"
+color = "blue"
+description = "Summer Vacation"
+
+# A yearly range
+[[ranges]]
+start = "12-24"
+end = "12-31"
+color = "cyan"
+description = "Holiday Break"
+
+# A single day; the color is optional
+[dates."{year}-03-15"]
+description = "Project Deadline"
+color = "orange"
+
+# A yearly date
+[dates."02-14"]
+description = "Valentine's Day"
+"#
+        );
+
+        if !seed.is_empty() {
+            output.push_str(&format!("\n# Holidays {}\n", year));
+        }
+        for (date, description) in seed {
+            output.push('\n');
+            writeln!(output, "[dates.\"{}\"]", date.format("%Y-%m-%d")).unwrap();
+            push_value(&mut output, "description", description);
+            push_value(&mut output, "color", "red");
+        }
+        output
+    }
+
+    /// Serialize to hand-editable TOML, with ranges and dates sorted and
+    /// grouped under `# March` style month comments
+    pub fn to_commented_toml(&self) -> String {
+        let mut output = String::new();
+        if self.readonly {
+            output.push_str("readonly = true\n\n");
+        }
+        if let Some(timezone) = &self.timezone {
+            push_value(&mut output, "timezone", timezone);
+            output.push('\n');
+        }
+        if !self.include.is_empty() {
+            let include: toml::value::Array = self
+                .include
+                .iter()
+                .cloned()
+                .map(toml::Value::String)
+                .collect();
+            writeln!(output, "include = {}\n", toml::Value::Array(include)).unwrap();
+        }
+        if !self.vdir.is_empty() {
+            let vdir: toml::value::Array =
+                self.vdir.iter().cloned().map(toml::Value::String).collect();
+            writeln!(output, "vdir = {}\n", toml::Value::Array(vdir)).unwrap();
+        }
+        for (key, entries) in [
+            ("non_working", &self.non_working),
+            ("working_weekends", &self.working_weekends),
+        ] {
+            if !entries.is_empty() {
+                let entries: toml::value::Array =
+                    entries.iter().cloned().map(toml::Value::String).collect();
+                writeln!(output, "{} = {}\n", key, toml::Value::Array(entries)).unwrap();
+            }
+        }
+        let settings = self.settings.to_toml_lines();
+        if !settings.is_empty() {
+            output.push_str("[settings]\n");
+            for line in settings {
+                writeln!(output, "{}", line).unwrap();
+            }
+            output.push('\n');
+        }
+        let style = self.style.to_toml_lines();
+        if !style.is_empty() {
+            output.push_str("[style]\n");
+            for line in style {
+                writeln!(output, "{}", line).unwrap();
+            }
+            output.push('\n');
+        }
+        if let Some(holidays) = &self.holidays {
+            output.push_str("[holidays]\n");
+            push_value(&mut output, "country", &holidays.country);
+            for (key, value) in [
+                ("source", &holidays.source),
+                ("url", &holidays.url),
+                ("color", &holidays.color),
+                ("ttl", &holidays.ttl),
+            ] {
+                if let Some(value) = value {
+                    push_value(&mut output, key, value);
+                }
+            }
+            output.push('\n');
+        }
+        if let Some(daylight) = &self.daylight {
+            output.push_str("[daylight]\n");
+            writeln!(output, "latitude = {}", daylight.latitude).unwrap();
+            writeln!(output, "longitude = {}", daylight.longitude).unwrap();
+            for (key, times) in [("sunrise", &daylight.sunrise), ("sunset", &daylight.sunset)] {
+                if !times.is_empty() {
+                    let times: toml::value::Array =
+                        times.iter().cloned().map(toml::Value::String).collect();
+                    writeln!(output, "{} = {}", key, toml::Value::Array(times)).unwrap();
+                }
+            }
+            output.push('\n');
+        }
+        if let Some(weather) = &self.weather {
+            output.push_str("[weather]\n");
+            for (key, value) in [
+                ("latitude", weather.latitude),
+                ("longitude", weather.longitude),
+            ] {
+                if let Some(value) = value {
+                    writeln!(output, "{} = {}", key, value).unwrap();
+                }
+            }
+            if let Some(days) = weather.days {
+                writeln!(output, "days = {}", days).unwrap();
+            }
+            for (key, value) in [("url", &weather.url), ("ttl", &weather.ttl)] {
+                if let Some(value) = value {
+                    push_value(&mut output, key, value);
+                }
+            }
+            output.push('\n');
+        }
+        let mut categories: Vec<(&String, &RawCategory)> = self.categories.iter().collect();
+        categories.sort_by_key(|(tag, _)| *tag);
+        for (tag, category) in categories {
+            writeln!(output, "[categories.{}]", toml_key(tag)).unwrap();
+            if let Some(color) = &category.color {
+                push_value(&mut output, "color", color);
+            }
+            if category.hidden {
+                output.push_str("hidden = true\n");
+            }
+            output.push('\n');
+        }
+
+        if let Some(sprints) = &self.sprints {
+            output.push_str("[sprints]\n");
+            push_value(&mut output, "start", &sprints.start);
+            if let Some(weeks) = sprints.weeks {
+                writeln!(output, "weeks = {}", weeks).unwrap();
+            }
+            if let Some(name) = &sprints.name {
+                push_value(&mut output, "name", name);
+            }
+            if let Some(first) = sprints.first {
+                writeln!(output, "first = {}", first).unwrap();
+            }
+            if !sprints.colors.is_empty() {
+                let colors: toml::value::Array = sprints
+                    .colors
+                    .iter()
+                    .cloned()
+                    .map(toml::Value::String)
+                    .collect();
+                writeln!(output, "colors = {}", toml::Value::Array(colors)).unwrap();
+            }
+            if let Some(layer) = sprints.layer {
+                writeln!(output, "layer = {}", layer).unwrap();
+            }
+            output.push('\n');
+        }
+
+        if let Some(payperiods) = &self.payperiods {
+            output.push_str("[payperiods]\n");
+            push_value(&mut output, "payday", &payperiods.payday);
+            push_value(&mut output, "interval", &payperiods.interval);
+            if let Some(name) = &payperiods.name {
+                push_value(&mut output, "name", name);
+            }
+            if let Some(color) = &payperiods.color {
+                push_value(&mut output, "color", color);
+            }
+            if payperiods.shade {
+                output.push_str("shade = true\n");
+            }
+            if !payperiods.colors.is_empty() {
+                let colors: toml::value::Array = payperiods
+                    .colors
+                    .iter()
+                    .cloned()
+                    .map(toml::Value::String)
+                    .collect();
+                writeln!(output, "colors = {}", toml::Value::Array(colors)).unwrap();
+            }
+            output.push('\n');
+        }
+
+        if let Some(shifts) = &self.shifts {
+            output.push_str("[shifts]\n");
+            push_value(&mut output, "start", &shifts.start);
+            push_value(&mut output, "pattern", &shifts.pattern);
+            output.push('\n');
+            let mut codes: Vec<(&String, &RawShiftCode)> = shifts.codes.iter().collect();
+            codes.sort_by_key(|(code, _)| *code);
+            for (code, shift) in codes {
+                writeln!(output, "[shifts.codes.{}]", toml_key(code)).unwrap();
+                push_value(&mut output, "name", &shift.name);
+                push_value(&mut output, "color", &shift.color);
+                output.push('\n');
+            }
+        }
+
+        for source in &self.sources {
+            output.push_str("[[sources]]\n");
+            push_value(&mut output, "url", &source.url);
+            for (key, value) in [
+                ("username", &source.username),
+                ("password", &source.password),
+                ("color", &source.color),
+                ("ttl", &source.ttl),
+            ] {
+                if let Some(value) = value {
+                    push_value(&mut output, key, value);
+                }
+            }
+            output.push('\n');
+        }
+
+        for calendar in &self.google {
+            output.push_str("[[google]]\n");
+            push_value(&mut output, "calendar", &calendar.calendar);
+            for (key, value) in [
+                ("api_key", &calendar.api_key),
+                ("client_id", &calendar.client_id),
+                ("client_secret", &calendar.client_secret),
+                ("color", &calendar.color),
+                ("ttl", &calendar.ttl),
+            ] {
+                if let Some(value) = value {
+                    push_value(&mut output, key, value);
+                }
+            }
+            output.push('\n');
+        }
+
+        for term in &self.terms {
+            output.push_str("[[terms]]\n");
+            push_value(&mut output, "name", &term.name);
+            push_value(&mut output, "start", &term.start);
+            push_value(&mut output, "end", &term.end);
+            if let Some(color) = &term.color {
+                push_value(&mut output, "color", color);
+            }
+            if !term.breaks.is_empty() {
+                let breaks: toml::value::Array = term
+                    .breaks
+                    .iter()
+                    .cloned()
+                    .map(toml::Value::String)
+                    .collect();
+                writeln!(output, "breaks = {}", toml::Value::Array(breaks)).unwrap();
+            }
+            if let Some(week_label) = &term.week_label {
+                push_value(&mut output, "week_label", week_label);
+            }
+            output.push('\n');
+        }
+
+        if let Some(labels) = &self.week_labels {
+            output.push_str("[week_labels]\n");
+            if let Some(format) = &labels.format {
+                push_value(&mut output, "format", format);
+            }
+            if let Some(start) = &labels.start {
+                push_value(&mut output, "start", start);
+            }
+            if let Some(every) = labels.every {
+                writeln!(output, "every = {}", every).unwrap();
+            }
+            if let Some(first) = labels.first {
+                writeln!(output, "first = {}", first).unwrap();
+            }
+            if !labels.weeks.is_empty() {
+                output.push_str("\n[week_labels.weeks]\n");
+                let mut weeks: Vec<(&String, &String)> = labels.weeks.iter().collect();
+                weeks.sort_by_key(|(date_str, _)| sort_key(date_str));
+                for (date_str, label) in weeks {
+                    writeln!(output, "{} = {}", toml_string(date_str), toml_string(label)).unwrap();
+                }
+            }
+            output.push('\n');
+        }
+
+        let mut ranges: Vec<&RawDateRange> = self.ranges.iter().collect();
+        ranges.sort_by_key(|range| sort_key(&range.start));
+        if !ranges.is_empty() {
+            output.push_str("# Date ranges\n");
+        }
+        let mut current_month = None;
+        for range in ranges {
+            push_month_comment(&mut output, &mut current_month, &range.start);
+            output.push_str("[[ranges]]\n");
+            push_value(&mut output, "start", &range.start);
+            push_value(&mut output, "end", &range.end);
+            if let Some(color) = &range.color {
+                push_value(&mut output, "color", color);
+            }
+            if let Some(tag) = &range.tag {
+                push_value(&mut output, "tag", tag);
+            }
+            if let Some(description) = &range.description {
+                push_value(&mut output, "description", description);
+            }
+            if let Some(kind) = &range.kind {
+                push_value(&mut output, "kind", kind);
+            }
+            if let Some(timezone) = &range.timezone {
+                push_value(&mut output, "timezone", timezone);
+            }
+            push_metadata(&mut output, &range.id, &range.tags, &range.owner);
+            if let Some(cost) = range.cost {
+                writeln!(output, "cost = {}", cost).unwrap();
+            }
+            if let Some(priority) = range.priority {
+                writeln!(output, "priority = {}", priority).unwrap();
+            }
+            if let Some(layer) = range.layer {
+                writeln!(output, "layer = {}", layer).unwrap();
+            }
+            if let Some(status) = &range.status {
+                push_value(&mut output, "status", status);
+            }
+            if let Some(gradient) = &range.gradient {
+                push_value(&mut output, "gradient", gradient);
+            }
+            if let Some(icon) = &range.icon {
+                push_value(&mut output, "icon", icon);
+            }
+        }
+
+        let mut dates: Vec<(&String, &Vec<RawDateDetail>)> = self.dates.iter().collect();
+        dates.sort_by_key(|(date_str, _)| sort_key(date_str));
+        if !dates.is_empty() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("# Dates\n");
+        }
+        let mut current_month = None;
+        for (date_str, details) in dates {
+            push_month_comment(&mut output, &mut current_month, date_str);
+            if let [detail] = &details[..] {
+                writeln!(output, "[dates.{}]", toml_string(date_str)).unwrap();
+                push_detail(&mut output, detail);
+                continue;
+            }
+            for (i, detail) in details.iter().enumerate() {
+                if i > 0 {
+                    output.push('\n');
+                }
+                writeln!(output, "[[dates.{}]]", toml_string(date_str)).unwrap();
+                push_detail(&mut output, detail);
+            }
+        }
+
+        if !self.weekly.is_empty() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("# Weekly events\n");
+        }
+        for weekly in &self.weekly {
+            output.push_str("\n[[weekly]]\n");
+            push_value(&mut output, "every", &weekly.every);
+            if let Some(from) = &weekly.from {
+                push_value(&mut output, "from", from);
+            }
+            if let Some(until) = &weekly.until {
+                push_value(&mut output, "until", until);
+            }
+            push_detail(&mut output, &weekly.detail);
+        }
+
+        if !self.availability.is_empty() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("# Weekly busy blocks\n");
+        }
+        for block in &self.availability {
+            output.push_str("\n[[availability]]\n");
+            push_value(&mut output, "days", &block.days);
+            push_value(&mut output, "start", &block.start);
+            push_value(&mut output, "end", &block.end);
+            if let Some(description) = &block.description {
+                push_value(&mut output, "description", description);
+            }
+        }
+
+        output
+    }
+}
+
+/// Year (absent for yearly entries), month and day of a config date
+pub(crate) type MonthDay = (Option<i32>, u32, u32);
+
+/// Parse either a full `YYYY-MM-DD` date or a yearly `MM-DD` date
+pub(crate) fn parse_month_day(date_str: &str) -> Option<MonthDay> {
+    if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+        return Some((Some(date.year()), date.month(), date.day()));
+    }
+    // Leap year, so "02-29" is accepted
+    NaiveDate::parse_from_str(&format!("2000-{}", date_str), "%Y-%m-%d")
+        .ok()
+        .map(|date| (None, date.month(), date.day()))
+}
+
+fn parse_date(date_str: &str) -> Result<DateExpr> {
+    DateExpr::parse(date_str).map_err(Error::InvalidEntry)
+}
+
+/// First and last day of a stretch of days
+pub type DaySpan = (NaiveDate, NaiveDate);
+
+/// A `non_working` or `working_weekends` entry in `year`: one date, or
+/// Paths matched by `entries` of the `key` list, relative to `dir` after
+/// expanding `${VAR}` references. Plain paths must exist; globs may match
+/// nothing.
+fn resolve_paths(entries: &[String], dir: &Path, key: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for include in entries {
+        let include = &paths::expand_env(include)?;
+        let pattern = dir.join(include);
+        if !include.contains(['*', '?', '[']) {
+            if !pattern.exists() {
+                return Err(Error::ConfigNotFound(pattern));
+            }
+            paths.push(pattern);
+            continue;
+        }
+
+        let pattern_str = pattern.to_string_lossy();
+        let matches = glob::glob(&pattern_str).map_err(|e| {
+            Error::InvalidEntry(format!("invalid {} pattern '{}': {}", key, include, e))
+        })?;
+        let mut matched: Vec<PathBuf> = matches.filter_map(|entry| entry.ok()).collect();
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// "start..end" with both days included
+pub fn parse_day_span(entry: &str, year: i32, anchors: &Anchors) -> Result<DaySpan> {
+    let (start, end) = entry.split_once("..").unwrap_or((entry, entry));
+    let date = |date_str: &str| {
+        let date_str = date_str.trim();
+        resolve_date(&parse_date(date_str)?, date_str, Some(year), anchors)
+    };
+    let (start, end) = (date(start)?, date(end)?);
+    if end < start {
+        return Err(Error::InvalidEntry("ends before it starts".to_string()));
+    }
+    Ok((start, end))
+}
+
+/// The date an expression names; yearly ones need a `year`, and ones
+/// relative to another entry need its date in `anchors`
+fn resolve_date(
+    expr: &DateExpr,
+    date_str: &str,
+    year: Option<i32>,
+    anchors: &Anchors,
+) -> Result<NaiveDate> {
+    if let Some(name) = expr.anchor() {
+        if !anchors.contains_key(name) {
+            return Err(Error::InvalidEntry(format!(
+                "unknown or circular reference to id '{}'",
+                name
+            )));
+        }
+    }
+    match (expr, year) {
+        (DateExpr::Fixed(date), _) => Ok(*date),
+        (expr, None) if !expr.is_yearly() => expr
+            .resolve(0, anchors)
+            .ok_or_else(|| invalid_date(date_str)),
+        (expr, Some(year)) => expr.resolve(year, anchors).ok_or_else(|| {
+            Error::InvalidEntry(format!("'{}' does not fall in {}", date_str, year))
+        }),
+        (_, None) => Err(invalid_date(date_str)),
+    }
+}
+
+/// An entry's own color, or else the color of its category
+fn category_color<'a>(
+    color: &'a Option<String>,
+    tag: &Option<String>,
+    categories: &'a HashMap<String, RawCategory>,
+) -> Option<&'a String> {
+    color.as_ref().or_else(|| {
+        tag.as_ref()
+            .and_then(|tag| categories.get(tag))
+            .and_then(|category| category.color.as_ref())
+    })
+}
+
+fn invalid_date(date_str: &str) -> Error {
+    Error::InvalidEntry(format!("invalid date '{}'", date_str))
+}
+
+/// Yearly entries sort before dated ones, unparseable entries last
+fn sort_key(date_str: &str) -> (bool, Option<MonthDay>, String) {
+    let parsed = parse_month_day(date_str);
+    (parsed.is_none(), parsed, date_str.to_string())
+}
+
+fn push_month_comment(output: &mut String, current: &mut Option<Option<u32>>, date_str: &str) {
+    let month = parse_month_day(date_str).map(|(_, month, _)| month);
+    if *current != Some(month) {
+        let name = match month {
+            Some(month) => MonthInfo::from_month(month).name,
+            None => "Other",
+        };
+        writeln!(output, "\n# {}", name).unwrap();
+        *current = Some(month);
+    } else {
+        output.push('\n');
+    }
+}
+
+fn push_value(output: &mut String, key: &str, value: &str) {
+    writeln!(output, "{} = {}", key, toml_string(value)).unwrap();
+}
+
+/// The fields of a date entry, after its table header
+fn push_detail(output: &mut String, detail: &RawDateDetail) {
+    push_value(output, "description", &detail.description);
+    if let Some(color) = &detail.color {
+        push_value(output, "color", color);
+    }
+    if let Some(tag) = &detail.tag {
+        push_value(output, "tag", tag);
+    }
+    if let Some(date) = &detail.date {
+        push_value(output, "date", date);
+    }
+    if let Some(anniversary) = &detail.anniversary {
+        push_value(output, "anniversary", anniversary);
+    }
+    if let Some(leap_day) = &detail.leap_day {
+        push_value(output, "leap_day", leap_day);
+    }
+    if let Some(nth) = &detail.nth_weekday {
+        writeln!(
+            output,
+            "nth_weekday = {{ n = {}, weekday = {}, month = {} }}",
+            nth.n,
+            toml_string(&nth.weekday),
+            nth.month
+        )
+        .unwrap();
+    }
+    if let Some(kind) = &detail.kind {
+        push_value(output, "kind", kind);
+    }
+    push_metadata(output, &detail.id, &detail.tags, &detail.owner);
+    if let Some(cost) = detail.cost {
+        writeln!(output, "cost = {}", cost).unwrap();
+    }
+    if let Some(priority) = detail.priority {
+        writeln!(output, "priority = {}", priority).unwrap();
+    }
+    if let Some(icon) = &detail.icon {
+        push_value(output, "icon", icon);
+    }
+}
+
+fn push_metadata(
+    output: &mut String,
+    id: &Option<String>,
+    tags: &[String],
+    owner: &Option<String>,
+) {
+    if let Some(id) = id {
+        push_value(output, "id", id);
+    }
+    if !tags.is_empty() {
+        let tags: toml::value::Array = tags.iter().cloned().map(toml::Value::String).collect();
+        writeln!(output, "tags = {}", toml::Value::Array(tags)).unwrap();
+    }
+    if let Some(owner) = owner {
+        push_value(output, "owner", owner);
+    }
+}
+
+/// A bare key when possible, `[categories."day off"]` otherwise
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+/// Read each `[dates]` value as either one entry or a list of them, keeping
+/// the field errors of the entries themselves
+fn one_or_more_details<'de, D>(
+    deserializer: D,
+) -> std::result::Result<HashMap<String, Vec<RawDateDetail>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct Details(Vec<RawDateDetail>);
+
+    impl<'de> Deserialize<'de> for Details {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            deserializer.deserialize_any(DetailsVisitor)
+        }
+    }
+
+    struct DetailsVisitor;
+
+    impl<'de> Visitor<'de> for DetailsVisitor {
+        type Value = Details;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a date entry or a list of date entries")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Details, A::Error> {
+            let detail = RawDateDetail::deserialize(MapAccessDeserializer::new(map))?;
+            Ok(Details(vec![detail]))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> std::result::Result<Details, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq)).map(Details)
+        }
+    }
+
+    let dates = HashMap::<String, Details>::deserialize(deserializer)?;
+    Ok(dates
+        .into_iter()
+        .map(|(date_str, details)| (date_str, details.0))
+        .collect())
 }
@@ -0,0 +1,108 @@
+//! Overlapping colored events, of which only one color can show on a day:
+//! a date's own color beats any range, the first of several colored entries
+//! on a date wins, a range on a higher `layer` beats lower ones, and on the
+//! same layer the range given first wins.
+
+use crate::models::{Calendar, Event, Status};
+use chrono::NaiveDate;
+use std::fmt;
+
+/// Two colored events sharing the days from `start` to `end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<'a> {
+    /// The event whose color is shown on those days
+    pub shown: &'a Event,
+    /// The event whose color is hidden there
+    pub hidden: &'a Event,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl fmt::Display for Conflict<'_> {
+    /// "2024-07-10 to 2024-07-12: Conference (purple) is hidden by Vacation
+    /// (blue)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.start.format("%Y-%m-%d"))?;
+        if self.end != self.start {
+            write!(f, " to {}", self.end.format("%Y-%m-%d"))?;
+        }
+        write!(
+            f,
+            ": {} is hidden by {}",
+            label(self.hidden),
+            label(self.shown)
+        )
+    }
+}
+
+impl Calendar {
+    /// Every pair of overlapping colored ranges, and every colored date
+    /// inside a colored range or sharing its day with another, in date order
+    pub fn conflicts(&self) -> Vec<Conflict<'_>> {
+        let ranges: Vec<&Event> = self
+            .ranges
+            .iter()
+            .filter(|range| colors_days(range))
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (i, first) in ranges.iter().enumerate() {
+            for second in &ranges[i + 1..] {
+                let (shown, hidden) = if second.layer > first.layer {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                let start = shown.start.max(hidden.start);
+                let end = shown.end.min(hidden.end);
+                if start <= end {
+                    conflicts.push(Conflict {
+                        shown,
+                        hidden,
+                        start,
+                        end,
+                    });
+                }
+            }
+        }
+        for (&date, details) in &self.details {
+            let colored: Vec<&Event> = details
+                .iter()
+                .filter(|detail| colors_days(detail))
+                .collect();
+            let Some((&shown, others)) = colored.split_first() else {
+                continue;
+            };
+            let ranges = self.ranges_on(date).filter(|range| colors_days(range));
+            for hidden in others.iter().copied().chain(ranges) {
+                conflicts.push(Conflict {
+                    shown,
+                    hidden,
+                    start: date,
+                    end: date,
+                });
+            }
+        }
+        conflicts.sort_by_key(|conflict| (conflict.start, conflict.end));
+        conflicts
+    }
+}
+
+/// Whether the event colors the days it covers; rejected time off does not
+fn colors_days(event: &Event) -> bool {
+    event.color.is_some() && event.status != Some(Status::Rejected)
+}
+
+/// The description, or the dates when there is none, then the color
+fn label(event: &Event) -> String {
+    let name = match &event.description {
+        Some(description) => description.clone(),
+        None if event.is_range() => format!(
+            "{} to {}",
+            event.start.format("%Y-%m-%d"),
+            event.end.format("%Y-%m-%d")
+        ),
+        None => event.start.format("%Y-%m-%d").to_string(),
+    };
+    format!("{} ({})", name, event.color.as_deref().unwrap_or_default())
+}
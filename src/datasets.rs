@@ -0,0 +1,185 @@
+//! Holiday datasets refreshed by `update-data`, in
+//! `$XDG_DATA_HOME/compact-calendar/holidays`, so the holidays of years the
+//! built-in rules get wrong can be fixed without a new release. A manifest
+//! lists a file per country with its SHA-256; the manifest itself is only
+//! trusted when its own SHA-256 is the one pinned in the binary (or given
+//! with `--sha256`), and a file only installed when it matches. An installed
+//! dataset wins over the built-in holidays for the years it covers.
+
+use crate::holidays::{self, Country};
+use crate::paths;
+use crate::sources::fetch_url;
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// Manifest of the datasets published with the project, `data/manifest.json`
+pub const MANIFEST_URL: &str =
+    "https://raw.githubusercontent.com/wcampbell0x2a/compact-calendar-cli/main/data/manifest.json";
+
+/// SHA-256 of the published manifest, updated along with `data/`
+pub const MANIFEST_SHA256: &str =
+    "d311e9939b978c26e4ff164ac0ce4d47645ec4e31bb2f02dfad923a15ae269fc";
+
+/// A list of datasets, like
+/// `{"holidays": [{"country": "us", "url": "holidays-us.json", "sha256": "..."}]}`,
+/// with URLs relative to the manifest's own
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    holidays: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    country: String,
+    url: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Datasets {
+    dir: PathBuf,
+}
+
+impl Datasets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The user's data directory. Without `XDG_DATA_HOME` or `HOME` there is
+    /// none, rather than a shared one anyone could plant holidays in.
+    pub fn user() -> Result<Self, String> {
+        paths::data_dir()
+            .map(Self::new)
+            .ok_or_else(|| "no data directory; set XDG_DATA_HOME or HOME".to_string())
+    }
+
+    /// File holding the holidays of `country`
+    pub fn path(&self, country: Country) -> PathBuf {
+        self.dir
+            .join("holidays")
+            .join(format!("{}.json", country.code()))
+    }
+
+    /// The holidays of `country` in `year` from its installed dataset, when
+    /// the dataset covers that year
+    pub fn holidays(&self, country: Country, year: i32) -> Option<Vec<(NaiveDate, String)>> {
+        let contents = fs::read_to_string(self.path(country)).ok()?;
+        let days: Vec<(NaiveDate, String)> = holidays::from_api_response(&contents)
+            .ok()?
+            .into_iter()
+            .filter(|(date, _)| date.year() == year)
+            .collect();
+        (!days.is_empty()).then_some(days)
+    }
+
+    /// Fetch the manifest at `manifest_url`, whose SHA-256 must be
+    /// `manifest_sha256`, and install each dataset it lists whose hash
+    /// matches, leaving the installed copy of any other in place. Each line
+    /// of the result reports a dataset, installed or why not.
+    pub fn update(
+        &self,
+        manifest_url: &str,
+        manifest_sha256: &str,
+    ) -> Result<Vec<Result<String, String>>, String> {
+        let fetch = |url: &str| fetch_url(url, &[], []).map_err(|e| e.to_string());
+        let contents = fetch(manifest_url)?;
+        verify(&contents, manifest_sha256)
+            .map_err(|e| format!("manifest at {}: {}", manifest_url, e))?;
+        let manifest: Manifest = serde_json::from_str(&contents)
+            .map_err(|e| format!("invalid manifest at {}: {}", manifest_url, e))?;
+        Ok(manifest
+            .holidays
+            .iter()
+            .map(|entry| {
+                let name = |e: String| format!("holidays for {}: {}", entry.country, e);
+                let country = Country::parse(&entry.country).map_err(name)?;
+                let contents = fetch(&relative_to(manifest_url, &entry.url)).map_err(name)?;
+                self.install(country, &contents, &entry.sha256)
+                    .map_err(name)
+            })
+            .collect())
+    }
+
+    /// Install `contents` as the dataset of `country` if its SHA-256 is
+    /// `sha256`, describing what it holds
+    pub fn install(
+        &self,
+        country: Country,
+        contents: &str,
+        sha256: &str,
+    ) -> Result<String, String> {
+        verify(contents, sha256)?;
+        let days = holidays::from_api_response(contents)?;
+        let (Some(first), Some(last)) = (days.first(), days.last()) else {
+            return Err("the dataset has no holidays".to_string());
+        };
+        let path = self.path(country);
+        let partial = path.with_extension("new");
+        let write_error = |e: std::io::Error| format!("could not write {}: {}", path.display(), e);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::write(&partial, contents).map_err(write_error)?;
+        fs::rename(&partial, &path).map_err(write_error)?;
+        Ok(format!(
+            "holidays for {}: {} days from {} to {}",
+            country.code(),
+            days.len(),
+            first.0.year(),
+            last.0.year()
+        ))
+    }
+}
+
+/// The holidays of `country` in `year` from the dataset installed in
+/// `datasets`, else built in
+pub fn holidays_or_builtin(
+    datasets: Option<&Datasets>,
+    country: Country,
+    year: i32,
+) -> Vec<(NaiveDate, String)> {
+    datasets
+        .and_then(|datasets| datasets.holidays(country, year))
+        .unwrap_or_else(|| {
+            country
+                .holidays(year)
+                .into_iter()
+                .map(|(date, name)| (date, name.to_string()))
+                .collect()
+        })
+}
+
+/// Fail unless the SHA-256 of `contents` is `sha256`
+fn verify(contents: &str, sha256: &str) -> Result<(), String> {
+    let actual = sha256_hex(contents.as_bytes());
+    if actual.eq_ignore_ascii_case(sha256.trim()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "SHA-256 {} doesn't match the expected {}",
+            actual, sha256
+        ))
+    }
+}
+
+/// `url` resolved against `base` when it isn't absolute
+fn relative_to(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+    match base.rfind('/') {
+        Some(end) => format!("{}/{}", &base[..end], url),
+        None => url.to_string(),
+    }
+}
+
+/// SHA-256 of `data` in lowercase hex
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,252 @@
+//! The dates and ranges added, removed or changed between two versions of a
+//! calendar, for `diff`, to review edits to a shared config.
+
+use crate::listing::{format_dates, DATES_WIDTH};
+use crate::models::{Calendar, CalendarChanges, Event};
+use crate::rendering::{Background, ColorPalette};
+use chrono::Datelike;
+use std::io::{self, Write};
+
+/// One difference between the two calendars
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    Added(Event),
+    Removed(Event),
+    /// The same date or range with other details, or the same event moved
+    Changed {
+        before: Box<Event>,
+        after: Box<Event>,
+    },
+}
+
+impl Difference {
+    /// The event as it is after the change, or as it was before a removal
+    pub fn event(&self) -> &Event {
+        match self {
+            Difference::Added(event) | Difference::Removed(event) => event,
+            Difference::Changed { after, .. } => after,
+        }
+    }
+
+    fn sign(&self) -> char {
+        match self {
+            Difference::Added(_) => '+',
+            Difference::Removed(_) => '-',
+            Difference::Changed { .. } => '~',
+        }
+    }
+
+    fn sign_color(&self) -> &'static str {
+        match self {
+            Difference::Added(_) => "green",
+            Difference::Removed(_) => "red",
+            Difference::Changed { .. } => "yellow",
+        }
+    }
+}
+
+/// Differences between two calendars of the same year, in date order
+pub struct ConfigDiff {
+    differences: Vec<Difference>,
+    changes: CalendarChanges,
+    palette: ColorPalette,
+}
+
+impl ConfigDiff {
+    /// What changed from `before` to `after`, within `after`'s year
+    pub fn new(before: &Calendar, after: &Calendar) -> Self {
+        let year = after.year;
+        let changes = after.changes_since(before);
+        let in_year = |events: &[Event]| -> Vec<Event> {
+            events
+                .iter()
+                .filter(|event| event.start.year() <= year && event.end.year() >= year)
+                .cloned()
+                .collect()
+        };
+        let mut added = in_year(&changes.events);
+        let removed = in_year(&changes.removed);
+
+        // An event removed and one added with the same dates, or else the same
+        // description, are the same event changed
+        let mut differences = Vec::new();
+        let removed = pair_changed(removed, &mut added, &mut differences, |a, b| {
+            a.start == b.start && a.end == b.end
+        });
+        let removed = pair_changed(removed, &mut added, &mut differences, |a, b| {
+            a.description.is_some() && a.description == b.description
+        });
+        differences.extend(added.into_iter().map(Difference::Added));
+        differences.extend(removed.into_iter().map(Difference::Removed));
+        differences.sort_by_key(|difference| {
+            let event = difference.event();
+            (event.start, event.end)
+        });
+
+        Self {
+            differences,
+            changes,
+            palette: ColorPalette::new()
+                .with_contrast(after.options.contrast, Background::detect())
+                .with_theme_colors(after.options.theme.colors.clone())
+                .with_dim_factor(after.options.dim_factor),
+        }
+    }
+
+    pub fn differences(&self) -> &[Difference] {
+        &self.differences
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// The changed days and events, for highlighting them in the calendar
+    /// with [`crate::rendering::CalendarRenderer::with_changes`]
+    pub fn changes(&self) -> &CalendarChanges {
+        &self.changes
+    }
+
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.palette = self.palette.with_colors(enabled);
+        self
+    }
+
+    pub fn render(&self) {
+        let mut out = io::stdout().lock();
+        self.render_to(&mut out)
+            .and_then(|_| out.flush())
+            .expect("failed printing to stdout");
+    }
+
+    /// Render without colors, for snapshots and plain-text consumers
+    pub fn render_to_string(&self) -> String {
+        let mut output = Vec::new();
+        ConfigDiff {
+            differences: self.differences.clone(),
+            changes: self.changes.clone(),
+            palette: ColorPalette::disabled(),
+        }
+        .render_to(&mut output)
+        .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("diff is valid UTF-8")
+    }
+
+    /// A line per difference, like `list`'s, with what changed in the
+    /// changed ones below it, and the count of each at the end
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.is_empty() {
+            return writeln!(w, "No differences");
+        }
+        for difference in &self.differences {
+            let event = difference.event();
+            let dates = format_dates(event);
+            let style = self.palette.annotation_style(Some(difference.sign_color()));
+            let line = format!(
+                "{}{} {}{}{}  {}",
+                style.render(),
+                difference.sign(),
+                dates,
+                style.render_reset(),
+                " ".repeat(DATES_WIDTH - dates.len()),
+                event.description.as_deref().unwrap_or("").trim_start(),
+            );
+            writeln!(w, "{}", line.trim_end())?;
+            if let Difference::Changed { before, after } = difference {
+                for (field, was, is) in changed_fields(before, after) {
+                    writeln!(w, "    {}: {} → {}", field, was, is)?;
+                }
+            }
+        }
+        let count = |sign| {
+            self.differences
+                .iter()
+                .filter(|difference| difference.sign() == sign)
+                .count()
+        };
+        writeln!(
+            w,
+            "{} added, {} removed, {} changed",
+            count('+'),
+            count('-'),
+            count('~')
+        )
+    }
+}
+
+/// Pair each of `removed` with an event of the same kind in `added` that
+/// `same` matches, as changed; returns the ones left without a pair
+fn pair_changed(
+    removed: Vec<Event>,
+    added: &mut Vec<Event>,
+    changed: &mut Vec<Difference>,
+    same: impl Fn(&Event, &Event) -> bool,
+) -> Vec<Event> {
+    let mut unpaired = Vec::new();
+    for before in removed {
+        match added
+            .iter()
+            .position(|after| after.kind == before.kind && same(&before, after))
+        {
+            Some(position) => changed.push(Difference::Changed {
+                before: Box::new(before),
+                after: Box::new(added.remove(position)),
+            }),
+            None => unpaired.push(before),
+        }
+    }
+    unpaired
+}
+
+/// The fields that differ between two versions of an event, as text
+fn changed_fields(before: &Event, after: &Event) -> Vec<(&'static str, String, String)> {
+    let text = |value: Option<&str>| value.unwrap_or("none").to_string();
+    let fields = [
+        ("dates", format_dates(before), format_dates(after)),
+        (
+            "description",
+            text(before.description.as_deref()),
+            text(after.description.as_deref()),
+        ),
+        (
+            "color",
+            text(before.color.as_deref()),
+            text(after.color.as_deref()),
+        ),
+        (
+            "status",
+            text(before.status.map(|status| status.name())),
+            text(after.status.map(|status| status.name())),
+        ),
+        (
+            "gradient",
+            text(before.gradient.as_ref().map(|gradient| gradient.name())),
+            text(after.gradient.as_ref().map(|gradient| gradient.name())),
+        ),
+        (
+            "icon",
+            text(before.icon.map(String::from).as_deref()),
+            text(after.icon.map(String::from).as_deref()),
+        ),
+        ("tags", before.tags.join(", "), after.tags.join(", ")),
+        (
+            "owner",
+            text(before.owner.as_deref()),
+            text(after.owner.as_deref()),
+        ),
+        (
+            "cost",
+            before
+                .cost
+                .map_or("none".to_string(), |cost| cost.to_string()),
+            after
+                .cost
+                .map_or("none".to_string(), |cost| cost.to_string()),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, was, is)| was != is)
+        .collect()
+}
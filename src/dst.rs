@@ -0,0 +1,114 @@
+//! The days the clocks change in the configured timezone, found by stepping
+//! through the year and narrowing down each change of UTC offset.
+
+use crate::models::{Event, EventKind};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{OffsetName, Tz};
+
+/// Below the config's own entries, like the other computed notes
+const PRIORITY: i32 = -1;
+
+/// A change of the clocks, e.g. from 02:00 CET to 03:00 CEST
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    /// Wall clock time just before and just after the change
+    pub before: NaiveDateTime,
+    pub after: NaiveDateTime,
+    /// Abbreviations of the offsets before and after, e.g. "CET"
+    pub from: String,
+    pub to: String,
+}
+
+impl Transition {
+    /// The day of the change, on the clock it was made by
+    pub fn date(&self) -> NaiveDate {
+        self.before.date()
+    }
+
+    pub fn is_forward(&self) -> bool {
+        self.after > self.before
+    }
+
+    /// Single-width marker shown next to the day
+    pub fn glyph(&self) -> char {
+        if self.is_forward() {
+            '»'
+        } else {
+            '«'
+        }
+    }
+
+    /// e.g. "Clocks go forward 1h (02:00 CET → 03:00 CEST)"
+    pub fn description(&self) -> String {
+        let shift = (self.after - self.before).abs();
+        let shift = match (shift.num_hours(), shift.num_minutes() % 60) {
+            (hours, 0) => format!("{}h", hours),
+            (0, minutes) => format!("{}m", minutes),
+            (hours, minutes) => format!("{}h{:02}", hours, minutes),
+        };
+        format!(
+            "Clocks go {} {} ({} {} → {} {})",
+            if self.is_forward() { "forward" } else { "back" },
+            shift,
+            self.before.format("%H:%M"),
+            self.from,
+            self.after.format("%H:%M"),
+            self.to
+        )
+    }
+
+    pub fn to_event(&self) -> Option<Event> {
+        Event::builder(EventKind::Date, self.date())
+            .description(self.description())
+            .priority(PRIORITY)
+            .build()
+            .ok()
+    }
+}
+
+/// Every clock change in `timezone` falling in `year`, in order
+pub fn transitions(timezone: Tz, year: i32) -> Vec<Transition> {
+    let offset = |instant: NaiveDateTime| {
+        timezone
+            .offset_from_utc_datetime(&instant)
+            .fix()
+            .local_minus_utc()
+    };
+    // A day either side, for changes near midnight on New Year's Eve
+    let start = NaiveDate::from_ymd_opt(year - 1, 12, 31)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap();
+    let end = start + Duration::days(367);
+
+    let mut transitions = Vec::new();
+    let mut hour = start;
+    while hour < end {
+        let next = hour + Duration::hours(1);
+        if offset(hour) != offset(next) {
+            // Changes fall on the minute, though not always on the hour
+            let mut instant = hour + Duration::minutes(1);
+            while offset(instant) == offset(hour) {
+                instant += Duration::minutes(1);
+            }
+            // Zones without abbreviations are named by their offset
+            let name = |instant: NaiveDateTime| {
+                let offset = timezone.offset_from_utc_datetime(&instant);
+                match offset.abbreviation() {
+                    Some(name) if !name.starts_with(['+', '-']) => name.to_string(),
+                    _ => format!("UTC{}", offset.fix()),
+                }
+            };
+            let transition = Transition {
+                before: instant + Duration::seconds(offset(hour) as i64),
+                after: instant + Duration::seconds(offset(instant) as i64),
+                from: name(hour),
+                to: name(instant),
+            };
+            if transition.date().year() == year {
+                transitions.push(transition);
+            }
+        }
+        hour = next;
+    }
+    transitions
+}
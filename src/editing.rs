@@ -0,0 +1,127 @@
+//! In-place edits of config files that keep existing comments and formatting.
+
+use crate::config::CalendarConfig;
+use crate::error::{Error, Result};
+use crate::rendering::ColorPalette;
+use chrono::NaiveDate;
+use toml_edit::{value, ArrayOfTables, DocumentMut, Item, Key, Table};
+
+/// Add a `[dates."<date>"]` entry to the config document, turning the date
+/// into a `[[dates."<date>"]]` list when it already has one
+pub fn add_date(
+    contents: &str,
+    date: &str,
+    description: &str,
+    color: Option<&str>,
+) -> Result<String> {
+    validate_date(date)?;
+    if let Some(color) = color {
+        validate_color(color)?;
+    }
+
+    let mut document = parse_document(contents)?;
+    let dates = document
+        .entry("dates")
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| Error::InvalidEntry("`dates` is not a table".to_string()))?;
+
+    let mut entry = Table::new();
+    entry.insert("description", value(description));
+    if let Some(color) = color {
+        entry.insert("color", value(color));
+    }
+    match dates.get_mut(date) {
+        Some(Item::ArrayOfTables(entries)) => entries.push(entry),
+        Some(item) => {
+            let first = std::mem::take(item)
+                .into_table()
+                .map_err(|_| Error::InvalidEntry(format!("`dates.\"{}\"` is not a table", date)))?;
+            let mut entries = ArrayOfTables::new();
+            entries.push(first);
+            entries.push(entry);
+            *item = Item::ArrayOfTables(entries);
+        }
+        None => {
+            // Quote the key to match the `[dates."MM-DD"]` style used in configs
+            let key: Key = format!("\"{}\"", date)
+                .parse()
+                .map_err(|e: toml_edit::TomlError| Error::InvalidEntry(e.to_string()))?;
+            dates.insert_formatted(&key, Item::Table(entry));
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Append a `[[ranges]]` entry to the config document
+pub fn add_range(
+    contents: &str,
+    start: &str,
+    end: &str,
+    color: &str,
+    description: Option<&str>,
+) -> Result<String> {
+    let start_date = validate_date(start)?;
+    let end_date = validate_date(end)?;
+    if end_date < start_date {
+        return Err(Error::InvalidEntry(format!(
+            "range end {} is before its start {}",
+            end, start
+        )));
+    }
+    validate_color(color)?;
+
+    let mut document = parse_document(contents)?;
+    let ranges = document
+        .entry("ranges")
+        .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| Error::InvalidEntry("`ranges` is not an array of tables".to_string()))?;
+
+    let mut entry = Table::new();
+    entry.insert("start", value(start));
+    entry.insert("end", value(end));
+    entry.insert("color", value(color));
+    if let Some(description) = description {
+        entry.insert("description", value(description));
+    }
+    ranges.push(entry);
+
+    Ok(document.to_string())
+}
+
+fn parse_document(contents: &str) -> Result<DocumentMut> {
+    // Validate against the calendar schema first for a located error
+    toml::from_str::<CalendarConfig>(contents)
+        .map_err(|e| Error::parse("<config>".into(), contents, e))?;
+    contents
+        .parse::<DocumentMut>()
+        .map_err(|e| Error::InvalidEntry(e.to_string()))
+}
+
+/// Accept a full `YYYY-MM-DD` date or a yearly `MM-DD` date, returning a
+/// date that orders correctly against others of the same form
+fn validate_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        // Leap year, so a yearly "02-29" is accepted
+        .or_else(|_| NaiveDate::parse_from_str(&format!("2000-{}", date), "%Y-%m-%d"))
+        .map_err(|_| {
+            Error::InvalidEntry(format!(
+                "invalid date '{}', expected YYYY-MM-DD or MM-DD",
+                date
+            ))
+        })
+}
+
+fn validate_color(color: &str) -> Result<()> {
+    if ColorPalette::get_color_value(color).is_some() {
+        Ok(())
+    } else {
+        Err(Error::InvalidEntry(format!("unknown color '{}'", color)))
+    }
+}
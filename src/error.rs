@@ -0,0 +1,122 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The config file does not exist
+    ConfigNotFound(PathBuf),
+    /// The config file exists but could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The config file is not valid TOML for the calendar schema
+    Parse {
+        path: PathBuf,
+        /// 1-based line and column of the error, when known
+        location: Option<(usize, usize)>,
+        source: Box<toml::de::Error>,
+    },
+    /// The config file is not valid YAML for the calendar schema
+    ParseYaml {
+        path: PathBuf,
+        source: Box<serde_yaml::Error>,
+    },
+    /// The config file is not valid JSON for the calendar schema
+    ParseJson {
+        path: PathBuf,
+        source: Box<serde_json::Error>,
+    },
+    /// An event in a `vdir` collection could not be read
+    ParseVdir(crate::vdir::ParseError),
+    /// A new or edited config entry is invalid
+    InvalidEntry(String),
+    /// The config must not be modified (`readonly = true` or `--read-only`)
+    ReadOnly(PathBuf),
+    /// Config files include each other, starting and ending with the same file
+    IncludeCycle(Vec<PathBuf>),
+    /// An exported file or its directory could not be written
+    Write { path: PathBuf, source: io::Error },
+}
+
+impl Error {
+    pub(crate) fn parse(path: PathBuf, contents: &str, source: toml::de::Error) -> Self {
+        let location = source.span().map(|span| line_col(contents, span.start));
+        Error::Parse {
+            path,
+            location,
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Convert a byte offset into a 1-based line and column
+pub(crate) fn line_col(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ConfigNotFound(path) => write!(f, "config file not found at {:?}", path),
+            Error::Io { path, source } => {
+                write!(f, "failed to read config file {:?}: {}", path, source)
+            }
+            Error::Parse {
+                path,
+                location: Some((line, column)),
+                source,
+            } => write!(
+                f,
+                "failed to parse config file {:?} at line {}, column {}: {}",
+                path,
+                line,
+                column,
+                source.message()
+            ),
+            Error::Parse {
+                path,
+                location: None,
+                source,
+            } => write!(
+                f,
+                "failed to parse config file {:?}: {}",
+                path,
+                source.message()
+            ),
+            Error::ParseYaml { path, source } => {
+                write!(f, "failed to parse config file {:?}: {}", path, source)
+            }
+            Error::ParseJson { path, source } => {
+                write!(f, "failed to parse config file {:?}: {}", path, source)
+            }
+            Error::ParseVdir(source) => write!(f, "failed to read vdir event {}", source),
+            Error::InvalidEntry(message) => write!(f, "{}", message),
+            Error::ReadOnly(path) => write!(f, "config file {:?} is read-only", path),
+            Error::IncludeCycle(paths) => {
+                let chain: Vec<String> = paths.iter().map(|path| format!("{:?}", path)).collect();
+                write!(f, "config files include each other: {}", chain.join(" -> "))
+            }
+            Error::Write { path, source } => write!(f, "failed to write {:?}: {}", path, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ConfigNotFound(_)
+            | Error::InvalidEntry(_)
+            | Error::ReadOnly(_)
+            | Error::IncludeCycle(_) => None,
+            Error::Io { source, .. } | Error::Write { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            Error::ParseYaml { source, .. } => Some(source),
+            Error::ParseJson { source, .. } => Some(source),
+            Error::ParseVdir(source) => Some(source),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Bulk export of several years in several formats at once, with the years
+//! and the files for each of them rendered in parallel.
+
+use crate::config::CalendarConfig;
+use crate::error::{Error, Result};
+use crate::ics::IcsExport;
+use crate::listing::EventList;
+use crate::models::{Calendar, CalendarOptions};
+use crate::rendering::CalendarRenderer;
+use crate::summary::Summary;
+use rayon::prelude::*;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The calendar as plain text
+    Text,
+    /// The calendar with ANSI colors, for `less -R` or `cat`
+    Ansi,
+    /// The chronological event list
+    List,
+    /// Per-month counts and costs by color
+    Summary,
+    /// All-day iCalendar events, for calendar apps
+    Ics,
+}
+
+impl ExportFormat {
+    pub fn parse(input: &str) -> std::result::Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "txt" | "text" => Ok(Self::Text),
+            "ansi" => Ok(Self::Ansi),
+            "list" => Ok(Self::List),
+            "summary" => Ok(Self::Summary),
+            "ics" => Ok(Self::Ics),
+            _ => Err(format!(
+                "Invalid export format: '{}'. Use txt, ansi, list, summary or ics",
+                input
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Ansi => "ansi",
+            Self::List => "list",
+            Self::Summary => "summary",
+            Self::Ics => "ics",
+        }
+    }
+
+    /// File name extension, which keeps the text formats apart
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Ansi => "ansi",
+            Self::List => "list.txt",
+            Self::Summary => "summary.txt",
+            Self::Ics => "ics",
+        }
+    }
+
+    pub fn render(&self, calendar: &Calendar) -> String {
+        match self {
+            Self::Text => CalendarRenderer::new(calendar).render_to_string(),
+            Self::Ansi => {
+                let mut output = Vec::new();
+                CalendarRenderer::new(calendar)
+                    .with_colors(true)
+                    .render_to(&mut output)
+                    .expect("writing to a Vec cannot fail");
+                String::from_utf8(output).expect("rendered calendar is valid UTF-8")
+            }
+            Self::List => EventList::new(calendar).render_to_string(),
+            Self::Summary => Summary::new(calendar).render_to_string(),
+            Self::Ics => IcsExport::new(calendar).render_to_string(),
+        }
+    }
+}
+
+/// Parse "2024", "2024..2027" or "2024..=2027"; both ends are included
+pub fn parse_years(input: &str) -> std::result::Result<RangeInclusive<i32>, String> {
+    let invalid = || {
+        format!(
+            "Invalid years: '{}'. Use a year or a range like 2024..2027",
+            input
+        )
+    };
+    let year = |value: &str| value.trim().parse::<i32>().map_err(|_| invalid());
+    let (first, last) = match input.split_once("..") {
+        Some((first, last)) => (year(first)?, year(last.trim_start_matches('='))?),
+        None => (year(input)?, year(input)?),
+    };
+    if last < first {
+        return Err(format!("Invalid years: '{}' ends before it starts", input));
+    }
+    Ok(first..=last)
+}
+
+/// A rendered file, named like `calendar-2024.ics`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportFile {
+    pub year: i32,
+    pub format: ExportFormat,
+    pub contents: String,
+}
+
+impl ExportFile {
+    pub fn file_name(&self) -> String {
+        format!("calendar-{}.{}", self.year, self.format.extension())
+    }
+}
+
+/// Render every format for every year, ordered by year and then format
+pub fn export_all(
+    config: &CalendarConfig,
+    options: &CalendarOptions,
+    years: RangeInclusive<i32>,
+    formats: &[ExportFormat],
+) -> Vec<ExportFile> {
+    let calendars: Vec<Calendar> = years
+        .into_par_iter()
+        .map(|year| crate::build_calendar(year, options.clone(), config))
+        .collect();
+
+    calendars
+        .par_iter()
+        .flat_map(|calendar| {
+            formats.par_iter().map(move |format| ExportFile {
+                year: calendar.year,
+                format: *format,
+                contents: format.render(calendar),
+            })
+        })
+        .collect()
+}
+
+/// Write the files into `dir`, creating it if needed; returns their paths
+pub fn write_all(dir: &Path, files: &[ExportFile]) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).map_err(|source| Error::Write {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    files
+        .par_iter()
+        .map(|file| {
+            let path = dir.join(file.file_name());
+            fs::write(&path, &file.contents).map_err(|source| Error::Write {
+                path: path.clone(),
+                source,
+            })?;
+            Ok(path)
+        })
+        .collect()
+}
@@ -0,0 +1,186 @@
+//! Date expressions used for config dates: fixed and yearly dates, floating
+//! dates such as "2nd monday of october" or "easter", and offsets from any of
+//! these like "easter+39" or "2025-12-25 - 1d", all resolved for each year.
+//! An expression can also name another entry by its `id`, as in
+//! "launch - 14d", so dependent dates move with it.
+
+use crate::holidays::{easter_sunday, last_weekday, nth_weekday};
+use chrono::{Datelike, Days, Month, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+/// Dates of the entries with an `id`, which other expressions can refer to
+pub type Anchors = HashMap<String, NaiveDate>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateExpr {
+    /// "YYYY-MM-DD"
+    Fixed(NaiveDate),
+    /// "MM-DD", every year
+    Yearly { month: u32, day: u32 },
+    /// "2nd monday of october" or "last monday of may", every year
+    NthWeekday(NthWeekday),
+    /// "easter", Western Easter Sunday of every year
+    Easter,
+    /// "easter+39", "2025-12-25 - 1d" or "11-01 + 2w": another date shifted
+    /// by a number of days
+    Offset { base: Box<DateExpr>, days: i64 },
+    /// "launch", the date of the entry with that `id`
+    Anchor(String),
+}
+
+/// The `n`th `weekday` of a month; negative `n` counts from the end, so -1
+/// is the last one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NthWeekday {
+    pub n: i32,
+    pub weekday: Weekday,
+    pub month: u32,
+}
+
+impl NthWeekday {
+    pub fn new(n: i32, weekday: Weekday, month: u32) -> Result<Self, String> {
+        if n == 0 || !(-5..=5).contains(&n) {
+            return Err(format!(
+                "invalid weekday number {}, expected 1 to 5 or -1 for the last",
+                n
+            ));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(format!("invalid month {}, expected 1 to 12", month));
+        }
+        Ok(Self { n, weekday, month })
+    }
+
+    /// The date in `year`, if the month has that many of the weekday
+    pub fn resolve(&self, year: i32) -> Option<NaiveDate> {
+        let date = if self.n > 0 {
+            nth_weekday(year, self.month, self.weekday, self.n as u32)
+        } else {
+            last_weekday(year, self.month, self.weekday)
+                - chrono::Duration::weeks((-self.n - 1) as i64)
+        };
+        (date.month() == self.month && date.year() == year).then_some(date)
+    }
+}
+
+impl DateExpr {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+            return Ok(Self::Fixed(date));
+        }
+        // Leap year, so "02-29" is accepted
+        if let Ok(date) = NaiveDate::parse_from_str(&format!("2000-{}", input), "%Y-%m-%d") {
+            return Ok(Self::Yearly {
+                month: date.month(),
+                day: date.day(),
+            });
+        }
+        if input.eq_ignore_ascii_case("easter") {
+            return Ok(Self::Easter);
+        }
+        if let Some(nth) = parse_nth_weekday(input) {
+            return Ok(Self::NthWeekday(nth));
+        }
+        if let Some(offset) = parse_offset(input) {
+            return Ok(offset);
+        }
+        if is_anchor_name(input) {
+            return Ok(Self::Anchor(input.to_string()));
+        }
+        Err(format!("invalid date '{}'", input))
+    }
+
+    /// The date in `year`, looking up named dates in `anchors`; fixed dates
+    /// and offsets from them ignore the year
+    pub fn resolve(&self, year: i32, anchors: &Anchors) -> Option<NaiveDate> {
+        match self {
+            Self::Fixed(date) => Some(*date),
+            Self::Yearly { month, day } => NaiveDate::from_ymd_opt(year, *month, *day),
+            Self::NthWeekday(nth) => nth.resolve(year),
+            Self::Easter => Some(easter_sunday(year)),
+            Self::Offset { base, days } => {
+                let date = base.resolve(year, anchors)?;
+                let shift = Days::new(days.unsigned_abs());
+                if *days < 0 {
+                    date.checked_sub_days(shift)
+                } else {
+                    date.checked_add_days(shift)
+                }
+            }
+            Self::Anchor(name) => anchors.get(name).copied(),
+        }
+    }
+
+    /// Whether the expression names a date in every year rather than one day;
+    /// anchors count as one day, since the entry they name decides
+    pub fn is_yearly(&self) -> bool {
+        match self {
+            Self::Fixed(_) | Self::Anchor(_) => false,
+            Self::Offset { base, .. } => base.is_yearly(),
+            _ => true,
+        }
+    }
+
+    /// The `id` the expression refers to, if any
+    pub fn anchor(&self) -> Option<&str> {
+        match self {
+            Self::Anchor(name) => Some(name),
+            Self::Offset { base, .. } => base.anchor(),
+            _ => None,
+        }
+    }
+}
+
+/// Ids look like "launch" or "v2-release": a letter, then letters, digits,
+/// '-', '_' or '.'
+fn is_anchor_name(input: &str) -> bool {
+    let mut chars = input.chars();
+    chars.next().is_some_and(char::is_alphabetic)
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Parse "<date> + 39", "<date>-1d" or "<date> + 2w", splitting at the last
+/// sign so offsets can be chained
+fn parse_offset(input: &str) -> Option<DateExpr> {
+    let split = input.rfind(['+', '-'])?;
+    let (base, offset) = input.split_at(split);
+    let (sign, amount) = offset.split_at(1);
+    let amount = amount.trim().to_lowercase();
+    let (count, unit) = match amount.strip_suffix('d') {
+        Some(count) => (count, 1),
+        None => match amount.strip_suffix('w') {
+            Some(count) => (count, 7),
+            None => (amount.as_str(), 1),
+        },
+    };
+    let count: i64 = count.trim().parse().ok()?;
+    let days = count.checked_mul(unit)?;
+    let days = if sign == "-" { -days } else { days };
+    let base = DateExpr::parse(base).ok()?;
+    Some(DateExpr::Offset {
+        base: Box::new(base),
+        days,
+    })
+}
+
+/// Parse "2nd monday of october", "first tue of nov" or "last monday of may"
+fn parse_nth_weekday(input: &str) -> Option<NthWeekday> {
+    let lower = input.to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let [ordinal, weekday, "of", month] = words[..] else {
+        return None;
+    };
+    let n = match ordinal {
+        "1st" | "first" => 1,
+        "2nd" | "second" => 2,
+        "3rd" | "third" => 3,
+        "4th" | "fourth" => 4,
+        "5th" | "fifth" => 5,
+        "last" => -1,
+        _ => return None,
+    };
+    let weekday = weekday.parse::<Weekday>().ok()?;
+    let month = month.parse::<Month>().ok()?.number_from_month();
+    NthWeekday::new(n, weekday, month).ok()
+}
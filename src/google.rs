@@ -0,0 +1,297 @@
+//! Fetching `[[google]]` calendars from the Google Calendar API, read-only,
+//! with `curl`. Public calendars only need an API key; private ones an OAuth
+//! client, authorized once through the device flow, after which its refresh
+//! token is kept in the cache directory. Fetches are cached like
+//! `[[sources]]`.
+
+use crate::cache::{self, Cache, CachePolicy};
+use crate::config::RawGoogleCalendar;
+use crate::paths;
+use crate::sources::{self, quote, FetchError};
+use crate::vdir::{Collection, VEvent};
+use chrono::{DateTime, NaiveDate};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const EVENTS_URL: &str = "https://www.googleapis.com/calendar/v3/calendars";
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/calendar.readonly";
+
+/// Palette color of each Google event color, by `colorId`: lavender, sage,
+/// grape, flamingo, banana, tangerine, peacock, graphite, blueberry, basil
+/// and tomato
+pub const EVENT_COLORS: [&str; 11] = [
+    "light_purple",
+    "light_green",
+    "purple",
+    "light_red",
+    "yellow",
+    "orange",
+    "cyan",
+    "gray",
+    "blue",
+    "green",
+    "red",
+];
+
+/// Fetch the events of `calendar` around `year`, or use the cached copy.
+/// `prompt` is shown the URL to visit and the code to enter when the OAuth
+/// client still needs authorizing.
+pub fn fetch(
+    calendar: &RawGoogleCalendar,
+    year: i32,
+    policy: CachePolicy,
+    prompt: &dyn Fn(&str, &str),
+) -> Result<sources::Fetched, String> {
+    let name = |e: String| format!("Google calendar {}: {}", calendar.calendar, e);
+    let ttl = match &calendar.ttl {
+        Some(ttl) => cache::parse_ttl(ttl).map_err(name)?,
+        None => cache::DEFAULT_TTL,
+    };
+    let cache = Cache::user().map_err(name)?;
+    let key = format!("{} {}", calendar.calendar, year);
+    let path = cache.path("google", &key, "json");
+    let cached = cache
+        .get(&path, &key, ttl, policy, || request(calendar, year, prompt))
+        .map_err(name)?;
+    let events = events_from_response(&cached.contents).map_err(name)?;
+    Ok(sources::Fetched {
+        collection: Collection {
+            events,
+            color: calendar.color.clone(),
+            tags: Vec::new(),
+        },
+        warning: cached
+            .stale
+            .map(|e| format!("{}; using the cached copy", name(e))),
+    })
+}
+
+/// The events of an `events.list` response, or of several pages' `items`
+/// joined into one list, skipping cancelled ones
+pub fn events_from_response(response: &str) -> Result<Vec<VEvent>, String> {
+    let response: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| format!("unexpected response from Google: {}", e))?;
+    if let Some(message) = response["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+    let items = response["items"]
+        .as_array()
+        .ok_or("unexpected response from Google: no items")?;
+    let mut events = Vec::new();
+    for item in items {
+        if item["status"] == "cancelled" {
+            continue;
+        }
+        let Some((start, timed)) = event_date(&item["start"]) else {
+            continue;
+        };
+        // All-day ends are exclusive; timed ones fall on the day they end
+        let end = match event_date(&item["end"]) {
+            Some((end, false)) => end.pred_opt().unwrap_or(end),
+            Some((end, true)) if timed => end,
+            _ => start,
+        };
+        let color = item["colorId"]
+            .as_str()
+            .and_then(|id| id.parse::<usize>().ok())
+            .and_then(|id| EVENT_COLORS.get(id.wrapping_sub(1)))
+            .map(|color| color.to_string());
+        let summary = item["summary"].as_str().unwrap_or("(No title)");
+        events.push(VEvent::new(summary.to_string(), start, end, color));
+    }
+    Ok(events)
+}
+
+/// The day of a `{"date": ...}` or `{"dateTime": ...}` value, in the offset
+/// it was given in, and whether it had a time
+fn event_date(value: &serde_json::Value) -> Option<(NaiveDate, bool)> {
+    if let Some(date) = value["date"].as_str() {
+        return Some((NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, false));
+    }
+    let time = DateTime::parse_from_rfc3339(value["dateTime"].as_str()?).ok()?;
+    Some((time.date_naive(), true))
+}
+
+/// Every page of events around `year`, as one response
+fn request(
+    calendar: &RawGoogleCalendar,
+    year: i32,
+    prompt: &dyn Fn(&str, &str),
+) -> Result<String, String> {
+    let mut auth = match (&calendar.client_id, &calendar.api_key) {
+        (Some(client_id), _) => {
+            let client_id = paths::expand_env(client_id).map_err(|e| e.to_string())?;
+            let secret = match &calendar.client_secret {
+                Some(secret) => paths::expand_env(secret).map_err(|e| e.to_string())?,
+                None => return Err("client_id needs a client_secret".to_string()),
+            };
+            let token = access_token(&client_id, &secret, prompt)?;
+            vec![format!(
+                "header = {}",
+                quote(&format!("Authorization: Bearer {}", token))
+            )]
+        }
+        (None, Some(key)) => {
+            let key = paths::expand_env(key).map_err(|e| e.to_string())?;
+            vec![data("key", &key)]
+        }
+        (None, None) => return Err("needs an api_key or a client_id".to_string()),
+    };
+    auth.push("get".to_string());
+
+    let url = format!(
+        "{}/{}/events",
+        EVENTS_URL,
+        percent_encode(&calendar.calendar)
+    );
+    let mut items = Vec::new();
+    let mut page: Option<String> = None;
+    loop {
+        let mut options = auth.clone();
+        options.extend([
+            data("singleEvents", "true"),
+            data("maxResults", "2500"),
+            data("timeMin", &format!("{}-12-24T00:00:00Z", year - 1)),
+            data("timeMax", &format!("{}-01-08T00:00:00Z", year + 1)),
+        ]);
+        if let Some(page) = &page {
+            options.push(data("pageToken", page));
+        }
+        let response = json(&call(&url, &options)?)?;
+        items.extend(response["items"].as_array().cloned().unwrap_or_default());
+        match response["nextPageToken"].as_str() {
+            Some(token) => page = Some(token.to_string()),
+            None => break,
+        }
+    }
+    Ok(serde_json::json!({ "items": items }).to_string())
+}
+
+/// Where the refresh token of an OAuth client is kept, which `cache clear`
+/// leaves alone
+fn token_path(client_id: &str) -> Result<PathBuf, String> {
+    Ok(Cache::user()?.path("google", client_id, "token"))
+}
+
+/// An access token from the stored refresh token, authorizing the client
+/// through the device flow first when there is none or it was revoked
+fn access_token(
+    client_id: &str,
+    secret: &str,
+    prompt: &dyn Fn(&str, &str),
+) -> Result<String, String> {
+    let path = token_path(client_id)?;
+    if let Ok(refresh_token) = fs::read_to_string(&path) {
+        let response = call(
+            TOKEN_URL,
+            &[
+                data("client_id", client_id),
+                data("client_secret", secret),
+                data("refresh_token", refresh_token.trim()),
+                data("grant_type", "refresh_token"),
+            ],
+        )?;
+        // A revoked token fails with `invalid_grant`; authorize again then
+        if let Some(token) = json(&response)
+            .ok()
+            .and_then(|response| response["access_token"].as_str().map(str::to_string))
+        {
+            return Ok(token);
+        }
+    }
+
+    let device = json(&call(
+        DEVICE_CODE_URL,
+        &[data("client_id", client_id), data("scope", SCOPE)],
+    )?)?;
+    let field = |name: &str| {
+        device[name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("unexpected response from Google: no {}", name))
+    };
+    let device_code = field("device_code")?;
+    prompt(&field("verification_url")?, &field("user_code")?);
+    let mut interval = device["interval"].as_u64().unwrap_or(5);
+    loop {
+        std::thread::sleep(Duration::from_secs(interval));
+        let response = json(&call(
+            TOKEN_URL,
+            &[
+                data("client_id", client_id),
+                data("client_secret", secret),
+                data("device_code", &device_code),
+                data("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ],
+        )?);
+        let response = match response {
+            Ok(response) => response,
+            Err(e) if e.contains("authorization_pending") => continue,
+            Err(e) if e.contains("slow_down") => {
+                interval += 5;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let token = response["access_token"]
+            .as_str()
+            .ok_or("unexpected response from Google: no access_token")?;
+        if let Some(refresh_token) = response["refresh_token"].as_str() {
+            save_token(&path, refresh_token).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        return Ok(token.to_string());
+    }
+}
+
+/// Write a refresh token readable only by its owner
+fn save_token(path: &Path, token: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+fn json(response: &str) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| format!("unexpected response from Google: {}", e))?;
+    match (&value["error"], value["error_description"].as_str()) {
+        (serde_json::Value::Null, _) => Ok(value),
+        (serde_json::Value::String(error), description) => {
+            Err(description.map_or(error.clone(), |d| format!("{}: {}", error, d)))
+        }
+        (error, _) => Err(error["message"]
+            .as_str()
+            .unwrap_or("unexpected response from Google")
+            .to_string()),
+    }
+}
+
+/// The body of a request to `url`, with `options` given to curl, error
+/// bodies too for their messages
+fn call(url: &str, options: &[String]) -> Result<String, String> {
+    sources::fetch_url(url, options, []).or_else(FetchError::into_body)
+}
+
+/// A curl option sending `name=value` URL-encoded
+fn data(name: &str, value: &str) -> String {
+    format!("data-urlencode = {}", quote(&format!("{}={}", name, value)))
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
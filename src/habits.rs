@@ -0,0 +1,91 @@
+//! A log of the days a habit was kept, for "don't break the chain"
+//! tracking: one date per line, or a TOML file with a `done` list.
+
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HabitLog {
+    days: BTreeSet<NaiveDate>,
+}
+
+impl HabitLog {
+    /// Single-width marker shown next to the days done
+    pub const MARK: char = '✓';
+
+    /// Parse a log of "YYYY-MM-DD" lines, where anything after the date,
+    /// blank lines and `#` comments are ignored, or a TOML file like
+    /// `done = ["2025-01-01", 2025-01-02]`
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        if let Ok(table) = toml::from_str::<toml::Table>(contents) {
+            if let Some(done) = table.get("done") {
+                return Self::from_toml(done);
+            }
+        }
+        let mut days = BTreeSet::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some(date) = line.split_whitespace().next() else {
+                continue;
+            };
+            days.insert(parse_day(date).map_err(|e| format!("line {}: {}", i + 1, e))?);
+        }
+        Ok(Self { days })
+    }
+
+    fn from_toml(done: &toml::Value) -> Result<Self, String> {
+        let Some(done) = done.as_array() else {
+            return Err("done must be a list of dates".to_string());
+        };
+        let days = done
+            .iter()
+            .map(|value| match value {
+                toml::Value::String(date) => parse_day(date),
+                toml::Value::Datetime(date) => parse_day(&date.to_string()),
+                other => Err(format!("invalid date {}, expected YYYY-MM-DD", other)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { days })
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.days.contains(&date)
+    }
+
+    /// Days in a row done up to `today`; a chain not yet extended today
+    /// still counts through yesterday
+    pub fn current_streak(&self, today: NaiveDate) -> usize {
+        let end = if self.contains(today) {
+            today
+        } else {
+            today.pred_opt().unwrap_or(today)
+        };
+        self.days
+            .range(..=end)
+            .rev()
+            .zip(std::iter::successors(Some(end), NaiveDate::pred_opt))
+            .take_while(|(done, expected)| *done == expected)
+            .count()
+    }
+
+    /// Most days in a row done from `start` to `end`
+    pub fn longest_streak(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &date in self.days.range(start..=end) {
+            streak = match previous {
+                Some(previous) if previous.succ_opt() == Some(date) => streak + 1,
+                _ => 1,
+            };
+            longest = longest.max(streak);
+            previous = Some(date);
+        }
+        longest
+    }
+}
+
+fn parse_day(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", date))
+}
@@ -0,0 +1,157 @@
+//! Activity counts per day, such as commits in a git repository or GitHub
+//! contributions, drawn as a heatmap on the days without a color of their
+//! own.
+
+use crate::sources::{self, FetchError};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Shades of the heatmap, besides days without any activity
+pub const LEVELS: u8 = 4;
+
+/// Glyph of a heatmap level, lightest to densest so the shading reads
+/// without colors too, or a dot without any activity
+pub fn glyph(level: Option<u8>) -> char {
+    match level {
+        Some(level) => ['░', '▒', '▓', '█'][(level.clamp(1, LEVELS) - 1) as usize],
+        None => '·',
+    }
+}
+
+const GITHUB_GRAPHQL: &str = "https://api.github.com/graphql";
+
+const CONTRIBUTIONS_QUERY: &str = "query($login: String!, $from: DateTime!, $to: DateTime!) { \
+    user(login: $login) { contributionsCollection(from: $from, to: $to) { \
+    contributionCalendar { weeks { contributionDays { date contributionCount } } } } } }";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Heatmap {
+    counts: HashMap<NaiveDate, usize>,
+}
+
+impl Heatmap {
+    pub fn new(counts: HashMap<NaiveDate, usize>) -> Self {
+        Self { counts }
+    }
+
+    /// Commits per day around `year` on the checked-out branch of the
+    /// repository at `repo`, by author date
+    pub fn from_git(repo: &Path, year: i32) -> Result<Self, String> {
+        // `--since` stops at the first older commit, which rebased or
+        // cherry-picked history can put before newer ones, so filter here
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["log", "--format=%ad", "--date=short"])
+            .output()
+            .map_err(|e| format!("could not run git: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        // A week either side, for the days of other years in the first and
+        // last weeks
+        let (start, end) = (
+            NaiveDate::from_ymd_opt(year - 1, 12, 24).unwrap(),
+            NaiveDate::from_ymd_opt(year + 1, 1, 8).unwrap(),
+        );
+        let mut counts = HashMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let date = NaiveDate::parse_from_str(line.trim(), "%Y-%m-%d");
+            if let Some(date) = date.ok().filter(|date| (start..=end).contains(date)) {
+                *counts.entry(date).or_insert(0) += 1;
+            }
+        }
+        Ok(Self { counts })
+    }
+
+    /// `login`'s GitHub contributions per day in `year`, fetched from the
+    /// GraphQL API with `curl`. The token is passed on curl's standard input,
+    /// keeping it off the command line.
+    pub fn from_github(login: &str, token: &str, year: i32) -> Result<Self, String> {
+        let body = serde_json::json!({
+            "query": CONTRIBUTIONS_QUERY,
+            "variables": {
+                "login": login,
+                "from": format!("{}-01-01T00:00:00Z", year),
+                "to": format!("{}-12-31T23:59:59Z", year),
+            },
+        });
+        let authorization = format!("Authorization: bearer {}", token);
+        let response = sources::fetch_url(
+            GITHUB_GRAPHQL,
+            &[format!("header = {}", sources::quote(&authorization))],
+            ["--data".into(), body.to_string().into()],
+        )
+        .or_else(FetchError::into_body)?;
+        Self::from_github_response(&response)
+    }
+
+    /// Contributions per day in a response to the GraphQL query
+    pub fn from_github_response(response: &str) -> Result<Self, String> {
+        let response: serde_json::Value = serde_json::from_str(response)
+            .map_err(|e| format!("unexpected response from GitHub: {}", e))?;
+        if let Some(message) = response["errors"][0]["message"]
+            .as_str()
+            .or(response["message"].as_str())
+        {
+            return Err(format!("GitHub: {}", message));
+        }
+        let weeks = response["data"]["user"]["contributionsCollection"]["contributionCalendar"]
+            ["weeks"]
+            .as_array()
+            .ok_or("unexpected response from GitHub: no contribution calendar")?;
+        let mut counts = HashMap::new();
+        for day in weeks
+            .iter()
+            .filter_map(|week| week["contributionDays"].as_array())
+            .flatten()
+        {
+            let (Some(date), Some(count)) =
+                (day["date"].as_str(), day["contributionCount"].as_u64())
+            else {
+                continue;
+            };
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                counts.insert(date, count as usize);
+            }
+        }
+        Ok(Self { counts })
+    }
+
+    pub fn count(&self, date: NaiveDate) -> usize {
+        self.counts.get(&date).copied().unwrap_or(0)
+    }
+
+    /// Shade of `date` from 1 to [`LEVELS`], scaled to the busiest day from
+    /// `start` to `end`, or `None` without any activity
+    pub fn level(&self, date: NaiveDate, start: NaiveDate, end: NaiveDate) -> Option<u8> {
+        let count = self.count(date);
+        let (_, max) = self.busiest(start, end)?;
+        (count > 0).then(|| (count * LEVELS as usize).div_ceil(max) as u8)
+    }
+
+    /// Total activity from `start` to `end`
+    pub fn total(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        self.in_span(start, end).map(|(_, count)| count).sum()
+    }
+
+    /// The day with the most activity from `start` to `end`, the earliest
+    /// of any ties
+    pub fn busiest(&self, start: NaiveDate, end: NaiveDate) -> Option<(NaiveDate, usize)> {
+        self.in_span(start, end)
+            .max_by_key(|&(date, count)| (count, std::cmp::Reverse(date)))
+    }
+
+    fn in_span(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> impl Iterator<Item = (NaiveDate, usize)> + '_ {
+        self.counts
+            .iter()
+            .filter(move |(date, count)| (start..=end).contains(*date) && **count > 0)
+            .map(|(date, count)| (*date, *count))
+    }
+}
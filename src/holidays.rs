@@ -0,0 +1,202 @@
+use crate::secondary::chinese_date;
+use crate::sun;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// Public holidays of any country, from Nager.Date
+pub const API_URL: &str = "https://date.nager.at/api/v3/PublicHolidays/{year}/{country}";
+
+/// Countries with built-in national holidays, for seeding new configs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Country {
+    /// United States (federal holidays)
+    Us,
+    /// United Kingdom (England and Wales bank holidays)
+    Gb,
+    /// Germany (nationwide holidays)
+    De,
+    /// China (public holidays, without the make-up working days)
+    Cn,
+    /// South Korea (public holidays)
+    Kr,
+}
+
+impl Country {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "us" | "usa" => Ok(Self::Us),
+            "gb" | "uk" => Ok(Self::Gb),
+            "de" => Ok(Self::De),
+            "cn" => Ok(Self::Cn),
+            "kr" => Ok(Self::Kr),
+            _ => Err(format!(
+                "Unknown country: '{}'. Supported: us, gb, de, cn, kr",
+                input
+            )),
+        }
+    }
+
+    /// Lowercase ISO 3166 code, naming the country's dataset
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Us => "us",
+            Self::Gb => "gb",
+            Self::De => "de",
+            Self::Cn => "cn",
+            Self::Kr => "kr",
+        }
+    }
+
+    /// National holidays in `year`, in date order. Substitute days for
+    /// holidays falling on a weekend are not included, and the days around
+    /// the multi-day lunar festivals are left to the config.
+    pub fn holidays(&self, year: i32) -> Vec<(NaiveDate, &'static str)> {
+        let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let lunar = |month, day| chinese_date(year, month, day).expect("lunar date in range");
+        let easter = easter_sunday(year);
+
+        let mut holidays = match self {
+            Self::Us => vec![
+                (date(1, 1), "New Year's Day"),
+                (
+                    nth_weekday(year, 1, Weekday::Mon, 3),
+                    "Martin Luther King Jr. Day",
+                ),
+                (nth_weekday(year, 2, Weekday::Mon, 3), "Presidents' Day"),
+                (last_weekday(year, 5, Weekday::Mon), "Memorial Day"),
+                (date(6, 19), "Juneteenth"),
+                (date(7, 4), "Independence Day"),
+                (nth_weekday(year, 9, Weekday::Mon, 1), "Labor Day"),
+                (nth_weekday(year, 10, Weekday::Mon, 2), "Columbus Day"),
+                (date(11, 11), "Veterans Day"),
+                (nth_weekday(year, 11, Weekday::Thu, 4), "Thanksgiving"),
+                (date(12, 25), "Christmas Day"),
+            ],
+            Self::Gb => vec![
+                (date(1, 1), "New Year's Day"),
+                (easter - Duration::days(2), "Good Friday"),
+                (easter + Duration::days(1), "Easter Monday"),
+                (
+                    nth_weekday(year, 5, Weekday::Mon, 1),
+                    "Early May Bank Holiday",
+                ),
+                (last_weekday(year, 5, Weekday::Mon), "Spring Bank Holiday"),
+                (last_weekday(year, 8, Weekday::Mon), "Summer Bank Holiday"),
+                (date(12, 25), "Christmas Day"),
+                (date(12, 26), "Boxing Day"),
+            ],
+            Self::De => vec![
+                (date(1, 1), "Neujahr"),
+                (easter - Duration::days(2), "Karfreitag"),
+                (easter + Duration::days(1), "Ostermontag"),
+                (date(5, 1), "Tag der Arbeit"),
+                (easter + Duration::days(39), "Christi Himmelfahrt"),
+                (easter + Duration::days(50), "Pfingstmontag"),
+                (date(10, 3), "Tag der Deutschen Einheit"),
+                (date(12, 25), "1. Weihnachtstag"),
+                (date(12, 26), "2. Weihnachtstag"),
+            ],
+            Self::Cn => vec![
+                (date(1, 1), "New Year's Day"),
+                (lunar(1, 1), "Spring Festival"),
+                (qingming(year), "Qingming Festival"),
+                (date(5, 1), "Labour Day"),
+                (lunar(5, 5), "Dragon Boat Festival"),
+                (lunar(8, 15), "Mid-Autumn Festival"),
+                (date(10, 1), "National Day"),
+            ],
+            Self::Kr => vec![
+                (date(1, 1), "New Year's Day"),
+                (lunar(1, 1), "Seollal"),
+                (date(3, 1), "Independence Movement Day"),
+                (date(5, 5), "Children's Day"),
+                (lunar(4, 8), "Buddha's Birthday"),
+                (date(6, 6), "Memorial Day"),
+                (date(8, 15), "Liberation Day"),
+                (lunar(8, 15), "Chuseok"),
+                (date(10, 3), "National Foundation Day"),
+                (date(10, 9), "Hangul Day"),
+                (date(12, 25), "Christmas Day"),
+            ],
+        };
+        holidays.sort_by_key(|(date, _)| *date);
+        holidays
+    }
+}
+
+/// The nationwide holidays of a holiday API response, a list of
+/// `{"date": ..., "localName": ..., "global": ...}` like Nager.Date's, in
+/// date order
+pub fn from_api_response(response: &str) -> Result<Vec<(NaiveDate, String)>, String> {
+    let response: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| format!("unexpected response from the holiday API: {}", e))?;
+    let items = response
+        .as_array()
+        .ok_or("unexpected response from the holiday API: not a list")?;
+    let mut holidays = Vec::new();
+    for item in items {
+        // Regional holidays are left out, like the built-in ones
+        if item["global"] == false {
+            continue;
+        }
+        let Some(date) = item["date"]
+            .as_str()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        let name = item["localName"]
+            .as_str()
+            .or(item["name"].as_str())
+            .unwrap_or("Holiday");
+        holidays.push((date, name.to_string()));
+    }
+    holidays.sort_by_key(|(date, _)| *date);
+    Ok(holidays)
+}
+
+/// The `n`th (1-based) `weekday` of a month
+pub fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u32) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let offset = (7 + weekday.num_days_from_monday() - first.weekday().num_days_from_monday()) % 7;
+    first + Duration::days((offset + 7 * (n - 1)) as i64)
+}
+
+/// The last `weekday` of a month
+pub fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let last = NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .unwrap()
+        .pred_opt()
+        .unwrap();
+    let offset = (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    last - Duration::days(offset as i64)
+}
+
+/// Qingming, the day the sun reaches 15 degrees past the March equinox,
+/// in Beijing time
+pub fn qingming(year: i32) -> NaiveDate {
+    (sun::solar_term(year, 15.0) + Duration::hours(8)).date_naive()
+}
+
+/// Western (Gregorian) Easter Sunday, by the anonymous Gregorian algorithm
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
@@ -0,0 +1,115 @@
+use crate::models::{Calendar, Event, Status};
+use chrono::{Datelike, Days};
+use std::io::{self, Write};
+
+/// Maximum length of an iCalendar content line, in octets, before folding
+const LINE_LIMIT: usize = 75;
+
+/// The year's events as an iCalendar (RFC 5545) file of all-day events
+pub struct IcsExport<'a> {
+    calendar: &'a Calendar,
+}
+
+impl<'a> IcsExport<'a> {
+    pub fn new(calendar: &'a Calendar) -> Self {
+        Self { calendar }
+    }
+
+    pub fn render_to_string(&self) -> String {
+        let mut output = Vec::new();
+        self.render_to(&mut output)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("iCalendar output is valid UTF-8")
+    }
+
+    /// Events touching the calendar's year, in chronological order
+    pub fn events(&self) -> Vec<&'a Event> {
+        let year = self.calendar.year;
+        self.calendar
+            .events()
+            .into_iter()
+            .filter(|event| event.start.year() <= year && event.end.year() >= year)
+            .collect()
+    }
+
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        // Stamped with "today", so pinning it gives reproducible files
+        let stamp = self.calendar.today().format("%Y%m%dT000000Z").to_string();
+
+        write_line(w, "BEGIN:VCALENDAR")?;
+        write_line(w, "VERSION:2.0")?;
+        write_line(w, "PRODID:-//compact-calendar-cli//EN")?;
+        write_line(w, "CALSCALE:GREGORIAN")?;
+        for (i, event) in self.events().into_iter().enumerate() {
+            write_line(w, "BEGIN:VEVENT")?;
+            let uid = match &event.id {
+                Some(id) => format!("UID:{}@compact-calendar-cli", id),
+                None => format!(
+                    "UID:{}-{}@compact-calendar-cli",
+                    event.start.format("%Y%m%d"),
+                    i
+                ),
+            };
+            write_line(w, &uid)?;
+            write_line(w, &format!("DTSTAMP:{}", stamp))?;
+            write_line(
+                w,
+                &format!("DTSTART;VALUE=DATE:{}", event.start.format("%Y%m%d")),
+            )?;
+            // All-day events end on the (exclusive) following day
+            let end = event.end + Days::new(1);
+            write_line(w, &format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")))?;
+            if let Some(description) = &event.description {
+                write_line(w, &format!("SUMMARY:{}", escape(description)))?;
+            }
+            let categories: Vec<String> = event.all_tags().map(escape).collect();
+            if !categories.is_empty() {
+                write_line(w, &format!("CATEGORIES:{}", categories.join(",")))?;
+            }
+            if let Some(status) = event.status {
+                let status = match status {
+                    Status::Requested => "TENTATIVE",
+                    Status::Approved => "CONFIRMED",
+                    Status::Rejected => "CANCELLED",
+                };
+                write_line(w, &format!("STATUS:{}", status))?;
+            }
+            write_line(w, "END:VEVENT")?;
+        }
+        write_line(w, "END:VCALENDAR")
+    }
+}
+
+/// Escape a TEXT value: backslashes, separators and newlines
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a content line ending in CRLF, folding it onto continuation lines
+/// (starting with a space) so none exceeds the line limit
+fn write_line<W: Write>(w: &mut W, line: &str) -> io::Result<()> {
+    let mut rest = line;
+    let mut limit = LINE_LIMIT;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(w, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        // The leading space counts toward the continuation line's length
+        limit = LINE_LIMIT - 1;
+    }
+    write!(w, "{}\r\n", rest)
+}
@@ -0,0 +1,41 @@
+use chrono::NaiveDate;
+
+/// Index of date intervals answering "which intervals contain this day"
+/// without scanning all of them, for lookups made once per rendered day
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IntervalIndex {
+    /// Start, end and original position of each interval, sorted by start
+    entries: Vec<(NaiveDate, NaiveDate, usize)>,
+    /// Latest end among `entries[..=i]`, so a lookup can stop early
+    max_end: Vec<NaiveDate>,
+}
+
+impl IntervalIndex {
+    pub(crate) fn new(intervals: impl IntoIterator<Item = (NaiveDate, NaiveDate)>) -> Self {
+        let mut entries: Vec<_> = intervals
+            .into_iter()
+            .enumerate()
+            .map(|(position, (start, end))| (start, end, position))
+            .collect();
+        entries.sort_by_key(|(start, _, _)| *start);
+
+        let max_end = entries
+            .iter()
+            .scan(NaiveDate::MIN, |max_end, (_, end, _)| {
+                *max_end = (*max_end).max(*end);
+                Some(*max_end)
+            })
+            .collect();
+        Self { entries, max_end }
+    }
+
+    /// Positions of the intervals containing `date`, in no particular order
+    pub(crate) fn containing(&self, date: NaiveDate) -> impl Iterator<Item = usize> + '_ {
+        let started = self.entries.partition_point(|(start, _, _)| *start <= date);
+        (0..started)
+            .rev()
+            .take_while(move |&i| self.max_end[i] >= date)
+            .filter(move |&i| self.entries[i].1 >= date)
+            .map(|i| self.entries[i].2)
+    }
+}
@@ -1,38 +1,213 @@
+pub mod availability;
+pub mod cache;
 pub mod config;
+pub mod conflicts;
+pub mod datasets;
+pub mod diff;
+pub mod dst;
+pub mod editing;
+pub mod error;
+pub mod export;
+pub mod expressions;
 pub mod formatting;
+#[cfg(feature = "google")]
+pub mod google;
+pub mod habits;
+pub mod heatmap;
+pub mod holidays;
+pub mod ics;
+mod index;
+pub mod listing;
+pub mod locale;
 pub mod models;
+pub mod moon;
+pub mod named_colors;
+pub mod pager;
+pub mod paths;
+pub mod planning;
+pub mod profile;
+pub mod remind;
 pub mod rendering;
+pub mod secondary;
+pub mod settings;
+pub mod sources;
+pub mod summary;
+pub mod sun;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod text_width;
+pub mod theme;
+pub mod upcoming;
+pub mod validation;
+pub mod vdir;
+pub mod weather;
+pub mod workdays;
 
-use config::CalendarConfig;
+use config::{CalendarConfig, ConfigFormat};
+pub use error::{Error, Result};
 use models::{Calendar, CalendarOptions};
+use std::borrow::Borrow;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn load_config(config_path: &PathBuf) -> CalendarConfig {
+/// Load a config along with every fragment it includes, recursively. The
+/// format of each file is detected from its extension.
+pub fn load_config(config_path: &PathBuf) -> Result<CalendarConfig> {
+    load_config_as(config_path, ConfigFormat::from_path(config_path))
+}
+
+/// Load a config in the given format; included fragments are still detected
+/// from their extensions
+pub fn load_config_as(config_path: &PathBuf, format: ConfigFormat) -> Result<CalendarConfig> {
+    let mut chain = Vec::new();
+    let mut loaded = Vec::new();
+    load_with_includes(config_path, format, &mut chain, &mut loaded)
+}
+
+fn read_config(config_path: &PathBuf, format: ConfigFormat) -> Result<CalendarConfig> {
     if !config_path.exists() {
-        eprintln!(
-            "Config file not found at {:?}, using empty configuration",
-            config_path
-        );
-        return CalendarConfig {
-            dates: Default::default(),
-            ranges: Default::default(),
-        };
+        return Err(Error::ConfigNotFound(config_path.clone()));
+    }
+
+    let contents = fs::read_to_string(config_path).map_err(|source| Error::Io {
+        path: config_path.clone(),
+        source,
+    })?;
+
+    let mut config: CalendarConfig = match format {
+        ConfigFormat::Toml => {
+            toml::from_str(&contents).map_err(|e| Error::parse(config_path.clone(), &contents, e))
+        }
+        ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(|e| Error::ParseYaml {
+            path: config_path.clone(),
+            source: Box::new(e),
+        }),
+        ConfigFormat::Json => serde_json::from_str(&contents).map_err(|e| Error::ParseJson {
+            path: config_path.clone(),
+            source: Box::new(e),
+        }),
+        ConfigFormat::Remind => {
+            let (mut config, skipped) = remind::parse(&contents);
+            config.warnings = skipped
+                .iter()
+                .map(|line| format!("{:?} {}; skipped", config_path, line))
+                .collect();
+            Ok(config)
+        }
+    }?;
+
+    // A busy block that doesn't make sense is left out of the calendar
+    let invalid_blocks: Vec<String> = config
+        .availability
+        .iter()
+        .filter_map(|block| {
+            let e = block.to_block().err()?;
+            Some(format!(
+                "{:?} busy block on {}: {}; skipped",
+                config_path, block.days, e
+            ))
+        })
+        .collect();
+    config.warnings.extend(invalid_blocks);
+    Ok(config)
+}
+
+/// A calendar from elsewhere, to be written out as TOML by `import`: an
+/// iCalendar `.ics` file, a vdir collection directory, or a config in any
+/// format, detected from its extension
+pub fn import_config(path: &PathBuf) -> Result<CalendarConfig> {
+    let collection = if path.is_dir() {
+        vdir::Collection::read(path).map_err(Error::ParseVdir)?
+    } else if path.extension().is_some_and(|ext| ext == "ics") {
+        let contents = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let events = vdir::parse(&contents).map_err(|(line, message)| {
+            Error::ParseVdir(vdir::ParseError {
+                path: path.clone(),
+                line,
+                message,
+            })
+        })?;
+        vdir::Collection {
+            events,
+            ..vdir::Collection::default()
+        }
+    } else {
+        return load_config(path);
+    };
+    let mut config = CalendarConfig::default();
+    collection.add_to(&mut config);
+    Ok(config)
+}
+
+/// `chain` holds the files currently being included, to catch cycles;
+/// `loaded` every file merged so far, so shared fragments are merged once
+fn load_with_includes(
+    config_path: &PathBuf,
+    format: ConfigFormat,
+    chain: &mut Vec<PathBuf>,
+    loaded: &mut Vec<PathBuf>,
+) -> Result<CalendarConfig> {
+    let mut config = read_config(config_path, format)?;
+    let canonical = fs::canonicalize(config_path).map_err(|source| Error::Io {
+        path: config_path.clone(),
+        source,
+    })?;
+    chain.push(canonical.clone());
+    loaded.push(canonical);
+
+    let dir = config_path.parent().unwrap_or(Path::new(""));
+    for collection in config.vdir_paths(dir)? {
+        vdir::Collection::read(&collection)
+            .map_err(Error::ParseVdir)?
+            .add_to(&mut config);
+    }
+    for include in config.include_paths(dir)? {
+        let canonical = fs::canonicalize(&include).map_err(|source| Error::Io {
+            path: include.clone(),
+            source,
+        })?;
+        if let Some(start) = chain.iter().position(|path| *path == canonical) {
+            let mut cycle = chain[start..].to_vec();
+            cycle.push(canonical);
+            return Err(Error::IncludeCycle(cycle));
+        }
+        if loaded.contains(&canonical) {
+            continue;
+        }
+        let format = ConfigFormat::from_path(&include);
+        let fragment = load_with_includes(&include, format, chain, loaded)?;
+        config.merge(fragment);
     }
 
-    let contents = fs::read_to_string(config_path).unwrap_or_else(|e| {
-        eprintln!("Failed to read config file {:?}: {}", config_path, e);
-        std::process::exit(1);
-    });
+    chain.pop();
+    Ok(config)
+}
 
-    toml::from_str(&contents).unwrap_or_else(|e| {
-        eprintln!("Failed to parse TOML config: {}", e);
-        std::process::exit(1);
-    })
+/// Refuse to modify a config marked `readonly = true`, or one that can't be
+/// loaded as `format` to tell. A missing config is not refused, so it can
+/// still be created.
+pub fn ensure_writable(config_path: &PathBuf, format: ConfigFormat) -> Result<()> {
+    match load_config_as(config_path, format) {
+        Ok(config) if config.readonly => Err(Error::ReadOnly(config_path.clone())),
+        Ok(_) | Err(Error::ConfigNotFound(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
 }
 
-pub fn build_calendar(year: i32, options: CalendarOptions, config: CalendarConfig) -> Calendar {
-    let details = config.parse_dates_for_year(year);
-    let ranges = config.parse_ranges_for_year(year);
-    Calendar::new(year, options, details, ranges)
+/// The calendar of `year` from `config`, owned or borrowed so several years
+/// can share one config
+pub fn build_calendar(
+    year: i32,
+    options: CalendarOptions,
+    config: impl Borrow<CalendarConfig>,
+) -> Calendar {
+    let config = config.borrow();
+    Calendar::new(year, options, config.events_for_year(year))
+        .with_availability(config.parse_availability())
+        .with_non_working(config.non_working_for_year(year))
+        .with_working_weekends(config.working_weekends_for_year(year))
+        .with_week_labels(config.week_labels_for_year(year))
 }
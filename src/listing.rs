@@ -0,0 +1,104 @@
+use crate::models::{Calendar, Event, Status};
+use crate::rendering::{Background, ColorPalette};
+use crate::text_width;
+use chrono::Datelike;
+use std::io::{self, Write};
+
+/// Width of the date column, fitting "YYYY-MM-DD to YYYY-MM-DD"
+pub(crate) const DATES_WIDTH: usize = 24;
+
+/// "YYYY-MM-DD", or "YYYY-MM-DD to YYYY-MM-DD" for multi-day events
+pub(crate) fn format_dates(event: &Event) -> String {
+    if event.is_single_day() {
+        event.start.format("%Y-%m-%d").to_string()
+    } else {
+        format!(
+            "{} to {}",
+            event.start.format("%Y-%m-%d"),
+            event.end.format("%Y-%m-%d")
+        )
+    }
+}
+
+/// Plain listing of a calendar's events, one per line in chronological order
+pub struct EventList<'a> {
+    calendar: &'a Calendar,
+    palette: ColorPalette,
+}
+
+impl<'a> EventList<'a> {
+    pub fn new(calendar: &'a Calendar) -> Self {
+        Self {
+            calendar,
+            palette: ColorPalette::new()
+                .with_contrast(calendar.options.contrast, Background::detect())
+                .with_theme_colors(calendar.options.theme.colors.clone())
+                .with_dim_factor(calendar.options.dim_factor),
+        }
+    }
+
+    pub fn render(&self) {
+        let mut out = io::stdout().lock();
+        self.render_to(&mut out)
+            .and_then(|_| out.flush())
+            .expect("failed printing to stdout");
+    }
+
+    /// Render without colors, for snapshots and plain-text consumers
+    pub fn render_to_string(&self) -> String {
+        let mut output = Vec::new();
+        EventList {
+            calendar: self.calendar,
+            palette: ColorPalette::disabled(),
+        }
+        .render_to(&mut output)
+        .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("event list is valid UTF-8")
+    }
+
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.palette = self.palette.with_colors(enabled);
+        self
+    }
+
+    /// Events touching the calendar's year, in chronological order
+    pub fn events(&self) -> Vec<&'a Event> {
+        let year = self.calendar.year;
+        self.calendar
+            .events()
+            .into_iter()
+            .filter(|event| event.start.year() <= year && event.end.year() >= year)
+            .collect()
+    }
+
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let events = self.events();
+        let color_width = events
+            .iter()
+            .filter_map(|event| event.color.as_deref())
+            .map(text_width::width)
+            .max()
+            .unwrap_or(0);
+
+        for event in events {
+            let dates = format_dates(event);
+            let mut description = event.description.clone().unwrap_or_default();
+            if let Some(status @ (Status::Requested | Status::Rejected)) = event.status {
+                description = format!("{} ({})", description, status.name());
+            }
+            let style = self.palette.annotation_style(event.color.as_deref());
+            let line = format!(
+                "{}{}{}{}  {}  {}",
+                style.render(),
+                dates,
+                style.render_reset(),
+                " ".repeat(DATES_WIDTH - dates.len()),
+                text_width::pad_end(event.color.as_deref().unwrap_or(""), color_width),
+                description.trim_start(),
+            );
+            writeln!(w, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use crate::models::{Direction, WeekStart};
+use chrono::Weekday;
+
+/// Language and regional conventions for labels and dates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    EnUs,
+    De,
+    Fi,
+    Fr,
+    Nl,
+    Sv,
+    He,
+    Ar,
+}
+
+impl Locale {
+    /// Parse a locale tag such as `de`, `de-DE`, or `de_DE.UTF-8`
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let tag = input
+            .split('.')
+            .next()
+            .unwrap_or("")
+            .replace('_', "-")
+            .to_lowercase();
+        let (language, region) = match tag.split_once('-') {
+            Some((language, region)) => (language, Some(region)),
+            None => (tag.as_str(), None),
+        };
+
+        match (language, region) {
+            ("en", Some("us")) => Ok(Locale::EnUs),
+            ("en", _) | ("c", None) | ("posix", None) => Ok(Locale::En),
+            ("de", _) => Ok(Locale::De),
+            ("fi", _) => Ok(Locale::Fi),
+            ("fr", _) => Ok(Locale::Fr),
+            ("nl", _) => Ok(Locale::Nl),
+            ("sv", _) => Ok(Locale::Sv),
+            ("he", _) | ("iw", _) => Ok(Locale::He),
+            ("ar", _) => Ok(Locale::Ar),
+            _ => Err(format!(
+                "Unsupported locale: '{}'. Use one of en, en-US, de, fi, fr, nl, sv, he, ar",
+                input
+            )),
+        }
+    }
+
+    /// Prefix placed before week numbers, e.g. "W01" or "KW01"
+    pub fn week_prefix(&self) -> &'static str {
+        match self {
+            Locale::En | Locale::EnUs => "W",
+            Locale::De => "KW",
+            Locale::Fi => "vko",
+            Locale::Fr => "S",
+            Locale::Nl => "wk",
+            Locale::Sv => "v",
+            Locale::He => "שב",
+            Locale::Ar => "أس",
+        }
+    }
+
+    /// First day of the week customary for the locale
+    pub fn week_start(&self) -> WeekStart {
+        match self {
+            Locale::EnUs | Locale::He | Locale::Ar => WeekStart::Sunday,
+            _ => WeekStart::Monday,
+        }
+    }
+
+    /// Which way the locale's script runs, and so the calendar's days
+    pub fn direction(&self) -> Direction {
+        match self {
+            Locale::He | Locale::Ar => Direction::Rtl,
+            _ => Direction::Ltr,
+        }
+    }
+
+    /// `strftime` format for the month and day in annotations
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Locale::En | Locale::EnUs => "%m/%d",
+            Locale::De | Locale::Fi => "%d.%m.",
+            Locale::Fr | Locale::He | Locale::Ar => "%d/%m",
+            Locale::Nl => "%d-%m",
+            Locale::Sv => "%m-%d",
+        }
+    }
+
+    pub fn month_name(&self, month: u32) -> &'static str {
+        Self::lookup_month(self.month_names(), month)
+    }
+
+    pub fn short_month_name(&self, month: u32) -> &'static str {
+        Self::lookup_month(self.short_month_names(), month)
+    }
+
+    fn lookup_month(names: [&'static str; 12], month: u32) -> &'static str {
+        match month {
+            1..=12 => names[month as usize - 1],
+            _ => "",
+        }
+    }
+
+    fn month_names(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En | Locale::EnUs => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Locale::De => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Locale::Fi => [
+                "tammikuu",
+                "helmikuu",
+                "maaliskuu",
+                "huhtikuu",
+                "toukokuu",
+                "kesäkuu",
+                "heinäkuu",
+                "elokuu",
+                "syyskuu",
+                "lokakuu",
+                "marraskuu",
+                "joulukuu",
+            ],
+            Locale::Fr => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Locale::Nl => [
+                "januari",
+                "februari",
+                "maart",
+                "april",
+                "mei",
+                "juni",
+                "juli",
+                "augustus",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            Locale::Sv => [
+                "januari",
+                "februari",
+                "mars",
+                "april",
+                "maj",
+                "juni",
+                "juli",
+                "augusti",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            Locale::He => [
+                "ינואר",
+                "פברואר",
+                "מרץ",
+                "אפריל",
+                "מאי",
+                "יוני",
+                "יולי",
+                "אוגוסט",
+                "ספטמבר",
+                "אוקטובר",
+                "נובמבר",
+                "דצמבר",
+            ],
+            Locale::Ar => [
+                "يناير",
+                "فبراير",
+                "مارس",
+                "أبريل",
+                "مايو",
+                "يونيو",
+                "يوليو",
+                "أغسطس",
+                "سبتمبر",
+                "أكتوبر",
+                "نوفمبر",
+                "ديسمبر",
+            ],
+        }
+    }
+
+    fn short_month_names(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En | Locale::EnUs => [
+                "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+            ],
+            Locale::De => [
+                "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+            ],
+            Locale::Fi => [
+                "tam", "hel", "maa", "huh", "tou", "kes", "hei", "elo", "syy", "lok", "mar", "jou",
+            ],
+            Locale::Fr => [
+                "janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov",
+                "déc",
+            ],
+            Locale::Nl => [
+                "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+            ],
+            Locale::Sv => [
+                "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+            ],
+            Locale::He => [
+                "ינו׳", "פבר׳", "מרץ", "אפר׳", "מאי", "יוני", "יולי", "אוג׳", "ספט׳", "אוק׳",
+                "נוב׳", "דצמ׳",
+            ],
+            // Arabic months are seldom shortened
+            Locale::Ar => self.month_names(),
+        }
+    }
+
+    /// Abbreviated weekday name used in the column header
+    pub fn weekday_name(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::En | Locale::EnUs => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            Locale::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+            Locale::Fi => ["ma", "ti", "ke", "to", "pe", "la", "su"],
+            Locale::Fr => ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+            Locale::Nl => ["ma", "di", "wo", "do", "vr", "za", "zo"],
+            Locale::Sv => ["mån", "tis", "ons", "tor", "fre", "lör", "sön"],
+            Locale::He => ["ב׳", "ג׳", "ד׳", "ה׳", "ו׳", "ש׳", "א׳"],
+            Locale::Ar => ["ن", "ث", "ر", "خ", "ج", "س", "ح"],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+}
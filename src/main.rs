@@ -1,10 +1,42 @@
-use chrono::Datelike;
-use clap::Parser;
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand};
+use compact_calendar_cli::cache::{Cache, CachePolicy};
+use compact_calendar_cli::config::{CalendarConfig, ConfigFormat};
+use compact_calendar_cli::datasets::{self, Datasets};
+use compact_calendar_cli::diff::ConfigDiff;
+use compact_calendar_cli::editing;
+use compact_calendar_cli::export::{self, ExportFormat};
+#[cfg(feature = "google")]
+use compact_calendar_cli::google;
+use compact_calendar_cli::habits::HabitLog;
+use compact_calendar_cli::heatmap::Heatmap;
+use compact_calendar_cli::holidays::Country;
+use compact_calendar_cli::listing::EventList;
+use compact_calendar_cli::locale::Locale;
 use compact_calendar_cli::models::{
-    CalendarOptions, ColorMode, MonthFilter, PastDateDisplay, WeekStart, WeekendDisplay,
+    parse_timezone, AnnotationOverflow, Calendar, CalendarChanges, CalendarOptions, CellWidth,
+    ColorMode, ContrastMode, DayNumbering, Direction, Highlight, Layout, MonthFilter,
+    OverlapDisplay, PastDateDisplay, PlainMarker, Status, TodayMarker, WeekColumn, WeekStart,
+    WeekendDisplay,
 };
-use compact_calendar_cli::rendering::CalendarRenderer;
-use std::path::PathBuf;
+use compact_calendar_cli::pager::{self, Paging};
+use compact_calendar_cli::paths;
+use compact_calendar_cli::planning::Plan;
+use compact_calendar_cli::profile::Profile;
+use compact_calendar_cli::rendering::{self, CalendarRenderer, YearLayout};
+use compact_calendar_cli::secondary::SecondaryCalendar;
+use compact_calendar_cli::settings::Settings;
+use compact_calendar_cli::sources;
+use compact_calendar_cli::summary::{Summary, SummaryCategory};
+use compact_calendar_cli::theme::Theme;
+use compact_calendar_cli::upcoming::Upcoming;
+use compact_calendar_cli::validation;
+use std::fmt::Display;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// Restore the default SIGPIPE signal handler.
 ///
@@ -22,32 +54,327 @@ fn restore_sigpipe_default() {
 }
 
 #[derive(Parser, Debug)]
-#[command(version, about)]
-struct Args {
+#[command(version, about, subcommand_negates_reqs = true)]
+struct Cli {
     /// Year to display (defaults to current year)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     year: Option<i32>,
 
-    /// Path to TOML configuration file with date details
-    #[arg(short, long, default_value = "calendar.toml")]
-    config: PathBuf,
+    /// Path to TOML configuration file with date details, or the https:// URL
+    /// of a shared one [default: the first calendar.toml found, see
+    /// `config-path`]
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
 
-    /// Week starts on Sunday (default is Monday)
-    #[arg(short, long)]
+    /// Format of the config file: toml, yaml, json or remind [default:
+    /// detected from the extension, TOML otherwise]
+    #[arg(long, global = true, value_parser = ConfigFormat::parse)]
+    config_format: Option<ConfigFormat>,
+
+    /// Load defaults and the config path from a named profile
+    /// ($XDG_CONFIG_HOME/compact-calendar/profiles/<PROFILE>.toml)
+    #[arg(short, long, global = true)]
+    profile: Option<String>,
+
+    /// Refuse to run commands that modify the config file
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Only include ranges with these approval statuses (requested, approved,
+    /// rejected) [default: requested,approved]
+    #[arg(long, global = true, value_delimiter = ',', value_parser = Status::parse)]
+    status: Vec<Status>,
+
+    /// Leave out dates and ranges with a lower `priority` (0 when unset)
+    #[arg(long, global = true, allow_negative_numbers = true)]
+    min_priority: Option<i32>,
+
+    /// Timezone deciding which day is today, e.g. "Asia/Tokyo" [default: the
+    /// config's `timezone`, else the local one]
+    #[arg(long, global = true, value_parser = parse_timezone)]
+    timezone: Option<Tz>,
+
+    /// Fetch the config's `[[sources]]` again instead of using their cached
+    /// copies
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    /// Use the cached copies of a remote config and its sources without
+    /// fetching anything
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    offline: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    show: ShowArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the calendar (the default when no command is given)
+    Show(Box<ShowArgs>),
+    /// Write a commented starter config file
+    Init(InitArgs),
+    /// Answer a few questions to write a complete config for the year
+    Plan(PlanArgs),
+    /// Add a date (or a range, with --end) to the config file
+    Add(AddArgs),
+    /// Open the config file in $VISUAL or $EDITOR, then check it
+    Edit,
+    /// Check the config file and report every problem with its line
+    Validate,
+    /// List every date and range of the year in chronological order
+    List(FilterArgs),
+    /// Count events, days and costs per color for each month of the year
+    Summary(SummaryArgs),
+    /// Show the next upcoming events with a countdown from today
+    Next(NextArgs),
+    /// Find stretches of consecutive days free of weekly busy blocks
+    Free(FreeArgs),
+    /// Report the dates and ranges added, removed or changed between two
+    /// configs for the year
+    Diff(DiffArgs),
+    /// Write the calendar to files
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Print which config file is used, and why
+    ConfigPath,
+    /// Inspect or empty the cache of fetched calendars and configs
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Fetch refreshed holiday datasets, which win over the built-in holidays
+    UpdateData(UpdateDataArgs),
+    /// Print an .ics file, vdir collection or YAML, JSON or remind config as
+    /// commented TOML, grouped by month
+    Import(ImportArgs),
+}
+
+/// Where the config path came from
+enum ConfigSource {
+    Flag,
+    Profile(String),
+    Search,
+    Default,
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Flag => write!(f, "from --config"),
+            ConfigSource::Profile(name) => write!(f, "from profile '{}'", name),
+            ConfigSource::Search => write!(f, "found in the config search path"),
+            ConfigSource::Default => write!(f, "default; no config file found"),
+        }
+    }
+}
+
+/// Which dates and ranges to show or export
+#[derive(Args, Debug)]
+struct FilterArgs {
+    /// Only include dates and ranges with one of these tags
+    #[arg(long = "tag", value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// Only include dates and ranges with one of these owners
+    #[arg(long = "owner", value_delimiter = ',')]
+    owners: Vec<String>,
+
+    /// Only include dates and ranges of one of these colors
+    #[arg(long = "color", value_delimiter = ',')]
+    colors: Vec<String>,
+
+    /// Leave out dates and ranges that end before this day (YYYY-MM-DD)
+    #[arg(long)]
+    from: Option<NaiveDate>,
+
+    /// Leave out dates and ranges that start after this day (YYYY-MM-DD)
+    #[arg(long)]
+    to: Option<NaiveDate>,
+}
+
+impl FilterArgs {
+    fn apply(&self, options: CalendarOptions) -> CalendarOptions {
+        CalendarOptions {
+            tags: self.tags.clone(),
+            owners: self.owners.clone(),
+            colors: self.colors.clone(),
+            from: self.from,
+            to: self.to,
+            ..options
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct SummaryArgs {
+    /// Group events by tag instead of by color
+    #[arg(long)]
+    by_tag: bool,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+struct NextArgs {
+    /// Number of events to show
+    #[arg(default_value_t = 5)]
+    count: usize,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+struct FreeArgs {
+    /// Minimum number of consecutive free days
+    #[arg(short, long, default_value_t = 3)]
+    days: usize,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// File or vdir collection directory to convert
+    path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct UpdateDataArgs {
+    /// Manifest listing the datasets and their SHA-256 [default: the
+    /// project's, checked against the SHA-256 pinned in this build]
+    #[arg(long, value_name = "URL", requires = "sha256")]
+    manifest: Option<String>,
+
+    /// SHA-256 the manifest given with --manifest must have
+    #[arg(long, value_name = "HASH", requires = "manifest")]
+    sha256: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// The config as it was
+    before: PathBuf,
+
+    /// The config as it is now
+    after: PathBuf,
+
+    /// Also render the newer calendar with the changed days highlighted
+    #[arg(long)]
+    calendar: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportCommand {
+    /// Export several years in several formats at once, in parallel
+    All(ExportAllArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// List the cached copies with their size and age
+    Status,
+    /// Remove every cached copy, keeping Google sign-ins
+    Clear,
+}
+
+#[derive(Args, Debug)]
+struct ExportAllArgs {
+    /// Years to export, both ends included (e.g. 2024..2027) [default: --year]
+    #[arg(long, value_parser = export::parse_years)]
+    years: Option<RangeInclusive<i32>>,
+
+    /// Formats to write: txt, ansi, list, summary, ics
+    #[arg(long, value_delimiter = ',', value_parser = ExportFormat::parse, default_value = "txt")]
+    formats: Vec<ExportFormat>,
+
+    /// Directory for the files, named like calendar-2024.ics
+    #[arg(short, long, default_value = ".")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+}
+
+#[derive(Args, Debug)]
+struct InitArgs {
+    /// Seed the config with the year's national holidays (us, gb, de, cn or kr)
+    #[arg(long, value_name = "COUNTRY")]
+    holidays: Option<String>,
+
+    /// Overwrite an existing config file
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+struct PlanArgs {
+    /// Overwrite an existing config file
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+struct AddArgs {
+    /// Date of the event (YYYY-MM-DD, or MM-DD to repeat yearly)
+    date: String,
+
+    /// Description shown next to the week
+    description: String,
+
+    /// Highlight color (required for ranges)
+    #[arg(long)]
+    color: Option<String>,
+
+    /// Last day of a date range starting at DATE
+    #[arg(long)]
+    end: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ShowArgs {
+    /// Week starts on Sunday, like --week-start sun
+    #[arg(short, long, conflicts_with = "week_start")]
     sunday: bool,
 
+    /// First day of the week: mon or sun [default: sun for the en-US, he and
+    /// ar locales, else mon]
+    #[arg(long, value_name = "DAY", value_parser = WeekStart::parse)]
+    week_start: Option<WeekStart>,
+
     /// Don't dim weekend dates (by default weekends are dimmed)
-    #[arg(long)]
+    #[arg(long, overrides_with = "dim_weekends")]
     no_dim_weekends: bool,
 
+    /// Dim weekend dates even if the settings say not to
+    #[arg(long, overrides_with = "no_dim_weekends", hide = true)]
+    dim_weekends: bool,
+
     /// Work mode: never apply colors to Saturday/Sunday
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_work")]
     work: bool,
 
+    /// Turn off work mode set in the settings
+    #[arg(long, overrides_with = "work", hide = true)]
+    no_work: bool,
+
     /// Don't strikethrough past dates (by default past dates are crossed out)
-    #[arg(long)]
+    #[arg(long, overrides_with = "strikethrough_past")]
     no_strikethrough_past: bool,
 
+    /// Cross out past dates even if the settings say not to
+    #[arg(long, overrides_with = "no_strikethrough_past", hide = true)]
+    strikethrough_past: bool,
+
+    /// Fade past dates instead of crossing them out, older months more
+    #[arg(long, overrides_with = "no_fade_past")]
+    fade_past: bool,
+
+    /// Don't fade past dates even if the settings do
+    #[arg(long, overrides_with = "fade_past", hide = true)]
+    no_fade_past: bool,
+
     /// Display a specific month (number 1-12, name like "march", or "current")
     #[arg(short = 'm', long)]
     month: Option<String>,
@@ -55,29 +382,1219 @@ struct Args {
     /// Display current month plus N additional months (requires --month current)
     #[arg(short = 'f', long)]
     following_months: Option<u32>,
+
+    /// Start at the current week, hiding the weeks already gone
+    #[arg(long, overrides_with = "no_remaining")]
+    remaining: bool,
+
+    /// Show the whole year even if the settings only show what remains
+    #[arg(long, overrides_with = "remaining", hide = true)]
+    no_remaining: bool,
+
+    /// Narrow day cells and abbreviated month names, for small panes
+    #[arg(long, conflicts_with = "wide", overrides_with = "no_compact")]
+    compact: bool,
+
+    /// Normal cells even if the settings make them compact
+    #[arg(long, overrides_with = "compact", hide = true)]
+    no_compact: bool,
+
+    /// Extra spacing around each day
+    #[arg(long, overrides_with = "no_wide")]
+    wide: bool,
+
+    /// Normal cells even if the settings make them wide
+    #[arg(long, overrides_with = "wide", hide = true)]
+    no_wide: bool,
+
+    /// High-contrast colors with a marker next to every colored date
+    #[arg(long, conflicts_with = "compact", overrides_with = "no_high_contrast")]
+    high_contrast: bool,
+
+    /// Normal colors even if the settings' theme is high-contrast
+    #[arg(long, overrides_with = "high_contrast", hide = true)]
+    no_high_contrast: bool,
+
+    /// Locale for month names, week labels and date formats (e.g. "de", "en-US")
+    #[arg(short, long)]
+    locale: Option<String>,
+
+    /// Which way the days run: ltr, or rtl with the week labels on the right
+    /// [default: rtl for the he and ar locales, else ltr]
+    #[arg(long, value_name = "DIR", value_parser = Direction::parse)]
+    direction: Option<Direction>,
+
+    /// Cut the annotations after each week to N characters, ending in "…",
+    /// and list the cut-off ones under the calendar
+    #[arg(long, value_name = "N")]
+    annotation_width: Option<usize>,
+
+    /// Continue annotations longer than --annotation-width on extra rows
+    /// instead of cutting them off
+    #[arg(long, overrides_with = "no_wrap_annotations")]
+    wrap_annotations: bool,
+
+    /// Cut annotations off even if the settings wrap them
+    #[arg(long, overrides_with = "wrap_annotations", hide = true)]
+    no_wrap_annotations: bool,
+
+    /// List what each color stands for below the calendar
+    #[arg(long, overrides_with = "no_legend")]
+    legend: bool,
+
+    /// Leave out the legend even if the settings show it
+    #[arg(long, overrides_with = "legend", hide = true)]
+    no_legend: bool,
+
+    /// Warn about overlapping colored ranges, and colored dates inside them,
+    /// where only one color can show
+    #[arg(long, overrides_with = "no_warn_conflicts")]
+    warn_conflicts: bool,
+
+    /// Don't warn about overlaps even if the settings do
+    #[arg(long, overrides_with = "warn_conflicts", hide = true)]
+    no_warn_conflicts: bool,
+
+    /// Paint days inside several colored ranges in a mix of their colors
+    /// instead of the color of the range on top
+    #[arg(long, overrides_with = "no_blend_overlaps")]
+    blend_overlaps: bool,
+
+    /// Don't blend overlaps even if the settings do
+    #[arg(long, overrides_with = "blend_overlaps", hide = true)]
+    no_blend_overlaps: bool,
+
+    /// How to mark today: underline, bold, inverse, a background color or a
+    /// character such as "▶" before the day
+    #[arg(long, value_name = "STYLE")]
+    today_marker: Option<String>,
+
+    /// How to mark colored days when NO_COLOR is set: none, star (*14), brackets ([14]) or symbols, one per color
+    /// [default: symbols]
+    #[arg(long, value_name = "STYLE", value_parser = PlainMarker::parse)]
+    plain_marker: Option<PlainMarker>,
+
+    /// Mark new moons with ● and full moons with ○ before the day
+    #[arg(long, overrides_with = "no_moon")]
+    moon: bool,
+
+    /// Leave out moon phases even if the settings show them
+    #[arg(long, overrides_with = "moon", hide = true)]
+    no_moon: bool,
+
+    /// Shade the plain days of even ISO weeks, for "A week / B week"
+    /// schedules
+    #[arg(long, overrides_with = "no_alternate_weeks")]
+    alternate_weeks: bool,
+
+    /// Don't shade alternate weeks even if the settings do
+    #[arg(long, overrides_with = "alternate_weeks", hide = true)]
+    no_alternate_weeks: bool,
+
+    /// Note February 29th and the 53rd ISO week of long years, which throw
+    /// off yearly and weekly schedules
+    #[arg(long, overrides_with = "no_leap")]
+    leap: bool,
+
+    /// Leave out leap notes even if the settings show them
+    #[arg(long, overrides_with = "leap", hide = true)]
+    no_leap: bool,
+
+    /// Number the days 001-365 through the year instead of by month
+    #[arg(long, overrides_with = "no_day_of_year")]
+    day_of_year: bool,
+
+    /// Number days by month even if the settings number them through the year
+    #[arg(long, overrides_with = "day_of_year", hide = true)]
+    no_day_of_year: bool,
+
+    /// What each day shows: day (01), unpadded ( 1), day-of-year (001) or a
+    /// strftime format such as "%m/%d"; cells widen to fit [default: day]
+    #[arg(long, value_name = "FORMAT", value_parser = DayNumbering::parse, conflicts_with = "day_of_year")]
+    day_format: Option<DayNumbering>,
+
+    /// Show the Julian Day Number (jd) or ISO week date (iso) of each
+    /// week's first day beside it
+    #[arg(long, value_name = "KIND")]
+    week_column: Option<String>,
+
+    /// Note the first day of each month of another calendar: hebrew,
+    /// islamic or chinese
+    #[arg(long, value_name = "CALENDAR")]
+    secondary_calendar: Option<String>,
+
+    /// How the year is laid out: vertical (weeks running down), horizontal
+    /// (months running across, for wide terminals), grid (a block per month)
+    /// or heatmap (a small shaded cell per day)
+    #[arg(long, value_name = "LAYOUT")]
+    layout: Option<String>,
+
+    /// Months to a row of --layout grid [default: 3]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=12))]
+    grid_columns: Option<u16>,
+
+    /// When to page the output through $PAGER (less otherwise): auto (when
+    /// it's taller than the terminal), always or never
+    #[arg(long, value_name = "WHEN", value_parser = Paging::parse)]
+    paging: Option<Paging>,
+
+    /// Note solstices, equinoxes and the days sunrise or sunset crosses the
+    /// times in the config's [daylight] section
+    #[arg(long, overrides_with = "no_daylight")]
+    daylight: bool,
+
+    /// Leave out daylight notes even if the settings show them
+    #[arg(long, overrides_with = "daylight", hide = true)]
+    no_daylight: bool,
+
+    /// Mark the coming days with their forecast weather (☀ ☁ ≡ ☂ ❄ ↯), from
+    /// Open-Meteo at the config's [weather] or [daylight] location
+    #[arg(long, overrides_with = "no_weather")]
+    weather: bool,
+
+    /// Leave out the weather even if the settings show it
+    #[arg(long, overrides_with = "weather", hide = true)]
+    no_weather: bool,
+
+    /// Tick off the days listed in a habit log (one YYYY-MM-DD date per
+    /// line, or a TOML `done` list) with ✓ and show the streak
+    #[arg(long, value_name = "FILE")]
+    log: Option<PathBuf>,
+
+    /// Shade each day by the commits made on it in a git repository, as a
+    /// heatmap
+    #[arg(long, value_name = "REPO")]
+    git: Option<PathBuf>,
+
+    /// Shade each day by a GitHub user's contributions on it, as a heatmap;
+    /// needs a token in GITHUB_TOKEN
+    #[arg(long, value_name = "USER", conflicts_with = "git")]
+    github: Option<String>,
+
+    /// Color a date without editing the config, optionally with a label
+    /// (e.g. 2025-08-15:red:Launch); may be repeated
+    #[arg(long, value_name = "DATE[:COLOR[:LABEL]]", value_parser = Highlight::parse)]
+    highlight: Vec<Highlight>,
+
+    /// Also copy the plain-text calendar (without colors) to the clipboard
+    #[arg(long)]
+    copy: bool,
+
+    /// Re-render when the config file changes, highlighting what changed
+    #[arg(long)]
+    watch: bool,
+
+    /// Show another config's calendar for the same year beside this one,
+    /// e.g. a partner's plans or last year's
+    #[arg(long, value_name = "CONFIG", conflicts_with = "watch")]
+    compare: Option<PathBuf>,
+
+    #[command(flatten)]
+    filters: FilterArgs,
 }
 
-fn main() {
-    restore_sigpipe_default();
-    let args = Args::parse();
-    let year = args.year.unwrap_or_else(|| chrono::Local::now().year());
+/// Print an error and exit with a failure status
+fn fail(error: impl Display) -> ! {
+    eprintln!("Error: {}", error);
+    std::process::exit(1);
+}
+
+/// Load the config along with the events of its `[[sources]]`. A source that
+/// can't be fetched is warned about and left out.
+fn load_with_sources(
+    path: &PathBuf,
+    format: ConfigFormat,
+    year: i32,
+    policy: CachePolicy,
+) -> compact_calendar_cli::Result<CalendarConfig> {
+    let mut config = compact_calendar_cli::load_config_as(path, format)?;
+    for warning in &config.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let mut fetched: Vec<Result<sources::Fetched, String>> = config
+        .sources
+        .iter()
+        .map(|source| sources::fetch(source, policy))
+        .collect();
+    if let Some(holidays) = &config.holidays {
+        fetched.push(sources::fetch_holidays(holidays, year, policy));
+    }
+    #[cfg(feature = "google")]
+    fetched.extend(config.google.iter().map(|calendar| {
+        google::fetch(calendar, year, policy, &|url, code| {
+            eprintln!(
+                "To read Google calendar {}, visit {} and enter {}",
+                calendar.calendar, url, code
+            )
+        })
+    }));
+    #[cfg(not(feature = "google"))]
+    if !config.google.is_empty() {
+        fetched.push(Err(
+            "[[google]] calendars need a build with the `google` feature".to_string(),
+        ));
+    }
+    for fetched in fetched {
+        match fetched {
+            Ok(fetched) => {
+                if let Some(warning) = fetched.warning {
+                    eprintln!("Warning: {}", warning);
+                }
+                fetched.collection.add_to(&mut config);
+            }
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+    Ok(config)
+}
 
-    let config = compact_calendar_cli::load_config(&args.config);
+/// Load the config, falling back to an empty one when the file doesn't exist
+fn load_config_or_empty(
+    path: &PathBuf,
+    format: ConfigFormat,
+    year: i32,
+    policy: CachePolicy,
+) -> CalendarConfig {
+    match load_with_sources(path, format, year, policy) {
+        Ok(config) => config,
+        Err(compact_calendar_cli::Error::ConfigNotFound(path)) => {
+            eprintln!(
+                "Config file not found at {:?}, using empty configuration",
+                path
+            );
+            Default::default()
+        }
+        Err(e) => fail(e),
+    }
+}
 
+/// Fill in defaults wherever the command line left them unset; an opposite
+/// flag like `--no-moon` keeps a setting from turning its flag on
+fn apply_settings(mut args: ShowArgs, settings: &Settings) -> ShowArgs {
+    if args.week_start.is_none() && !args.sunday {
+        args.week_start = settings
+            .week_start
+            .as_deref()
+            .map(WeekStart::parse)
+            .transpose()
+            .unwrap_or_else(|e| fail(e));
+        args.sunday = settings.sunday;
+    }
+    args.no_dim_weekends |= settings.no_dim_weekends && !args.dim_weekends;
+    args.work |= settings.work && !args.no_work;
+    args.no_strikethrough_past |= settings.no_strikethrough_past && !args.strikethrough_past;
+    args.fade_past |= settings.fade_past && !args.no_fade_past;
+    args.remaining |= settings.remaining && !args.no_remaining;
+    args.moon |= settings.moon && !args.no_moon;
+    args.alternate_weeks |= settings.alternate_weeks && !args.no_alternate_weeks;
+    args.leap |= settings.leap && !args.no_leap;
+    args.day_of_year |= settings.day_of_year && !args.no_day_of_year;
+    if args.day_format.is_none() && !args.day_of_year {
+        args.day_format = settings
+            .day_format
+            .as_deref()
+            .map(DayNumbering::parse)
+            .transpose()
+            .unwrap_or_else(|e| fail(e));
+    }
+    args.daylight |= settings.daylight && !args.no_daylight;
+    args.weather |= settings.weather && !args.no_weather;
+    if !args.compact && !args.wide {
+        args.compact = settings.compact && !args.no_compact;
+        args.wide = settings.wide && !args.no_wide;
+    }
+    if args.month.is_none() {
+        args.month = settings.month.clone();
+        args.following_months = args.following_months.or(settings.following_months);
+    }
+    if args.locale.is_none() {
+        args.locale = settings.locale.clone();
+    }
+    if args.direction.is_none() {
+        args.direction = settings
+            .direction
+            .as_deref()
+            .map(Direction::parse)
+            .transpose()
+            .unwrap_or_else(|e| fail(e));
+    }
+    args.annotation_width = args.annotation_width.or(settings.annotation_width);
+    args.wrap_annotations |= settings.wrap_annotations && !args.no_wrap_annotations;
+    args.legend |= settings.legend && !args.no_legend;
+    args.warn_conflicts |= settings.warn_conflicts && !args.no_warn_conflicts;
+    args.blend_overlaps |= settings.blend_overlaps && !args.no_blend_overlaps;
+    if args.week_column.is_none() {
+        args.week_column = settings.week_column.clone();
+    }
+    if args.secondary_calendar.is_none() {
+        args.secondary_calendar = settings.secondary_calendar.clone();
+    }
+    if args.layout.is_none() {
+        args.layout = settings.layout.clone();
+    }
+    args.grid_columns = args.grid_columns.or(settings.grid_columns);
+    if args.paging.is_none() {
+        args.paging = settings
+            .paging
+            .as_deref()
+            .map(Paging::parse)
+            .transpose()
+            .unwrap_or_else(|e| fail(e));
+    }
+    if args.today_marker.is_none() {
+        args.today_marker = settings.today_marker.clone();
+    }
+    if args.plain_marker.is_none() {
+        args.plain_marker = settings
+            .plain_marker
+            .as_deref()
+            .map(PlainMarker::parse)
+            .transpose()
+            .unwrap_or_else(|e| fail(e));
+    }
+    // The marker needs cell padding, so compact cells keep the normal theme
+    if !args.high_contrast && !args.no_high_contrast && !args.compact {
+        let contrast = settings.contrast().unwrap_or_else(|e| fail(e));
+        args.high_contrast = contrast == Some(ContrastMode::High);
+    }
+    args
+}
+
+fn show(
+    year: i32,
+    config_path: &Path,
+    format: ConfigFormat,
+    config: CalendarConfig,
+    args: ShowArgs,
+    filters: CalendarOptions,
+    policy: CachePolicy,
+) {
+    let month_filter = MonthFilter::from_cli_args(args.month.as_deref(), args.following_months)
+        .unwrap_or_else(|e| fail(e));
+
+    let locale = args
+        .locale
+        .as_deref()
+        .map(Locale::parse)
+        .transpose()
+        .unwrap_or_else(|e| fail(e))
+        .unwrap_or_default();
+
+    let today_marker = args
+        .today_marker
+        .as_deref()
+        .map(TodayMarker::parse)
+        .transpose()
+        .unwrap_or_else(|e| fail(e))
+        .unwrap_or(TodayMarker::Underline);
+
+    let week_column = args
+        .week_column
+        .as_deref()
+        .map(WeekColumn::parse)
+        .transpose()
+        .unwrap_or_else(|e| fail(e));
+
+    let secondary_calendar = args
+        .secondary_calendar
+        .as_deref()
+        .map(SecondaryCalendar::parse)
+        .transpose()
+        .unwrap_or_else(|e| fail(e));
+
+    let mut layout = args
+        .layout
+        .as_deref()
+        .map(Layout::parse)
+        .transpose()
+        .unwrap_or_else(|e| fail(e))
+        .unwrap_or(Layout::Vertical);
+    if let (Layout::Grid { columns }, Some(grid_columns)) = (&mut layout, args.grid_columns) {
+        *columns = grid_columns as usize;
+    }
+
+    let daylight = if args.daylight {
+        let daylight = config.daylight().unwrap_or_else(|e| fail(e));
+        Some(daylight.unwrap_or_else(|| {
+            fail("--daylight needs a [daylight] section with the latitude and longitude")
+        }))
+    } else {
+        None
+    };
+
+    // A forecast that can't be had leaves the calendar as it is
+    let forecast = if args.weather {
+        let weather = config.weather().unwrap_or_else(|e| fail(e));
+        let weather = weather.unwrap_or_else(|| {
+            fail("--weather needs a latitude and longitude in a [weather] or [daylight] section")
+        });
+        match sources::fetch_forecast(&weather, policy) {
+            Ok((forecast, warning)) => {
+                if let Some(warning) = warning {
+                    eprintln!("Warning: {}", warning);
+                }
+                let today = filters.today();
+                forecast
+                    .into_iter()
+                    .filter(|day| day.date >= today)
+                    .collect()
+            }
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let habits = args.log.as_deref().map(|path| {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+        HabitLog::parse(&contents).unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)))
+    });
+
+    let heatmap = if let Some(repo) = args.git.as_deref() {
+        Some(
+            Heatmap::from_git(repo, year)
+                .unwrap_or_else(|e| fail(format!("{}: {}", repo.display(), e))),
+        )
+    } else if let Some(login) = args.github.as_deref() {
+        let token = std::env::var("GITHUB_TOKEN")
+            .or_else(|_| std::env::var("GH_TOKEN"))
+            .unwrap_or_else(|_| fail("--github needs a token in GITHUB_TOKEN"));
+        Some(Heatmap::from_github(login, &token, year).unwrap_or_else(|e| fail(e)))
+    } else {
+        None
+    };
+
+    // --week-start or --sunday overrides the locale's customary first day of
+    // the week
+    let week_start = args
+        .week_start
+        .or(args.sunday.then_some(WeekStart::Sunday))
+        .unwrap_or_else(|| locale.week_start());
+
+    // Which events to include comes from the global options
     let options = CalendarOptions {
-        week_start: WeekStart::from_sunday_flag(args.sunday),
+        week_start,
         weekend_display: WeekendDisplay::from_no_dim_flag(args.no_dim_weekends),
         color_mode: ColorMode::from_work_flag(args.work),
-        past_date_display: PastDateDisplay::from_no_strikethrough_flag(args.no_strikethrough_past),
-        month_filter: MonthFilter::from_cli_args(args.month.as_deref(), args.following_months)
-            .unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            }),
+        past_date_display: PastDateDisplay::from_flags(args.no_strikethrough_past, args.fade_past),
+        month_filter,
+        remaining: args.remaining,
+        cell_width: CellWidth::from_flags(args.compact, args.wide),
+        direction: args.direction.unwrap_or(locale.direction()),
+        locale,
+        contrast: ContrastMode::from_high_contrast_flag(args.high_contrast),
+        annotation_width: args.annotation_width,
+        annotation_overflow: AnnotationOverflow::from_wrap_flag(args.wrap_annotations),
+        legend: args.legend,
+        overlap_display: OverlapDisplay::from_blend_flag(args.blend_overlaps),
+        highlights: args.highlight,
+        today_marker,
+        plain_marker: args.plain_marker.unwrap_or(PlainMarker::Symbols),
+        moon: args.moon,
+        alternate_weeks: args.alternate_weeks,
+        leap_markers: args.leap,
+        day_numbering: args
+            .day_format
+            .unwrap_or(DayNumbering::from_day_of_year_flag(args.day_of_year)),
+        week_column,
+        secondary_calendar,
+        layout,
+        daylight,
+        habits,
+        heatmap,
+        forecast,
+        ..filters
     };
 
-    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+    let calendar = compact_calendar_cli::build_calendar(year, options.clone(), config);
 
     let renderer = CalendarRenderer::new(&calendar);
-    renderer.render();
+    let paging = args.paging.unwrap_or_default();
+    if args.watch {
+        clear_screen();
+    }
+    let compared = args.compare.as_deref().map(|path| {
+        let config = load_with_sources(
+            &path.to_path_buf(),
+            ConfigFormat::from_path(path),
+            year,
+            policy,
+        )
+        .unwrap_or_else(|e| fail(e));
+        (
+            path,
+            compact_calendar_cli::build_calendar(year, options.clone(), config),
+        )
+    });
+    // Side by side, what --copy copies is too
+    let copied = match &compared {
+        Some((path, other)) => {
+            let other = CalendarRenderer::new(other);
+            let labelled = |path: &Path, text: String| format!("{}\n{}", path.display(), text);
+            print_paged(
+                &rendering::side_by_side(
+                    &labelled(config_path, render_to_string_with_colors(&renderer)),
+                    &labelled(path, render_to_string_with_colors(&other)),
+                ),
+                paging,
+            );
+            args.copy.then(|| {
+                rendering::side_by_side(
+                    &labelled(config_path, renderer.render_to_string()),
+                    &labelled(path, other.render_to_string()),
+                )
+            })
+        }
+        // A re-rendering calendar can't be paged
+        None if args.watch => {
+            renderer.render();
+            None
+        }
+        None => {
+            print_paged(&render_to_string_with_colors(&renderer), paging);
+            None
+        }
+    };
+    if args.warn_conflicts {
+        for conflict in calendar.conflicts() {
+            eprintln!("Warning: {}", conflict);
+        }
+    }
+    if args.copy {
+        copy_to_clipboard(&copied.unwrap_or_else(|| renderer.render_to_string()));
+        eprintln!("Copied to the clipboard");
+    }
+    if args.watch {
+        watch(year, config_path, format, options, calendar, policy);
+    }
+}
+
+/// How often `--watch` checks the config for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// How long changed days stay highlighted after a re-render
+const HIGHLIGHT_FOR: Duration = Duration::from_secs(3);
+
+/// Re-render whenever the config file changes, briefly highlighting the days
+/// and annotations that changed. Only the weeks touched by a change are
+/// rendered again.
+fn watch(
+    year: i32,
+    config_path: &Path,
+    format: ConfigFormat,
+    options: CalendarOptions,
+    mut previous: Calendar,
+    policy: CachePolicy,
+) -> ! {
+    let modified = || {
+        std::fs::metadata(config_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    let mut last_modified = modified();
+    let mut layout = CalendarRenderer::new(&previous).layout();
+    let mut highlighted = CalendarChanges::default();
+    let mut highlight_until = None;
+
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        let current = modified();
+        if current != last_modified {
+            last_modified = current;
+            // Keep showing the last good render while the config is broken
+            match load_with_sources(&config_path.to_path_buf(), format, year, policy) {
+                Ok(config) => {
+                    let calendar =
+                        compact_calendar_cli::build_calendar(year, options.clone(), config);
+                    let changes = calendar.changes_since(&previous);
+                    // Weeks still highlighted from the last change are redrawn too
+                    let mut touched = changes.clone();
+                    touched.extend(std::mem::take(&mut highlighted));
+                    layout.update(
+                        &CalendarRenderer::new(&calendar).with_changes(changes.clone()),
+                        &touched,
+                    );
+                    clear_screen();
+                    print_layout(&layout);
+                    previous = calendar;
+                    highlighted = changes;
+                    highlight_until = Some(Instant::now() + HIGHLIGHT_FOR);
+                }
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        } else if highlight_until.is_some_and(|until| Instant::now() >= until) {
+            highlight_until = None;
+            layout.update(
+                &CalendarRenderer::new(&previous),
+                &std::mem::take(&mut highlighted),
+            );
+            clear_screen();
+            print_layout(&layout);
+        }
+    }
+}
+
+fn render_to_string_with_colors(renderer: &CalendarRenderer) -> String {
+    let mut out = Vec::new();
+    renderer
+        .render_to(&mut out)
+        .expect("writing to a Vec cannot fail");
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn print_paged(text: &str, paging: Paging) {
+    pager::print(text, paging).expect("failed printing to stdout");
+}
+
+fn print_layout(layout: &YearLayout) {
+    use std::io::Write;
+
+    let mut out = io::stdout().lock();
+    layout
+        .write_to(&mut out)
+        .and_then(|_| out.flush())
+        .expect("failed printing to stdout");
+}
+
+fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+}
+
+/// Clipboard commands to try in order, with their arguments
+fn clipboard_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        // Wayland, then X11, then WSL's bridge to the Windows clipboard
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+            ("clip.exe", &[]),
+        ]
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    for (program, args) in clipboard_commands() {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = child
+            .stdin
+            .take()
+            .map(|mut stdin| stdin.write_all(text.as_bytes()))
+            .transpose();
+        if child.wait().is_ok_and(|status| status.success()) && written.is_ok() {
+            return;
+        }
+    }
+
+    let tried: Vec<&str> = clipboard_commands()
+        .iter()
+        .map(|(program, _)| *program)
+        .collect();
+    fail(format!(
+        "could not copy to the clipboard, tried {}",
+        tried.join(", ")
+    ));
+}
+
+fn list(year: i32, config: CalendarConfig, options: CalendarOptions) {
+    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+    EventList::new(&calendar).render();
+}
+
+fn summary(year: i32, config: CalendarConfig, options: CalendarOptions, args: SummaryArgs) {
+    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+    Summary::new(&calendar)
+        .with_category(SummaryCategory::from_by_tag_flag(args.by_tag))
+        .render();
+}
+
+fn next(config: CalendarConfig, options: CalendarOptions, args: NextArgs) {
+    // Look into next year too, so yearly dates keep coming up in December
+    let year = options.today().year();
+    let events = config.events_for_years(year..=year + 1);
+    let calendar = Calendar::new(year, options, events);
+    Upcoming::new(&calendar, args.count).render();
+}
+
+fn diff(year: i32, options: CalendarOptions, args: DiffArgs, policy: CachePolicy) {
+    let calendar = |path: &PathBuf| {
+        let config = load_with_sources(path, ConfigFormat::from_path(path), year, policy)
+            .unwrap_or_else(|e| fail(e));
+        compact_calendar_cli::build_calendar(year, options.clone(), config)
+    };
+    let (before, after) = (calendar(&args.before), calendar(&args.after));
+    let diff = ConfigDiff::new(&before, &after);
+    if args.calendar {
+        CalendarRenderer::new(&after)
+            .with_changes(diff.changes().clone())
+            .render();
+        println!();
+    }
+    diff.render();
+}
+
+fn free(year: i32, config: CalendarConfig, options: CalendarOptions, args: FreeArgs) {
+    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+    let today = calendar.today();
+    let (start, end) = MonthFilter::All.get_date_range(year, today);
+    // Only look ahead from today when searching the current year
+    let from = if year == today.year() { today } else { start };
+
+    for (first, last) in calendar.free_runs(from, end, args.days) {
+        println!(
+            "{} to {}  {}-{}  {} days",
+            first.format("%Y-%m-%d"),
+            last.format("%Y-%m-%d"),
+            first.format("%a"),
+            last.format("%a"),
+            (last - first).num_days() + 1
+        );
+    }
+}
+
+fn export_all(year: i32, config: CalendarConfig, options: CalendarOptions, args: ExportAllArgs) {
+    let years = args.years.unwrap_or(year..=year);
+    let files = export::export_all(&config, &options, years, &args.formats);
+    let paths = export::write_all(&args.output, &files).unwrap_or_else(|e| fail(e));
+    for path in paths {
+        println!("Wrote {}", path.display());
+    }
+}
+
+fn init(year: i32, config_path: &Path, format: ConfigFormat, args: InitArgs) {
+    refuse_overwrite(config_path, format, args.force);
+
+    let holidays = match args.holidays.as_deref() {
+        Some(country) => datasets::holidays_or_builtin(
+            Datasets::user().ok().as_ref(),
+            Country::parse(country).unwrap_or_else(|e| fail(e)),
+            year,
+        ),
+        None => Vec::new(),
+    };
+    let seed: Vec<(NaiveDate, &str)> = holidays
+        .iter()
+        .map(|(date, name)| (*date, name.as_str()))
+        .collect();
+
+    write_new_config(config_path, &CalendarConfig::starter(year, &seed));
+}
+
+fn plan(year: i32, config_path: &Path, format: ConfigFormat, args: PlanArgs) {
+    refuse_overwrite(config_path, format, args.force);
+
+    let plan = Plan::ask(year, io::stdin().lock(), io::stdout()).unwrap_or_else(|e| fail(e));
+    println!();
+    write_new_config(config_path, &plan.to_toml());
+}
+
+/// Stop before writing TOML into a config in another format
+fn require_toml(config_path: &Path, format: ConfigFormat) {
+    if format != ConfigFormat::Toml {
+        fail(format!(
+            "{} is not a TOML config; only TOML configs can be written",
+            config_path.display()
+        ));
+    }
+}
+
+/// Stop before replacing an existing config unless `--force` was given
+fn refuse_overwrite(config_path: &Path, format: ConfigFormat, force: bool) {
+    require_toml(config_path, format);
+    if config_path.exists() && !force {
+        fail(format!(
+            "{} already exists, use --force to overwrite it",
+            config_path.display()
+        ));
+    }
+}
+
+fn write_new_config(config_path: &Path, contents: &str) {
+    if let Some(dir) = config_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(dir).unwrap_or_else(|e| fail(e));
+    }
+    std::fs::write(config_path, contents).unwrap_or_else(|e| fail(e));
+    println!("Wrote {}", config_path.display());
+}
+
+fn add(config_path: &PathBuf, format: ConfigFormat, args: AddArgs) {
+    require_toml(config_path, format);
+    let contents = if config_path.exists() {
+        std::fs::read_to_string(config_path).unwrap_or_else(|e| fail(e))
+    } else {
+        String::new()
+    };
+
+    let updated = match &args.end {
+        Some(end) => {
+            let color = args
+                .color
+                .as_deref()
+                .unwrap_or_else(|| fail("ranges require --color"));
+            editing::add_range(&contents, &args.date, end, color, Some(&args.description))
+        }
+        None => editing::add_date(
+            &contents,
+            &args.date,
+            &args.description,
+            args.color.as_deref(),
+        ),
+    }
+    .unwrap_or_else(|e| fail(e));
+
+    std::fs::write(config_path, updated).unwrap_or_else(|e| fail(e));
+    println!("Added {} to {}", args.date, config_path.display());
+}
+
+fn edit(config_path: &PathBuf, format: ConfigFormat, theme: &Theme) {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| fail("neither $VISUAL nor $EDITOR is set"));
+    // Allow editors configured with arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_else(|| fail("$EDITOR is empty"));
+
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(config_path)
+        .status()
+        .unwrap_or_else(|e| fail(format!("failed to run {}: {}", program, e)));
+    if !status.success() {
+        fail(format!("{} exited with {}", program, status));
+    }
+
+    validate(config_path, format, theme);
+}
+
+/// Report every problem in the config, exiting with a failure status if any
+fn validate(config_path: &PathBuf, format: ConfigFormat, theme: &Theme) {
+    if !config_path.exists() {
+        fail(compact_calendar_cli::Error::ConfigNotFound(
+            config_path.clone(),
+        ));
+    }
+    let contents = std::fs::read_to_string(config_path).unwrap_or_else(|e| fail(e));
+
+    let problems = validation::validate_with_theme(&contents, format, theme);
+    for problem in &problems {
+        eprintln!("{}: {}", config_path.display(), problem);
+    }
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
+    println!("{} is valid", config_path.display());
+}
+
+fn config_path_report(config_path: &Path, source: ConfigSource, remote: Option<&str>) {
+    match remote {
+        Some(url) => println!("{} ({}, cached at {})", url, source, config_path.display()),
+        None => println!("{} ({})", config_path.display(), source),
+    }
+    if matches!(source, ConfigSource::Search | ConfigSource::Default) {
+        println!("searched:");
+        for candidate in paths::config_candidates() {
+            println!("  {}", candidate.display());
+        }
+    }
+}
+
+fn import(args: ImportArgs) {
+    let config = compact_calendar_cli::import_config(&args.path).unwrap_or_else(|e| fail(e));
+    for warning in &config.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    print!("{}", config.to_commented_toml());
+}
+
+fn update_data(args: UpdateDataArgs) {
+    let datasets = Datasets::user().unwrap_or_else(|e| fail(e));
+    let (manifest, sha256) = match (&args.manifest, &args.sha256) {
+        (Some(manifest), Some(sha256)) => (manifest.as_str(), sha256.as_str()),
+        _ => (datasets::MANIFEST_URL, datasets::MANIFEST_SHA256),
+    };
+    let results = datasets
+        .update(manifest, sha256)
+        .unwrap_or_else(|e| fail(e));
+    let mut failed = false;
+    for result in results {
+        match result {
+            Ok(installed) => println!("Installed {}", installed),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn cache(command: CacheCommand) {
+    let cache = Cache::user().unwrap_or_else(|e| fail(e));
+    match command {
+        CacheCommand::Status => {
+            let entries = cache.entries();
+            println!("{}", cache.dir().display());
+            let width = entries.iter().map(|entry| entry.kind.len()).max();
+            for entry in &entries {
+                let age = entry.age.map_or("unknown age".to_string(), |age| {
+                    format!("{} old", format_age(age))
+                });
+                println!(
+                    "  {:width$}  {:>8}  {:>9}  {}",
+                    entry.kind,
+                    format_size(entry.size),
+                    age,
+                    entry.key,
+                    width = width.unwrap_or(0)
+                );
+            }
+            let total = entries.iter().map(|entry| entry.size).sum();
+            let plural = if entries.len() == 1 { "copy" } else { "copies" };
+            println!(
+                "{} cached {}, {}",
+                entries.len(),
+                plural,
+                format_size(total)
+            );
+        }
+        CacheCommand::Clear => {
+            let removed = cache
+                .clear()
+                .unwrap_or_else(|e| fail(format!("{}: {}", cache.dir().display(), e)));
+            let plural = if removed == 1 { "copy" } else { "copies" };
+            println!("Removed {} cached {}", removed, plural);
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+    }
+}
+
+fn format_age(age: std::time::Duration) -> String {
+    match age.as_secs() {
+        seconds @ 0..=59 => format!("{}s", seconds),
+        seconds @ 60..=3599 => format!("{}m", seconds / 60),
+        seconds @ 3600..=86399 => format!("{}h", seconds / 3600),
+        seconds => format!("{}d", seconds / 86400),
+    }
+}
+
+/// Stop before a command that modifies the config, if that isn't allowed. A
+/// config that doesn't load only stops commands other than the ones that
+/// repair it.
+fn ensure_writable(config_path: &PathBuf, format: ConfigFormat, read_only: bool, repairs: bool) {
+    use compact_calendar_cli::Error;
+    if read_only {
+        fail(Error::ReadOnly(config_path.clone()));
+    }
+    match compact_calendar_cli::ensure_writable(config_path, format) {
+        Err(e @ Error::ReadOnly(_)) => fail(e),
+        Err(_) if repairs => {}
+        result => result.unwrap_or_else(|e| fail(e)),
+    }
+}
+
+fn main() {
+    restore_sigpipe_default();
+    let cli = Cli::parse();
+
+    // The cache is the same whichever config is used, and isn't fetched into
+    let cli = match cli.command {
+        Some(Command::Cache(command)) => return cache(command),
+        _ => cli,
+    };
+
+    let profile = cli
+        .profile
+        .as_deref()
+        .map(|name| Profile::load(name).unwrap_or_else(|e| fail(e)));
+    let (config_path, source) = match (cli.config, &profile) {
+        (Some(path), _) => (path, ConfigSource::Flag),
+        (None, Some(profile)) if profile.config.is_some() => (
+            profile.config.clone().unwrap(),
+            ConfigSource::Profile(cli.profile.clone().unwrap_or_default()),
+        ),
+        _ => match paths::find_config() {
+            Some(path) => (path, ConfigSource::Search),
+            None => (
+                PathBuf::from(paths::CONFIG_FILE_NAME),
+                ConfigSource::Default,
+            ),
+        },
+    };
+
+    let policy = match (cli.refresh, cli.offline) {
+        (true, _) => CachePolicy::Refresh,
+        (_, true) => CachePolicy::Offline,
+        _ => CachePolicy::Fresh,
+    };
+
+    // A config on a server is read from its cached copy, and never modified
+    let remote = config_path
+        .to_str()
+        .filter(|path| path.starts_with("https://") || path.starts_with("http://"))
+        .map(str::to_string);
+    let config_path = match &remote {
+        Some(url) => {
+            let (cached, warning) = sources::fetch_config(url, policy).unwrap_or_else(|e| fail(e));
+            if let Some(warning) = warning {
+                eprintln!("Warning: {}", warning);
+            }
+            cached
+        }
+        None => config_path,
+    };
+    let read_only = cli.read_only || remote.is_some();
+
+    let mut options = CalendarOptions::default();
+    if !cli.status.is_empty() {
+        options.statuses = cli.status;
+    }
+    options.min_priority = cli.min_priority;
+
+    let format = cli
+        .config_format
+        .unwrap_or_else(|| ConfigFormat::from_path(&config_path));
+
+    // The config's timezone decides today, and so the default year. A broken
+    // config is reported by the command itself, and left for `validate` and
+    // `edit` to point out.
+    let repairs_config = matches!(cli.command, Some(Command::Validate | Command::Edit));
+    options.timezone = cli.timezone.or_else(|| {
+        let config = compact_calendar_cli::load_config_as(&config_path, format).ok()?;
+        match config.timezone() {
+            Err(_) if repairs_config => None,
+            timezone => timezone.unwrap_or_else(|e| fail(e)),
+        }
+    });
+    let year = cli.year.unwrap_or_else(|| options.today().year());
+
+    // Only commands that draw anything need the theme, and a broken one
+    // mustn't stop the config being repaired
+    let renders = !matches!(
+        cli.command,
+        Some(
+            Command::Init(_)
+                | Command::Plan(_)
+                | Command::Add(_)
+                | Command::ConfigPath
+                | Command::Cache(_)
+                | Command::UpdateData(_)
+                | Command::Import(_)
+        )
+    );
+    if renders {
+        let theme = match profile
+            .as_ref()
+            .and_then(|profile| profile.theme_file.as_ref())
+        {
+            Some(path) => Theme::load_from(path),
+            None => Theme::load(),
+        };
+        options.theme = theme.unwrap_or_else(|e| {
+            if !repairs_config {
+                fail(e);
+            }
+            eprintln!("Warning: {}", e);
+            Theme::default()
+        });
+    }
+
+    match cli.command.unwrap_or(Command::Show(Box::new(cli.show))) {
+        Command::Show(args) => {
+            let mut args = *args;
+            // The command line wins over the profile, which wins over the config
+            let config = load_config_or_empty(&config_path, format, year, policy);
+            if let Some(profile) = &profile {
+                args = apply_settings(args, &profile.settings);
+            }
+            args = apply_settings(args, &config.settings);
+            for (_, conflict) in config.style.conflicts(&config.settings) {
+                eprintln!("Warning: {}", conflict);
+            }
+            let style = config.style.settings(&config.settings);
+            args = apply_settings(args, &style.unwrap_or_else(|e| fail(e)));
+            options.dim_factor = config.style.dim_factor().unwrap_or_else(|e| fail(e));
+            let options = args.filters.apply(options);
+            show(year, &config_path, format, config, args, options, policy)
+        }
+        Command::Init(args) => {
+            ensure_writable(&config_path, format, read_only, false);
+            init(year, &config_path, format, args)
+        }
+        Command::Plan(args) => {
+            ensure_writable(&config_path, format, read_only, false);
+            plan(year, &config_path, format, args)
+        }
+        Command::Add(args) => {
+            ensure_writable(&config_path, format, read_only, false);
+            add(&config_path, format, args)
+        }
+        Command::Edit => {
+            ensure_writable(&config_path, format, read_only, repairs_config);
+            edit(&config_path, format, &options.theme)
+        }
+        Command::Validate => validate(&config_path, format, &options.theme),
+        Command::List(filters) => list(
+            year,
+            load_config_or_empty(&config_path, format, year, policy),
+            filters.apply(options),
+        ),
+        Command::Summary(args) => summary(
+            year,
+            load_config_or_empty(&config_path, format, year, policy),
+            args.filters.apply(options),
+            args,
+        ),
+        Command::Next(args) => next(
+            load_config_or_empty(&config_path, format, year, policy),
+            args.filters.apply(options),
+            args,
+        ),
+        Command::Free(args) => free(
+            year,
+            load_config_or_empty(&config_path, format, year, policy),
+            options,
+            args,
+        ),
+        Command::Diff(args) => diff(year, options, args, policy),
+        Command::Export(ExportCommand::All(args)) => export_all(
+            year,
+            load_config_or_empty(&config_path, format, year, policy),
+            args.filters.apply(options),
+            args,
+        ),
+        Command::ConfigPath => config_path_report(&config_path, source, remote.as_deref()),
+        Command::UpdateData(args) => update_data(args),
+        Command::Import(args) => import(args),
+        Command::Cache(command) => cache(command),
+    }
 }
@@ -1,5 +1,22 @@
-use chrono::{Datelike, NaiveDate};
-use std::collections::HashMap;
+use crate::availability::AvailabilityBlock;
+use crate::dst::{self, Transition};
+use crate::error::Error;
+use crate::habits::HabitLog;
+use crate::heatmap::Heatmap;
+use crate::index::IntervalIndex;
+use crate::locale::Locale;
+use crate::moon::{self, MoonPhase};
+use crate::rendering::ColorPalette;
+use crate::secondary::SecondaryCalendar;
+use crate::sun::Daylight;
+use crate::text_width;
+use crate::theme::Theme;
+use crate::weather::{DayForecast, Weather};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WeekStart {
@@ -8,6 +25,14 @@ pub enum WeekStart {
 }
 
 impl WeekStart {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "mon" | "monday" => Ok(Self::Monday),
+            "sun" | "sunday" => Ok(Self::Sunday),
+            _ => Err(format!("Invalid week start: '{}'. Use mon or sun", input)),
+        }
+    }
+
     pub fn from_sunday_flag(sunday: bool) -> Self {
         if sunday {
             Self::Sunday
@@ -53,14 +78,369 @@ impl ColorMode {
 pub enum PastDateDisplay {
     Strikethrough,
     Normal,
+    /// Dimmed more the older they are, a month at a time
+    Faded,
 }
 
 impl PastDateDisplay {
-    pub fn from_no_strikethrough_flag(no_strikethrough: bool) -> Self {
-        if no_strikethrough {
+    pub fn from_flags(no_strikethrough: bool, fade: bool) -> Self {
+        match (no_strikethrough, fade) {
+            (_, true) => Self::Faded,
+            (true, _) => Self::Normal,
+            _ => Self::Strikethrough,
+        }
+    }
+}
+
+/// What happens to annotations longer than the annotation width
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationOverflow {
+    /// Cut off with "…"
+    Truncate,
+    /// Continued on extra rows under the week
+    Wrap,
+}
+
+impl AnnotationOverflow {
+    pub fn from_wrap_flag(wrap: bool) -> Self {
+        if wrap {
+            Self::Wrap
+        } else {
+            Self::Truncate
+        }
+    }
+}
+
+/// How a day inside several colored ranges is painted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapDisplay {
+    /// In the color of the range on top, see [`Calendar::coloring_event`]
+    Top,
+    /// In a mix of all their colors
+    Blend,
+}
+
+impl OverlapDisplay {
+    pub fn from_blend_flag(blend: bool) -> Self {
+        if blend {
+            Self::Blend
+        } else {
+            Self::Top
+        }
+    }
+}
+
+/// Parse an IANA timezone name such as "Asia/Tokyo"
+pub fn parse_timezone(input: &str) -> Result<Tz, String> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| format!("unknown timezone '{}'", input))
+}
+
+/// How today's date stands out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TodayMarker {
+    Underline,
+    Bold,
+    Inverse,
+    /// Today's cell painted in a color from the palette
+    Background(String),
+    /// A character such as '▶' just before the day; compact cells have no
+    /// room for it, so they underline today instead
+    Glyph(char),
+}
+
+impl TodayMarker {
+    /// Parse "underline", "bold", "inverse", a color name or a single
+    /// character
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let trimmed = input.trim();
+        match trimmed.to_lowercase().as_str() {
+            "underline" => return Ok(Self::Underline),
+            "bold" => return Ok(Self::Bold),
+            "inverse" => return Ok(Self::Inverse),
+            _ => {}
+        }
+        if ColorPalette::get_color_value(trimmed).is_some() {
+            return Ok(Self::Background(trimmed.to_string()));
+        }
+        let mut chars = trimmed.chars();
+        match (chars.next(), chars.next()) {
+            (Some(glyph), None) => Ok(Self::Glyph(glyph)),
+            _ => Err(format!(
+                "Invalid today marker: '{}'. Use underline, bold, inverse, a color or a single character",
+                input
+            )),
+        }
+    }
+}
+
+/// How colored days stand out when colors are off, so they don't look like
+/// every other day. Needs cell padding, so compact cells go without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlainMarker {
+    None,
+    /// `*14`
+    Star,
+    /// `[14]`
+    Brackets,
+    /// A symbol per color, such as `+14` and `#14`, leading the annotations
+    /// too so they tell which is which
+    Symbols,
+}
+
+impl PlainMarker {
+    /// Symbols handed to the colors in the order they first appear
+    pub const SYMBOLS: [char; 10] = ['*', '+', '#', '%', '@', '&', '=', '~', '^', '!'];
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "star" => Ok(Self::Star),
+            "brackets" => Ok(Self::Brackets),
+            "symbols" => Ok(Self::Symbols),
+            _ => Err(format!(
+                "Invalid plain marker: '{}'. Use none, star, brackets or symbols",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContrastMode {
+    Normal,
+    High,
+}
+
+impl ContrastMode {
+    pub fn from_high_contrast_flag(high_contrast: bool) -> Self {
+        if high_contrast {
+            Self::High
+        } else {
             Self::Normal
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellWidth {
+    Compact,
+    Normal,
+    Wide,
+}
+
+impl CellWidth {
+    pub fn from_flags(compact: bool, wide: bool) -> Self {
+        match (compact, wide) {
+            (true, _) => Self::Compact,
+            (false, true) => Self::Wide,
+            (false, false) => Self::Normal,
+        }
+    }
+
+    /// Spaces on each side of the two-digit day number
+    pub fn padding(&self) -> usize {
+        match self {
+            Self::Compact => 0,
+            Self::Normal => 1,
+            Self::Wide => 2,
+        }
+    }
+
+    /// Width of a single day cell, including the gap before the next day
+    pub fn cell_width(&self) -> usize {
+        2 + 2 * self.padding() + 1
+    }
+
+    /// Width of the seven day cells of a week row
+    pub fn calendar_width(&self) -> usize {
+        7 * self.cell_width() - 1
+    }
+}
+
+/// Which number each day cell shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DayNumbering {
+    /// 01 to 31
+    DayOfMonth,
+    /// 1 to 31, without the leading zero
+    Unpadded,
+    /// 001 to 365 (366 in leap years), for "DOY" schedules
+    DayOfYear,
+    /// A `strftime` format such as "%m/%d", right-aligned in `width` columns
+    Custom { format: String, width: usize },
+}
+
+impl DayNumbering {
+    pub fn from_day_of_year_flag(day_of_year: bool) -> Self {
+        if day_of_year {
+            Self::DayOfYear
         } else {
-            Self::Strikethrough
+            Self::DayOfMonth
+        }
+    }
+
+    /// Parse "day" (01), "unpadded" ( 1), "day-of-year" (001) or a
+    /// `strftime` format such as "%m/%d"
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "day" => return Ok(Self::DayOfMonth),
+            "unpadded" => return Ok(Self::Unpadded),
+            "day-of-year" => return Ok(Self::DayOfYear),
+            _ => {}
+        }
+        let invalid = || {
+            format!(
+                "Invalid day format: '{}'. Use day, unpadded, day-of-year or a strftime format such as \"%m/%d\"",
+                input
+            )
+        };
+        if !input.contains('%') || StrftimeItems::new(input).any(|item| item == Item::Error) {
+            return Err(invalid());
+        }
+        // Wide enough for every day of a leap year, whatever the month or
+        // weekday names. Times and zones can't be formatted from a date and
+        // fail here, as do newlines and tabs breaking up the grid.
+        let mut width = 0;
+        for date in NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .iter_days()
+            .take(366)
+        {
+            let mut text = String::new();
+            write!(text, "{}", date.format(input)).map_err(|_| invalid())?;
+            if text.chars().any(char::is_control) {
+                return Err(invalid());
+            }
+            width = width.max(text_width::width(&text));
+        }
+        if width == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::Custom {
+            format: input.to_string(),
+            width,
+        })
+    }
+
+    /// Columns taken by every day's text
+    pub fn width(&self) -> usize {
+        match self {
+            Self::DayOfMonth | Self::Unpadded => 2,
+            Self::DayOfYear => 3,
+            Self::Custom { width, .. } => *width,
+        }
+    }
+
+    /// The text shown for `date`, padded to [`Self::width`]
+    pub fn format(&self, date: NaiveDate) -> String {
+        match self {
+            Self::DayOfMonth => format!("{:02}", date.day()),
+            Self::Unpadded => format!("{:>2}", date.day()),
+            Self::DayOfYear => format!("{:03}", date.ordinal()),
+            Self::Custom { format, width } => {
+                text_width::pad_start(&date.format(format).to_string(), *width)
+            }
+        }
+    }
+}
+
+/// Which way the days of a week run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    /// Right to left, for Hebrew and Arabic: the days run leftwards and the
+    /// week labels sit on the right
+    Rtl,
+}
+
+impl Direction {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "ltr" => Ok(Self::Ltr),
+            "rtl" => Ok(Self::Rtl),
+            _ => Err(format!("Invalid direction: '{}'. Use ltr or rtl", input)),
+        }
+    }
+}
+
+/// How the weeks of the year are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// A row per week, with the months running down
+    Vertical,
+    /// A column per week and a row per weekday, with the months running
+    /// across, for wide terminals
+    Horizontal,
+    /// A block per month, `columns` to a row, like `cal -y`
+    Grid { columns: usize },
+    /// A small shaded cell per day, a column per week, like GitHub's
+    /// contribution graph
+    Heatmap,
+}
+
+impl Layout {
+    /// Months to a row of the grid when not given
+    pub const GRID_COLUMNS: usize = 3;
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "vertical" => Ok(Self::Vertical),
+            "horizontal" => Ok(Self::Horizontal),
+            "grid" => Ok(Self::Grid {
+                columns: Self::GRID_COLUMNS,
+            }),
+            "heatmap" => Ok(Self::Heatmap),
+            _ => Err(format!(
+                "Invalid layout: '{}'. Use vertical, horizontal, grid or heatmap",
+                input
+            )),
+        }
+    }
+}
+
+/// Extra number shown beside each week, for the week's first day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeekColumn {
+    /// Julian Day Number, e.g. 2460677
+    JulianDay,
+    /// ISO 8601 week date, e.g. 2024-W52-7
+    IsoWeekDate,
+}
+
+impl WeekColumn {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "jd" | "julian" => Ok(Self::JulianDay),
+            "iso" => Ok(Self::IsoWeekDate),
+            _ => Err(format!("Invalid week column: '{}'. Use jd or iso", input)),
+        }
+    }
+
+    /// Heading above the column
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::JulianDay => "JD",
+            Self::IsoWeekDate => "ISO",
+        }
+    }
+
+    /// Width of every value
+    pub fn width(&self) -> usize {
+        match self {
+            Self::JulianDay => 7,
+            Self::IsoWeekDate => 10,
+        }
+    }
+
+    pub fn format(&self, date: NaiveDate) -> String {
+        match self {
+            // Day 1 of the common era is JDN 1721426
+            Self::JulianDay => (date.num_days_from_ce() as i64 + 1721425).to_string(),
+            Self::IsoWeekDate => date.format("%G-W%V-%u").to_string(),
         }
     }
 }
@@ -155,36 +535,30 @@ impl MonthFilter {
         Ok(MonthFilter::Single(month_num))
     }
 
-    /// Get the range of months to display (start_month, end_month) for the given year
-    pub fn get_month_range(&self, _year: i32) -> (u32, u32) {
+    /// Get the range of months to display (start_month, end_month), where
+    /// "current" is the month of `today`
+    pub fn get_month_range(&self, today: NaiveDate) -> (u32, u32) {
         match self {
             MonthFilter::All => (1, 12),
             MonthFilter::Single(m) => (*m, *m),
-            MonthFilter::Current => {
-                let month = Self::get_current_month_number();
-                (month, month)
-            }
+            MonthFilter::Current => (today.month(), today.month()),
             MonthFilter::CurrentWithFollowing(n) => {
-                let start_month = Self::get_current_month_number();
+                let start_month = today.month();
                 let end_month = (start_month + n).min(12);
                 (start_month, end_month)
             }
         }
     }
 
-    fn get_current_month_number() -> u32 {
-        chrono::Local::now().date_naive().month()
-    }
-
     /// Check if a specific month should be displayed
-    pub fn should_display_month(&self, month: u32, year: i32) -> bool {
-        let (start, end) = self.get_month_range(year);
+    pub fn should_display_month(&self, month: u32, today: NaiveDate) -> bool {
+        let (start, end) = self.get_month_range(today);
         month >= start && month <= end
     }
 
     /// Get the filtered date range (start_date, end_date) for rendering
-    pub fn get_date_range(&self, year: i32) -> (NaiveDate, NaiveDate) {
-        let (start_month, end_month) = self.get_month_range(year);
+    pub fn get_date_range(&self, year: i32, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let (start_month, end_month) = self.get_month_range(today);
 
         let start_date = NaiveDate::from_ymd_opt(year, start_month, 1).unwrap();
         let end_date = Self::get_last_day_of_month(year, end_month);
@@ -205,18 +579,408 @@ impl MonthFilter {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct DateDetail {
-    pub description: String,
-    pub color: Option<String>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// A single annotated day
+    Date,
+    /// A colored span of days
+    Range,
+    /// A single day marking a deadline or deliverable
+    Milestone,
+    /// A yearly `MM-DD` date, resolved against the displayed year
+    Recurring,
+    /// A range spent in another timezone
+    Travel,
 }
 
-#[derive(Debug, Clone)]
-pub struct DateRange {
+impl EventKind {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "date" => Ok(Self::Date),
+            "range" => Ok(Self::Range),
+            "milestone" => Ok(Self::Milestone),
+            "recurring" => Ok(Self::Recurring),
+            "travel" => Ok(Self::Travel),
+            _ => Err(format!(
+                "Invalid event kind: '{}'. Use date, range, milestone, recurring or travel",
+                input
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Range => "range",
+            Self::Milestone => "milestone",
+            Self::Recurring => "recurring",
+            Self::Travel => "travel",
+        }
+    }
+}
+
+/// Where a range stands in the time-off approval cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Requested,
+    Approved,
+    Rejected,
+}
+
+impl Status {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "requested" => Ok(Self::Requested),
+            "approved" => Ok(Self::Approved),
+            "rejected" => Ok(Self::Rejected),
+            _ => Err(format!(
+                "Invalid status: '{}'. Use requested, approved or rejected",
+                input
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Requested => "requested",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+/// An event's `icon`: a single glyph, with an emoji variation selector after
+/// it dropped
+pub fn parse_icon(input: &str) -> Result<char, String> {
+    let mut chars = input.trim().chars().filter(|&c| c != '\u{FE0F}');
+    match (chars.next(), chars.next()) {
+        (Some(icon), None) => Ok(icon),
+        _ => Err(format!("Invalid icon: '{}'. Use a single character", input)),
+    }
+}
+
+/// How a range's color changes from its first day to its last, so progress
+/// through a long phase shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gradient {
+    /// From a light to a dark shade of the range's color
+    Shade,
+    /// From the range's color to another
+    To(String),
+}
+
+impl Gradient {
+    /// "shade", or the color the range ends in
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.trim().to_lowercase().as_str() {
+            "" => Err("Invalid gradient: ''. Use shade or a color".to_string()),
+            "shade" => Ok(Self::Shade),
+            color => Ok(Self::To(color.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Shade => "shade",
+            Self::To(color) => color,
+        }
+    }
+}
+
+/// A dated entry on the calendar: a single day, a range, a milestone or a
+/// yearly recurring date. Built through [`Event::builder`], which validates it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub id: Option<String>,
+    pub kind: EventKind,
     pub start: NaiveDate,
     pub end: NaiveDate,
-    pub color: String,
     pub description: Option<String>,
+    pub color: Option<String>,
+    /// Category the event belongs to, from its `tag`
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub owner: Option<String>,
+    /// Budgeted cost of the whole event
+    pub cost: Option<f64>,
+    /// Destination timezone of a travel range
+    pub timezone: Option<Tz>,
+    /// Approval status of a range; ranges without one are plain plans
+    pub status: Option<Status>,
+    /// Higher priorities are annotated first; 0 unless set
+    pub priority: i32,
+    /// Where overlapping ranges meet, the highest layer paints the day; 0
+    /// unless set
+    pub layer: i32,
+    /// Color change across a range; flat when unset
+    pub gradient: Option<Gradient>,
+    /// Glyph marking the event's days and annotation, e.g. '✈'
+    pub icon: Option<char>,
+}
+
+impl Event {
+    /// The event's `tag` followed by its `tags`, each once
+    pub fn all_tags(&self) -> impl Iterator<Item = &str> {
+        let category = self.category.as_deref();
+        let tags = self.tags.iter().map(String::as_str);
+        category
+            .into_iter()
+            .chain(tags.filter(move |tag| Some(*tag) != category))
+    }
+
+    /// Start building an event of `kind` on `start`; single-day unless
+    /// [`EventBuilder::end`] is set
+    pub fn builder(kind: EventKind, start: NaiveDate) -> EventBuilder {
+        EventBuilder {
+            event: Event {
+                id: None,
+                kind,
+                start,
+                end: start,
+                description: None,
+                color: None,
+                category: None,
+                tags: Vec::new(),
+                owner: None,
+                cost: None,
+                timezone: None,
+                status: None,
+                priority: 0,
+                layer: 0,
+                gradient: None,
+                icon: None,
+            },
+        }
+    }
+
+    pub fn is_single_day(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date >= self.start && date <= self.end
+    }
+
+    /// Whether the event is shown as a colored span rather than a single
+    /// annotated day
+    pub fn is_range(&self) -> bool {
+        matches!(self.kind, EventKind::Range | EventKind::Travel) || !self.is_single_day()
+    }
+
+    /// Destination UTC offset at the start of a travel range, e.g. "UTC+9"
+    /// or "UTC+5:30"
+    pub fn utc_offset_label(&self) -> Option<String> {
+        let tz = self.timezone?;
+        let noon = self.start.and_hms_opt(12, 0, 0)?;
+        let offset = tz.offset_from_utc_datetime(&noon).fix();
+        let seconds = offset.local_minus_utc();
+        let sign = if seconds < 0 { '-' } else { '+' };
+        let (hours, minutes) = (seconds.abs() / 3600, seconds.abs() % 3600 / 60);
+        Some(if minutes == 0 {
+            format!("UTC{}{}", sign, hours)
+        } else {
+            format!("UTC{}{}:{:02}", sign, hours, minutes)
+        })
+    }
+
+    /// The date at the destination of a travel range at the instant `now`
+    pub fn destination_date(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        self.timezone.map(|tz| now.with_timezone(&tz).date_naive())
+    }
+}
+
+/// Builder for [`Event`]; [`EventBuilder::build`] checks the event is consistent
+#[derive(Debug, Clone)]
+pub struct EventBuilder {
+    event: Event,
+}
+
+impl EventBuilder {
+    pub fn end(mut self, end: NaiveDate) -> Self {
+        self.event.end = end;
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.event.id = Some(id.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.event.description = Some(description.into());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.event.color = Some(color.into());
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.event.category = Some(category.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.event.tags.push(tag.into());
+        self
+    }
+
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event.tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn owner(mut self, owner: impl Into<String>) -> Self {
+        self.event.owner = Some(owner.into());
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> Self {
+        self.event.timezone = Some(timezone);
+        self
+    }
+
+    pub fn cost(mut self, cost: f64) -> Self {
+        self.event.cost = Some(cost);
+        self
+    }
+
+    pub fn status(mut self, status: Status) -> Self {
+        self.event.status = Some(status);
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.event.priority = priority;
+        self
+    }
+
+    pub fn layer(mut self, layer: i32) -> Self {
+        self.event.layer = layer;
+        self
+    }
+
+    pub fn gradient(mut self, gradient: Gradient) -> Self {
+        self.event.gradient = Some(gradient);
+        self
+    }
+
+    pub fn icon(mut self, icon: char) -> Self {
+        self.event.icon = Some(icon);
+        self
+    }
+
+    pub fn build(self) -> crate::Result<Event> {
+        let event = self.event;
+        let invalid = |reason: String| Err(Error::InvalidEntry(reason));
+
+        if event.end < event.start {
+            return invalid("event ends before it starts".to_string());
+        }
+        if matches!(event.kind, EventKind::Date | EventKind::Milestone) && !event.is_single_day() {
+            return invalid(format!("a {} must be a single day", event.kind.name()));
+        }
+        if event.kind == EventKind::Range && event.color.is_none() {
+            return invalid("range has no color".to_string());
+        }
+        match (event.kind, event.timezone) {
+            (EventKind::Travel, None) => {
+                return invalid("travel has no timezone".to_string());
+            }
+            (kind, Some(_)) if kind != EventKind::Travel => {
+                return invalid(format!("a {} cannot have a timezone", kind.name()));
+            }
+            _ => {}
+        }
+        if event.status.is_some() && !matches!(event.kind, EventKind::Range | EventKind::Travel) {
+            return invalid(format!("a {} cannot have a status", event.kind.name()));
+        }
+        if event.gradient.is_some() && !event.is_range() {
+            return invalid(format!("a {} cannot have a gradient", event.kind.name()));
+        }
+        if let Some(id) = &event.id {
+            if id.is_empty() || id.chars().any(char::is_whitespace) {
+                return invalid(format!("invalid event id '{}'", id));
+            }
+        }
+        if event
+            .owner
+            .as_deref()
+            .is_some_and(|owner| owner.trim().is_empty())
+        {
+            return invalid("event owner must not be empty".to_string());
+        }
+        if event
+            .category
+            .as_deref()
+            .is_some_and(|category| category.trim().is_empty())
+        {
+            return invalid("event category must not be empty".to_string());
+        }
+        for (i, tag) in event.tags.iter().enumerate() {
+            if tag.trim().is_empty() {
+                return invalid("event tags must not be empty".to_string());
+            }
+            if event.tags[..i].contains(tag) {
+                return invalid(format!("duplicate event tag '{}'", tag));
+            }
+        }
+
+        Ok(event)
+    }
+}
+
+/// A one-off colored date given on the command line as
+/// "2025-08-15[:color[:label]]", shown without editing the config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub date: NaiveDate,
+    pub color: String,
+    pub label: Option<String>,
+}
+
+impl Highlight {
+    /// Color of highlights that don't name one
+    pub const DEFAULT_COLOR: &'static str = "yellow";
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parts = input.splitn(3, ':');
+        let date = parts.next().unwrap_or_default().trim();
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            format!(
+                "Invalid highlight: '{}'. Use YYYY-MM-DD[:color[:label]]",
+                input
+            )
+        })?;
+        let color = match parts.next().map(str::trim) {
+            Some(color) if !color.is_empty() => color.to_string(),
+            _ => Self::DEFAULT_COLOR.to_string(),
+        };
+        if ColorPalette::get_color_value(&color).is_none() {
+            return Err(format!("Invalid highlight color: '{}'", color));
+        }
+        let label = parts
+            .next()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(str::to_string);
+        Ok(Self { date, color, label })
+    }
+
+    pub fn to_event(&self) -> Event {
+        let mut builder = Event::builder(EventKind::Date, self.date).color(&self.color);
+        if let Some(label) = &self.label {
+            builder = builder.description(label);
+        }
+        builder
+            .build()
+            .expect("highlights are valid single-day events")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -226,42 +990,746 @@ pub struct CalendarOptions {
     pub color_mode: ColorMode,
     pub past_date_display: PastDateDisplay,
     pub month_filter: MonthFilter,
+    /// Start at the week of today, leaving out the weeks already gone
+    pub remaining: bool,
+    pub cell_width: CellWidth,
+    pub layout: Layout,
+    pub locale: Locale,
+    /// Which way the days run in the vertical layout
+    pub direction: Direction,
+    pub contrast: ContrastMode,
+    /// Date treated as today; the date in `timezone` when unset
+    pub today: Option<NaiveDate>,
+    /// Timezone deciding which day is today; the local one when unset
+    pub timezone: Option<Tz>,
+    /// Approval statuses to include; ranges without a status are always shown
+    pub statuses: Vec<Status>,
+    /// Leave out events with a lower priority
+    pub min_priority: Option<i32>,
+    /// Only include events with one of these tags; all of them when empty
+    pub tags: Vec<String>,
+    /// Only include events with one of these owners; all of them when empty
+    pub owners: Vec<String>,
+    /// Only include events of one of these colors; all of them when empty
+    pub colors: Vec<String>,
+    /// Leave out events that end before this day
+    pub from: Option<NaiveDate>,
+    /// Leave out events that start after this day
+    pub to: Option<NaiveDate>,
+    /// Characters available to the annotations after each week; unlimited
+    /// when unset
+    pub annotation_width: Option<usize>,
+    pub annotation_overflow: AnnotationOverflow,
+    /// List what each color stands for below the calendar
+    pub legend: bool,
+    pub overlap_display: OverlapDisplay,
+    /// One-off dates colored on top of the config's
+    pub highlights: Vec<Highlight>,
+    pub today_marker: TodayMarker,
+    /// Marks of colored days when colors are off
+    pub plain_marker: PlainMarker,
+    /// Mark new and full moons next to their days
+    pub moon: bool,
+    /// Shade the even ISO weeks, for telling "A" and "B" weeks apart
+    pub alternate_weeks: bool,
+    /// Note February 29th and the 53rd ISO week of long years
+    pub leap_markers: bool,
+    pub day_numbering: DayNumbering,
+    pub week_column: Option<WeekColumn>,
+    /// Note the first day of each month of another calendar
+    pub secondary_calendar: Option<SecondaryCalendar>,
+    /// Note solstices, equinoxes and sunrise or sunset crossings here
+    pub daylight: Option<Daylight>,
+    /// Days a habit was kept, ticked off with a streak below the calendar
+    pub habits: Option<HabitLog>,
+    /// Activity per day, shading the days without a color of their own
+    pub heatmap: Option<Heatmap>,
+    /// Forecast for the coming days, marked with their weather
+    pub forecast: Vec<DayForecast>,
+    /// The user's colors, effects and borders
+    pub theme: Theme,
+    /// Brightness of dimmed colors from 0 to 1, instead of their own dimmed
+    /// shades
+    pub dim_factor: Option<f64>,
+}
+
+impl Default for CalendarOptions {
+    fn default() -> Self {
+        Self {
+            week_start: WeekStart::Monday,
+            weekend_display: WeekendDisplay::Dimmed,
+            color_mode: ColorMode::Normal,
+            past_date_display: PastDateDisplay::Strikethrough,
+            month_filter: MonthFilter::All,
+            remaining: false,
+            cell_width: CellWidth::Normal,
+            layout: Layout::Vertical,
+            locale: Locale::En,
+            direction: Direction::Ltr,
+            contrast: ContrastMode::Normal,
+            today: None,
+            timezone: None,
+            statuses: vec![Status::Requested, Status::Approved],
+            min_priority: None,
+            tags: Vec::new(),
+            owners: Vec::new(),
+            colors: Vec::new(),
+            from: None,
+            to: None,
+            annotation_width: None,
+            annotation_overflow: AnnotationOverflow::Truncate,
+            legend: false,
+            overlap_display: OverlapDisplay::Top,
+            highlights: Vec::new(),
+            today_marker: TodayMarker::Underline,
+            plain_marker: PlainMarker::None,
+            moon: false,
+            alternate_weeks: false,
+            leap_markers: false,
+            day_numbering: DayNumbering::DayOfMonth,
+            week_column: None,
+            secondary_calendar: None,
+            daylight: None,
+            habits: None,
+            heatmap: None,
+            forecast: Vec::new(),
+            theme: Theme::default(),
+            dim_factor: None,
+        }
+    }
+}
+
+impl CalendarOptions {
+    pub fn builder() -> CalendarOptionsBuilder {
+        CalendarOptionsBuilder::default()
+    }
+
+    /// The pinned "today", or the current date in the timezone
+    pub fn today(&self) -> NaiveDate {
+        self.today.unwrap_or_else(|| self.local_date(Utc::now()))
+    }
+
+    /// The day `instant` falls on in the timezone, or the local one
+    pub fn local_date(&self, instant: DateTime<Utc>) -> NaiveDate {
+        self.local_time(instant).date()
+    }
+
+    /// The clock time at `instant` in the timezone, or the local one
+    pub fn local_time(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self.timezone {
+            Some(tz) => instant.with_timezone(&tz).naive_local(),
+            None => instant.with_timezone(&chrono::Local).naive_local(),
+        }
+    }
+
+    /// Whether `event` passes the status, priority, tag, owner, color and
+    /// date filters
+    pub fn includes(&self, event: &Event) -> bool {
+        let any_of = |wanted: &[String], value: Option<&str>| {
+            wanted.is_empty() || value.is_some_and(|value| wanted.iter().any(|w| w == value))
+        };
+        event
+            .status
+            .is_none_or(|status| self.statuses.contains(&status))
+            && self
+                .min_priority
+                .is_none_or(|min_priority| event.priority >= min_priority)
+            && (self.tags.is_empty() || event.all_tags().any(|tag| any_of(&self.tags, Some(tag))))
+            && any_of(&self.owners, event.owner.as_deref())
+            && any_of(&self.colors, event.color.as_deref())
+            && self.from.is_none_or(|from| event.end >= from)
+            && self.to.is_none_or(|to| event.start <= to)
+    }
+}
+
+/// Builder for [`CalendarOptions`], starting from the CLI defaults
+#[derive(Debug, Clone, Default)]
+pub struct CalendarOptionsBuilder {
+    options: CalendarOptions,
+}
+
+impl CalendarOptionsBuilder {
+    pub fn week_start(mut self, week_start: WeekStart) -> Self {
+        self.options.week_start = week_start;
+        self
+    }
+
+    pub fn weekend_display(mut self, weekend_display: WeekendDisplay) -> Self {
+        self.options.weekend_display = weekend_display;
+        self
+    }
+
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.options.color_mode = color_mode;
+        self
+    }
+
+    pub fn past_date_display(mut self, past_date_display: PastDateDisplay) -> Self {
+        self.options.past_date_display = past_date_display;
+        self
+    }
+
+    pub fn month_filter(mut self, month_filter: MonthFilter) -> Self {
+        self.options.month_filter = month_filter;
+        self
+    }
+
+    pub fn remaining(mut self, remaining: bool) -> Self {
+        self.options.remaining = remaining;
+        self
+    }
+
+    pub fn cell_width(mut self, cell_width: CellWidth) -> Self {
+        self.options.cell_width = cell_width;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.options.layout = layout;
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.options.direction = direction;
+        self
+    }
+
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.options.locale = locale;
+        self
+    }
+
+    pub fn contrast(mut self, contrast: ContrastMode) -> Self {
+        self.options.contrast = contrast;
+        self
+    }
+
+    /// Pin "today" to a fixed date instead of reading the local clock
+    pub fn today(mut self, today: NaiveDate) -> Self {
+        self.options.today = Some(today);
+        self
+    }
+
+    pub fn statuses(mut self, statuses: Vec<Status>) -> Self {
+        self.options.statuses = statuses;
+        self
+    }
+
+    pub fn min_priority(mut self, min_priority: i32) -> Self {
+        self.options.min_priority = Some(min_priority);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.options.tags = tags;
+        self
+    }
+
+    pub fn owners(mut self, owners: Vec<String>) -> Self {
+        self.options.owners = owners;
+        self
+    }
+
+    pub fn colors(mut self, colors: Vec<String>) -> Self {
+        self.options.colors = colors;
+        self
+    }
+
+    pub fn from(mut self, from: NaiveDate) -> Self {
+        self.options.from = Some(from);
+        self
+    }
+
+    pub fn to(mut self, to: NaiveDate) -> Self {
+        self.options.to = Some(to);
+        self
+    }
+
+    pub fn annotation_width(mut self, annotation_width: usize) -> Self {
+        self.options.annotation_width = Some(annotation_width);
+        self
+    }
+
+    pub fn annotation_overflow(mut self, annotation_overflow: AnnotationOverflow) -> Self {
+        self.options.annotation_overflow = annotation_overflow;
+        self
+    }
+
+    pub fn legend(mut self, legend: bool) -> Self {
+        self.options.legend = legend;
+        self
+    }
+
+    pub fn overlap_display(mut self, overlap_display: OverlapDisplay) -> Self {
+        self.options.overlap_display = overlap_display;
+        self
+    }
+
+    pub fn highlights(mut self, highlights: Vec<Highlight>) -> Self {
+        self.options.highlights = highlights;
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> Self {
+        self.options.timezone = Some(timezone);
+        self
+    }
+
+    pub fn today_marker(mut self, today_marker: TodayMarker) -> Self {
+        self.options.today_marker = today_marker;
+        self
+    }
+
+    pub fn plain_marker(mut self, plain_marker: PlainMarker) -> Self {
+        self.options.plain_marker = plain_marker;
+        self
+    }
+
+    pub fn moon(mut self, moon: bool) -> Self {
+        self.options.moon = moon;
+        self
+    }
+
+    pub fn alternate_weeks(mut self, alternate_weeks: bool) -> Self {
+        self.options.alternate_weeks = alternate_weeks;
+        self
+    }
+
+    pub fn leap_markers(mut self, leap_markers: bool) -> Self {
+        self.options.leap_markers = leap_markers;
+        self
+    }
+
+    pub fn day_numbering(mut self, day_numbering: DayNumbering) -> Self {
+        self.options.day_numbering = day_numbering;
+        self
+    }
+
+    pub fn week_column(mut self, week_column: WeekColumn) -> Self {
+        self.options.week_column = Some(week_column);
+        self
+    }
+
+    pub fn secondary_calendar(mut self, secondary_calendar: SecondaryCalendar) -> Self {
+        self.options.secondary_calendar = Some(secondary_calendar);
+        self
+    }
+
+    pub fn daylight(mut self, daylight: Daylight) -> Self {
+        self.options.daylight = Some(daylight);
+        self
+    }
+
+    pub fn habits(mut self, habits: HabitLog) -> Self {
+        self.options.habits = Some(habits);
+        self
+    }
+
+    pub fn heatmap(mut self, heatmap: Heatmap) -> Self {
+        self.options.heatmap = Some(heatmap);
+        self
+    }
+
+    pub fn forecast(mut self, forecast: Vec<DayForecast>) -> Self {
+        self.options.forecast = forecast;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.options.theme = theme;
+        self
+    }
+
+    pub fn dim_factor(mut self, factor: f64) -> Self {
+        self.options.dim_factor = Some(factor);
+        self
+    }
+
+    pub fn build(self) -> CalendarOptions {
+        self.options
+    }
+}
+
+/// Differences between two versions of a calendar, see
+/// [`Calendar::changes_since`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarChanges {
+    /// Days whose color changed, including days that lost theirs
+    pub dates: HashSet<NaiveDate>,
+    /// Events that are new or were edited
+    pub events: Vec<Event>,
+    /// Events that were removed, including the old versions of edited ones
+    pub removed: Vec<Event>,
+}
+
+impl CalendarChanges {
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty() && self.events.is_empty() && self.removed.is_empty()
+    }
+
+    /// Add the changes from `other`, e.g. to also redraw days still
+    /// highlighted from an earlier change
+    pub fn extend(&mut self, other: CalendarChanges) {
+        self.dates.extend(other.dates);
+        self.events.extend(other.events);
+        self.removed.extend(other.removed);
+    }
+
+    /// Whether anything changed between `start` and `end`, both included
+    pub fn touches(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.dates.iter().any(|date| (start..=end).contains(date))
+            || self
+                .events
+                .iter()
+                .chain(&self.removed)
+                .any(|event| event.start <= end && event.end >= start)
+    }
 }
 
 pub struct Calendar {
     pub year: i32,
-    pub week_start: WeekStart,
-    pub weekend_display: WeekendDisplay,
-    pub color_mode: ColorMode,
-    pub past_date_display: PastDateDisplay,
-    pub month_filter: MonthFilter,
-    pub details: HashMap<NaiveDate, DateDetail>,
-    pub ranges: Vec<DateRange>,
+    pub options: CalendarOptions,
+    /// Single-day events by date, in the order they were given
+    pub details: HashMap<NaiveDate, Vec<Event>>,
+    /// Multi-day events, in the order they were given
+    pub ranges: Vec<Event>,
+    /// Weekly busy blocks
+    pub availability: Vec<AvailabilityBlock>,
+    /// Days the company is closed, as (first, last) pairs
+    pub non_working: Vec<(NaiveDate, NaiveDate)>,
+    /// Weekend days worked instead of a bridge day, as (first, last) pairs
+    pub working_weekends: Vec<(NaiveDate, NaiveDate)>,
+    /// Week labels by a day of their week, in priority order
+    pub week_labels: Vec<(NaiveDate, String)>,
+    /// New and full moons by day, when shown
+    moon_phases: HashMap<NaiveDate, MoonPhase>,
+    /// Forecast weather by day
+    weather: HashMap<NaiveDate, Weather>,
+    /// Clock changes in the timezone, by day
+    clock_changes: HashMap<NaiveDate, Transition>,
+    /// Index over `ranges`, for per-day lookups
+    range_index: IntervalIndex,
 }
 
 impl Calendar {
-    pub fn new(
-        year: i32,
-        options: CalendarOptions,
-        details: HashMap<NaiveDate, DateDetail>,
-        ranges: Vec<DateRange>,
-    ) -> Self {
+    pub fn new(year: i32, options: CalendarOptions, events: Vec<Event>) -> Self {
+        let (ranges, days): (Vec<Event>, Vec<Event>) = events
+            .into_iter()
+            .filter(|event| options.includes(event))
+            .partition(Event::is_range);
+        let daylight = options
+            .daylight
+            .as_ref()
+            .map(|daylight| daylight.events(year, |instant| options.local_time(instant)))
+            .unwrap_or_default();
+        let secondary = options
+            .secondary_calendar
+            .map(|calendar| calendar.events(year))
+            .unwrap_or_default();
+        let clock_changes: HashMap<NaiveDate, Transition> = options
+            .timezone
+            .map(|timezone| dst::transitions(timezone, year))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|transition| (transition.date(), transition))
+            .collect();
+        // Highlights come first, so their color wins on the day
+        let mut details: HashMap<NaiveDate, Vec<Event>> = HashMap::new();
+        for event in options
+            .highlights
+            .iter()
+            .map(Highlight::to_event)
+            .chain(days)
+            .chain(daylight)
+            .chain(secondary)
+            .chain(clock_changes.values().filter_map(Transition::to_event))
+            .chain(if options.leap_markers {
+                leap_events(year)
+            } else {
+                Vec::new()
+            })
+        {
+            details.entry(event.start).or_default().push(event);
+        }
+        let range_index = IntervalIndex::new(ranges.iter().map(|range| (range.start, range.end)));
+        let moon_phases = if options.moon {
+            moon::phases(year)
+                .into_iter()
+                .map(|(instant, phase)| (options.local_date(instant), phase))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let weather = options
+            .forecast
+            .iter()
+            .map(|day| (day.date, day.weather))
+            .collect();
+
         Calendar {
             year,
-            week_start: options.week_start,
-            weekend_display: options.weekend_display,
-            color_mode: options.color_mode,
-            past_date_display: options.past_date_display,
-            month_filter: options.month_filter,
+            options,
             details,
             ranges,
+            availability: Vec::new(),
+            non_working: Vec::new(),
+            working_weekends: Vec::new(),
+            week_labels: Vec::new(),
+            moon_phases,
+            weather,
+            clock_changes,
+            range_index,
         }
     }
 
+    pub fn with_availability(mut self, availability: Vec<AvailabilityBlock>) -> Self {
+        self.availability = availability;
+        self
+    }
+
+    pub fn with_non_working(mut self, non_working: Vec<(NaiveDate, NaiveDate)>) -> Self {
+        self.non_working = non_working;
+        self
+    }
+
+    pub fn with_working_weekends(mut self, working_weekends: Vec<(NaiveDate, NaiveDate)>) -> Self {
+        self.working_weekends = working_weekends;
+        self
+    }
+
+    pub fn with_week_labels(mut self, week_labels: Vec<(NaiveDate, String)>) -> Self {
+        self.week_labels = week_labels;
+        self
+    }
+
+    /// The label replacing the number of the week holding `dates`, if any
+    pub fn week_label(&self, dates: &[NaiveDate]) -> Option<&str> {
+        self.week_labels
+            .iter()
+            .find(|(date, _)| dates.contains(date))
+            .map(|(_, label)| label.as_str())
+    }
+
+    /// All events, ordered by start and then end date
+    pub fn events(&self) -> Vec<&Event> {
+        let mut events: Vec<&Event> = self
+            .details
+            .values()
+            .flatten()
+            .chain(&self.ranges)
+            .collect();
+        events.sort_by_key(|event| (event.start, event.end));
+        events
+    }
+
+    /// The configured "today", see [`CalendarOptions::today`]
+    pub fn today(&self) -> NaiveDate {
+        self.options.today()
+    }
+
+    /// Whether the year has February 29th
+    pub fn is_leap_year(&self) -> bool {
+        NaiveDate::from_ymd_opt(self.year, 2, 29).is_some()
+    }
+
+    /// Whether the ISO year has 53 weeks, which throws off weekly and
+    /// biweekly schedules counted by week number
+    pub fn is_long_year(&self) -> bool {
+        NaiveDate::from_isoywd_opt(self.year, 53, Weekday::Mon).is_some()
+    }
+
+    /// The new or full moon on `date`, when moons are shown
+    pub fn moon_phase(&self, date: NaiveDate) -> Option<MoonPhase> {
+        self.moon_phases.get(&date).copied()
+    }
+
+    /// The change of the clocks on `date`, when a timezone is set
+    pub fn clock_change(&self, date: NaiveDate) -> Option<&Transition> {
+        self.clock_changes.get(&date)
+    }
+
+    /// The forecast weather on `date`, when forecast
+    pub fn weather(&self, date: NaiveDate) -> Option<Weather> {
+        self.weather.get(&date).copied()
+    }
+
+    /// Whether `date` is ticked off in the habit log
+    pub fn is_habit_done(&self, date: NaiveDate) -> bool {
+        self.options
+            .habits
+            .as_ref()
+            .is_some_and(|habits| habits.contains(date))
+    }
+
+    /// Whether `date` is listed as a company non-working day
+    pub fn is_non_working(&self, date: NaiveDate) -> bool {
+        spans_contain(&self.non_working, date)
+    }
+
+    /// Single-day events on `date`, in the order they were given
+    pub fn details_on(&self, date: NaiveDate) -> std::slice::Iter<'_, Event> {
+        self.details
+            .get(&date)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+    }
+
+    /// Whether `date` falls on a Saturday or Sunday that isn't worked; a
+    /// working weekend day is shown and counted like a weekday
+    pub fn is_weekend(&self, date: NaiveDate) -> bool {
+        matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+            && !spans_contain(&self.working_weekends, date)
+    }
+
+    /// The colored date or range that fills `date`; rejected time off leaves
+    /// the day blank
+    pub fn coloring_event(&self, date: NaiveDate) -> Option<&Event> {
+        // In work mode, never color weekends
+        if self.options.color_mode == ColorMode::Work && self.is_weekend(date) {
+            return None;
+        }
+
+        // Check if date has a specific color; the first one given wins
+        if let Some(detail) = self.details_on(date).find(|detail| detail.color.is_some()) {
+            return Some(detail);
+        }
+
+        // Check if date is in a range; the highest layer wins, then the
+        // first one given
+        self.range_index
+            .containing(date)
+            .filter(|&position| {
+                let range = &self.ranges[position];
+                range.color.is_some() && range.status != Some(Status::Rejected)
+            })
+            .min_by_key(|&position| (std::cmp::Reverse(self.ranges[position].layer), position))
+            .map(|position| &self.ranges[position])
+    }
+
+    /// Colored ranges that could paint `date`, the one on top first: highest
+    /// layer, then the first given
+    pub fn coloring_ranges(&self, date: NaiveDate) -> Vec<&Event> {
+        let mut positions: Vec<usize> = self
+            .range_index
+            .containing(date)
+            .filter(|&position| {
+                let range = &self.ranges[position];
+                range.color.is_some() && range.status != Some(Status::Rejected)
+            })
+            .collect();
+        positions
+            .sort_by_key(|&position| (std::cmp::Reverse(self.ranges[position].layer), position));
+        positions
+            .into_iter()
+            .map(|position| &self.ranges[position])
+            .collect()
+    }
+
+    /// The icon marking `date`: the first of its own dates' icons, or else
+    /// that of a range containing it, highest layer first
+    pub fn icon(&self, date: NaiveDate) -> Option<char> {
+        self.details_on(date)
+            .find_map(|detail| detail.icon)
+            .or_else(|| {
+                self.ranges_on(date)
+                    .filter(|range| range.status != Some(Status::Rejected))
+                    .filter(|range| range.icon.is_some())
+                    .min_by_key(|range| std::cmp::Reverse(range.layer))
+                    .and_then(|range| range.icon)
+            })
+    }
+
+    /// Ranges containing `date`, in the order they were given
+    pub fn ranges_on(&self, date: NaiveDate) -> impl Iterator<Item = &Event> {
+        let mut positions: Vec<usize> = self.range_index.containing(date).collect();
+        positions.sort_unstable();
+        positions.into_iter().map(|position| &self.ranges[position])
+    }
+
+    /// Days and events that differ from `previous`, e.g. after the config
+    /// was edited
+    pub fn changes_since(&self, previous: &Calendar) -> CalendarChanges {
+        let previous_events = previous.events();
+        let mut changes = CalendarChanges::default();
+        let events = self.events();
+        for event in &events {
+            if !previous_events.contains(event) {
+                changes.events.push((*event).clone());
+            }
+        }
+        for event in &previous_events {
+            if !events.contains(event) {
+                changes.removed.push((*event).clone());
+            }
+        }
+
+        for event in events.into_iter().chain(previous_events) {
+            for date in event
+                .start
+                .iter_days()
+                .take_while(|date| *date <= event.end)
+            {
+                let fill = |calendar: &Calendar| {
+                    calendar
+                        .coloring_event(date)
+                        .map(|event| (event.color.clone(), event.status))
+                };
+                if fill(self) != fill(previous) {
+                    changes.dates.insert(date);
+                }
+            }
+        }
+        // Non-working days and weekends are dimmed, so moving them changes
+        // how the day looks too
+        let spans = [
+            &self.non_working,
+            &previous.non_working,
+            &self.working_weekends,
+            &previous.working_weekends,
+        ];
+        for &(first, last) in spans.into_iter().flatten() {
+            for date in first.iter_days().take_while(|date| *date <= last) {
+                let dimmed = |calendar: &Calendar| {
+                    (calendar.is_non_working(date), calendar.is_weekend(date))
+                };
+                if dimmed(self) != dimmed(previous) {
+                    changes.dates.insert(date);
+                }
+            }
+        }
+        changes
+    }
+
     pub fn get_weekday_num(&self, date: NaiveDate) -> u32 {
-        match self.week_start {
+        match self.options.week_start {
             WeekStart::Monday => date.weekday().num_days_from_monday(),
             WeekStart::Sunday => date.weekday().num_days_from_sunday(),
         }
     }
 }
+
+/// Whether any of the (first, last) pairs covers `date`
+/// Notes on February 29th and on the Monday of ISO week 53, when `year`
+/// has them
+fn leap_events(year: i32) -> Vec<Event> {
+    let leap_day = NaiveDate::from_ymd_opt(year, 2, 29).map(|date| (date, "Leap day".to_string()));
+    let week_53 = NaiveDate::from_isoywd_opt(year, 53, Weekday::Mon)
+        .map(|date| (date, format!("ISO week 53 of {}", year)));
+    leap_day
+        .into_iter()
+        .chain(week_53)
+        .filter_map(|(date, description)| {
+            Event::builder(EventKind::Date, date)
+                .description(description)
+                .priority(-1)
+                .build()
+                .ok()
+        })
+        .collect()
+}
+
+fn spans_contain(spans: &[(NaiveDate, NaiveDate)], date: NaiveDate) -> bool {
+    spans
+        .iter()
+        .any(|&(first, last)| (first..=last).contains(&date))
+}
@@ -0,0 +1,96 @@
+//! New and full moons, from the mean lunation and the main periodic terms of
+//! Meeus, "Astronomical Algorithms" (chapter 49); accurate to a few minutes,
+//! which is plenty for picking the day.
+
+use chrono::{DateTime, Utc};
+
+/// Julian Ephemeris Day of the Unix epoch
+const UNIX_EPOCH_JDE: f64 = 2440587.5;
+
+/// Seconds Terrestrial Time, which the formulas give, runs ahead of UTC
+/// in the 2020s
+const DELTA_T: f64 = 69.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoonPhase {
+    New,
+    Full,
+}
+
+impl MoonPhase {
+    /// Single-width marker shown next to the day
+    pub fn glyph(&self) -> char {
+        match self {
+            Self::New => '●',
+            Self::Full => '○',
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::New => "New moon",
+            Self::Full => "Full moon",
+        }
+    }
+}
+
+/// Every new and full moon from a few weeks before `year` to a few weeks
+/// after it, in order
+pub fn phases(year: i32) -> Vec<(DateTime<Utc>, MoonPhase)> {
+    // Lunations are counted from the new moon of 2000-01-06
+    let first = ((year - 2000) as f64 * 12.3685).floor() - 1.0;
+    (0..15)
+        .flat_map(|i| {
+            let k = first + i as f64;
+            [(k, MoonPhase::New), (k + 0.5, MoonPhase::Full)]
+        })
+        .filter_map(|(k, phase)| {
+            let seconds = (phase_jde(k, phase) - UNIX_EPOCH_JDE) * 86400.0 - DELTA_T;
+            DateTime::from_timestamp(seconds.round() as i64, 0).map(|instant| (instant, phase))
+        })
+        .collect()
+}
+
+/// Julian Ephemeris Day of lunation `k`, a whole number for new moons and
+/// a half for full moons
+fn phase_jde(k: f64, phase: MoonPhase) -> f64 {
+    let t = k / 1236.85;
+    let (t2, t3, t4) = (t * t, t * t * t, t * t * t * t);
+    let mean =
+        2451550.09766 + 29.530588861 * k + 0.00015437 * t2 - 0.000000150 * t3 + 0.00000000073 * t4;
+
+    // Eccentricity of Earth's orbit, and the Sun's and Moon's mean anomalies,
+    // the Moon's argument of latitude and the longitude of its node
+    let e = 1.0 - 0.002516 * t - 0.0000074 * t2;
+    let m = (2.5534 + 29.10535670 * k - 0.0000014 * t2 - 0.00000011 * t3).to_radians();
+    let mp = (201.5643 + 385.81693528 * k + 0.0107582 * t2 + 0.00001238 * t3 - 0.000000058 * t4)
+        .to_radians();
+    let f = (160.7108 + 390.67050284 * k - 0.0016118 * t2 - 0.00000227 * t3 + 0.000000011 * t4)
+        .to_radians();
+    let omega = (124.7746 - 1.56375588 * k + 0.0020672 * t2 + 0.00000215 * t3).to_radians();
+
+    let (c1, c2, c3, c4, c5, c6, c7) = match phase {
+        MoonPhase::New => (
+            -0.40720, 0.17241, 0.01608, 0.01039, 0.00739, -0.00514, 0.00208,
+        ),
+        MoonPhase::Full => (
+            -0.40614, 0.17302, 0.01614, 0.01043, 0.00734, -0.00515, 0.00209,
+        ),
+    };
+    let correction = c1 * mp.sin()
+        + c2 * e * m.sin()
+        + c3 * (2.0 * mp).sin()
+        + c4 * (2.0 * f).sin()
+        + c5 * e * (mp - m).sin()
+        + c6 * e * (mp + m).sin()
+        + c7 * e * e * (2.0 * m).sin()
+        - 0.00111 * (mp - 2.0 * f).sin()
+        - 0.00057 * (mp + 2.0 * f).sin()
+        + 0.00056 * e * (2.0 * mp + m).sin()
+        - 0.00042 * (3.0 * mp).sin()
+        + 0.00042 * e * (m + 2.0 * f).sin()
+        + 0.00038 * e * (m - 2.0 * f).sin()
+        - 0.00024 * e * (2.0 * mp - m).sin()
+        - 0.00017 * omega.sin();
+    mean + correction
+}
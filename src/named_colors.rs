@@ -0,0 +1,172 @@
+//! The CSS/X11 named colors ("tomato", "steelblue", ...), usable anywhere a
+//! color name is.
+
+use anstyle::RgbColor;
+
+/// The RGB value of a CSS color name, ignoring case
+pub fn rgb(name: &str) -> Option<RgbColor> {
+    let name = name.to_ascii_lowercase();
+    CSS_COLORS
+        .binary_search_by(|(candidate, _)| candidate.cmp(&name.as_str()))
+        .ok()
+        .map(|index| CSS_COLORS[index].1)
+}
+
+/// A `#rrggbb` color, ignoring any alpha after it
+pub fn parse_hex(color: &str) -> Option<RgbColor> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(RgbColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Every CSS color name with its value, sorted by name
+pub const CSS_COLORS: &[(&str, RgbColor)] = &[
+    ("aliceblue", RgbColor(240, 248, 255)),
+    ("antiquewhite", RgbColor(250, 235, 215)),
+    ("aqua", RgbColor(0, 255, 255)),
+    ("aquamarine", RgbColor(127, 255, 212)),
+    ("azure", RgbColor(240, 255, 255)),
+    ("beige", RgbColor(245, 245, 220)),
+    ("bisque", RgbColor(255, 228, 196)),
+    ("black", RgbColor(0, 0, 0)),
+    ("blanchedalmond", RgbColor(255, 235, 205)),
+    ("blue", RgbColor(0, 0, 255)),
+    ("blueviolet", RgbColor(138, 43, 226)),
+    ("brown", RgbColor(165, 42, 42)),
+    ("burlywood", RgbColor(222, 184, 135)),
+    ("cadetblue", RgbColor(95, 158, 160)),
+    ("chartreuse", RgbColor(127, 255, 0)),
+    ("chocolate", RgbColor(210, 105, 30)),
+    ("coral", RgbColor(255, 127, 80)),
+    ("cornflowerblue", RgbColor(100, 149, 237)),
+    ("cornsilk", RgbColor(255, 248, 220)),
+    ("crimson", RgbColor(220, 20, 60)),
+    ("cyan", RgbColor(0, 255, 255)),
+    ("darkblue", RgbColor(0, 0, 139)),
+    ("darkcyan", RgbColor(0, 139, 139)),
+    ("darkgoldenrod", RgbColor(184, 134, 11)),
+    ("darkgray", RgbColor(169, 169, 169)),
+    ("darkgreen", RgbColor(0, 100, 0)),
+    ("darkgrey", RgbColor(169, 169, 169)),
+    ("darkkhaki", RgbColor(189, 183, 107)),
+    ("darkmagenta", RgbColor(139, 0, 139)),
+    ("darkolivegreen", RgbColor(85, 107, 47)),
+    ("darkorange", RgbColor(255, 140, 0)),
+    ("darkorchid", RgbColor(153, 50, 204)),
+    ("darkred", RgbColor(139, 0, 0)),
+    ("darksalmon", RgbColor(233, 150, 122)),
+    ("darkseagreen", RgbColor(143, 188, 143)),
+    ("darkslateblue", RgbColor(72, 61, 139)),
+    ("darkslategray", RgbColor(47, 79, 79)),
+    ("darkslategrey", RgbColor(47, 79, 79)),
+    ("darkturquoise", RgbColor(0, 206, 209)),
+    ("darkviolet", RgbColor(148, 0, 211)),
+    ("deeppink", RgbColor(255, 20, 147)),
+    ("deepskyblue", RgbColor(0, 191, 255)),
+    ("dimgray", RgbColor(105, 105, 105)),
+    ("dimgrey", RgbColor(105, 105, 105)),
+    ("dodgerblue", RgbColor(30, 144, 255)),
+    ("firebrick", RgbColor(178, 34, 34)),
+    ("floralwhite", RgbColor(255, 250, 240)),
+    ("forestgreen", RgbColor(34, 139, 34)),
+    ("fuchsia", RgbColor(255, 0, 255)),
+    ("gainsboro", RgbColor(220, 220, 220)),
+    ("ghostwhite", RgbColor(248, 248, 255)),
+    ("gold", RgbColor(255, 215, 0)),
+    ("goldenrod", RgbColor(218, 165, 32)),
+    ("gray", RgbColor(128, 128, 128)),
+    ("green", RgbColor(0, 128, 0)),
+    ("greenyellow", RgbColor(173, 255, 47)),
+    ("grey", RgbColor(128, 128, 128)),
+    ("honeydew", RgbColor(240, 255, 240)),
+    ("hotpink", RgbColor(255, 105, 180)),
+    ("indianred", RgbColor(205, 92, 92)),
+    ("indigo", RgbColor(75, 0, 130)),
+    ("ivory", RgbColor(255, 255, 240)),
+    ("khaki", RgbColor(240, 230, 140)),
+    ("lavender", RgbColor(230, 230, 250)),
+    ("lavenderblush", RgbColor(255, 240, 245)),
+    ("lawngreen", RgbColor(124, 252, 0)),
+    ("lemonchiffon", RgbColor(255, 250, 205)),
+    ("lightblue", RgbColor(173, 216, 230)),
+    ("lightcoral", RgbColor(240, 128, 128)),
+    ("lightcyan", RgbColor(224, 255, 255)),
+    ("lightgoldenrodyellow", RgbColor(250, 250, 210)),
+    ("lightgray", RgbColor(211, 211, 211)),
+    ("lightgreen", RgbColor(144, 238, 144)),
+    ("lightgrey", RgbColor(211, 211, 211)),
+    ("lightpink", RgbColor(255, 182, 193)),
+    ("lightsalmon", RgbColor(255, 160, 122)),
+    ("lightseagreen", RgbColor(32, 178, 170)),
+    ("lightskyblue", RgbColor(135, 206, 250)),
+    ("lightslategray", RgbColor(119, 136, 153)),
+    ("lightslategrey", RgbColor(119, 136, 153)),
+    ("lightsteelblue", RgbColor(176, 196, 222)),
+    ("lightyellow", RgbColor(255, 255, 224)),
+    ("lime", RgbColor(0, 255, 0)),
+    ("limegreen", RgbColor(50, 205, 50)),
+    ("linen", RgbColor(250, 240, 230)),
+    ("magenta", RgbColor(255, 0, 255)),
+    ("maroon", RgbColor(128, 0, 0)),
+    ("mediumaquamarine", RgbColor(102, 205, 170)),
+    ("mediumblue", RgbColor(0, 0, 205)),
+    ("mediumorchid", RgbColor(186, 85, 211)),
+    ("mediumpurple", RgbColor(147, 112, 219)),
+    ("mediumseagreen", RgbColor(60, 179, 113)),
+    ("mediumslateblue", RgbColor(123, 104, 238)),
+    ("mediumspringgreen", RgbColor(0, 250, 154)),
+    ("mediumturquoise", RgbColor(72, 209, 204)),
+    ("mediumvioletred", RgbColor(199, 21, 133)),
+    ("midnightblue", RgbColor(25, 25, 112)),
+    ("mintcream", RgbColor(245, 255, 250)),
+    ("mistyrose", RgbColor(255, 228, 225)),
+    ("moccasin", RgbColor(255, 228, 181)),
+    ("navajowhite", RgbColor(255, 222, 173)),
+    ("navy", RgbColor(0, 0, 128)),
+    ("oldlace", RgbColor(253, 245, 230)),
+    ("olive", RgbColor(128, 128, 0)),
+    ("olivedrab", RgbColor(107, 142, 35)),
+    ("orange", RgbColor(255, 165, 0)),
+    ("orangered", RgbColor(255, 69, 0)),
+    ("orchid", RgbColor(218, 112, 214)),
+    ("palegoldenrod", RgbColor(238, 232, 170)),
+    ("palegreen", RgbColor(152, 251, 152)),
+    ("paleturquoise", RgbColor(175, 238, 238)),
+    ("palevioletred", RgbColor(219, 112, 147)),
+    ("papayawhip", RgbColor(255, 239, 213)),
+    ("peachpuff", RgbColor(255, 218, 185)),
+    ("peru", RgbColor(205, 133, 63)),
+    ("pink", RgbColor(255, 192, 203)),
+    ("plum", RgbColor(221, 160, 221)),
+    ("powderblue", RgbColor(176, 224, 230)),
+    ("purple", RgbColor(128, 0, 128)),
+    ("rebeccapurple", RgbColor(102, 51, 153)),
+    ("red", RgbColor(255, 0, 0)),
+    ("rosybrown", RgbColor(188, 143, 143)),
+    ("royalblue", RgbColor(65, 105, 225)),
+    ("saddlebrown", RgbColor(139, 69, 19)),
+    ("salmon", RgbColor(250, 128, 114)),
+    ("sandybrown", RgbColor(244, 164, 96)),
+    ("seagreen", RgbColor(46, 139, 87)),
+    ("seashell", RgbColor(255, 245, 238)),
+    ("sienna", RgbColor(160, 82, 45)),
+    ("silver", RgbColor(192, 192, 192)),
+    ("skyblue", RgbColor(135, 206, 235)),
+    ("slateblue", RgbColor(106, 90, 205)),
+    ("slategray", RgbColor(112, 128, 144)),
+    ("slategrey", RgbColor(112, 128, 144)),
+    ("snow", RgbColor(255, 250, 250)),
+    ("springgreen", RgbColor(0, 255, 127)),
+    ("steelblue", RgbColor(70, 130, 180)),
+    ("tan", RgbColor(210, 180, 140)),
+    ("teal", RgbColor(0, 128, 128)),
+    ("thistle", RgbColor(216, 191, 216)),
+    ("tomato", RgbColor(255, 99, 71)),
+    ("turquoise", RgbColor(64, 224, 208)),
+    ("violet", RgbColor(238, 130, 238)),
+    ("wheat", RgbColor(245, 222, 179)),
+    ("white", RgbColor(255, 255, 255)),
+    ("whitesmoke", RgbColor(245, 245, 245)),
+    ("yellow", RgbColor(255, 255, 0)),
+    ("yellowgreen", RgbColor(154, 205, 50)),
+];
@@ -0,0 +1,98 @@
+//! Piping long output through a pager, like bat and git do.
+
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// When to page the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Paging {
+    /// Only when stdout is a terminal and the output is taller than it
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Paging {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "Invalid paging: '{}'. Use auto, always or never",
+                input
+            )),
+        }
+    }
+
+    /// Whether `text` should go through the pager
+    pub fn pages(&self, text: &str) -> bool {
+        match self {
+            Paging::Always => true,
+            Paging::Never => false,
+            Paging::Auto => {
+                io::stdout().is_terminal()
+                    && terminal_height().is_some_and(|height| text.lines().count() > height)
+            }
+        }
+    }
+}
+
+/// The program and arguments of a `$PAGER` value, `less` when unset or
+/// empty. `less` is given `-R` so colors come through rather than as escape
+/// codes.
+pub fn pager_command(pager: Option<&str>) -> (String, Vec<String>) {
+    let mut words = pager.unwrap_or("").split_whitespace().map(str::to_string);
+    let program = words.next().unwrap_or_else(|| "less".to_string());
+    let mut args: Vec<String> = words.collect();
+    let is_less = std::path::Path::new(&program)
+        .file_stem()
+        .is_some_and(|stem| stem == "less");
+    if is_less && !args.iter().any(|arg| arg == "-R" || arg == "-r") {
+        args.push("-R".to_string());
+    }
+    (program, args)
+}
+
+/// Print `text`, through the pager when `paging` says so. Falls back to
+/// printing it directly when the pager can't be started.
+pub fn print(text: &str, paging: Paging) -> io::Result<()> {
+    if paging.pages(text) {
+        let (program, args) = pager_command(std::env::var("PAGER").ok().as_deref());
+        if let Ok(mut child) = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .spawn()
+        {
+            // The pager closing early (quitting before the end) is fine
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            child.wait()?;
+            return Ok(());
+        }
+    }
+    let mut out = io::stdout().lock();
+    out.write_all(text.as_bytes())?;
+    out.flush()
+}
+
+/// Rows of the terminal stdout is on
+#[cfg(unix)]
+fn terminal_height() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_row > 0)
+        .then_some(size.ws_row as usize)
+        .or_else(lines_from_env)
+}
+
+#[cfg(not(unix))]
+fn terminal_height() -> Option<usize> {
+    lines_from_env()
+}
+
+fn lines_from_env() -> Option<usize> {
+    std::env::var("LINES").ok()?.parse().ok()
+}
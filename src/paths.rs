@@ -0,0 +1,93 @@
+use crate::error::{Error, Result};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+/// Name of the calendar config file in every searched directory
+pub const CONFIG_FILE_NAME: &str = "calendar.toml";
+
+/// `$XDG_CONFIG_HOME/compact-calendar`, with `XDG_CONFIG_HOME` defaulting to
+/// `~/.config` on every platform
+pub fn xdg_config_dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("compact-calendar"))
+}
+
+/// `$XDG_CACHE_HOME/compact-calendar`, with `XDG_CACHE_HOME` defaulting to
+/// `~/.cache`, for fetched calendars
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_home.join("compact-calendar"))
+}
+
+/// `$XDG_DATA_HOME/compact-calendar`, with `XDG_DATA_HOME` defaulting to
+/// `~/.local/share`, for datasets installed by `update-data`
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_home.join("compact-calendar"))
+}
+
+/// The platform's own config directory, e.g. `~/Library/Application
+/// Support/compact-calendar` on macOS or `%APPDATA%\compact-calendar\config`
+/// on Windows
+pub fn platform_config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "compact-calendar").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// Config files to try when none is given, in order: the XDG directory,
+/// the platform directory, then the working directory
+pub fn config_candidates() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for dir in [xdg_config_dir(), platform_config_dir()]
+        .into_iter()
+        .flatten()
+    {
+        let candidate = dir.join(CONFIG_FILE_NAME);
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates.push(PathBuf::from(CONFIG_FILE_NAME));
+    candidates
+}
+
+/// The first candidate config that exists, if any
+pub fn find_config() -> Option<PathBuf> {
+    config_candidates()
+        .into_iter()
+        .find(|candidate| candidate.exists())
+}
+
+/// Expand `${VAR}` references to environment variables in a path from a
+/// config, e.g. `${COMPANY_CAL_DIR}/holidays.toml`. Unset variables are an
+/// error rather than silently becoming empty.
+pub fn expand_env(input: &str) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| Error::InvalidEntry(format!("unclosed '${{' in path '{}'", input)))?;
+        let name = &after[..end];
+        let value = std::env::var(name).map_err(|_| {
+            Error::InvalidEntry(format!(
+                "environment variable {} in path '{}' is not set",
+                name, input
+            ))
+        })?;
+        output.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
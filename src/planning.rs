@@ -0,0 +1,241 @@
+use crate::availability::TIME_OFF_TAG;
+use crate::config::{parse_month_day, CalendarConfig, RawAvailability, RawDateDetail};
+use crate::datasets::{self, Datasets};
+use crate::expressions::Anchors;
+use crate::holidays::Country;
+use crate::models::EventKind;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+/// Answers to the `plan` wizard, turned into a complete config by
+/// [`Plan::to_toml`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub year: i32,
+    /// Country whose national holidays are added as days off
+    pub country: Option<Country>,
+    /// Vacation days available over the year
+    pub vacation_days: Option<u32>,
+    /// Weekly busy blocks, such as work hours or a sports club
+    pub commitments: Vec<RawAvailability>,
+    /// Key deadlines: a `YYYY-MM-DD` (or yearly `MM-DD`) date and a description
+    pub deadlines: Vec<(String, String)>,
+}
+
+impl Plan {
+    pub fn new(year: i32) -> Self {
+        Self {
+            year,
+            country: None,
+            vacation_days: None,
+            commitments: Vec::new(),
+            deadlines: Vec::new(),
+        }
+    }
+
+    /// Ask the wizard's questions on `output`, reading answers from `input`
+    /// line by line. Empty answers skip a question, invalid ones are asked
+    /// again, and the end of input keeps whatever was answered so far.
+    pub fn ask<R: BufRead, W: Write>(year: i32, input: R, output: W) -> io::Result<Self> {
+        let mut prompt = Prompt { input, output };
+        let mut plan = Self::new(year);
+        writeln!(
+            prompt.output,
+            "Planning {}. Press Enter to skip a question.",
+            year
+        )?;
+
+        writeln!(prompt.output)?;
+        plan.country = prompt.ask_parsed(
+            "Country for public holidays (us, gb, de, cn, kr): ",
+            Country::parse,
+        )?;
+        writeln!(prompt.output)?;
+        plan.vacation_days = prompt.ask_parsed("Vacation days to plan for: ", |answer| {
+            answer
+                .parse()
+                .map_err(|_| format!("Invalid number of days: '{}'", answer))
+        })?;
+
+        writeln!(
+            prompt.output,
+            "\nRecurring commitments, one per line as DAYS HH:MM-HH:MM DESCRIPTION\n\
+             (e.g. \"mon-fri 09:00-17:00 Work\"). Empty line to finish."
+        )?;
+        while let Some(commitment) = prompt.ask_parsed("> ", parse_commitment)? {
+            plan.commitments.push(commitment);
+        }
+
+        writeln!(
+            prompt.output,
+            "\nKey deadlines, one per line as YYYY-MM-DD DESCRIPTION\n\
+             (MM-DD repeats every year). Empty line to finish."
+        )?;
+        while let Some(deadline) = prompt.ask_parsed("> ", |answer| parse_deadline(answer, year))? {
+            plan.deadlines.push(deadline);
+        }
+
+        Ok(plan)
+    }
+
+    /// The config built from the answers, with comments to guide later edits
+    pub fn to_toml(&self) -> String {
+        let mut config = CalendarConfig::default();
+        if let Some(country) = self.country {
+            let datasets = Datasets::user().ok();
+            for (date, name) in datasets::holidays_or_builtin(datasets.as_ref(), country, self.year)
+            {
+                config
+                    .dates
+                    .entry(date.format("%Y-%m-%d").to_string())
+                    .or_default()
+                    .push(RawDateDetail {
+                        description: name.to_string(),
+                        color: Some("red".to_string()),
+                        tags: vec![TIME_OFF_TAG.to_string()],
+                        ..Default::default()
+                    });
+            }
+        }
+        for (date_str, description) in &self.deadlines {
+            // Yearly deadlines stay recurring dates
+            let kind = matches!(parse_month_day(date_str), Some((Some(_), _, _)))
+                .then(|| EventKind::Milestone.name().to_string());
+            config
+                .dates
+                .entry(date_str.clone())
+                .or_default()
+                .push(RawDateDetail {
+                    description: description.clone(),
+                    color: Some("orange".to_string()),
+                    kind,
+                    ..Default::default()
+                });
+        }
+        config.availability = self.commitments.clone();
+
+        let mut output = format!(
+            r#"# compact-calendar-cli configuration for {year}, written by `plan`
+#
+# Dates are "YYYY-MM-DD", or "MM-DD" to repeat every year.
+# Colors: orange, yellow, green, blue, purple, red, cyan, gray, and their
+# light_ variants (light_blue, ...).
+"#,
+            year = self.year
+        );
+
+        output.push('\n');
+        if let Some(days) = self.vacation_days {
+            writeln!(output, "# Vacation budget: {} days.", days).unwrap();
+        }
+        write!(
+            output,
+            r#"# Add time off as ranges tagged "off"; mark them "requested" until
+# they are approved:
+#
+# [[ranges]]
+# start = "{year}-07-01"
+# end = "{year}-07-05"
+# color = "green"
+# description = "Vacation"
+# tags = ["off"]
+# status = "requested"
+"#,
+            year = self.year
+        )
+        .unwrap();
+
+        let body = config.to_commented_toml();
+        if !body.is_empty() {
+            output.push('\n');
+            output.push_str(&body);
+        }
+        output
+    }
+}
+
+struct Prompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    /// Ask until the answer parses; `None` for an empty answer or end of input
+    fn ask_parsed<T>(
+        &mut self,
+        question: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> io::Result<Option<T>> {
+        loop {
+            write!(self.output, "{}", question)?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                writeln!(self.output)?;
+                return Ok(None);
+            }
+            let answer = line.trim();
+            if answer.is_empty() {
+                return Ok(None);
+            }
+            match parse(answer) {
+                Ok(value) => return Ok(Some(value)),
+                Err(e) => writeln!(self.output, "{}", e)?,
+            }
+        }
+    }
+}
+
+/// Parse "mon-fri 09:00-17:00 Work" into a weekly busy block
+fn parse_commitment(answer: &str) -> Result<RawAvailability, String> {
+    let invalid = || {
+        format!(
+            "Invalid commitment: '{}'. Use DAYS HH:MM-HH:MM DESCRIPTION",
+            answer
+        )
+    };
+    let mut parts = answer.splitn(3, char::is_whitespace);
+    let days = parts.next().ok_or_else(invalid)?;
+    let (start, end) = parts
+        .next()
+        .and_then(|times| times.split_once('-'))
+        .ok_or_else(invalid)?;
+    let description = parts
+        .next()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    let commitment = RawAvailability {
+        days: days.to_string(),
+        start: start.to_string(),
+        end: end.to_string(),
+        description: description.map(str::to_string),
+    };
+    commitment.to_block().map_err(|e| e.to_string())?;
+    Ok(commitment)
+}
+
+/// Parse "2025-03-31 Tax return" into a date string and description
+fn parse_deadline(answer: &str, year: i32) -> Result<(String, String), String> {
+    let (date_str, description) = answer
+        .split_once(char::is_whitespace)
+        .map(|(date_str, description)| (date_str, description.trim()))
+        .unwrap_or((answer, ""));
+    if description.is_empty() {
+        return Err(format!(
+            "Invalid deadline: '{}'. Use YYYY-MM-DD DESCRIPTION",
+            answer
+        ));
+    }
+
+    let detail = RawDateDetail {
+        description: description.to_string(),
+        ..Default::default()
+    };
+    detail
+        .to_event(date_str, Some(year), &Anchors::new(), &HashMap::new())
+        .map_err(|e| e.to_string())?;
+    Ok((date_str.to_string(), description.to_string()))
+}
@@ -0,0 +1,81 @@
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::settings::Settings;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Named set of defaults, stored as
+/// `$XDG_CONFIG_HOME/compact-calendar/profiles/<name>.toml`: optional
+/// `config` and `theme_file` paths next to the same keys as a config's
+/// `[settings]` block.
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// Calendar config to use, relative to the profiles directory; `${VAR}`
+    /// references are expanded
+    pub config: Option<PathBuf>,
+    /// Theme file to use instead of the user's `theme.toml`, resolved the
+    /// same way
+    pub theme_file: Option<PathBuf>,
+    pub settings: Settings,
+}
+
+impl Profile {
+    /// Directory holding profiles: `$XDG_CONFIG_HOME/compact-calendar/profiles`,
+    /// with `XDG_CONFIG_HOME` defaulting to `~/.config`
+    pub fn dir() -> Option<PathBuf> {
+        paths::xdg_config_dir().map(|dir| dir.join("profiles"))
+    }
+
+    /// Load the profile called `name` from [`Profile::dir`]
+    pub fn load(name: &str) -> Result<Self> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(Error::InvalidEntry(format!(
+                "invalid profile name '{}'",
+                name
+            )));
+        }
+        let dir = Self::dir().ok_or_else(|| {
+            Error::InvalidEntry("cannot locate profiles without $XDG_CONFIG_HOME or $HOME".into())
+        })?;
+        Self::load_from(&dir.join(format!("{}.toml", name)))
+    }
+
+    /// Load a profile file; a relative `config` or `theme_file` is resolved
+    /// next to it
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(Error::ConfigNotFound(path.to_path_buf()));
+        }
+        let contents = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |e| Error::parse(path.to_path_buf(), &contents, e);
+
+        let mut table: toml::Table = toml::from_str(&contents).map_err(parse_error)?;
+        let mut path_key = |key: &str| -> Result<Option<PathBuf>> {
+            let value = table
+                .remove(key)
+                .map(|value| String::deserialize(value).map_err(parse_error))
+                .transpose()?
+                .map(|value| paths::expand_env(&value).map(PathBuf::from))
+                .transpose()?;
+            Ok(match (value, path.parent()) {
+                (Some(value), Some(dir)) => Some(dir.join(value)),
+                (value, _) => value,
+            })
+        };
+        let config = path_key("config")?;
+        let theme_file = path_key("theme_file")?;
+        // Deserialized separately so unknown keys are still rejected
+        let settings = Settings::deserialize(toml::Value::Table(table)).map_err(parse_error)?;
+        settings.contrast()?;
+
+        Ok(Profile {
+            config,
+            theme_file,
+            settings,
+        })
+    }
+}
@@ -0,0 +1,475 @@
+//! Reading remind(1) files as configs, for moving over from remind and
+//! rem2ps. Simple `REM` lines become dates, weekly entries and ranges, and
+//! `OMIT` lines non-working days:
+//!
+//! ```text
+//! REM 25 Dec MSG Christmas                   # yearly date
+//! REM 2025-03-14 MSG Tax deadline            # one day
+//! REM Mon 1 Jun --7 MSG Memorial Day         # last Monday of May
+//! REM Tue Thu MSG Standup                    # weekly
+//! REM 2025-07-07 THROUGH 2025-07-11 MSG Vacation
+//! OMIT 26 Dec MSG Boxing Day
+//! ```
+//!
+//! Other commands such as `SET` or `FSET` are ignored, as are lines that
+//! run programs; lines using remind features without a counterpart in the
+//! config are left out and reported with their line.
+
+use crate::config::{CalendarConfig, RawDateDetail, RawDateRange, RawWeekly};
+use crate::expressions::NthWeekday;
+use crate::rendering::ColorPalette;
+use anstyle::RgbColor;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use std::fmt;
+
+/// Color of ranges, which remind doesn't color unless they set one
+pub const RANGE_COLOR: &str = "light_blue";
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Clauses taking a value that only matter to remind itself
+const IGNORED_WITH_VALUE: [&str; 9] = [
+    "AT", "DURATION", "PRIORITY", "TAG", "SCHED", "WARN", "INFO", "SCANFROM", "SCAN",
+];
+const IGNORED: [&str; 6] = ["SKIP", "BEFORE", "AFTER", "ONCE", "NOQUEUE", "ADDOMIT"];
+/// Bodies that run programs or only work inside remind
+const SKIPPED_BODIES: [&str; 4] = ["RUN", "PS", "PSFILE", "SATISFY"];
+
+/// A line remind accepts but that can't be turned into config entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based, the first of a line continued with `\`
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Translate the contents of a remind file into a config, along with the
+/// lines left out of it
+pub fn parse(contents: &str) -> (CalendarConfig, Vec<ParseError>) {
+    let mut config = CalendarConfig::default();
+    let mut skipped = Vec::new();
+    let mut lines = contents.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        // Lines ending in a backslash continue on the next one
+        let mut line = line.to_string();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next),
+                None => break,
+            }
+        }
+        if let Err(message) = translate(&line, &mut config) {
+            skipped.push(ParseError {
+                line: i + 1,
+                message,
+            });
+        }
+    }
+    (config, skipped)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateToken {
+    Day(u32),
+    Month(u32),
+    Year(i32),
+    Weekday(Weekday),
+    Date(NaiveDate),
+}
+
+fn date_token(token: &str) -> Option<DateToken> {
+    // Dates may carry a time, as in 2025-03-14@09:00
+    let date = token.split('@').next().unwrap_or(token);
+    for format in ["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(date, format) {
+            return Some(DateToken::Date(date));
+        }
+    }
+    if let Ok(number) = token.parse::<i32>() {
+        return match number {
+            1..=31 => Some(DateToken::Day(number as u32)),
+            1900.. => Some(DateToken::Year(number)),
+            _ => None,
+        };
+    }
+    let lower = token.to_lowercase();
+    if lower.len() < 3 {
+        return None;
+    }
+    if let Some(month) = MONTHS.iter().position(|name| name.starts_with(&lower)) {
+        return Some(DateToken::Month(month as u32 + 1));
+    }
+    WEEKDAYS
+        .iter()
+        .position(|name| name.starts_with(&lower))
+        .map(|day| DateToken::Weekday(Weekday::try_from(day as u8).unwrap()))
+}
+
+/// The date spec of a `REM` or `OMIT` line, before its body
+#[derive(Debug, Default)]
+struct Spec {
+    day: Option<u32>,
+    month: Option<u32>,
+    year: Option<i32>,
+    weekdays: Vec<Weekday>,
+    /// Days before the date it names, from `-N` or `--N`
+    back: u32,
+    /// Days between repeats, from `*N`
+    repeat: Option<u32>,
+    from: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    through: Option<NaiveDate>,
+}
+
+impl Spec {
+    fn set(&mut self, token: DateToken) -> Result<(), String> {
+        match token {
+            DateToken::Day(day) if self.day.is_none() => self.day = Some(day),
+            DateToken::Month(month) if self.month.is_none() => self.month = Some(month),
+            DateToken::Year(year) if self.year.is_none() => self.year = Some(year),
+            DateToken::Weekday(weekday) => self.weekdays.push(weekday),
+            DateToken::Date(date) if self.day.is_none() && self.month.is_none() => {
+                self.day = Some(date.day());
+                self.month = Some(date.month());
+                self.year = Some(date.year());
+            }
+            _ => return Err("the date is given twice".to_string()),
+        }
+        Ok(())
+    }
+
+    /// A full date, as `UNTIL`, `FROM` and `THROUGH` take
+    fn full_date(&self) -> Option<NaiveDate> {
+        match (self.year, self.month, self.day, self.weekdays.is_empty()) {
+            (Some(year), Some(month), Some(day), true) => NaiveDate::from_ymd_opt(year, month, day),
+            _ => None,
+        }
+    }
+
+    /// The day it names, as a config date expression
+    fn date_expr(&self) -> Result<String, String> {
+        let back = |expr: String| match self.back {
+            0 => expr,
+            days => format!("{} - {}d", expr, days),
+        };
+        let moved_back = |date: NaiveDate| {
+            date.checked_sub_days(Days::new(self.back.into()))
+                .ok_or_else(|| format!("moving {} back {} days is out of range", date, self.back))
+        };
+        if let Some(date) = self.full_date() {
+            return Ok(moved_back(date)?.format("%Y-%m-%d").to_string());
+        }
+        match (&self.weekdays[..], self.day, self.month) {
+            ([], Some(day), Some(month)) if self.year.is_none() => {
+                Ok(back(format!("{:02}-{:02}", month, day)))
+            }
+            // The first weekday on or after the 1st, 8th, 15th or 22nd is the
+            // first, second, third or fourth of the month
+            ([weekday], Some(day @ (1 | 8 | 15 | 22)), Some(month)) => {
+                let n = (day - 1) / 7 + 1;
+                match self.year {
+                    Some(year) => {
+                        let nth = NthWeekday::new(n as i32, *weekday, month)?;
+                        let date = nth
+                            .resolve(year)
+                            .ok_or("the date doesn't exist in that year")?;
+                        Ok(moved_back(date)?.format("%Y-%m-%d").to_string())
+                    }
+                    None => Ok(back(format!(
+                        "{} {} of {}",
+                        ["1st", "2nd", "3rd", "4th"][n as usize - 1],
+                        WEEKDAYS[weekday.num_days_from_monday() as usize],
+                        MONTHS[month as usize - 1]
+                    ))),
+                }
+            }
+            _ => Err("unsupported date; use a day and month, a full date, \
+                 or a weekday with the 1st, 8th, 15th or 22nd of a month"
+                .to_string()),
+        }
+    }
+}
+
+/// Read tokens after `UNTIL`, `FROM` or `THROUGH` into a full date
+fn clause_date<'a>(
+    keyword: &str,
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = (usize, &'a str)>>,
+) -> Result<NaiveDate, String> {
+    let mut spec = Spec::default();
+    while let Some(token) = tokens.peek().and_then(|(_, token)| date_token(token)) {
+        spec.set(token)?;
+        tokens.next();
+    }
+    spec.full_date()
+        .ok_or_else(|| format!("{} needs a full date", keyword))
+}
+
+/// The text shown for a body: the part quoted with `%"` when there is one,
+/// without remind's `%` substitutions
+fn message(body: &str) -> String {
+    let quoted = body
+        .split_once("%\"")
+        .and_then(|(_, rest)| rest.split_once("%\""))
+        .map(|(quoted, _)| quoted);
+    let mut text = String::new();
+    let mut chars = quoted.unwrap_or(body).chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {
+                if chars.next() == Some('%') {
+                    text.push('%');
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Add the entries of one line to `config`
+fn translate(line: &str, config: &mut CalendarConfig) -> Result<(), String> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+        return Ok(());
+    }
+    let mut tokens = line
+        .split_whitespace()
+        .map(|token| (token.as_ptr() as usize - line.as_ptr() as usize, token))
+        .peekable();
+    let omit = match tokens
+        .peek()
+        .map(|(_, token)| token.to_uppercase())
+        .as_deref()
+    {
+        Some("REM") => {
+            tokens.next();
+            false
+        }
+        Some("OMIT") => {
+            tokens.next();
+            true
+        }
+        // REM may be left out before a date
+        Some(token) if date_token(token).is_some() => false,
+        _ => return Ok(()),
+    };
+
+    let mut spec = Spec::default();
+    let mut body: Option<(String, usize)> = None;
+    while let Some((offset, token)) = tokens.next() {
+        let keyword = token.to_uppercase();
+        match keyword.as_str() {
+            "MSG" | "MSF" | "CAL" | "SPECIAL" => {
+                body = Some((keyword, offset + token.len()));
+                break;
+            }
+            keyword if SKIPPED_BODIES.contains(&keyword) => return Ok(()),
+            "UNTIL" => spec.until = Some(clause_date("UNTIL", &mut tokens)?),
+            "FROM" => spec.from = Some(clause_date("FROM", &mut tokens)?),
+            "THROUGH" => spec.through = Some(clause_date("THROUGH", &mut tokens)?),
+            // A local OMIT lists weekdays to skip
+            "OMIT" => {
+                while tokens
+                    .next_if(|(_, token)| matches!(date_token(token), Some(DateToken::Weekday(_))))
+                    .is_some()
+                {}
+            }
+            keyword if IGNORED_WITH_VALUE.contains(&keyword) => {
+                tokens.next();
+            }
+            keyword if IGNORED.contains(&keyword) => {}
+            _ if token.starts_with('+') => {}
+            _ if token.starts_with('-') => {
+                spec.back = token
+                    .trim_start_matches('-')
+                    .parse()
+                    .map_err(|_| format!("invalid delta '{}'", token))?;
+            }
+            _ if token.starts_with('*') => {
+                spec.repeat = Some(
+                    token[1..]
+                        .parse()
+                        .map_err(|_| format!("invalid repeat '{}'", token))?,
+                );
+            }
+            _ => match date_token(token) {
+                Some(date) => spec.set(date)?,
+                None => return Err(format!("unsupported '{}'", token)),
+            },
+        }
+    }
+
+    let mut detail = None;
+    let mut color = None;
+    if let Some((keyword, start)) = body {
+        let text = &line[start..];
+        let text = if keyword == "SPECIAL" {
+            let mut words = text.split_whitespace();
+            if !matches!(
+                words.next().map(str::to_uppercase).as_deref(),
+                Some("COLOR" | "COLOUR")
+            ) {
+                // Shading, moon phases and other rem2ps specials
+                return Ok(());
+            }
+            let rgb: Vec<u8> = words
+                .by_ref()
+                .take(3)
+                .filter_map(|c| c.parse().ok())
+                .collect();
+            let [r, g, b] = rgb[..] else {
+                return Err("SPECIAL COLOR needs red, green and blue values".to_string());
+            };
+            color = Some(ColorPalette::nearest_color_name(RgbColor(r, g, b)).to_string());
+            words.collect::<Vec<_>>().join(" ")
+        } else {
+            text.to_string()
+        };
+        detail = Some(RawDateDetail {
+            description: message(&text),
+            color: color.clone(),
+            ..RawDateDetail::default()
+        });
+    }
+
+    if omit {
+        let entry = match spec.through {
+            Some(end) => format!("{}..{}", spec.date_expr()?, end.format("%Y-%m-%d")),
+            None => spec.date_expr()?,
+        };
+        config.non_working.push(entry);
+        if let (Some(detail), None) = (detail, spec.through) {
+            config
+                .dates
+                .entry(spec.date_expr()?)
+                .or_default()
+                .push(detail);
+        }
+        return Ok(());
+    }
+    let Some(detail) = detail else {
+        return Ok(());
+    };
+
+    let every = |weekdays: &[Weekday]| {
+        weekdays
+            .iter()
+            .map(|weekday| WEEKDAYS[weekday.num_days_from_monday() as usize][..3].to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let iso = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+    let through = match (spec.through, spec.repeat, spec.until) {
+        (Some(end), None, _) => Some(end),
+        (None, Some(1), Some(end)) => Some(end),
+        _ => None,
+    };
+    if let Some(end) = through {
+        let start = spec.full_date().ok_or("a range needs a full start date")?;
+        config.ranges.push(RawDateRange {
+            start: iso(start),
+            end: iso(end),
+            color: Some(color.unwrap_or_else(|| RANGE_COLOR.to_string())),
+            tag: None,
+            description: Some(detail.description),
+            kind: None,
+            timezone: None,
+            id: None,
+            tags: Vec::new(),
+            owner: None,
+            cost: None,
+            priority: None,
+            layer: None,
+            status: None,
+            gradient: None,
+            icon: None,
+        });
+    } else if let Some(7) = spec.repeat {
+        let start = spec
+            .full_date()
+            .ok_or("a weekly repeat needs a full start date")?;
+        config.weekly.push(RawWeekly {
+            every: every(&[start.weekday()]),
+            from: Some(iso(start)),
+            until: spec.until.map(iso),
+            detail,
+        });
+    } else if let Some(repeat) = spec.repeat {
+        return Err(format!("unsupported repeat '*{}'", repeat));
+    } else if !spec.weekdays.is_empty()
+        && spec.day.is_none()
+        && spec.month.is_none()
+        && spec.year.is_none()
+    {
+        config.weekly.push(RawWeekly {
+            every: every(&spec.weekdays),
+            from: spec.from.map(iso),
+            until: spec.until.map(iso),
+            detail,
+        });
+    } else if let (Some(day), None, None, true) =
+        (spec.day, spec.month, spec.year, spec.weekdays.is_empty())
+    {
+        // The same day every month, skipped in months without it
+        for month in 1..=12 {
+            if NaiveDate::from_ymd_opt(2000, month, day).is_some() {
+                let spec = Spec {
+                    month: Some(month),
+                    ..spec_for_day(day, spec.back)
+                };
+                config
+                    .dates
+                    .entry(spec.date_expr()?)
+                    .or_default()
+                    .push(detail.clone());
+            }
+        }
+    } else {
+        config
+            .dates
+            .entry(spec.date_expr()?)
+            .or_default()
+            .push(detail);
+    }
+    Ok(())
+}
+
+fn spec_for_day(day: u32, back: u32) -> Spec {
+    Spec {
+        day: Some(day),
+        back,
+        ..Spec::default()
+    }
+}
@@ -1,8 +1,21 @@
-use crate::formatting::{MonthInfo, WeekLayout};
-use crate::models::{Calendar, ColorMode, DateDetail, PastDateDisplay, WeekStart, WeekendDisplay};
+use crate::dst::Transition;
+use crate::formatting::WeekLayout;
+use crate::habits::HabitLog;
+use crate::heatmap::{self, Heatmap};
+use crate::models::{
+    AnnotationOverflow, Calendar, CalendarChanges, CellWidth, ContrastMode, Direction, Event,
+    Gradient, Layout, OverlapDisplay, PastDateDisplay, PlainMarker, Status, TodayMarker, WeekStart,
+    WeekendDisplay,
+};
+use crate::named_colors;
+use crate::text_width;
+use crate::theme::Borders;
 use anstyle::{AnsiColor, Color, Effects, RgbColor, Style};
 use chrono::Weekday;
 use chrono::{Datelike, NaiveDate};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
 
 #[derive(Debug, Clone, Copy)]
 pub struct ColorValue {
@@ -15,6 +28,15 @@ impl ColorValue {
         Self { normal, dimmed }
     }
 
+    /// `normal`, with the dimmed shade at 70% like the palette's own colors
+    pub fn from_rgb(normal: RgbColor) -> Self {
+        let dim = |channel: u8| ((channel as u16 * 7 + 5) / 10) as u8;
+        Self::new(
+            normal,
+            RgbColor(dim(normal.0), dim(normal.1), dim(normal.2)),
+        )
+    }
+
     pub fn get_normal_style(&self) -> Style {
         Style::new().bg_color(Some(Color::Rgb(self.normal)))
     }
@@ -22,17 +44,92 @@ impl ColorValue {
     pub fn get_dimmed_style(&self) -> Style {
         Style::new().bg_color(Some(Color::Rgb(self.dimmed)))
     }
+
+    /// The average of `values`, channel by channel; `None` when empty
+    pub fn blend(values: &[ColorValue]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let average = |channel: fn(&ColorValue) -> RgbColor| {
+            let sum = values.iter().map(channel).fold([0u32; 3], |sum, color| {
+                [
+                    sum[0] + color.0 as u32,
+                    sum[1] + color.1 as u32,
+                    sum[2] + color.2 as u32,
+                ]
+            });
+            let count = values.len() as u32;
+            RgbColor(
+                (sum[0] / count) as u8,
+                (sum[1] / count) as u8,
+                (sum[2] / count) as u8,
+            )
+        };
+        Some(Self::new(
+            average(|value| value.normal),
+            average(|value| value.dimmed),
+        ))
+    }
+}
+
+/// Minimum WCAG contrast ratio between text and cell background in high-contrast mode
+pub const HIGH_CONTRAST_RATIO: f64 = 7.0;
+
+/// Past dates fade toward the background in steps of one in `FADE_STEPS`
+pub const FADE_STEPS: u16 = 12;
+/// Fade of the oldest past dates, which stay readable
+pub const MAX_FADE_STEP: u16 = 6;
+
+/// Steps a range's gradient is spread over, however long the range
+pub const GRADIENT_STEPS: u16 = 64;
+
+/// Marker paired with every colored date and annotation in high-contrast mode
+pub const HIGH_CONTRAST_MARKER: char = '•';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// Detect the terminal background from `COLORFGBG`, assuming dark when unknown
+    pub fn detect() -> Self {
+        std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|value| Self::from_colorfgbg(&value))
+            .unwrap_or(Background::Dark)
+    }
+
+    /// Parse a `COLORFGBG` value such as "15;0", whose last field is the background
+    pub fn from_colorfgbg(value: &str) -> Option<Self> {
+        let bg: u8 = value.rsplit(';').next()?.parse().ok()?;
+        match bg {
+            7 | 9..=15 => Some(Background::Light),
+            _ => Some(Background::Dark),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ColorPalette {
     colors_enabled: bool,
+    contrast: ContrastMode,
+    background: Background,
+    /// A theme's own colors, looked up before the built-in ones
+    theme_colors: BTreeMap<String, ColorValue>,
+    /// Brightness of dimmed colors, instead of their own dimmed shades
+    dim_factor: Option<f64>,
 }
 
 impl Default for ColorPalette {
     fn default() -> Self {
         Self {
             colors_enabled: !Self::is_color_disabled(),
+            contrast: ContrastMode::Normal,
+            background: Background::Dark,
+            theme_colors: BTreeMap::new(),
+            dim_factor: None,
         }
     }
 }
@@ -46,10 +143,65 @@ impl ColorPalette {
         std::env::var("NO_COLOR").is_ok()
     }
 
+    pub fn disabled() -> Self {
+        Self {
+            colors_enabled: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_contrast(mut self, contrast: ContrastMode, background: Background) -> Self {
+        self.contrast = contrast;
+        self.background = background;
+        self
+    }
+
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.colors_enabled = enabled;
+        self
+    }
+
+    /// Colors of a [`Theme`] to use by name, over the built-in ones
+    pub fn with_theme_colors(mut self, colors: BTreeMap<String, ColorValue>) -> Self {
+        self.theme_colors = colors;
+        self
+    }
+
+    /// Dim colors to `factor` of their brightness, from 0 (black) to 1
+    pub fn with_dim_factor(mut self, factor: Option<f64>) -> Self {
+        self.dim_factor = factor;
+        self
+    }
+
+    /// The dimmed shade of `value`
+    fn dimmed(&self, value: ColorValue) -> RgbColor {
+        match self.dim_factor {
+            Some(factor) => {
+                let dim = |channel: u8| (channel as f64 * factor).round() as u8;
+                RgbColor(
+                    dim(value.normal.0),
+                    dim(value.normal.1),
+                    dim(value.normal.2),
+                )
+            }
+            None => value.dimmed,
+        }
+    }
+
+    /// The theme's color called `name`, or else the built-in one
+    pub fn color_value(&self, name: &str) -> Option<ColorValue> {
+        self.theme_colors
+            .get(name)
+            .copied()
+            .or_else(|| Self::get_color_value(name))
+    }
+
     pub fn are_colors_enabled(&self) -> bool {
         self.colors_enabled
     }
 
+    /// The palette's own shade of `name`, or else its CSS color
     pub fn get_color_value(name: &str) -> Option<ColorValue> {
         match name {
             "orange" => Some(ColorValue::new(
@@ -109,18 +261,55 @@ impl ColorPalette {
                 RgbColor(144, 225, 198),
                 RgbColor(101, 158, 139),
             )),
-            _ => None,
+            // CSS names the palette above doesn't have
+            _ => named_colors::rgb(name).map(ColorValue::from_rgb),
         }
     }
 
+    /// The base color closest to `rgb`, for colors given as numbers by other
+    /// tools. Channels are weighted by how the eye sees them ("redmean"), so
+    /// pure red is red rather than orange.
+    pub fn nearest_color_name(rgb: RgbColor) -> &'static str {
+        let distance = |name: &str| {
+            let RgbColor(r, g, b) = Self::get_color_value(name).unwrap().normal;
+            let mean = (r as i32 + rgb.0 as i32) / 2;
+            let (dr, dg, db) = (
+                r as i32 - rgb.0 as i32,
+                g as i32 - rgb.1 as i32,
+                b as i32 - rgb.2 as i32,
+            );
+            (512 + mean) * dr * dr + 1024 * dg * dg + (767 - mean) * db * db
+        };
+        [
+            "orange", "yellow", "green", "blue", "purple", "red", "cyan", "gray",
+        ]
+        .into_iter()
+        .min_by_key(|name| distance(name))
+        .unwrap()
+    }
+
     pub fn get_style(&self, color_name: &str, dimmed: bool) -> Style {
+        self.get_blended_style(&[color_name], dimmed)
+    }
+
+    /// Background mixing every one of the named colors, for days inside
+    /// several ranges
+    pub fn get_blended_style(&self, color_names: &[&str], dimmed: bool) -> Style {
         if !self.colors_enabled {
             return Style::new();
         }
 
-        if let Some(color_value) = Self::get_color_value(color_name) {
-            if dimmed {
-                color_value.get_dimmed_style()
+        let values: Vec<ColorValue> = color_names
+            .iter()
+            .filter_map(|name| self.color_value(name))
+            .collect();
+        if let Some(color_value) = ColorValue::blend(&values) {
+            if self.contrast == ContrastMode::High {
+                // Dimming would eat into the contrast margin, so it is skipped
+                let color = Self::high_contrast_color(color_value.normal, self.background);
+                Style::new().bg_color(Some(Color::Rgb(color)))
+            } else if dimmed {
+                Style::new().bg_color(Some(Color::Rgb(self.dimmed(color_value))))
             } else {
                 color_value.get_normal_style()
             }
@@ -129,274 +318,1224 @@ impl ColorPalette {
         }
     }
 
-    pub fn black_text() -> Style {
-        Style::new().fg_color(Some(Color::Ansi(AnsiColor::Black)))
+    /// Background `step` of [`GRADIENT_STEPS`] along a range's gradient from
+    /// `color_name`: from a light to a dark shade of it, or into another color
+    pub fn get_gradient_style(
+        &self,
+        color_name: &str,
+        gradient: &Gradient,
+        step: u16,
+        dimmed: bool,
+    ) -> Style {
+        if !self.colors_enabled {
+            return Style::new();
+        }
+        let Some(from) = self.color_value(color_name) else {
+            return Style::new();
+        };
+        let pick = |value: ColorValue| {
+            if dimmed {
+                self.dimmed(value)
+            } else {
+                value.normal
+            }
+        };
+        let (start, end) = match gradient {
+            Gradient::Shade => (
+                Self::mix(pick(from), RgbColor(255, 255, 255), 1, 3),
+                Self::mix(pick(from), RgbColor(0, 0, 0), 1, 3),
+            ),
+            Gradient::To(name) => match self.color_value(name) {
+                Some(to) => (pick(from), pick(to)),
+                None => (pick(from), pick(from)),
+            },
+        };
+        let mut color = Self::mix(start, end, step.min(GRADIENT_STEPS), GRADIENT_STEPS);
+        if self.contrast == ContrastMode::High {
+            color = Self::high_contrast_color(color, self.background);
+        }
+        Style::new().bg_color(Some(Color::Rgb(color)))
     }
-}
 
-struct ColorCodes;
-
-impl ColorCodes {
-    fn is_color_disabled() -> bool {
-        std::env::var("NO_COLOR").is_ok()
+    /// Fade a past day's style `step` of [`FADE_STEPS`] toward the terminal
+    /// background: its cell color when it has one, else its text. High
+    /// contrast cells keep their color.
+    pub fn faded_style(&self, style: Style, step: u16) -> Style {
+        if !self.colors_enabled {
+            return style;
+        }
+        let (toward, text) = match self.background {
+            Background::Dark => (RgbColor(0, 0, 0), RgbColor(192, 192, 192)),
+            Background::Light => (RgbColor(255, 255, 255), RgbColor(64, 64, 64)),
+        };
+        match style.get_bg_color() {
+            Some(Color::Rgb(color)) if self.contrast != ContrastMode::High => {
+                style.bg_color(Some(Color::Rgb(Self::mix(color, toward, step, FADE_STEPS))))
+            }
+            Some(_) => style,
+            None => style.fg_color(Some(Color::Rgb(Self::mix(text, toward, step, FADE_STEPS)))),
+        }
     }
 
-    fn get_bg_color(color: &str) -> Style {
-        if Self::is_color_disabled() {
+    /// Faint background a shade off the terminal's, for telling alternate
+    /// weeks apart
+    pub fn shaded_style(&self) -> Style {
+        if !self.colors_enabled {
             return Style::new();
         }
-        let palette = ColorPalette::new();
-        palette.get_style(color, false)
+        let shade = match self.background {
+            Background::Dark => RgbColor(48, 48, 48),
+            Background::Light => RgbColor(232, 232, 232),
+        };
+        Style::new().bg_color(Some(Color::Rgb(shade)))
     }
 
-    fn get_dimmed_bg_color(color: &str) -> Style {
-        if Self::is_color_disabled() {
+    /// Background of a heatmap `level` from 1 to [`heatmap::LEVELS`], darker
+    /// on light terminals and brighter on dark ones the busier the day, with
+    /// whichever text reads best on it
+    pub fn heatmap_style(&self, level: u8) -> Style {
+        if !self.colors_enabled {
             return Style::new();
         }
-        let palette = ColorPalette::new();
-        palette.get_style(color, true)
+        const DARK: [RgbColor; heatmap::LEVELS as usize] = [
+            RgbColor(14, 68, 41),
+            RgbColor(0, 109, 50),
+            RgbColor(38, 166, 65),
+            RgbColor(57, 211, 83),
+        ];
+        const LIGHT: [RgbColor; heatmap::LEVELS as usize] = [
+            RgbColor(155, 233, 168),
+            RgbColor(64, 196, 99),
+            RgbColor(48, 161, 78),
+            RgbColor(33, 110, 57),
+        ];
+        let shades = match self.background {
+            Background::Dark => DARK,
+            Background::Light => LIGHT,
+        };
+        let shade = shades[(level.clamp(1, heatmap::LEVELS) - 1) as usize];
+        let text = if Self::contrast_ratio(shade, RgbColor(0, 0, 0))
+            >= Self::contrast_ratio(shade, RgbColor(255, 255, 255))
+        {
+            Self::black_text()
+        } else {
+            Self::white_text()
+        };
+        text.bg_color(Some(Color::Rgb(shade)))
+    }
+
+    /// Style for text labelled with an event color, such as annotations
+    pub fn annotation_style(&self, color: Option<&str>) -> Style {
+        match color {
+            Some(color) if self.colors_enabled => self
+                .get_style(color, false)
+                .fg_color(self.text_style().get_fg_color()),
+            _ => Style::new(),
+        }
+    }
+
+    pub fn black_text() -> Style {
+        Style::new().fg_color(Some(Color::Ansi(AnsiColor::Black)))
+    }
+
+    pub fn white_text() -> Style {
+        Style::new().fg_color(Some(Color::Ansi(AnsiColor::BrightWhite)))
+    }
+
+    /// Text style drawn on top of colored backgrounds
+    pub fn text_style(&self) -> Style {
+        match (self.contrast, self.background) {
+            (ContrastMode::High, Background::Light) => Self::white_text(),
+            _ => Self::black_text(),
+        }
     }
 
-    fn black_text() -> Style {
-        ColorPalette::black_text()
+    /// Lighten (dark terminals, black text) or darken (light terminals, white
+    /// text) a color until it meets [`HIGH_CONTRAST_RATIO`] against the text
+    pub fn high_contrast_color(color: RgbColor, background: Background) -> RgbColor {
+        let (toward, text) = match background {
+            Background::Dark => (RgbColor(255, 255, 255), RgbColor(0, 0, 0)),
+            Background::Light => (RgbColor(0, 0, 0), RgbColor(255, 255, 255)),
+        };
+        const STEPS: u16 = 20;
+        (0..=STEPS)
+            .map(|step| Self::mix(color, toward, step, STEPS))
+            .find(|&candidate| Self::contrast_ratio(candidate, text) >= HIGH_CONTRAST_RATIO)
+            .unwrap_or(toward)
     }
 
-    fn underline() -> Effects {
-        Effects::UNDERLINE
+    fn mix(from: RgbColor, to: RgbColor, step: u16, steps: u16) -> RgbColor {
+        let channel = |a: u8, b: u8| {
+            ((a as u16 * (steps - step) + b as u16 * step + steps / 2) / steps) as u8
+        };
+        RgbColor(
+            channel(from.0, to.0),
+            channel(from.1, to.1),
+            channel(from.2, to.2),
+        )
     }
 
-    fn strikethrough() -> Effects {
-        Effects::STRIKETHROUGH
+    /// WCAG 2 relative luminance
+    pub fn relative_luminance(color: RgbColor) -> f64 {
+        let linear = |channel: u8| {
+            let c = channel as f64 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(color.0) + 0.7152 * linear(color.1) + 0.0722 * linear(color.2)
     }
 
-    fn dim() -> Effects {
-        Effects::DIMMED
+    /// WCAG 2 contrast ratio, from 1.0 (identical) to 21.0 (black on white)
+    pub fn contrast_ratio(a: RgbColor, b: RgbColor) -> f64 {
+        let (la, lb) = (Self::relative_luminance(a), Self::relative_luminance(b));
+        let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+        (lighter + 0.05) / (darker + 0.05)
     }
 }
 
 const DAYS_IN_WEEK: usize = 7;
-const CALENDAR_WIDTH: usize = 34;
-const HEADER_WIDTH: usize = 48;
+
+/// Most weeks a month's days fall in
+const WEEKS_IN_MONTH: usize = 6;
+
+/// A rendered calendar kept week by week, built by
+/// [`CalendarRenderer::layout`]. Live views update it with each change
+/// instead of rendering the whole year again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearLayout {
+    header: String,
+    weeks: Vec<RenderedWeek>,
+    /// "Today" when rendered; past days and today's marker depend on it
+    today: NaiveDate,
+    /// Legend and habit streak; the legend depends on every event, so this
+    /// is rebuilt on any change
+    footer: String,
+    borders: Borders,
+    /// Columns of the grid to mirror, for right-to-left
+    mirrored: Option<MirroredGrid>,
+}
+
+/// Columns of a grid row: the label and day cells, each between borders or
+/// gaps of one column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MirroredGrid {
+    label_width: usize,
+    /// Width of a day cell, not counting the gap after it
+    cell_width: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RenderedWeek {
+    number: i32,
+    start: NaiveDate,
+    text: String,
+    /// Footer lines for the annotations cut off in this week
+    overflow: String,
+}
+
+impl YearLayout {
+    /// Re-render the weeks touched by `changes` with `renderer`, which must
+    /// be for the same year and options. Everything is re-rendered when the
+    /// day has rolled over. Returns the number of weeks re-rendered.
+    pub fn update(&mut self, renderer: &CalendarRenderer, changes: &CalendarChanges) -> usize {
+        // Other layouts are kept whole, as the header
+        if renderer.calendar.today() != self.today
+            || renderer.calendar.options.layout != Layout::Vertical
+        {
+            *self = renderer.layout();
+            return self.weeks.len();
+        }
+
+        let mut updated = 0;
+        for (i, week) in self.weeks.iter_mut().enumerate() {
+            // A week's text only depends on the days and events within it
+            let end = week.start + chrono::Days::new(DAYS_IN_WEEK as u64 - 1);
+            if changes.touches(week.start, end) {
+                week.text = renderer.week_text(week.number, week.start, i == 0);
+                week.overflow = renderer.overflow_text(week.start, i == 0);
+                updated += 1;
+            }
+        }
+        if !changes.is_empty() {
+            self.footer = renderer.footer_text();
+        }
+        updated
+    }
+
+    /// The whole calendar, as [`CalendarRenderer::render_to`] writes it
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut output = Vec::new();
+        output.extend_from_slice(self.header.as_bytes());
+        for week in &self.weeks {
+            output.extend_from_slice(week.text.as_bytes());
+        }
+        let overflow: String = self
+            .weeks
+            .iter()
+            .map(|week| week.overflow.as_str())
+            .collect();
+        write_overflow_footer(&mut output, &overflow)?;
+        output.extend_from_slice(self.footer.as_bytes());
+        writeln!(output)?;
+        let output = String::from_utf8_lossy(&output);
+        let output = mirror_grid(&output, self.mirrored);
+        w.write_all(self.borders.apply(&output).as_bytes())
+    }
+}
 
 pub struct CalendarRenderer<'a> {
     calendar: &'a Calendar,
+    palette: ColorPalette,
+    changes: CalendarChanges,
+    /// Symbol of each color for [`PlainMarker::Symbols`]
+    plain_symbols: HashMap<String, char>,
 }
 
+/// Prefix of annotations for events that changed since the previous render
+pub const CHANGED_MARKER: char = '*';
+
 impl<'a> CalendarRenderer<'a> {
     pub fn new(calendar: &'a Calendar) -> Self {
-        CalendarRenderer { calendar }
+        CalendarRenderer {
+            calendar,
+            palette: ColorPalette::new()
+                .with_contrast(calendar.options.contrast, Background::detect())
+                .with_theme_colors(calendar.options.theme.colors.clone())
+                .with_dim_factor(calendar.options.dim_factor),
+            changes: CalendarChanges::default(),
+            plain_symbols: plain_symbols(calendar),
+        }
     }
 
+    /// Render to stdout, colored unless `NO_COLOR` is set
     pub fn render(&self) {
-        self.print_header();
-        self.print_weeks();
-        println!();
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        self.render_to(&mut out)
+            .and_then(|_| out.flush())
+            .expect("failed printing to stdout");
     }
 
+    /// Render without colors, for snapshots and plain-text consumers
     pub fn render_to_string(&self) -> String {
-        let mut output = String::new();
+        let renderer = CalendarRenderer {
+            calendar: self.calendar,
+            palette: ColorPalette::disabled()
+                .with_contrast(self.calendar.options.contrast, Background::Dark),
+            changes: self.changes.clone(),
+            plain_symbols: self.plain_symbols.clone(),
+        };
+        let mut output = Vec::new();
+        renderer
+            .render_to(&mut output)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("rendered calendar is valid UTF-8")
+    }
 
-        let prev_no_color = std::env::var("NO_COLOR").ok();
-        std::env::set_var("NO_COLOR", "1");
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.palette.colors_enabled = enabled;
+        self
+    }
 
-        output.push_str(&self.header_to_string());
-        output.push_str(&self.weeks_to_string());
-        output.push('\n');
+    /// Highlight changed days and mark changed annotations, e.g. with
+    /// [`Calendar::changes_since`] the previous version
+    pub fn with_changes(mut self, changes: CalendarChanges) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// Render the calendar into any writer
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let borders = &self.calendar.options.theme.borders;
+        let mirrored = self.mirrored_grid();
+        if *borders == Borders::default() && mirrored.is_none() {
+            return self.write_calendar(w);
+        }
+        // Drawn left to right with the default borders, then mirrored and
+        // redrawn with the theme's
+        let mut output = Vec::new();
+        self.write_calendar(&mut output)?;
+        let output = String::from_utf8_lossy(&output);
+        let output = mirror_grid(&output, mirrored);
+        w.write_all(borders.apply(&output).as_bytes())
+    }
+
+    /// Columns of the grid, when it runs right to left
+    fn mirrored_grid(&self) -> Option<MirroredGrid> {
+        let options = &self.calendar.options;
+        (options.direction == Direction::Rtl && options.layout == Layout::Vertical).then(|| {
+            MirroredGrid {
+                label_width: self.label_width(),
+                cell_width: self.cell_width() - 1,
+            }
+        })
+    }
+
+    fn write_calendar<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self.calendar.options.layout {
+            Layout::Vertical => {
+                self.write_header(w)?;
+                self.write_weeks(w)?;
+                let overflow: String = self
+                    .weeks()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (_, start))| self.overflow_text(start, i == 0))
+                    .collect();
+                write_overflow_footer(w, &overflow)?;
+            }
+            Layout::Horizontal => self.write_horizontal(w)?,
+            Layout::Grid { columns } => self.write_grid(w, columns)?,
+            Layout::Heatmap => self.write_heatmap(w)?,
+        }
+        w.write_all(self.footer_text().as_bytes())?;
+        writeln!(w)
+    }
+
+    /// Render into a [`YearLayout`] that keeps each week's text, so later
+    /// changes only re-render the weeks they touch. Other layouts than the
+    /// vertical one are kept whole.
+    pub fn layout(&self) -> YearLayout {
+        let mut header = Vec::new();
+        let weeks = match self.calendar.options.layout {
+            Layout::Vertical => {
+                self.write_header(&mut header)
+                    .expect("writing to a Vec cannot fail");
+                self.weeks()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (number, start))| RenderedWeek {
+                        number,
+                        start,
+                        text: self.week_text(number, start, i == 0),
+                        overflow: self.overflow_text(start, i == 0),
+                    })
+                    .collect()
+            }
+            Layout::Horizontal => {
+                self.write_horizontal(&mut header)
+                    .expect("writing to a Vec cannot fail");
+                Vec::new()
+            }
+            Layout::Grid { columns } => {
+                self.write_grid(&mut header, columns)
+                    .expect("writing to a Vec cannot fail");
+                Vec::new()
+            }
+            Layout::Heatmap => {
+                self.write_heatmap(&mut header)
+                    .expect("writing to a Vec cannot fail");
+                Vec::new()
+            }
+        };
+        YearLayout {
+            header: String::from_utf8(header).expect("rendered calendar is valid UTF-8"),
+            weeks,
+            today: self.calendar.today(),
+            footer: self.footer_text(),
+            borders: self.calendar.options.theme.borders,
+            mirrored: self.mirrored_grid(),
+        }
+    }
+
+    /// Lines below the weeks: the legend, the habit streak and the heatmap's
+    /// totals
+    fn footer_text(&self) -> String {
+        self.legend_text() + &self.streak_text() + &self.heatmap_text()
+    }
+
+    /// With a heatmap, the year's total and its busiest day
+    fn heatmap_text(&self) -> String {
+        let Some(heatmap) = &self.calendar.options.heatmap else {
+            return String::new();
+        };
+        let (start, end) = self.year_span();
+        let total = heatmap.total(start, end);
+        match heatmap.busiest(start, end) {
+            Some((date, count)) => format!(
+                "Activity: {} in {}, busiest {} with {}\n",
+                total,
+                self.calendar.year,
+                date.format(self.calendar.options.locale.date_format()),
+                count
+            ),
+            None => format!("Activity: none in {}\n", self.calendar.year),
+        }
+    }
+
+    /// With a habit log, the current streak and the year's longest
+    fn streak_text(&self) -> String {
+        let Some(habits) = &self.calendar.options.habits else {
+            return String::new();
+        };
+        let days = |count: usize| match count {
+            1 => "1 day".to_string(),
+            count => format!("{} days", count),
+        };
+        let (start, end) = self.year_span();
+        format!(
+            "Streak: {}, longest in {}: {}\n",
+            days(habits.current_streak(self.calendar.today())),
+            self.calendar.year,
+            days(habits.longest_streak(start, end))
+        )
+    }
 
-        match prev_no_color {
-            Some(val) => std::env::set_var("NO_COLOR", val),
-            None => std::env::remove_var("NO_COLOR"),
+    /// With `legend` set, one line per color shown naming what it stands
+    /// for: the categories using it, or else the descriptions of its ranges
+    /// and then its dates
+    fn legend_text(&self) -> String {
+        if !self.calendar.options.legend {
+            return String::new();
         }
 
-        output
+        let (start, end) = self.get_filtered_date_range();
+        // Color, then its categories, range and date descriptions
+        let mut colors: Vec<(&str, [Vec<&str>; 3])> = Vec::new();
+        for event in self.calendar.events() {
+            let Some(color) = event.color.as_deref() else {
+                continue;
+            };
+            if event.start > end || event.end < start || event.status == Some(Status::Rejected) {
+                continue;
+            }
+            let idx = match colors.iter().position(|(c, _)| *c == color) {
+                Some(idx) => idx,
+                None => {
+                    colors.push((color, Default::default()));
+                    colors.len() - 1
+                }
+            };
+            let meanings = &mut colors[idx].1;
+            let (slot, meaning) = match (&event.category, event.is_range()) {
+                (Some(category), _) => (0, Some(category.as_str())),
+                (None, true) => (1, event.description.as_deref()),
+                (None, false) => (2, event.description.as_deref()),
+            };
+            if let Some(meaning) = meaning {
+                if !meanings[slot].contains(&meaning) {
+                    meanings[slot].push(meaning);
+                }
+            }
+        }
+        if colors.is_empty() {
+            return String::new();
+        }
+
+        let width = colors
+            .iter()
+            .map(|(color, _)| text_width::width(color))
+            .max()
+            .unwrap_or(0);
+        let mut legend = String::from("Legend:\n");
+        for (color, meanings) in &colors {
+            let meaning = meanings
+                .iter()
+                .find(|meanings| !meanings.is_empty())
+                .map(|meanings| meanings.join(", "))
+                .unwrap_or_default();
+            let style = self.get_annotation_style(Some(color));
+            let symbol = self
+                .plain_symbol(color)
+                .map(|symbol| format!("{} ", symbol))
+                .unwrap_or_default();
+            let line = format!(
+                "  {}{}{}{}  {}",
+                symbol,
+                style.render(),
+                text_width::pad_end(color, width),
+                style.render_reset(),
+                meaning,
+            );
+            legend.push_str(line.trim_end());
+            legend.push('\n');
+        }
+        legend
+    }
+
+    fn week_text(&self, week_num: i32, week_start: NaiveDate, is_first: bool) -> String {
+        let mut output = Vec::new();
+        self.write_week(&mut output, week_num, week_start, is_first)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("rendered calendar is valid UTF-8")
+    }
+
+    /// Footer lines for the week's annotations that `--annotation-width` cut
+    /// off, in full
+    fn overflow_text(&self, week_start: NaiveDate, is_first: bool) -> String {
+        let options = &self.calendar.options;
+        let (Some(width), AnnotationOverflow::Truncate) =
+            (options.annotation_width, options.annotation_overflow)
+        else {
+            return String::new();
+        };
+
+        let annotations = self.annotations(&WeekLayout::new(week_start), is_first);
+        let shown = truncate_annotations(&annotations, width);
+        let cut = shown
+            .iter()
+            .zip(&annotations)
+            .take_while(|((shown, _), (text, _))| shown == text)
+            .count();
+        annotations[cut..]
+            .iter()
+            .map(|(text, style)| format!("  {}{}{}\n", style.render(), text, style.render_reset()))
+            .collect()
     }
 
     /// Check if a week should be rendered based on month filter
     fn should_render_week(&self, layout: &WeekLayout) -> bool {
         // Include week if ANY of its 7 days fall within the filtered month range
+        let today = self.calendar.today();
         layout.dates.iter().any(|date| {
             if date.year() != self.calendar.year {
                 false
             } else {
                 self.calendar
+                    .options
                     .month_filter
-                    .should_display_month(date.month(), self.calendar.year)
+                    .should_display_month(date.month(), today)
             }
         })
     }
 
-    /// Get the filtered date range based on month filter
-    fn get_filtered_date_range(&self) -> (NaiveDate, NaiveDate) {
-        self.calendar
-            .month_filter
-            .get_date_range(self.calendar.year)
+    fn padding(&self) -> &'static str {
+        &"  "[..self.padding_width()]
+    }
+
+    /// Spaces on each side of a day; compact cells keep one for the marks of
+    /// colored days when colors are off
+    fn padding_width(&self) -> usize {
+        let options = &self.calendar.options;
+        let marked =
+            !self.palette.are_colors_enabled() && options.plain_marker != PlainMarker::None;
+        match options.cell_width.padding() {
+            0 if marked => 1,
+            padding => padding,
+        }
     }
 
-    fn header_to_string(&self) -> String {
-        let mut output = String::new();
-        output.push_str(&format!("┌{:─<width$}┐\n", "", width = HEADER_WIDTH));
+    /// Width of a day cell, including the gap before the next day
+    fn cell_width(&self) -> usize {
+        self.calendar.options.day_numbering.width() + 2 * self.padding_width() + 1
+    }
 
-        // Center the title
-        let title = format!("COMPACT CALENDAR {}", self.calendar.year);
-        output.push_str(&format!("│{:^width$}│\n", title, width = HEADER_WIDTH));
+    fn calendar_width(&self) -> usize {
+        DAYS_IN_WEEK * self.cell_width() - 1
+    }
 
-        output.push_str(&format!("├{:─<width$}┤\n", "", width = HEADER_WIDTH));
-        output.push_str("│              ");
-        match self.calendar.week_start {
-            WeekStart::Monday => output.push_str("Mon  Tue  Wed  Thu  Fri  Sat  Sun │\n"),
-            WeekStart::Sunday => output.push_str("Sun  Mon  Tue  Wed  Thu  Fri  Sat │\n"),
+    fn month_name(&self, month: u32) -> &'static str {
+        let locale = self.calendar.options.locale;
+        match self.calendar.options.cell_width {
+            CellWidth::Compact => locale.short_month_name(month),
+            CellWidth::Normal | CellWidth::Wide => locale.month_name(month),
         }
-        output
     }
 
-    fn weeks_to_string(&self) -> String {
-        let mut output = String::new();
-        let (start_date, end_date) = self.get_filtered_date_range();
+    fn month_name_width(&self) -> usize {
+        (1..=12)
+            .map(|month| text_width::width(self.month_name(month)))
+            .max()
+            .unwrap_or(0)
+    }
 
-        let mut current_date = self.align_to_week_start(start_date);
-        let mut week_num = 1;
-        let mut current_month: Option<u32> = None;
+    /// Width of the week numbers, or of the longest week label
+    fn week_number_width(&self) -> usize {
+        let number_width = text_width::width(self.calendar.options.locale.week_prefix()) + 2;
+        self.calendar
+            .week_labels
+            .iter()
+            .map(|(_, label)| text_width::width(label))
+            .fold(number_width, usize::max)
+    }
 
-        let mut details_queue: Vec<(NaiveDate, DateDetail)> = Vec::new();
-        let mut shown_ranges: Vec<usize> = Vec::new();
+    /// Width of the extra week column and the space before it, if shown
+    fn week_column_width(&self) -> usize {
+        self.calendar
+            .options
+            .week_column
+            .map_or(0, |column| column.width() + 1)
+    }
 
-        let mut is_first_month = true;
+    /// Width of the week number, month name and extra week column
+    fn label_width(&self) -> usize {
+        self.week_number_width() + 1 + self.month_name_width() + self.week_column_width()
+    }
 
-        while current_date <= end_date {
-            let layout = WeekLayout::new(current_date);
+    /// Width of the whole box, excluding the outer borders
+    fn header_width(&self) -> usize {
+        self.label_width() + 1 + self.calendar_width()
+    }
 
-            // Skip weeks that don't contain filtered months
-            if !self.should_render_week(&layout) {
-                current_date = current_date
-                    .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
-                    .unwrap();
-                continue;
-            }
+    fn label_padding(&self) -> String {
+        " ".repeat(self.label_width())
+    }
 
-            let next_week_date = current_date
-                .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
-                .unwrap();
-            let next_layout = WeekLayout::new(next_week_date);
+    fn week_label(&self, week_num: i32, layout: &WeekLayout) -> String {
+        let (start, _) = self.get_filtered_date_range();
+        let month_name = match layout.month_start_idx {
+            Some((_, month)) => self.month_name(month),
+            // With --remaining, the first week can start mid-month
+            None if layout.dates.contains(&start) => self.month_name(start.month()),
+            None => "",
+        };
+        let week = match self.calendar.week_label(&layout.dates) {
+            Some(label) => label.to_string(),
+            None => format!(
+                "{}{:02}",
+                self.calendar.options.locale.week_prefix(),
+                week_num
+            ),
+        };
+        let column = self
+            .calendar
+            .options
+            .week_column
+            .map(|column| format!(" {}", column.format(layout.dates[0])))
+            .unwrap_or_default();
+        format!(
+            "{} {}{}",
+            text_width::pad_end(&week, self.week_number_width()),
+            text_width::pad_end(month_name, self.month_name_width()),
+            column,
+        )
+    }
 
-            if let Some((_, month)) = layout.month_start_idx {
-                current_month = Some(month);
-                if is_first_month {
-                    output.push_str(&self.month_border_to_string(&layout, current_month));
-                    is_first_month = false;
-                }
-            }
+    /// Blank label column of the weekday header, naming the extra week column
+    fn header_label(&self) -> String {
+        match self.calendar.options.week_column {
+            Some(column) => text_width::pad_start(column.title(), self.label_width()),
+            None => self.label_padding(),
+        }
+    }
 
-            self.collect_details(&layout, &mut details_queue);
+    fn first_weekday(&self) -> Weekday {
+        match self.calendar.options.week_start {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
+    }
 
-            output.push_str(&self.week_row_to_string(week_num, &layout, current_month));
+    /// The weekday's name, shortened to two letters for compact cells and
+    /// three otherwise
+    fn short_weekday_name(&self, day: Weekday) -> String {
+        let name_len = match self.calendar.options.cell_width {
+            CellWidth::Compact => 2,
+            CellWidth::Normal | CellWidth::Wide => 3,
+        };
+        self.calendar
+            .options
+            .locale
+            .weekday_name(day)
+            .chars()
+            .take(name_len)
+            .collect()
+    }
 
-            output.push_str(&self.annotations_to_string(
-                &layout,
-                &mut details_queue,
-                &mut shown_ranges,
-            ));
+    fn weekday_header(&self) -> String {
+        let padding = self.padding_width();
+
+        let mut names = String::new();
+        let mut day = self.first_weekday();
+        for _ in 0..DAYS_IN_WEEK {
+            let name = self.short_weekday_name(day);
+            // Right-align the name with the day digits below it
+            let day_width = self.calendar.options.day_numbering.width();
+            let name = text_width::pad_start(&name, padding + day_width);
+            names.push_str(&text_width::pad_end(&name, self.cell_width()));
+            day = day.succ();
+        }
+        let width = self.calendar_width();
+        text_width::pad_end(text_width::truncate(&names, width), width)
+    }
 
-            output.push('\n');
+    /// Get the filtered date range based on month filter, from the week of
+    /// today on with `--remaining` when today is in it
+    fn get_filtered_date_range(&self) -> (NaiveDate, NaiveDate) {
+        let today = self.calendar.today();
+        let (start, end) = self
+            .calendar
+            .options
+            .month_filter
+            .get_date_range(self.calendar.year, today);
+        if self.calendar.options.remaining && (start..=end).contains(&today) {
+            (self.align_to_week_start(today).max(start), end)
+        } else {
+            (start, end)
+        }
+    }
 
-            let is_last_week =
-                next_week_date.year() > self.calendar.year || next_week_date > end_date;
+    fn write_header<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let header_width = self.header_width();
+        writeln!(w, "┌{:─<width$}┐", "", width = header_width)?;
 
-            if is_last_week {
-                let mut month_boundary_idx = None;
-                for (idx, &date) in layout.dates.iter().enumerate() {
-                    if idx > 0 {
-                        let prev_date = layout.dates[idx - 1];
-                        if date.month() != prev_date.month() || date.year() != prev_date.year() {
-                            month_boundary_idx = Some(idx);
-                            break;
-                        }
-                    }
-                }
+        // Center the title
+        let title = format!("COMPACT CALENDAR {}", self.calendar.year);
+        writeln!(w, "│{:^width$}│", title, width = header_width)?;
 
-                if let Some(boundary_idx) = month_boundary_idx {
-                    let dashes_before = (boundary_idx - 1) * 5 + 4;
-                    let dashes_after = (DAYS_IN_WEEK - boundary_idx) * 5 - 1;
-                    output.push_str(&format!(
-                        "└{:─<13}┴{:─<before$}┴{:─<after$}┘\n",
-                        "",
-                        "",
-                        "",
-                        before = dashes_before,
-                        after = dashes_after
-                    ));
-                } else {
-                    output.push_str(&format!(
-                        "└{:─<13}┴{:─<width$}┘\n",
-                        "",
-                        "",
-                        width = CALENDAR_WIDTH
-                    ));
-                }
-            } else if let Some((idx, _)) = layout.month_start_idx {
-                if idx > 0 {
-                    output.push_str(&self.separator_to_string(&layout, current_month));
-                }
-            } else if next_layout.month_start_idx.is_some()
-                && next_week_date <= end_date
-                && next_week_date.year() == self.calendar.year
-            {
-                output.push_str(&self.separator_before_month_to_string(
-                    &layout,
-                    current_month,
-                    &next_layout,
-                ));
-            }
+        writeln!(w, "├{:─<width$}┤", "", width = header_width)?;
+        writeln!(w, "│{} {}│", self.header_label(), self.weekday_header())
+    }
 
-            current_date = next_week_date;
-            week_num += 1;
+    /// The year on its side: a column per week and a row per weekday, with
+    /// the months named above the week of their first day and a line
+    /// between them. Annotations are listed below, a line each.
+    fn write_horizontal<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let today = self.calendar.today();
+        self.write_week_columns(
+            w,
+            self.cell_width(),
+            true,
+            |month| self.month_name(month),
+            |date| self.day_cell(date, today),
+        )?;
+        self.write_annotation_list(w)
+    }
 
-            if current_date.year() > self.calendar.year {
-                break;
-            }
+    /// A small cell a day, shaded by its activity from `--git` or
+    /// `--github`, or else by how many events fall on it and colored like
+    /// the event filling it, with a key below
+    fn write_heatmap<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let activity = self.activity();
+        let (start, end) = self.year_span();
+        let level = |date| activity.level(date, start, end);
+        self.write_week_columns(
+            w,
+            2,
+            false,
+            |month| self.calendar.options.locale.short_month_name(month),
+            |date| {
+                let style = self.heatmap_cell_style(date, level(date));
+                format!(
+                    "{}{}{}",
+                    style.render(),
+                    heatmap::glyph(level(date)),
+                    style.render_reset()
+                )
+            },
+        )?;
+
+        let mut key = String::from("  Less ");
+        for level in std::iter::once(None).chain((1..=heatmap::LEVELS).map(Some)) {
+            let style = match level {
+                Some(level) => self.heatmap_glyph_style(level),
+                None => Style::new(),
+            };
+            key.push_str(&format!(
+                "{}{}{} ",
+                style.render(),
+                heatmap::glyph(level),
+                style.render_reset()
+            ));
         }
+        writeln!(w, "{}More", key)
+    }
 
-        output
+    /// The activity to shade a heatmap by: `--git` or `--github`'s, or the
+    /// number of events on each day of the year
+    fn activity(&self) -> Cow<'_, Heatmap> {
+        if let Some(heatmap) = &self.calendar.options.heatmap {
+            return Cow::Borrowed(heatmap);
+        }
+        let (start, end) = self.year_span();
+        let counts = start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(|date| {
+                let count =
+                    self.calendar.details_on(date).count() + self.calendar.ranges_on(date).count();
+                (date, count)
+            })
+            .collect();
+        Cow::Owned(Heatmap::new(counts))
     }
 
-    fn month_border_to_string(&self, layout: &WeekLayout, _current_month: Option<u32>) -> String {
-        let mut output = String::new();
-        if let Some((idx, _)) = layout.month_start_idx {
-            if idx > 0 {
-                output.push_str("│             ┌");
-                let dashes_before = (idx - 1) * 5 + 4;
-                for _ in 0..dashes_before {
-                    output.push('─');
-                }
-                output.push('┬');
-                let dashes_after = (DAYS_IN_WEEK - idx) * 5 - 1;
-                output.push_str(&format!("{:─<width$}┤\n", "", width = dashes_after));
+    fn heatmap_cell_style(&self, date: NaiveDate, level: Option<u8>) -> Style {
+        if self.calendar.options.heatmap.is_none() {
+            if let Some(color) = self
+                .calendar
+                .coloring_event(date)
+                .and_then(|event| event.color.as_deref())
+            {
+                let shade = self.palette.get_style(color, false).get_bg_color();
+                return Style::new().fg_color(shade);
             }
         }
-        output
+        match level {
+            Some(level) => self.heatmap_glyph_style(level),
+            None => Style::new(),
+        }
     }
 
-    fn week_row_to_string(
+    /// The heatmap shade of `level` as the color of a glyph
+    fn heatmap_glyph_style(&self, level: u8) -> Style {
+        Style::new().fg_color(self.palette.heatmap_style(level).get_bg_color())
+    }
+
+    /// A column per week and a row per weekday, in a frame titled with the
+    /// year. With `month_lines`, each month is a block of the weeks touching
+    /// it, named above and set off by a line, so every row of a block is as
+    /// wide; otherwise the weeks run on with each month's name above the
+    /// week of its first day. Each column is `cell_width` wide, counting the
+    /// gap before the next.
+    fn write_week_columns<W: Write>(
         &self,
-        week_num: i32,
-        layout: &WeekLayout,
-        _current_month: Option<u32>,
-    ) -> String {
-        let mut output = String::new();
-        let month_name = if let Some((_, month)) = layout.month_start_idx {
-            MonthInfo::from_month(month).name
+        w: &mut W,
+        cell_width: usize,
+        month_lines: bool,
+        month_name: impl Fn(u32) -> &'static str,
+        cell: impl Fn(NaiveDate) -> String,
+    ) -> io::Result<()> {
+        let (start, end) = self.get_filtered_date_range();
+        let shown = |date: NaiveDate| start <= date && date <= end;
+        // The month each block is limited to, and its weeks' first days
+        let blocks: Vec<(Option<u32>, Vec<NaiveDate>)> = if month_lines {
+            (start.month()..=end.month())
+                .filter_map(|month| {
+                    let first = NaiveDate::from_ymd_opt(start.year(), month, 1)?.max(start);
+                    let last = (first.with_day(1)? + chrono::Months::new(1))
+                        .pred_opt()?
+                        .min(end);
+                    let weeks = self
+                        .align_to_week_start(first)
+                        .iter_weeks()
+                        .take_while(|week| *week <= last)
+                        .collect();
+                    Some((Some(month), weeks))
+                })
+                .collect()
         } else {
-            ""
+            let weeks = self.weeks().into_iter().map(|(_, start)| start).collect();
+            vec![(None, weeks)]
         };
+        let block_width = |weeks: &[NaiveDate]| (weeks.len() * cell_width).saturating_sub(1);
 
-        if !month_name.is_empty() {
-            output.push_str(&format!("│W{:02} {:<9}", week_num, month_name));
-        } else {
-            output.push_str(&format!("│W{:02}          ", week_num));
+        let mut day = self.first_weekday();
+        let mut names = Vec::new();
+        for _ in 0..DAYS_IN_WEEK {
+            names.push(self.short_weekday_name(day));
+            day = day.succ();
+        }
+        let label_width = names
+            .iter()
+            .map(|name| text_width::width(name))
+            .max()
+            .unwrap_or(0);
+        let days_width = blocks
+            .iter()
+            .map(|(_, weeks)| block_width(weeks) + 1)
+            .sum::<usize>()
+            .saturating_sub(1);
+        // Widened for the title when only a few weeks are shown
+        let title = format!("COMPACT CALENDAR {}", self.calendar.year);
+        let width = days_width.max(title.chars().count().saturating_sub(label_width + 1));
+        let header_width = label_width + 1 + width;
+
+        writeln!(w, "┌{:─<width$}┐", "", width = header_width)?;
+        writeln!(w, "│{:^width$}│", title, width = header_width)?;
+        writeln!(
+            w,
+            "├{:─<label$}┬{:─<width$}┤",
+            "",
+            "",
+            label = label_width,
+            width = width
+        )?;
+
+        // Each month's name runs until the next one starts, at the start of
+        // its block or else above the week of its first day
+        let mut labels = Vec::new();
+        let mut offset = 0;
+        for (month, weeks) in &blocks {
+            match month {
+                Some(month) => labels.push((offset, *month)),
+                None => {
+                    for (i, week_start) in weeks.iter().enumerate() {
+                        let month = match WeekLayout::new(*week_start).month_start_idx {
+                            Some((idx, month))
+                                if shown(*week_start + chrono::Duration::days(idx as i64)) =>
+                            {
+                                month
+                            }
+                            // With --remaining, the first week can start mid-month
+                            _ if i == 0 => start.month(),
+                            _ => continue,
+                        };
+                        labels.push((offset + i * cell_width, month));
+                    }
+                }
+            }
+            offset += block_width(weeks) + 1;
+        }
+        let mut months: Vec<char> = vec![' '; width];
+        for (mut column, month) in labels {
+            // A wide character takes its column and leaves the next empty
+            for c in month_name(month).chars() {
+                let columns = text_width::width(c.encode_utf8(&mut [0; 4]));
+                if column + columns > width {
+                    break;
+                }
+                months[column] = c;
+                for slot in &mut months[column + 1..column + columns] {
+                    *slot = '\0';
+                }
+                column += columns;
+            }
+        }
+        let months: String = months.into_iter().filter(|&c| c != '\0').collect();
+        writeln!(w, "│{:label$}│{}│", "", months, label = label_width)?;
+
+        for (row, name) in names.iter().enumerate() {
+            write!(w, "│{}│", text_width::pad_end(name, label_width))?;
+            for (b, (month, weeks)) in blocks.iter().enumerate() {
+                if b > 0 {
+                    write!(w, "│")?;
+                }
+                for (i, week_start) in weeks.iter().enumerate() {
+                    if i > 0 {
+                        write!(w, " ")?;
+                    }
+                    let date = *week_start + chrono::Duration::days(row as i64);
+                    if shown(date) && month.is_none_or(|month| date.month() == month) {
+                        write!(w, "{}", cell(date))?;
+                    } else {
+                        write!(w, "{:width$}", "", width = cell_width - 1)?;
+                    }
+                }
+            }
+            writeln!(w, "{:pad$}│", "", pad = width - days_width)?;
         }
+        writeln!(
+            w,
+            "└{:─<label$}┴{:─<width$}┘",
+            "",
+            "",
+            label = label_width,
+            width = width
+        )
+    }
 
-        output.push('│');
+    /// Months in blocks of a week per row, `columns` blocks side by side,
+    /// as `cal -y` lays them out. Annotations are listed below, a line each.
+    fn write_grid<W: Write>(&self, w: &mut W, columns: usize) -> io::Result<()> {
+        let (start, end) = self.get_filtered_date_range();
+        let today = self.calendar.today();
+        let cell_width = self.cell_width();
+        let block_width = self.calendar_width();
+        let months: Vec<NaiveDate> = (1..=12)
+            .filter_map(|month| NaiveDate::from_ymd_opt(self.calendar.year, month, 1))
+            .filter(|first| {
+                let next = *first + chrono::Months::new(1);
+                *first <= end && start < next
+            })
+            .collect();
+
+        const GAP: &str = "   ";
+        let columns = columns.clamp(1, months.len().max(1));
+        let days_width = columns * block_width + (columns - 1) * GAP.len();
+        let title = format!("COMPACT CALENDAR {}", self.calendar.year);
+        let width = days_width.max(title.chars().count());
 
-        for (idx, &date) in layout.dates.iter().enumerate() {
+        writeln!(w, "┌{:─<width$}┐", "", width = width + 2)?;
+        writeln!(w, "│{:^width$}│", title, width = width + 2)?;
+        writeln!(w, "├{:─<width$}┤", "", width = width + 2)?;
+
+        let weekdays = self.weekday_header();
+        for (row, blocks) in months.chunks(columns).enumerate() {
+            if row > 0 {
+                writeln!(w, "│ {:width$} │", "", width = width)?;
+            }
+            let mut lines = vec![String::new(); 2 + WEEKS_IN_MONTH];
+            for (i, first) in blocks.iter().enumerate() {
+                if i > 0 {
+                    for line in lines.iter_mut() {
+                        line.push_str(GAP);
+                    }
+                }
+                let name = self.calendar.options.locale.month_name(first.month());
+                lines[0].push_str(&text_width::center(name, block_width));
+                lines[1].push_str(&weekdays);
+
+                let mut date = self.align_to_week_start(*first);
+                for line in lines.iter_mut().skip(2) {
+                    for day in 0..DAYS_IN_WEEK {
+                        if day > 0 {
+                            line.push(' ');
+                        }
+                        if date.month() != first.month() || date < start || end < date {
+                            line.push_str(&" ".repeat(cell_width - 1));
+                        } else {
+                            line.push_str(&self.day_cell(date, today));
+                        }
+                        date += chrono::Duration::days(1);
+                    }
+                }
+            }
+            // Blocks missing from the last row leave blank space
+            let pad = width - (blocks.len() * (block_width + GAP.len()) - GAP.len());
+            for line in lines {
+                writeln!(w, "│ {}{:pad$} │", line, "", pad = pad)?;
+            }
+        }
+        writeln!(w, "└{:─<width$}┘", "", width = width + 2)?;
+        self.write_annotation_list(w)
+    }
+
+    /// Every week's annotations, a line each, for the layouts that have no
+    /// room for them beside the days
+    fn write_annotation_list<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for (i, (_, week_start)) in self.weeks().iter().enumerate() {
+            for (text, style) in self.annotations(&WeekLayout::new(*week_start), i == 0) {
+                writeln!(w, "  {}{}{}", style.render(), text, style.render_reset())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_weeks<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for (i, (week_num, week_start)) in self.weeks().into_iter().enumerate() {
+            self.write_week(w, week_num, week_start, i == 0)?;
+        }
+        Ok(())
+    }
+
+    /// Number and first day of every week shown, in order
+    fn weeks(&self) -> Vec<(i32, NaiveDate)> {
+        let (start_date, end_date) = self.get_filtered_date_range();
+
+        let mut weeks = Vec::new();
+        let mut current_date = self.align_to_week_start(start_date);
+        let mut week_num = 1;
+        while current_date <= end_date && current_date.year() <= self.calendar.year {
+            // Skip weeks that don't contain filtered months
+            if self.should_render_week(&WeekLayout::new(current_date)) {
+                weeks.push((week_num, current_date));
+                week_num += 1;
+            }
+            current_date = current_date
+                .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
+                .unwrap();
+        }
+        weeks
+    }
+
+    /// One week's row, annotations and the border or separator below it.
+    /// Depends only on the events overlapping the week, so weeks can be
+    /// re-rendered on their own.
+    fn write_week<W: Write>(
+        &self,
+        w: &mut W,
+        week_num: i32,
+        week_start: NaiveDate,
+        is_first: bool,
+    ) -> io::Result<()> {
+        let (_, end_date) = self.get_filtered_date_range();
+        let layout = WeekLayout::new(week_start);
+        let next_week_date = week_start
+            .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
+            .unwrap();
+        let next_layout = WeekLayout::new(next_week_date);
+        let current_month = layout.month_start_idx.map(|(_, month)| month);
+
+        if is_first {
+            self.write_month_border(w, &layout)?;
+        }
+
+        self.write_week_row(w, week_num, &layout)?;
+
+        self.write_annotations(w, &layout, is_first)?;
+
+        writeln!(w)?;
+
+        let is_last_week = next_week_date.year() > self.calendar.year || next_week_date > end_date;
+
+        if is_last_week {
+            self.write_bottom_border(w, &layout)?;
+        } else if let Some((idx, _)) = layout.month_start_idx {
+            if idx > 0 {
+                self.write_separator(w, &layout, current_month)?;
+            }
+        } else if next_layout.month_start_idx.is_some()
+            && next_week_date <= end_date
+            && next_week_date.year() == self.calendar.year
+        {
+            self.write_separator_before_month(w, &next_layout)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_month_border<W: Write>(&self, w: &mut W, layout: &WeekLayout) -> io::Result<()> {
+        if let Some((idx, _)) = layout.month_start_idx {
+            if idx > 0 {
+                let dashes_before = idx * self.cell_width() - 1;
+                let dashes_after = (DAYS_IN_WEEK - idx) * self.cell_width() - 1;
+                writeln!(
+                    w,
+                    "│{}┌{:─<before$}┬{:─<after$}┤",
+                    self.label_padding(),
+                    "",
+                    "",
+                    before = dashes_before,
+                    after = dashes_after
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bottom_border<W: Write>(&self, w: &mut W, layout: &WeekLayout) -> io::Result<()> {
+        let mut month_boundary_idx = None;
+        for (idx, &date) in layout.dates.iter().enumerate() {
+            if idx > 0 {
+                let prev_date = layout.dates[idx - 1];
+                if date.month() != prev_date.month() || date.year() != prev_date.year() {
+                    month_boundary_idx = Some(idx);
+                    break;
+                }
+            }
+        }
+
+        if let Some(boundary_idx) = month_boundary_idx {
+            let dashes_before = boundary_idx * self.cell_width() - 1;
+            let dashes_after = (DAYS_IN_WEEK - boundary_idx) * self.cell_width() - 1;
+            writeln!(
+                w,
+                "└{:─<label$}┴{:─<before$}┴{:─<after$}┘",
+                "",
+                "",
+                "",
+                label = self.label_width(),
+                before = dashes_before,
+                after = dashes_after
+            )
+        } else {
+            writeln!(
+                w,
+                "└{:─<label$}┴{:─<width$}┘",
+                "",
+                "",
+                label = self.label_width(),
+                width = self.calendar_width()
+            )
+        }
+    }
+
+    fn write_week_row<W: Write>(
+        &self,
+        w: &mut W,
+        week_num: i32,
+        layout: &WeekLayout,
+    ) -> io::Result<()> {
+        write!(w, "│{}│", self.week_label(week_num, layout))?;
+
+        let today = self.calendar.today();
+
+        for (idx, &date) in layout.dates.iter().enumerate() {
             let is_month_boundary = if idx > 0 {
                 let prev_date = layout.dates[idx - 1];
                 date.month() != prev_date.month() || date.year() != prev_date.year()
@@ -405,87 +1544,410 @@ impl<'a> CalendarRenderer<'a> {
             };
 
             if is_month_boundary {
-                output.push('│');
+                write!(w, "│")?;
             }
 
-            output.push_str(&format!(" {:02}", date.day()));
+            write!(w, "{}", self.day_cell(date, today))?;
 
             if idx < 6 {
                 let next_date = layout.dates[idx + 1];
                 let next_is_boundary =
                     date.month() != next_date.month() || date.year() != next_date.year();
-                if next_is_boundary {
-                    output.push(' ');
-                } else {
-                    output.push_str("  ");
+                if !next_is_boundary {
+                    write!(w, " ")?;
                 }
+            }
+        }
+
+        write!(w, "│")
+    }
+
+    /// A day's number between its paddings. A glyph too wide for the padding
+    /// before the day takes the room of the padding after it.
+    fn day_cell(&self, date: NaiveDate, today: NaiveDate) -> String {
+        let style = self.get_day_style(date, today);
+        let left = self.left_padding(date, today);
+        let right = self.right_padding(date);
+        let overflow = text_width::width(&left).saturating_sub(self.padding().len());
+        let right_width = text_width::width(&right).saturating_sub(overflow);
+        format!(
+            "{}{}{}{}{}",
+            left,
+            style.render(),
+            self.calendar.options.day_numbering.format(date),
+            style.render_reset(),
+            text_width::truncate(&right, right_width)
+        )
+    }
+
+    /// Padding before a day, carrying today's glyph, else the habit log's
+    /// mark, else the clock change's, else the forecast weather's, else the
+    /// moon's
+    fn left_padding(&self, date: NaiveDate, today: NaiveDate) -> String {
+        let padding = self.padding();
+        if padding.is_empty() {
+            return String::new();
+        }
+        // With colors off, the color's mark takes the place of other glyphs
+        let glyph = self
+            .plain_mark(date)
+            .or_else(|| match self.calendar.options.today_marker {
+                TodayMarker::Glyph(glyph) if date == today => Some(glyph),
+                _ => self
+                    .calendar
+                    .icon(date)
+                    .or_else(|| self.calendar.is_habit_done(date).then_some(HabitLog::MARK))
+                    .or_else(|| self.calendar.clock_change(date).map(Transition::glyph))
+                    .or_else(|| self.calendar.weather(date).map(|weather| weather.glyph()))
+                    .or_else(|| self.calendar.moon_phase(date).map(|phase| phase.glyph())),
+            });
+        match glyph {
+            Some(glyph) => text_width::pad_start(&glyph.to_string(), padding.len()),
+            None => padding.to_string(),
+        }
+    }
+
+    /// Padding after a day, carrying the high-contrast marker when the day is colored
+    fn right_padding(&self, date: NaiveDate) -> String {
+        let padding = self.padding();
+        if self.plain_mark(date) == Some('[') {
+            format!("]{}", &padding[1..])
+        } else if self.is_high_contrast()
+            && !padding.is_empty()
+            && self.get_date_color(date).is_some()
+        {
+            format!("{}{}", HIGH_CONTRAST_MARKER, &padding[1..])
+        } else {
+            padding.to_string()
+        }
+    }
+
+    /// What marks a colored day in place of its color when colors are off
+    fn plain_mark(&self, date: NaiveDate) -> Option<char> {
+        if self.palette.are_colors_enabled() {
+            return None;
+        }
+        let color = self.get_date_color(date)?;
+        match self.calendar.options.plain_marker {
+            PlainMarker::None => None,
+            PlainMarker::Star => Some('*'),
+            PlainMarker::Brackets => Some('['),
+            PlainMarker::Symbols => self.plain_symbol(&color),
+        }
+    }
+
+    /// The symbol standing for `color` when colors are off, with
+    /// [`PlainMarker::Symbols`]
+    fn plain_symbol(&self, color: &str) -> Option<char> {
+        if self.palette.are_colors_enabled()
+            || self.calendar.options.plain_marker != PlainMarker::Symbols
+        {
+            return None;
+        }
+        self.plain_symbols.get(color).copied()
+    }
+
+    fn is_high_contrast(&self) -> bool {
+        self.calendar.options.contrast == ContrastMode::High
+    }
+
+    fn get_day_style(&self, date: NaiveDate, today: NaiveDate) -> Style {
+        if !self.palette.are_colors_enabled() {
+            return Style::new();
+        }
+
+        let is_today = date == today;
+        let is_past = self.calendar.options.past_date_display == PastDateDisplay::Strikethrough
+            && date < today;
+        // Non-working days are dimmed like weekends
+        let is_weekend = self.calendar.options.weekend_display == WeekendDisplay::Dimmed
+            && (self.calendar.is_weekend(date) || self.calendar.is_non_working(date));
+
+        let theme = &self.calendar.options.theme;
+        let mut effects = Effects::new();
+        if is_past {
+            effects |= theme.past.unwrap_or(Effects::STRIKETHROUGH);
+        }
+        let today_marker = &self.calendar.options.today_marker;
+        if is_today {
+            effects |= match today_marker {
+                TodayMarker::Bold => Effects::BOLD,
+                TodayMarker::Inverse => Effects::INVERT,
+                TodayMarker::Background(_) => Effects::new(),
+                TodayMarker::Glyph(_) if !self.padding().is_empty() => Effects::new(),
+                _ => theme.today.unwrap_or(Effects::UNDERLINE),
+            };
+        }
+        if self.changes.dates.contains(&date) {
+            effects |= Effects::BOLD | Effects::INVERT;
+        }
+        if let (true, TodayMarker::Background(color)) = (is_today, today_marker) {
+            return self
+                .palette
+                .get_style(color, false)
+                .fg_color(self.palette.text_style().get_fg_color())
+                .effects(effects);
+        }
+
+        let style = if let Some(event) = self.calendar.coloring_event(date) {
+            // Requested time off is drawn faded and slanted until it is approved
+            let requested = event.status == Some(Status::Requested);
+            if requested {
+                effects |= Effects::ITALIC;
+            }
+            let dimmed = is_weekend || requested;
+            let style = if self.calendar.options.overlap_display == OverlapDisplay::Blend
+                && event.is_range()
+            {
+                let colors: Vec<&str> = self
+                    .calendar
+                    .coloring_ranges(date)
+                    .into_iter()
+                    .filter_map(|range| range.color.as_deref())
+                    .collect();
+                self.palette.get_blended_style(&colors, dimmed)
+            } else if let Some(gradient) = event.gradient.as_ref().filter(|_| event.is_range()) {
+                let color = event.color.as_deref().unwrap_or_default();
+                let length = (event.end - event.start).num_days().max(1);
+                let step = (date - event.start).num_days() * GRADIENT_STEPS as i64 / length;
+                self.palette
+                    .get_gradient_style(color, gradient, step as u16, dimmed)
+            } else {
+                let color = event.color.as_deref().unwrap_or_default();
+                self.palette.get_style(color, dimmed)
+            };
+            style
+                .fg_color(self.palette.text_style().get_fg_color())
+                .effects(effects)
+        } else {
+            if is_weekend {
+                effects |= theme.weekend.unwrap_or(Effects::DIMMED);
+            }
+            if let Some(level) = self.heatmap_level(date) {
+                self.palette.heatmap_style(level).effects(effects)
+            } else if self.is_shaded_week(date) {
+                self.palette.shaded_style().effects(effects)
             } else {
-                output.push(' ');
+                Style::new().effects(effects)
             }
+        };
+        match self.fade_step(date, today) {
+            Some(step) => self.palette.faded_style(style, step),
+            None => style,
+        }
+    }
+
+    /// Heatmap shade of `date`, scaled to the busiest day of the year
+    fn heatmap_level(&self, date: NaiveDate) -> Option<u8> {
+        let (start, end) = self.year_span();
+        self.calendar
+            .options
+            .heatmap
+            .as_ref()?
+            .level(date, start, end)
+    }
+
+    fn year_span(&self) -> (NaiveDate, NaiveDate) {
+        let year = self.calendar.year;
+        (
+            NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(year, 12, 31).unwrap(),
+        )
+    }
+
+    /// Whether the row holding `date` is an even ISO week, shaded when
+    /// alternate weeks are; the row's Thursday decides with either week start
+    fn is_shaded_week(&self, date: NaiveDate) -> bool {
+        if !self.calendar.options.alternate_weeks {
+            return false;
         }
+        let thursday = self.align_to_week_start(date) + chrono::Duration::days(3);
+        thursday.iso_week().week().is_multiple_of(2)
+    }
 
-        output.push('│');
-        output
+    /// How far a past date fades in [`PastDateDisplay::Faded`] mode: one step
+    /// for each month it lies back, up to [`MAX_FADE_STEP`]
+    fn fade_step(&self, date: NaiveDate, today: NaiveDate) -> Option<u16> {
+        if self.calendar.options.past_date_display != PastDateDisplay::Faded || date >= today {
+            return None;
+        }
+        let months_ago =
+            (today.year() - date.year()) * 12 + today.month() as i32 - date.month() as i32;
+        Some((months_ago as u16 + 1).min(MAX_FADE_STEP))
     }
 
-    fn annotations_to_string(
+    /// Annotate the week's dates, and the ranges first shown in this week:
+    /// those starting in it, or already underway in the first week shown.
+    /// Higher-priority events come first.
+    fn write_annotations<W: Write>(
         &self,
+        w: &mut W,
         layout: &WeekLayout,
-        details_queue: &mut Vec<(NaiveDate, DateDetail)>,
-        shown_ranges: &mut Vec<usize>,
-    ) -> String {
-        let mut output = String::new();
+        is_first: bool,
+    ) -> io::Result<()> {
+        let annotations = self.annotations(layout, is_first);
+        let options = &self.calendar.options;
+        match options.annotation_width {
+            None => write_joined(w, &annotations),
+            Some(width) => match options.annotation_overflow {
+                AnnotationOverflow::Truncate => {
+                    write_joined(w, &truncate_annotations(&annotations, width))
+                }
+                AnnotationOverflow::Wrap => {
+                    for (i, line) in wrap_annotations(&annotations, width).iter().enumerate() {
+                        if i > 0 {
+                            writeln!(w)?;
+                            self.write_continuation_row(w, layout)?;
+                        }
+                        write_joined(w, line)?;
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// The week's date and range annotations with their styles, highest
+    /// priority first
+    fn annotations(&self, layout: &WeekLayout, is_first: bool) -> Vec<(String, Style)> {
         let week_start = layout.dates[0];
         let week_end = layout.dates[DAYS_IN_WEEK - 1];
-        let mut annotations = Vec::new();
+        let mut annotations: Vec<(i32, String, Style)> = Vec::new();
+        let date_format = self.calendar.options.locale.date_format();
 
         // Collect all details that occur in this week
-        let mut details_to_remove = Vec::new();
-        for (i, (detail_date, detail)) in details_queue.iter().enumerate() {
-            if *detail_date >= week_start && *detail_date <= week_end {
-                annotations.push(format!(
-                    "{} - {}",
-                    detail_date.format("%m/%d"),
-                    detail.description
-                ));
-                details_to_remove.push(i);
-            }
-        }
-        // Remove details in reverse order to maintain indices
-        for &i in details_to_remove.iter().rev() {
-            details_queue.remove(i);
+        for detail in layout
+            .dates
+            .iter()
+            .flat_map(|&date| self.calendar.details_on(date))
+        {
+            let text = match &detail.description {
+                Some(desc) => format!("{} - {}", detail.start.format(date_format), desc),
+                None => detail.start.format(date_format).to_string(),
+            };
+            let text = self.mark_annotation(with_icon(detail, text), detail.color.as_deref());
+            let style = self.get_annotation_style(detail.color.as_deref());
+            let (text, style) = self.mark_changed(detail, text, style);
+            annotations.push((detail.priority, text, style));
         }
 
         // Collect all ranges that overlap with this week
-        for (idx, range) in self.calendar.ranges.iter().enumerate() {
-            if !shown_ranges.contains(&idx) && range.start <= week_end && range.end >= week_start {
-                if let Some(desc) = &range.description {
-                    annotations.push(format!(
+        for range in &self.calendar.ranges {
+            let first_shown = is_first || range.start >= week_start;
+            if first_shown && range.start <= week_end && range.end >= week_start {
+                let mut text = if let Some(desc) = &range.description {
+                    format!(
                         "{} to {} - {}",
-                        range.start.format("%m/%d"),
-                        range.end.format("%m/%d"),
+                        range.start.format(date_format),
+                        range.end.format(date_format),
                         desc
-                    ));
+                    )
                 } else {
-                    annotations.push(format!(
+                    format!(
                         "{} to {}",
-                        range.start.format("%m/%d"),
-                        range.end.format("%m/%d")
-                    ));
+                        range.start.format(date_format),
+                        range.end.format(date_format)
+                    )
+                };
+                // Travel ranges carry the destination's UTC offset as a badge
+                if let Some(offset) = range.utc_offset_label() {
+                    text = format!("{} ({})", text, offset);
+                }
+                if let Some(status @ (Status::Requested | Status::Rejected)) = range.status {
+                    text = format!("{} ({})", text, status.name());
                 }
-                shown_ranges.push(idx);
+                let color = range
+                    .color
+                    .as_deref()
+                    .filter(|_| range.status != Some(Status::Rejected));
+                let text = self.mark_annotation(with_icon(range, text), color);
+                let style = self.get_range_annotation_style(range);
+                let (text, style) = self.mark_changed(range, text, style);
+                annotations.push((range.priority, text, style));
             }
         }
 
-        // Join all annotations with commas
-        output.push_str(&annotations.join(", "));
+        // Highest priority first, otherwise dates before ranges in order
+        annotations.sort_by_key(|(priority, _, _)| std::cmp::Reverse(*priority));
+        annotations
+            .into_iter()
+            .map(|(_, text, style)| (text, style))
+            .collect()
+    }
 
-        output
+    /// The week's borders without its days, for annotations wrapped onto
+    /// another row
+    fn write_continuation_row<W: Write>(&self, w: &mut W, layout: &WeekLayout) -> io::Result<()> {
+        let inner = DAYS_IN_WEEK * self.cell_width() - 1;
+        match layout.month_start_idx {
+            Some((idx, _)) if idx > 0 => {
+                let before = idx * self.cell_width() - 1;
+                write!(
+                    w,
+                    "│{}│{:before$}│{:after$}│",
+                    self.label_padding(),
+                    "",
+                    "",
+                    before = before,
+                    after = inner - before - 1
+                )
+            }
+            _ => write!(w, "│{}│{:inner$}│", self.label_padding(), "", inner = inner),
+        }
     }
 
-    fn separator_to_string(&self, layout: &WeekLayout, current_month: Option<u32>) -> String {
-        let mut output = String::new();
-        output.push_str("│             ├");
+    fn mark_annotation(&self, text: String, color: Option<&str>) -> String {
+        let Some(color) = color else {
+            return text;
+        };
+        if self.is_high_contrast() {
+            format!("{} {}", HIGH_CONTRAST_MARKER, text)
+        } else if let Some(symbol) = self.plain_symbol(color) {
+            format!("{} {}", symbol, text)
+        } else {
+            text
+        }
+    }
+
+    fn mark_changed(&self, event: &Event, text: String, style: Style) -> (String, Style) {
+        if self.changes.events.contains(event) {
+            let style = if self.palette.are_colors_enabled() {
+                style.effects(style.get_effects() | Effects::BOLD)
+            } else {
+                style
+            };
+            (format!("{} {}", CHANGED_MARKER, text), style)
+        } else {
+            (text, style)
+        }
+    }
+
+    fn get_annotation_style(&self, color: Option<&str>) -> Style {
+        self.palette.annotation_style(color)
+    }
+
+    fn get_range_annotation_style(&self, range: &Event) -> Style {
+        if !self.palette.are_colors_enabled() {
+            return Style::new();
+        }
+        match (range.status, range.color.as_deref()) {
+            (Some(Status::Requested), Some(color)) => self
+                .palette
+                .get_style(color, true)
+                .fg_color(self.palette.text_style().get_fg_color())
+                .effects(Effects::ITALIC),
+            (Some(Status::Rejected), _) => Style::new().effects(Effects::STRIKETHROUGH),
+            (_, color) => self.get_annotation_style(color),
+        }
+    }
+
+    fn write_separator<W: Write>(
+        &self,
+        w: &mut W,
+        layout: &WeekLayout,
+        current_month: Option<u32>,
+    ) -> io::Result<()> {
+        write!(w, "│{}├", self.label_padding())?;
 
         let mut first_bar_idx = None;
         for (idx, &date) in layout.dates.iter().enumerate() {
@@ -502,144 +1964,45 @@ impl<'a> CalendarRenderer<'a> {
             }
         }
 
-        if let Some(bar_idx) = first_bar_idx {
-            if bar_idx > 0 {
-                let dashes = (bar_idx - 1) * 5 + 4;
-                output.push_str(&format!("{:─<width$}┘", "", width = dashes));
-                let spaces = (DAYS_IN_WEEK - bar_idx) * 5 - 1;
-                output.push_str(&format!("{: <width$}│\n", "", width = spaces));
-            } else {
-                output.push_str("───────────────────────────────┤│\n");
+        match first_bar_idx {
+            Some(bar_idx) if bar_idx > 0 => {
+                let dashes = bar_idx * self.cell_width() - 1;
+                let spaces = (DAYS_IN_WEEK - bar_idx) * self.cell_width() - 1;
+                writeln!(
+                    w,
+                    "{:─<dashes$}┘{: <spaces$}│",
+                    "",
+                    "",
+                    dashes = dashes,
+                    spaces = spaces
+                )
             }
-        } else {
-            output.push_str("───────────────────────────────┤│\n");
+            _ => writeln!(w, "{:─<width$}┤│", "", width = self.calendar_width() - 3),
         }
-
-        output
     }
 
-    fn separator_before_month_to_string(
+    fn write_separator_before_month<W: Write>(
         &self,
-        _current_layout: &WeekLayout,
-        _current_month: Option<u32>,
+        w: &mut W,
         next_layout: &WeekLayout,
-    ) -> String {
-        let mut output = String::new();
+    ) -> io::Result<()> {
         if let Some((next_month_start_idx, _)) = next_layout.month_start_idx {
             if next_month_start_idx == 0 {
-                output.push_str("│             ├");
-                output.push_str(&format!("{:─<width$}┤", "", width = CALENDAR_WIDTH));
+                write!(w, "│{}├", self.label_padding())?;
+                write!(w, "{:─<width$}┤", "", width = self.calendar_width())?;
             } else {
-                output.push_str("│             │");
-                let spaces_before = (next_month_start_idx - 1) * 5 + 4;
-                output.push_str(&format!("{: <width$}┌", "", width = spaces_before));
-                let dashes = (DAYS_IN_WEEK - 1 - next_month_start_idx) * 5 + 4;
-                output.push_str(&format!("{:─<width$}┤", "", width = dashes));
+                write!(w, "│{}│", self.label_padding())?;
+                let spaces_before = next_month_start_idx * self.cell_width() - 1;
+                write!(w, "{: <width$}┌", "", width = spaces_before)?;
+                let dashes = (DAYS_IN_WEEK - next_month_start_idx) * self.cell_width() - 1;
+                write!(w, "{:─<width$}┤", "", width = dashes)?;
             }
         } else {
-            output.push_str("│             │");
-            output.push_str(&format!("{: <width$}", "", width = DAYS_IN_WEEK * 4 + 3));
+            write!(w, "│{}│", self.label_padding())?;
+            write!(w, "{: <width$}", "", width = self.calendar_width() - 3)?;
         }
 
-        output.push('\n');
-        output
-    }
-
-    fn print_header(&self) {
-        print!("{}", self.header_to_string());
-    }
-
-    fn print_weeks(&self) {
-        let (start_date, end_date) = self.get_filtered_date_range();
-
-        let mut current_date = self.align_to_week_start(start_date);
-        let mut week_num = 1;
-        let mut current_month: Option<u32> = None;
-
-        let mut details_queue: Vec<(NaiveDate, DateDetail)> = Vec::new();
-        let mut shown_ranges: Vec<usize> = Vec::new();
-
-        let mut is_first_month = true;
-
-        while current_date <= end_date {
-            let layout = WeekLayout::new(current_date);
-
-            // Skip weeks that don't contain filtered months
-            if !self.should_render_week(&layout) {
-                current_date = current_date
-                    .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
-                    .unwrap();
-                continue;
-            }
-
-            let next_week_date = current_date
-                .checked_add_signed(chrono::Duration::days(DAYS_IN_WEEK as i64))
-                .unwrap();
-            let next_layout = WeekLayout::new(next_week_date);
-
-            if let Some((_, month)) = layout.month_start_idx {
-                current_month = Some(month);
-                if is_first_month {
-                    self.print_month_border(&layout, current_month);
-                    is_first_month = false;
-                }
-            }
-
-            self.collect_details(&layout, &mut details_queue);
-
-            self.print_week_row(week_num, &layout, current_month);
-
-            self.print_annotations(&layout, &mut details_queue, &mut shown_ranges);
-
-            println!();
-
-            let is_last_week =
-                next_week_date.year() > self.calendar.year || next_week_date > end_date;
-
-            if is_last_week {
-                let mut month_boundary_idx = None;
-                for (idx, &date) in layout.dates.iter().enumerate() {
-                    if idx > 0 {
-                        let prev_date = layout.dates[idx - 1];
-                        if date.month() != prev_date.month() || date.year() != prev_date.year() {
-                            month_boundary_idx = Some(idx);
-                            break;
-                        }
-                    }
-                }
-
-                if let Some(boundary_idx) = month_boundary_idx {
-                    let dashes_before = (boundary_idx - 1) * 5 + 4;
-                    let dashes_after = (DAYS_IN_WEEK - boundary_idx) * 5 - 1;
-                    println!(
-                        "└{:─<13}┴{:─<before$}┴{:─<after$}┘",
-                        "",
-                        "",
-                        "",
-                        before = dashes_before,
-                        after = dashes_after
-                    );
-                } else {
-                    println!("└{:─<13}┴{:─<width$}┘", "", "", width = CALENDAR_WIDTH);
-                }
-            } else if let Some((idx, _)) = layout.month_start_idx {
-                if idx > 0 {
-                    self.print_separator(&layout, current_month);
-                }
-            } else if next_layout.month_start_idx.is_some()
-                && next_week_date <= end_date
-                && next_week_date.year() == self.calendar.year
-            {
-                self.print_separator_before_month(&layout, current_month, &next_layout);
-            }
-
-            current_date = next_week_date;
-            week_num += 1;
-
-            if current_date.year() > self.calendar.year {
-                break;
-            }
-        }
+        writeln!(w)
     }
 
     fn align_to_week_start(&self, date: NaiveDate) -> NaiveDate {
@@ -651,264 +2014,221 @@ impl<'a> CalendarRenderer<'a> {
     }
 
     fn get_date_color(&self, date: NaiveDate) -> Option<String> {
-        // In work mode, never color weekends
-        if self.calendar.color_mode == ColorMode::Work
-            && (date.weekday() == Weekday::Sat || date.weekday() == Weekday::Sun)
-        {
-            return None;
-        }
+        self.calendar
+            .coloring_event(date)
+            .and_then(|event| event.color.clone())
+    }
+}
 
-        // Check if date has a specific color
-        if let Some(detail) = self.calendar.details.get(&date) {
-            if let Some(color) = &detail.color {
-                return Some(color.clone());
-            }
+const ANNOTATION_SEPARATOR: &str = ", ";
+
+/// A symbol for each color, in the order the colors first appear, from
+/// [`PlainMarker::SYMBOLS`] and around again when they run out
+fn plain_symbols(calendar: &Calendar) -> HashMap<String, char> {
+    let mut symbols = HashMap::new();
+    for color in calendar
+        .events()
+        .into_iter()
+        .filter_map(|event| event.color.as_ref())
+    {
+        if !symbols.contains_key(color) {
+            let symbol = PlainMarker::SYMBOLS[symbols.len() % PlainMarker::SYMBOLS.len()];
+            symbols.insert(color.clone(), symbol);
         }
+    }
+    symbols
+}
 
-        // Check if date is in a range
-        for range in &self.calendar.ranges {
-            if date >= range.start && date <= range.end {
-                return Some(range.color.clone());
-            }
+/// `text`, drawn left to right, with the rows of its `grid` mirrored: the
+/// day cells and labels swap sides, each keeping its own text, markers and
+/// colors. The box's top and title, the annotations after each row and
+/// everything below the bottom border stay as they are.
+fn mirror_grid(text: &str, grid: Option<MirroredGrid>) -> Cow<'_, str> {
+    let Some(grid) = grid else {
+        return Cow::Borrowed(text);
+    };
+    let mut mirrored = String::with_capacity(text.len());
+    let mut in_grid = true;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if i < 3 || !in_grid {
+            mirrored.push_str(line);
+            continue;
         }
-
-        None
+        in_grid = !line.starts_with('└');
+        let mut rest = line;
+        let mut columns = Vec::new();
+        // Border, label, border, then each cell followed by a gap or border
+        let widths = [1, grid.label_width, 1]
+            .into_iter()
+            .chain((0..DAYS_IN_WEEK).flat_map(|_| [grid.cell_width, 1]));
+        for (j, width) in widths.enumerate() {
+            let (column, after) = text_width::split_at(rest, width);
+            columns.push(if j == 1 {
+                mirror_words(column)
+            } else {
+                column.chars().map(flip_corner).collect()
+            });
+            rest = after;
+        }
+        columns.reverse();
+        mirrored.extend(columns);
+        mirrored.push_str(rest);
     }
+    Cow::Owned(mirrored)
+}
 
-    fn print_month_border(&self, layout: &WeekLayout, current_month: Option<u32>) {
-        print!("{}", self.month_border_to_string(layout, current_month));
+/// A box-drawing corner or tee facing the other way; other characters as
+/// they are
+fn flip_corner(c: char) -> char {
+    match c {
+        '┌' => '┐',
+        '┐' => '┌',
+        '└' => '┘',
+        '┘' => '└',
+        '├' => '┤',
+        '┤' => '├',
+        _ => c,
     }
+}
 
-    fn collect_details(
-        &self,
-        layout: &WeekLayout,
-        details_queue: &mut Vec<(NaiveDate, DateDetail)>,
-    ) {
-        for &date in &layout.dates {
-            if let Some(detail) = self.calendar.details.get(&date) {
-                if !details_queue.iter().any(|(d, _)| d == &date) {
-                    details_queue.push((date, detail.clone()));
+/// `text` reversed word by word: spaces and border glyphs one by one, with
+/// the corners and tees flipped, and runs of other text (with their escape
+/// codes) as a whole
+fn mirror_words(text: &str) -> String {
+    let mut pieces = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            word.push(c);
+            for c in chars.by_ref() {
+                word.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
                 }
             }
+            continue;
         }
-    }
-
-    fn print_week_row(&self, week_num: i32, layout: &WeekLayout, _current_month: Option<u32>) {
-        let month_name = if let Some((_, month)) = layout.month_start_idx {
-            MonthInfo::from_month(month).name
-        } else {
-            ""
-        };
-
-        if !month_name.is_empty() {
-            print!("│W{:02} {:<9}", week_num, month_name);
-        } else {
-            print!("│W{:02}          ", week_num);
+        if !matches!(c, ' ' | '─' | '│' | '┬' | '┴' | '┼') && flip_corner(c) == c {
+            word.push(c);
+            continue;
         }
+        if !word.is_empty() {
+            pieces.push(std::mem::take(&mut word));
+        }
+        pieces.push(flip_corner(c).to_string());
+    }
+    if !word.is_empty() {
+        pieces.push(word);
+    }
+    pieces.into_iter().rev().collect()
+}
 
-        print!("│");
-
-        for (idx, &date) in layout.dates.iter().enumerate() {
-            let is_month_boundary = if idx > 0 {
-                let prev_date = layout.dates[idx - 1];
-                date.month() != prev_date.month() || date.year() != prev_date.year()
-            } else {
-                false
-            };
-
-            if is_month_boundary {
-                print!("│");
-            }
-
-            let today = chrono::Local::now().date_naive();
-            let is_today = date == today;
-            let is_past =
-                self.calendar.past_date_display == PastDateDisplay::Strikethrough && date < today;
-
-            let is_weekend = self.calendar.weekend_display == WeekendDisplay::Dimmed
-                && (date.weekday() == Weekday::Sat || date.weekday() == Weekday::Sun);
-
-            if let Some(color) = self.get_date_color(date) {
-                let mut style = if is_weekend {
-                    ColorCodes::get_dimmed_bg_color(&color)
-                } else {
-                    ColorCodes::get_bg_color(&color)
-                };
-
-                if ColorCodes::is_color_disabled() {
-                    print!(" {:02}", date.day());
-                } else {
-                    style = style.fg_color(ColorCodes::black_text().get_fg_color());
-
-                    let mut effects = Effects::new();
-                    if is_past {
-                        effects |= ColorCodes::strikethrough();
-                    }
-                    if is_today {
-                        effects |= ColorCodes::underline();
-                    }
-                    style = style.effects(effects);
-
-                    print!(
-                        " {}{:02}{}",
-                        style.render(),
-                        date.day(),
-                        style.render_reset()
-                    );
-                }
-            } else if ColorCodes::is_color_disabled() {
-                print!(" {:02}", date.day());
-            } else {
-                let mut style = Style::new();
-                let mut effects = Effects::new();
-
-                if is_past {
-                    effects |= ColorCodes::strikethrough();
-                }
-                if is_today {
-                    effects |= ColorCodes::underline();
-                }
-                if is_weekend {
-                    effects |= ColorCodes::dim();
-                }
-
-                style = style.effects(effects);
+/// An annotation led by its event's icon, if it has one
+fn with_icon(event: &Event, text: String) -> String {
+    match event.icon {
+        Some(icon) => format!("{} {}", icon, text),
+        None => text,
+    }
+}
 
-                if effects == Effects::new() {
-                    print!(" {:02}", date.day());
-                } else {
-                    print!(
-                        " {}{:02}{}",
-                        style.render(),
-                        date.day(),
-                        style.render_reset()
-                    );
-                }
-            }
+/// List the cut-off annotations after the calendar, so none go unseen
+fn write_overflow_footer<W: Write>(w: &mut W, overflow: &str) -> io::Result<()> {
+    if overflow.is_empty() {
+        return Ok(());
+    }
+    writeln!(w, "More events:")?;
+    w.write_all(overflow.as_bytes())
+}
 
-            if idx < 6 {
-                let next_date = layout.dates[idx + 1];
-                let next_is_boundary =
-                    date.month() != next_date.month() || date.year() != next_date.year();
-                if next_is_boundary {
-                    print!(" ");
-                } else {
-                    print!("  ");
-                }
-            } else {
-                print!(" ");
-            }
+/// Write styled annotations separated by commas
+fn write_joined<W: Write>(w: &mut W, annotations: &[(String, Style)]) -> io::Result<()> {
+    for (i, (text, style)) in annotations.iter().enumerate() {
+        if i > 0 {
+            write!(w, "{}", ANNOTATION_SEPARATOR)?;
         }
+        write!(w, "{}{}{}", style.render(), text, style.render_reset())?;
+    }
+    Ok(())
+}
+
+/// Width of the annotations once joined
+fn joined_width(annotations: &[(String, Style)]) -> usize {
+    let text: usize = annotations
+        .iter()
+        .map(|(text, _)| text_width::width(text))
+        .sum();
+    text + ANNOTATION_SEPARATOR.len() * annotations.len().saturating_sub(1)
+}
 
-        print!("│");
+/// Cut the joined annotations down to `width` characters, ending in "…"
+fn truncate_annotations(annotations: &[(String, Style)], width: usize) -> Vec<(String, Style)> {
+    if joined_width(annotations) <= width {
+        return annotations.to_vec();
     }
 
-    fn print_annotations(
-        &self,
-        layout: &WeekLayout,
-        details_queue: &mut Vec<(NaiveDate, DateDetail)>,
-        shown_ranges: &mut Vec<usize>,
-    ) {
-        let week_start = layout.dates[0];
-        let week_end = layout.dates[DAYS_IN_WEEK - 1];
-        let mut first = true;
-
-        // Collect and print all details that occur in this week
-        let mut details_to_remove = Vec::new();
-        for (i, (detail_date, detail)) in details_queue.iter().enumerate() {
-            if *detail_date >= week_start && *detail_date <= week_end {
-                if !first {
-                    print!(", ");
-                }
-                first = false;
-
-                if ColorCodes::is_color_disabled() {
-                    print!("{} - {}", detail_date.format("%m/%d"), detail.description);
-                } else if let Some(color) = &detail.color {
-                    let style = ColorCodes::get_bg_color(color)
-                        .fg_color(ColorCodes::black_text().get_fg_color());
-                    print!(
-                        "{}{} - {}{}",
-                        style.render(),
-                        detail_date.format("%m/%d"),
-                        detail.description,
-                        style.render_reset()
-                    );
-                } else {
-                    print!("{} - {}", detail_date.format("%m/%d"), detail.description);
-                }
-                details_to_remove.push(i);
+    // Room left before the ellipsis
+    let mut budget = width.saturating_sub(1);
+    let mut truncated: Vec<(String, Style)> = Vec::new();
+    for (i, (text, style)) in annotations.iter().enumerate() {
+        if i > 0 {
+            // Only start another annotation if some of it fits
+            if budget <= ANNOTATION_SEPARATOR.len() {
+                break;
             }
+            budget -= ANNOTATION_SEPARATOR.len();
         }
-        // Remove details in reverse order to maintain indices
-        for &i in details_to_remove.iter().rev() {
-            details_queue.remove(i);
-        }
-
-        // Collect and print all ranges that overlap with this week
-        for (idx, range) in self.calendar.ranges.iter().enumerate() {
-            if !shown_ranges.contains(&idx) && range.start <= week_end && range.end >= week_start {
-                if !first {
-                    print!(", ");
-                }
-                first = false;
-
-                if ColorCodes::is_color_disabled() {
-                    if let Some(desc) = &range.description {
-                        print!(
-                            "{} to {} - {}",
-                            range.start.format("%m/%d"),
-                            range.end.format("%m/%d"),
-                            desc
-                        );
-                    } else {
-                        print!(
-                            "{} to {}",
-                            range.start.format("%m/%d"),
-                            range.end.format("%m/%d")
-                        );
-                    }
-                } else {
-                    let style = ColorCodes::get_bg_color(&range.color)
-                        .fg_color(ColorCodes::black_text().get_fg_color());
-
-                    if let Some(desc) = &range.description {
-                        print!(
-                            "{}{} to {} - {}{}",
-                            style.render(),
-                            range.start.format("%m/%d"),
-                            range.end.format("%m/%d"),
-                            desc,
-                            style.render_reset()
-                        );
-                    } else {
-                        print!(
-                            "{}{} to {}{}",
-                            style.render(),
-                            range.start.format("%m/%d"),
-                            range.end.format("%m/%d"),
-                            style.render_reset()
-                        );
-                    }
-                }
-                shown_ranges.push(idx);
-            }
+        let len = text_width::width(text);
+        if len > budget {
+            let cut = text_width::truncate(text, budget);
+            truncated.push((cut.trim_end().to_string(), *style));
+            break;
         }
+        truncated.push((text.clone(), *style));
+        budget -= len;
+    }
+    match truncated.last_mut() {
+        Some((text, _)) => text.push('…'),
+        None => truncated.push(("…".to_string(), Style::new())),
     }
+    truncated
+}
 
-    fn print_separator(&self, layout: &WeekLayout, current_month: Option<u32>) {
-        print!("{}", self.separator_to_string(layout, current_month));
+/// Split the annotations into rows of at most `width` characters, breaking
+/// between annotations; one too long for a row of its own is truncated
+fn wrap_annotations(annotations: &[(String, Style)], width: usize) -> Vec<Vec<(String, Style)>> {
+    let mut rows: Vec<Vec<(String, Style)>> = vec![Vec::new()];
+    for annotation in annotations {
+        let annotation = truncate_annotations(std::slice::from_ref(annotation), width);
+        let row = rows.last_mut().unwrap();
+        let mut candidate = row.clone();
+        candidate.extend(annotation.iter().cloned());
+        if row.is_empty() || joined_width(&candidate) <= width {
+            *row = candidate;
+        } else {
+            rows.push(annotation);
+        }
     }
+    rows
+}
 
-    fn print_separator_before_month(
-        &self,
-        current_layout: &WeekLayout,
-        current_month: Option<u32>,
-        next_layout: &WeekLayout,
-    ) {
-        print!(
-            "{}",
-            self.separator_before_month_to_string(current_layout, current_month, next_layout)
-        );
+/// Two rendered calendars next to each other, each line of `left` padded to
+/// its widest so `right` lines up, as `--compare` shows them
+pub fn side_by_side(left: &str, right: &str) -> String {
+    const GAP: &str = "   ";
+    let width = left.lines().map(text_width::width).max().unwrap_or(0);
+    let mut left = left.lines();
+    let mut right = right.lines();
+    let mut output = String::new();
+    loop {
+        let (line, other) = match (left.next(), right.next()) {
+            (None, None) => break,
+            (line, other) => (line.unwrap_or_default(), other.unwrap_or_default()),
+        };
+        let pad = width - text_width::width(line);
+        let joined = format!("{}{:pad$}{}{}", line, "", GAP, other, pad = pad);
+        output.push_str(joined.trim_end());
+        output.push('\n');
     }
+    output
 }
@@ -0,0 +1,302 @@
+//! Month starts of other calendars, for noting them beside the Gregorian
+//! one: the arithmetic Hebrew calendar, the tabular Islamic calendar (which
+//! can differ by a day or two from dates set by sighting the moon), and the
+//! Chinese lunisolar calendar, reckoned from new moons and solar terms in
+//! Beijing time. Day counts are fixed day numbers, with 0001-01-01 as day 1.
+
+use crate::models::{Event, EventKind};
+use crate::moon::{self, MoonPhase};
+use crate::sun;
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
+
+/// Annotations below the colored dates, so they go first when cut short
+const PRIORITY: i32 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryCalendar {
+    Hebrew,
+    Islamic,
+    Chinese,
+}
+
+impl SecondaryCalendar {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.to_lowercase().as_str() {
+            "hebrew" | "jewish" => Ok(Self::Hebrew),
+            "islamic" | "hijri" => Ok(Self::Islamic),
+            "chinese" | "lunar" => Ok(Self::Chinese),
+            _ => Err(format!(
+                "Invalid secondary calendar: '{}'. Use hebrew, islamic or chinese",
+                input
+            )),
+        }
+    }
+
+    /// First days of the calendar's months in Gregorian `year`, with their
+    /// names, in order
+    pub fn month_starts(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        let mut starts = match self {
+            Self::Hebrew => hebrew_month_starts(year),
+            Self::Islamic => islamic_month_starts(year),
+            Self::Chinese => chinese_months(year)
+                .into_iter()
+                .map(|month| (month.start, month.name()))
+                .collect(),
+        };
+        starts.retain(|(date, _)| date.year() == year);
+        starts.sort_by_key(|(date, _)| *date);
+        starts
+    }
+
+    /// The month starts as notes on their days
+    pub fn events(&self, year: i32) -> Vec<Event> {
+        self.month_starts(year)
+            .into_iter()
+            .filter_map(|(date, name)| {
+                Event::builder(EventKind::Date, date)
+                    .description(name)
+                    .priority(PRIORITY)
+                    .build()
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+fn from_fixed(day: i64) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce_opt(day as i32).expect("date in range")
+}
+
+const ISLAMIC_EPOCH: i64 = 227015;
+
+const ISLAMIC_MONTHS: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabi' al-Awwal",
+    "Rabi' al-Thani",
+    "Jumada al-Ula",
+    "Jumada al-Akhirah",
+    "Rajab",
+    "Sha'ban",
+    "Ramadan",
+    "Shawwal",
+    "Dhu al-Qa'dah",
+    "Dhu al-Hijjah",
+];
+
+fn islamic_month_starts(year: i32) -> Vec<(NaiveDate, String)> {
+    // Islamic years are 11 days shorter, so about 33 pass for every 32
+    let approximate = ((year - 622) as i64 * 33) / 32;
+    (approximate - 1..=approximate + 2)
+        .flat_map(|islamic_year| {
+            (1..=12).map(move |month| {
+                let day = ISLAMIC_EPOCH - 1
+                    + (islamic_year - 1) * 354
+                    + (3 + 11 * islamic_year).div_euclid(30)
+                    + 29 * (month - 1)
+                    + (6 * month - 1).div_euclid(11)
+                    + 1;
+                let name = ISLAMIC_MONTHS[month as usize - 1];
+                (from_fixed(day), format!("{} {}", name, islamic_year))
+            })
+        })
+        .collect()
+}
+
+const HEBREW_EPOCH: i64 = -1373427;
+
+/// Months from Nisan, the first of the religious year; the civil year
+/// starts at Tishri, the seventh
+const NISAN: i64 = 1;
+const TISHRI: i64 = 7;
+
+fn hebrew_leap_year(year: i64) -> bool {
+    (7 * year + 1).rem_euclid(19) < 7
+}
+
+fn hebrew_last_month(year: i64) -> i64 {
+    if hebrew_leap_year(year) {
+        13
+    } else {
+        12
+    }
+}
+
+/// Days from the epoch to the molad of Tishri, after the first delays
+fn hebrew_elapsed_days(year: i64) -> i64 {
+    let months = (235 * year - 234).div_euclid(19);
+    let parts = 12084 + 13753 * months;
+    let days = 29 * months + parts.div_euclid(25920);
+    if (3 * (days + 1)).rem_euclid(7) < 3 {
+        days + 1
+    } else {
+        days
+    }
+}
+
+/// Delays keeping years from being 356 or 382 days long
+fn hebrew_year_correction(year: i64) -> i64 {
+    let (previous, this, next) = (
+        hebrew_elapsed_days(year - 1),
+        hebrew_elapsed_days(year),
+        hebrew_elapsed_days(year + 1),
+    );
+    if next - this == 356 {
+        2
+    } else if this - previous == 382 {
+        1
+    } else {
+        0
+    }
+}
+
+fn hebrew_new_year(year: i64) -> i64 {
+    HEBREW_EPOCH + hebrew_elapsed_days(year) + hebrew_year_correction(year)
+}
+
+fn hebrew_month_length(year: i64, month: i64) -> i64 {
+    let year_length = hebrew_new_year(year + 1) - hebrew_new_year(year);
+    let short = match month {
+        2 | 4 | 6 | 10 | 13 => true,
+        // Adar, when there is no Adar II
+        12 => !hebrew_leap_year(year),
+        // Heshvan is long, and Kislev short, in some years
+        8 => year_length % 10 != 5,
+        9 => year_length % 10 == 3,
+        _ => false,
+    };
+    if short {
+        29
+    } else {
+        30
+    }
+}
+
+fn hebrew_month_name(year: i64, month: i64) -> &'static str {
+    match month {
+        1 => "Nisan",
+        2 => "Iyar",
+        3 => "Sivan",
+        4 => "Tammuz",
+        5 => "Av",
+        6 => "Elul",
+        7 => "Tishrei",
+        8 => "Cheshvan",
+        9 => "Kislev",
+        10 => "Tevet",
+        11 => "Shevat",
+        12 if hebrew_leap_year(year) => "Adar I",
+        12 => "Adar",
+        _ => "Adar II",
+    }
+}
+
+fn hebrew_month_starts(year: i32) -> Vec<(NaiveDate, String)> {
+    // Hebrew year 5786 begins in September 2025
+    let first = year as i64 + 3760;
+    (first..=first + 1)
+        .flat_map(|hebrew_year| {
+            // Months in the order they fall, from Tishri
+            let order = (TISHRI..=hebrew_last_month(hebrew_year)).chain(NISAN..TISHRI);
+            let mut day = hebrew_new_year(hebrew_year);
+            order
+                .map(|month| {
+                    let start = day;
+                    day += hebrew_month_length(hebrew_year, month);
+                    let name = hebrew_month_name(hebrew_year, month);
+                    (from_fixed(start), format!("{} {}", name, hebrew_year))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The day an instant falls on in Beijing, which the Chinese calendar uses
+fn beijing_date(instant: DateTime<Utc>) -> NaiveDate {
+    (instant + TimeDelta::hours(8)).date_naive()
+}
+
+/// A month of the Chinese calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChineseMonth {
+    pub start: NaiveDate,
+    /// 1 to 12
+    pub number: u32,
+    /// Whether it repeats the number of the month before
+    pub leap: bool,
+}
+
+impl ChineseMonth {
+    fn name(&self) -> String {
+        match (self.number, self.leap) {
+            (number, true) => format!("Lunar leap month {}", number),
+            (1, false) => "Lunar New Year".to_string(),
+            (number, false) => format!("Lunar month {}", number),
+        }
+    }
+}
+
+/// Day `day` of lunar month `month` in the Chinese year beginning in
+/// Gregorian `year`; the ordinary month, not a leap one with its number
+pub fn chinese_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    let months = chinese_months(year);
+    let new_year = months
+        .iter()
+        .position(|m| m.number == 1 && !m.leap && m.start.year() == year)?;
+    let month = months[new_year..]
+        .iter()
+        .find(|m| m.number == month && !m.leap)?;
+    month
+        .start
+        .checked_add_days(chrono::Days::new(day as u64 - 1))
+}
+
+/// Chinese months from the 11th month of the year before `year` begins
+/// to the 11th month of the year after
+pub fn chinese_months(year: i32) -> Vec<ChineseMonth> {
+    let mut new_moons: Vec<NaiveDate> = (year - 1..=year + 1)
+        .flat_map(moon::phases)
+        .filter(|(_, phase)| *phase == MoonPhase::New)
+        .map(|(instant, _)| beijing_date(instant))
+        .collect();
+    new_moons.sort();
+    new_moons.dedup();
+    // Major solar terms, every 30 degrees of the sun's longitude
+    let major_terms: Vec<NaiveDate> = (year - 1..=year + 1)
+        .flat_map(|year| (0..12).map(move |i| beijing_date(sun::solar_term(year, i as f64 * 30.0))))
+        .collect();
+
+    // Each year of months runs from the 11th month, the one holding the
+    // winter solstice, to the next
+    let mut months = Vec::new();
+    for solstice_year in [year - 1, year] {
+        let solstice = beijing_date(sun::solar_term(solstice_year, 270.0));
+        let next_solstice = beijing_date(sun::solar_term(solstice_year + 1, 270.0));
+        let month_of = |date: NaiveDate| new_moons.iter().rposition(|moon| *moon <= date);
+        let (Some(first), Some(last)) = (month_of(solstice), month_of(next_solstice)) else {
+            continue;
+        };
+        // Thirteen months instead of twelve: the first without a major term
+        // is a leap month, repeating the number before it
+        let leap = (last - first == 13).then(|| {
+            (first..last).find(|&i| {
+                !major_terms
+                    .iter()
+                    .any(|term| (new_moons[i]..new_moons[i + 1]).contains(term))
+            })
+        });
+        let mut number = 10;
+        for (i, start) in new_moons.iter().enumerate().take(last).skip(first) {
+            let leap = leap == Some(Some(i));
+            if !leap {
+                number = number % 12 + 1;
+            }
+            months.push(ChineseMonth {
+                start: *start,
+                number,
+                leap,
+            });
+        }
+    }
+    months
+}
@@ -0,0 +1,311 @@
+use crate::error::{Error, Result};
+use crate::models::{ContrastMode, PastDateDisplay, WeekendDisplay};
+use serde::Deserialize;
+
+/// Default `show` flags, from a config's `[settings]` block or a profile.
+/// Each flag here turns the matching CLI flag on, unless the CLI turns it
+/// back off with its opposite, like `--no-moon` or `--dim-weekends`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// "normal" or "high-contrast"
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// "ltr" or "rtl"
+    #[serde(default)]
+    pub direction: Option<String>,
+    #[serde(default)]
+    pub month: Option<String>,
+    #[serde(default)]
+    pub following_months: Option<u32>,
+    #[serde(default)]
+    pub remaining: bool,
+    #[serde(default)]
+    pub sunday: bool,
+    /// "mon" or "sun"
+    #[serde(default)]
+    pub week_start: Option<String>,
+    #[serde(default)]
+    pub no_dim_weekends: bool,
+    #[serde(default)]
+    pub work: bool,
+    #[serde(default)]
+    pub no_strikethrough_past: bool,
+    #[serde(default)]
+    pub fade_past: bool,
+    #[serde(default)]
+    pub compact: bool,
+    #[serde(default)]
+    pub wide: bool,
+    #[serde(default)]
+    pub annotation_width: Option<usize>,
+    #[serde(default)]
+    pub wrap_annotations: bool,
+    #[serde(default)]
+    pub legend: bool,
+    #[serde(default)]
+    pub warn_conflicts: bool,
+    #[serde(default)]
+    pub blend_overlaps: bool,
+    /// "underline", "bold", "inverse", a color or a character like "▶"
+    #[serde(default)]
+    pub today_marker: Option<String>,
+    /// "none", "star", "brackets" or "symbols"
+    #[serde(default)]
+    pub plain_marker: Option<String>,
+    #[serde(default)]
+    pub moon: bool,
+    #[serde(default)]
+    pub alternate_weeks: bool,
+    #[serde(default)]
+    pub leap: bool,
+    #[serde(default)]
+    pub day_of_year: bool,
+    /// "day", "unpadded", "day-of-year" or a strftime format like "%m/%d"
+    #[serde(default)]
+    pub day_format: Option<String>,
+    /// "jd" or "iso"
+    #[serde(default)]
+    pub week_column: Option<String>,
+    /// "hebrew", "islamic" or "chinese"
+    #[serde(default)]
+    pub secondary_calendar: Option<String>,
+    /// "vertical", "horizontal", "grid" or "heatmap"
+    #[serde(default)]
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub grid_columns: Option<u16>,
+    /// "auto", "always" or "never"
+    #[serde(default)]
+    pub paging: Option<String>,
+    #[serde(default)]
+    pub daylight: bool,
+    #[serde(default)]
+    pub weather: bool,
+}
+
+impl Settings {
+    /// How past days are drawn, if a flag here picks it
+    pub fn past_display(&self) -> Option<PastDateDisplay> {
+        if self.fade_past {
+            Some(PastDateDisplay::Faded)
+        } else if self.no_strikethrough_past {
+            Some(PastDateDisplay::Normal)
+        } else {
+            None
+        }
+    }
+
+    /// How weekends are drawn, if a flag here picks it
+    pub fn weekend_display(&self) -> Option<WeekendDisplay> {
+        self.no_dim_weekends.then_some(WeekendDisplay::Normal)
+    }
+
+    /// The contrast mode picked by `theme`, if any
+    pub fn contrast(&self) -> Result<Option<ContrastMode>> {
+        match self.theme.as_deref() {
+            None => Ok(None),
+            Some("normal") => Ok(Some(ContrastMode::Normal)),
+            Some("high-contrast") => Ok(Some(ContrastMode::High)),
+            Some(theme) => Err(Error::InvalidEntry(format!(
+                "unknown theme '{}', expected \"normal\" or \"high-contrast\"",
+                theme
+            ))),
+        }
+    }
+
+    /// `key = value` lines for every setting that differs from the default
+    pub fn to_toml_lines(&self) -> Vec<String> {
+        let string = |key: &str, value: &Option<String>| {
+            value
+                .as_ref()
+                .map(|value| format!("{} = {}", key, toml::Value::String(value.clone())))
+        };
+        let flag = |key: &str, value: bool| value.then(|| format!("{} = true", key));
+
+        [
+            string("theme", &self.theme),
+            string("locale", &self.locale),
+            string("direction", &self.direction),
+            string("month", &self.month),
+            self.following_months
+                .map(|months| format!("following_months = {}", months)),
+            flag("remaining", self.remaining),
+            flag("sunday", self.sunday),
+            string("week_start", &self.week_start),
+            flag("no_dim_weekends", self.no_dim_weekends),
+            flag("work", self.work),
+            flag("no_strikethrough_past", self.no_strikethrough_past),
+            flag("fade_past", self.fade_past),
+            flag("compact", self.compact),
+            flag("wide", self.wide),
+            self.annotation_width
+                .map(|width| format!("annotation_width = {}", width)),
+            flag("wrap_annotations", self.wrap_annotations),
+            flag("legend", self.legend),
+            flag("warn_conflicts", self.warn_conflicts),
+            flag("blend_overlaps", self.blend_overlaps),
+            string("today_marker", &self.today_marker),
+            string("plain_marker", &self.plain_marker),
+            flag("moon", self.moon),
+            flag("alternate_weeks", self.alternate_weeks),
+            flag("leap", self.leap),
+            flag("day_of_year", self.day_of_year),
+            string("day_format", &self.day_format),
+            string("week_column", &self.week_column),
+            string("secondary_calendar", &self.secondary_calendar),
+            string("layout", &self.layout),
+            self.grid_columns
+                .map(|columns| format!("grid_columns = {}", columns)),
+            string("paging", &self.paging),
+            flag("daylight", self.daylight),
+            flag("weather", self.weather),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// How the calendar looks, from a config's `[style]` block: defaults for the
+/// display flags, by what they change, and how far colors dim. Where it
+/// disagrees with `[settings]`, `[settings]` wins and `validate` reports the
+/// conflict; the CLI wins over both.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Style {
+    /// Brightness of dimmed colors, from 0 (black) to 1 (not dimmed)
+    #[serde(default)]
+    pub dim_factor: Option<f64>,
+    /// "strike", "fade" or "none"
+    #[serde(default)]
+    pub past: Option<String>,
+    /// "dim" or "normal"
+    #[serde(default)]
+    pub weekends: Option<String>,
+    #[serde(default)]
+    pub annotation_width: Option<usize>,
+}
+
+impl Style {
+    /// How past days are drawn, if set
+    pub fn past_display(&self) -> Result<Option<PastDateDisplay>> {
+        match self.past.as_deref() {
+            None => Ok(None),
+            Some("strike") => Ok(Some(PastDateDisplay::Strikethrough)),
+            Some("fade") => Ok(Some(PastDateDisplay::Faded)),
+            Some("none") => Ok(Some(PastDateDisplay::Normal)),
+            Some(past) => Err(Error::InvalidEntry(format!(
+                "unknown past style '{}', expected \"strike\", \"fade\" or \"none\"",
+                past
+            ))),
+        }
+    }
+
+    /// How weekends are drawn, if set
+    pub fn weekend_display(&self) -> Result<Option<WeekendDisplay>> {
+        match self.weekends.as_deref() {
+            None => Ok(None),
+            Some("dim") => Ok(Some(WeekendDisplay::Dimmed)),
+            Some("normal") => Ok(Some(WeekendDisplay::Normal)),
+            Some(weekends) => Err(Error::InvalidEntry(format!(
+                "unknown weekends style '{}', expected \"dim\" or \"normal\"",
+                weekends
+            ))),
+        }
+    }
+
+    pub fn dim_factor(&self) -> Result<Option<f64>> {
+        match self.dim_factor {
+            Some(factor) if !(0.0..=1.0).contains(&factor) => Err(Error::InvalidEntry(format!(
+                "dim_factor {} is not between 0 and 1",
+                factor
+            ))),
+            factor => Ok(factor),
+        }
+    }
+
+    /// The `show` flags this style turns on, for what `settings` leaves
+    /// unset
+    pub fn settings(&self, settings: &Settings) -> Result<Settings> {
+        let past = self
+            .past_display()?
+            .filter(|_| settings.past_display().is_none());
+        let weekends = self
+            .weekend_display()?
+            .filter(|_| settings.weekend_display().is_none());
+        Ok(Settings {
+            no_strikethrough_past: past == Some(PastDateDisplay::Normal),
+            fade_past: past == Some(PastDateDisplay::Faded),
+            no_dim_weekends: weekends == Some(WeekendDisplay::Normal),
+            annotation_width: settings.annotation_width.or(self.annotation_width),
+            ..Settings::default()
+        })
+    }
+
+    /// The keys of this style that `settings` sets another way, each with
+    /// what it disagrees with
+    pub fn conflicts(&self, settings: &Settings) -> Vec<(&'static str, String)> {
+        let mut conflicts = Vec::new();
+        let mut conflict = |key, value: String, setting: String| {
+            conflicts.push((
+                key,
+                format!(
+                    "[style] {} = {} disagrees with [settings] {}, which wins",
+                    key, value, setting
+                ),
+            ));
+        };
+        let quoted = |value: &Option<String>| format!("{:?}", value.as_deref().unwrap_or(""));
+        if let (Some(style), Some(setting)) =
+            (self.past_display().ok().flatten(), settings.past_display())
+        {
+            if style != setting {
+                let key = if settings.fade_past {
+                    "fade_past"
+                } else {
+                    "no_strikethrough_past"
+                };
+                conflict("past", quoted(&self.past), format!("{} = true", key));
+            }
+        }
+        if let (Some(style), Some(setting)) = (
+            self.weekend_display().ok().flatten(),
+            settings.weekend_display(),
+        ) {
+            if style != setting {
+                let setting = "no_dim_weekends = true".to_string();
+                conflict("weekends", quoted(&self.weekends), setting);
+            }
+        }
+        if let (Some(style), Some(setting)) = (self.annotation_width, settings.annotation_width) {
+            if style != setting {
+                let setting = format!("annotation_width = {}", setting);
+                conflict("annotation_width", style.to_string(), setting);
+            }
+        }
+        conflicts
+    }
+
+    /// `key = value` lines for every key that is set
+    pub fn to_toml_lines(&self) -> Vec<String> {
+        let string = |key: &str, value: &Option<String>| {
+            value
+                .as_ref()
+                .map(|value| format!("{} = {}", key, toml::Value::String(value.clone())))
+        };
+        [
+            self.dim_factor
+                .map(|factor| format!("dim_factor = {}", toml::Value::Float(factor))),
+            string("past", &self.past),
+            string("weekends", &self.weekends),
+            self.annotation_width
+                .map(|width| format!("annotation_width = {}", width)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
@@ -0,0 +1,441 @@
+//! Fetching `[[sources]]` calendars, read-only, with `curl`: CalDAV
+//! collections, or `.ics` files subscribed to by URL. Each fetch is kept in
+//! the [`Cache`] for the source's `ttl`, and the cached copy stands in while
+//! the server can't be reached. Subscriptions are fetched again only when
+//! their ETag changed. `[holidays]` from a holiday API are fetched the same
+//! way.
+
+use crate::availability::TIME_OFF_TAG;
+use crate::cache::{self, Cache, CachePolicy};
+use crate::config::{RawHolidays, RawSource, RawWeather};
+use crate::datasets::{self, Datasets};
+use crate::holidays::{self, Country};
+use crate::paths;
+use crate::vdir::{self, Collection, VEvent};
+use crate::weather::{self, DayForecast};
+use chrono::NaiveDate;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How long holidays fetched from an API are used, unless `ttl` is set
+pub const HOLIDAYS_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How long a fetched forecast is used, unless `ttl` is set
+pub const FORECAST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Longest a request may take, so a server that stops answering can't hang
+/// the calendar
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// CalDAV query for every event in the collection
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"/></c:comp-filter></c:filter>
+</c:calendar-query>"#;
+
+/// A source's events, and why the cached copy was used instead of a fresh
+/// one when the fetch failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+    pub collection: Collection,
+    pub warning: Option<String>,
+}
+
+/// Fetch `source`, or use the cached copy as `policy` allows
+pub fn fetch(source: &RawSource, policy: CachePolicy) -> Result<Fetched, String> {
+    fetch_with_cache(source, &Cache::user()?, policy)
+}
+
+/// Fetch a config published at `url`, e.g. a team's shared calendar, into
+/// the cache; the cached file is returned along with why it was used instead
+/// of a fresh copy when the fetch failed
+pub fn fetch_config(url: &str, policy: CachePolicy) -> Result<(PathBuf, Option<String>), String> {
+    fetch_config_with_cache(url, &Cache::user()?, policy)
+}
+
+/// [`fetch_config`], caching in `cache`
+pub fn fetch_config_with_cache(
+    url: &str,
+    cache: &Cache,
+    policy: CachePolicy,
+) -> Result<(PathBuf, Option<String>), String> {
+    let source = RawSource {
+        url: url.to_string(),
+        username: None,
+        password: None,
+        color: None,
+        ttl: None,
+    };
+    // Keep the extension, which tells the config's format
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("toml");
+    let path = cache.path("configs", url, extension);
+    let cached = cache
+        .get(&path, url, cache::DEFAULT_TTL, policy, || {
+            subscribe(&source, &path)
+        })
+        .map_err(|e| format!("{}: {}", url, e))?;
+    let warning = cached
+        .stale
+        .map(|e| format!("{}: {}; using the cached copy", url, e));
+    Ok((cached.path, warning))
+}
+
+/// [`fetch`], caching in `cache`
+pub fn fetch_with_cache(
+    source: &RawSource,
+    cache: &Cache,
+    policy: CachePolicy,
+) -> Result<Fetched, String> {
+    let ttl = match &source.ttl {
+        Some(ttl) => cache::parse_ttl(ttl).map_err(|e| format!("{}: {}", source.url, e))?,
+        None => cache::DEFAULT_TTL,
+    };
+    let path = cache.path("sources", &source.url, "ics");
+    let cached = cache
+        .get(&path, &source.url, ttl, policy, || {
+            if is_subscription(source) {
+                subscribe(source, &path)
+            } else {
+                request(source)
+            }
+        })
+        .map_err(|e| format!("{}: {}", source.url, e))?;
+    let warning = cached
+        .stale
+        .map(|e| format!("{}: {}; using the cached copy", source.url, e));
+
+    let events = vdir::parse(&cached.contents)
+        .map_err(|(line, message)| format!("{}: line {}: {}", source.url, line, message))?;
+    Ok(Fetched {
+        collection: Collection {
+            events,
+            color: source.color.clone(),
+            tags: Vec::new(),
+        },
+        warning,
+    })
+}
+
+/// The `[holidays]` of `year`, embedded or fetched as `policy` allows
+pub fn fetch_holidays(
+    holidays: &RawHolidays,
+    year: i32,
+    policy: CachePolicy,
+) -> Result<Fetched, String> {
+    fetch_holidays_with_cache(holidays, year, &Cache::user()?, policy)
+}
+
+/// [`fetch_holidays`], caching in `cache`. While the API can't be reached
+/// and nothing is cached, the embedded holidays stand in when the country
+/// has them.
+pub fn fetch_holidays_with_cache(
+    config: &RawHolidays,
+    year: i32,
+    cache: &Cache,
+    policy: CachePolicy,
+) -> Result<Fetched, String> {
+    let name = |e: String| format!("holidays for {}: {}", config.country, e);
+    let color = config.color.as_deref().unwrap_or("red");
+    let collection = |days: Vec<(NaiveDate, String)>| Collection {
+        events: days
+            .into_iter()
+            .map(|(date, name)| VEvent::new(name, date, date, Some(color.to_string())))
+            .collect(),
+        color: None,
+        // Holidays are days off, not working days
+        tags: vec![TIME_OFF_TAG.to_string()],
+    };
+    let builtin = Country::parse(&config.country).ok().map(|country| {
+        let datasets = Datasets::user().ok();
+        collection(datasets::holidays_or_builtin(
+            datasets.as_ref(),
+            country,
+            year,
+        ))
+    });
+    if !config.use_api().map_err(name)? {
+        return builtin
+            .map(|collection| Fetched {
+                collection,
+                warning: None,
+            })
+            .ok_or_else(|| name("no built-in holidays".to_string()));
+    }
+
+    let ttl = match &config.ttl {
+        Some(ttl) => cache::parse_ttl(ttl).map_err(name)?,
+        None => HOLIDAYS_TTL,
+    };
+    let url = config
+        .url
+        .as_deref()
+        .unwrap_or(holidays::API_URL)
+        .replace("{year}", &year.to_string())
+        .replace("{country}", &config.country.to_uppercase());
+    let path = cache.path("holidays", &url, "json");
+    let fetched = cache
+        .get(&path, &url, ttl, policy, || {
+            fetch_url(&url, &[], []).map_err(|e| e.to_string())
+        })
+        .and_then(|cached| Ok((holidays::from_api_response(&cached.contents)?, cached.stale)));
+    match (fetched, builtin) {
+        (Ok((days, stale)), _) => Ok(Fetched {
+            collection: collection(days),
+            warning: stale.map(|e| format!("{}; using the cached copy", name(e))),
+        }),
+        (Err(e), Some(collection)) => Ok(Fetched {
+            collection,
+            warning: Some(format!("{}; using the built-in holidays", name(e))),
+        }),
+        (Err(e), None) => Err(name(e)),
+    }
+}
+
+/// The forecast at the `[weather]` location, fetched or cached as `policy`
+/// allows, and why the cached copy was used when the fetch failed
+pub fn fetch_forecast(
+    config: &RawWeather,
+    policy: CachePolicy,
+) -> Result<(Vec<DayForecast>, Option<String>), String> {
+    fetch_forecast_with_cache(config, &Cache::user()?, policy)
+}
+
+/// [`fetch_forecast`], caching in `cache`
+pub fn fetch_forecast_with_cache(
+    config: &RawWeather,
+    cache: &Cache,
+    policy: CachePolicy,
+) -> Result<(Vec<DayForecast>, Option<String>), String> {
+    let name = |e: String| format!("weather forecast: {}", e);
+    let ttl = match &config.ttl {
+        Some(ttl) => cache::parse_ttl(ttl).map_err(name)?,
+        None => FORECAST_TTL,
+    };
+    let (Some(latitude), Some(longitude)) = (config.latitude, config.longitude) else {
+        return Err(name("no latitude and longitude".to_string()));
+    };
+    let url = config
+        .url
+        .as_deref()
+        .unwrap_or(weather::API_URL)
+        .replace("{latitude}", &latitude.to_string())
+        .replace("{longitude}", &longitude.to_string())
+        .replace(
+            "{days}",
+            &config.days.unwrap_or(weather::DEFAULT_DAYS).to_string(),
+        );
+    let path = cache.path("weather", &url, "json");
+    let cached = cache
+        .get(&path, &url, ttl, policy, || {
+            fetch_url(&url, &[], []).map_err(|e| e.to_string())
+        })
+        .map_err(name)?;
+    let forecast = weather::from_response(&cached.contents).map_err(name)?;
+    let warning = cached
+        .stale
+        .map(|e| format!("{}; using the cached copy", name(e)));
+    Ok((forecast, warning))
+}
+
+/// The calendars of every event in the collection, as one iCalendar text
+fn request(source: &RawSource) -> Result<String, String> {
+    let args = [
+        "--request",
+        "REPORT",
+        "--header",
+        "Depth: 1",
+        "--header",
+        "Content-Type: application/xml; charset=utf-8",
+        "--data",
+        CALENDAR_QUERY,
+    ];
+    let output = fetch_url(&source.url, &credentials(source)?, args.map(OsString::from))
+        .map_err(|e| e.to_string())?;
+    Ok(calendar_data(&output).join("\n"))
+}
+
+/// A subscribed calendar, sending the ETag of the copy cached at `cached` so
+/// an unchanged calendar isn't downloaded again
+fn subscribe(source: &RawSource, cached: &Path) -> Result<String, String> {
+    let url = match source.url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => source.url.clone(),
+    };
+    let etag = cached.with_extension("etag");
+    let saved = cached.with_extension("etag.new");
+    let mut args: Vec<OsString> = vec![
+        "--location".into(),
+        "--write-out".into(),
+        "\n%{http_code}".into(),
+    ];
+    if etag.exists() && cached.exists() {
+        args.extend(["--etag-compare".into(), etag.clone().into()]);
+    }
+    if let Some(dir) = saved.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    args.extend(["--etag-save".into(), saved.clone().into()]);
+
+    let output = fetch_url(&url, &credentials(source)?, args).map_err(|e| e.to_string())?;
+    let (body, status) = output.rsplit_once('\n').unwrap_or((&output, ""));
+    if status == "304" {
+        let _ = fs::remove_file(&saved);
+        return fs::read_to_string(cached).map_err(|e| format!("{}: {}", cached.display(), e));
+    }
+    // Servers without ETags leave the saved one empty
+    let _ = match fs::metadata(&saved).map(|saved| saved.len()) {
+        Ok(1..) => fs::rename(&saved, &etag),
+        _ => fs::remove_file(&etag),
+    };
+    Ok(body.to_string())
+}
+
+/// Whether `source` is a subscribed `.ics` file rather than a CalDAV
+/// collection
+pub fn is_subscription(source: &RawSource) -> bool {
+    let path = source.url.split(['?', '#']).next().unwrap_or_default();
+    source.url.starts_with("webcal://") || path.to_lowercase().ends_with(".ics")
+}
+
+/// Why a request made with [`fetch_url`] failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    /// curl couldn't run, reach the server or finish in time
+    Failed(String),
+    /// The server answered with an error status
+    Status { message: String, body: String },
+}
+
+impl FetchError {
+    /// The body of an error answer, for APIs that explain their errors in
+    /// it, or else the error
+    pub fn into_body(self) -> Result<String, String> {
+        match self {
+            Self::Status { body, .. } if !body.trim().is_empty() => Ok(body),
+            e => Err(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Failed(message) | Self::Status { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+/// The body of `url`, fetched with curl within [`FETCH_TIMEOUT`]. `config`
+/// holds curl options such as `header = "..."` or `user = "..."`, given on
+/// curl's standard input to keep credentials off the command line, and
+/// `args` go on its command line.
+pub fn fetch_url(
+    url: &str,
+    config: &[String],
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<String, FetchError> {
+    let failed = |e: std::io::Error| FetchError::Failed(format!("could not run curl: {}", e));
+    let mut child = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail-with-body",
+            "--config",
+            "-",
+        ])
+        .args(["--header", "User-Agent: compact-calendar-cli"])
+        .arg("--max-time")
+        .arg(FETCH_TIMEOUT.as_secs().to_string())
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(failed)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}", config.join("\n")).map_err(failed)?;
+    }
+    let output = child.wait_with_output().map_err(failed)?;
+    let body = String::from_utf8_lossy(&output.stdout).into_owned();
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match output.status.code() {
+        Some(0) => Ok(body),
+        // curl's exit code for an HTTP error status
+        Some(22) => Err(FetchError::Status { message, body }),
+        _ => Err(FetchError::Failed(message)),
+    }
+}
+
+/// `value` quoted for a curl config file
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// curl options logging in as `source`'s user, if it has one
+fn credentials(source: &RawSource) -> Result<Vec<String>, String> {
+    let Some(username) = &source.username else {
+        return Ok(Vec::new());
+    };
+    let username = paths::expand_env(username).map_err(|e| e.to_string())?;
+    let password = match &source.password {
+        Some(password) => paths::expand_env(password).map_err(|e| e.to_string())?,
+        None => String::new(),
+    };
+    Ok(vec![format!(
+        "user = {}",
+        quote(&format!("{}:{}", username, password))
+    )])
+}
+
+/// The `calendar-data` of each response in a CalDAV multistatus, whatever
+/// prefix the server gives the CalDAV namespace
+pub fn calendar_data(multistatus: &str) -> Vec<String> {
+    let mut calendars = Vec::new();
+    let mut rest = multistatus;
+    while let Some(open) = rest.find("calendar-data") {
+        // Skip the name in a closing tag or in the query echoed back
+        let tag_start = rest[..open].rfind('<').unwrap_or(open);
+        let Some(tag_end) = rest[open..].find('>').map(|i| open + i) else {
+            break;
+        };
+        let tag = &rest[tag_start..=tag_end];
+        rest = &rest[tag_end + 1..];
+        if tag.starts_with("</") || tag.ends_with("/>") {
+            continue;
+        }
+        let Some(close) = rest.find("</") else {
+            break;
+        };
+        calendars.push(unescape_xml(&rest[..close]));
+        rest = &rest[close..];
+    }
+    calendars
+}
+
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    if let Some(data) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        return data.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,327 @@
+use crate::models::{Calendar, Event};
+use crate::rendering::{Background, ColorPalette};
+use crate::text_width;
+use anstyle::Style;
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+
+/// Number of events and distinct days of one category within one month
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SummaryCell {
+    pub events: usize,
+    pub days: usize,
+}
+
+/// What the columns of a [`Summary`] group events by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryCategory {
+    Color,
+    /// Events with several tags count towards each of them
+    Tag,
+}
+
+impl SummaryCategory {
+    pub fn from_by_tag_flag(by_tag: bool) -> Self {
+        if by_tag {
+            Self::Tag
+        } else {
+            Self::Color
+        }
+    }
+}
+
+/// Year-at-a-glance matrix of months against categories (colors or tags).
+/// Events without a category are left out of the matrix, but their cost
+/// still counts towards the cost column.
+pub struct Summary<'a> {
+    calendar: &'a Calendar,
+    category: SummaryCategory,
+    palette: ColorPalette,
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(calendar: &'a Calendar) -> Self {
+        Self {
+            calendar,
+            category: SummaryCategory::Color,
+            palette: ColorPalette::new()
+                .with_contrast(calendar.options.contrast, Background::detect())
+                .with_theme_colors(calendar.options.theme.colors.clone())
+                .with_dim_factor(calendar.options.dim_factor),
+        }
+    }
+
+    pub fn render(&self) {
+        let mut out = io::stdout().lock();
+        self.render_to(&mut out)
+            .and_then(|_| out.flush())
+            .expect("failed printing to stdout");
+    }
+
+    /// Render without colors, for snapshots and plain-text consumers
+    pub fn render_to_string(&self) -> String {
+        let mut output = Vec::new();
+        Summary {
+            calendar: self.calendar,
+            category: self.category,
+            palette: ColorPalette::disabled(),
+        }
+        .render_to(&mut output)
+        .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("summary is valid UTF-8")
+    }
+
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.palette = self.palette.with_colors(enabled);
+        self
+    }
+
+    pub fn with_category(mut self, category: SummaryCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Events touching the calendar's year
+    fn year_events(&self) -> Vec<&'a Event> {
+        let year = self.calendar.year;
+        self.calendar
+            .events()
+            .into_iter()
+            .filter(|event| event.start.year() <= year && event.end.year() >= year)
+            .collect()
+    }
+
+    fn categories(&self, event: &'a Event) -> Vec<&'a str> {
+        match self.category {
+            SummaryCategory::Color => event.color.as_deref().into_iter().collect(),
+            SummaryCategory::Tag => event.all_tags().collect(),
+        }
+    }
+
+    /// Cells per category, with one entry per month (January first)
+    pub fn matrix(&self) -> BTreeMap<&'a str, [SummaryCell; 12]> {
+        let mut by_category: BTreeMap<&'a str, Vec<&'a Event>> = BTreeMap::new();
+        for event in self.year_events() {
+            for category in self.categories(event) {
+                by_category.entry(category).or_default().push(event);
+            }
+        }
+
+        by_category
+            .into_iter()
+            .map(|(category, events)| (category, self.category_cells(&events)))
+            .collect()
+    }
+
+    /// Total cost per month, each event booked in the month it starts;
+    /// `None` when no event this year has a cost
+    pub fn monthly_costs(&self) -> Option<[f64; 12]> {
+        let year = self.calendar.year;
+        let mut costs = [0.0; 12];
+        let mut any = false;
+        for event in self.year_events() {
+            if let Some(cost) = event.cost {
+                let start = event
+                    .start
+                    .max(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+                costs[start.month0() as usize] += cost;
+                any = true;
+            }
+        }
+        any.then_some(costs)
+    }
+
+    fn category_cells(&self, events: &[&Event]) -> [SummaryCell; 12] {
+        let year = self.calendar.year;
+        let mut cells = [SummaryCell::default(); 12];
+        let mut days: [HashSet<NaiveDate>; 12] = Default::default();
+
+        for event in events {
+            let mut counted = [false; 12];
+            let mut date = event
+                .start
+                .max(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+            let last = event
+                .end
+                .min(NaiveDate::from_ymd_opt(year, 12, 31).unwrap());
+            while date <= last {
+                let month = date.month0() as usize;
+                days[month].insert(date);
+                if !counted[month] {
+                    cells[month].events += 1;
+                    counted[month] = true;
+                }
+                date = date.succ_opt().unwrap();
+            }
+        }
+
+        for (cell, days) in cells.iter_mut().zip(&days) {
+            cell.days = days.len();
+        }
+        cells
+    }
+
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let matrix = self.matrix();
+        let locale = self.calendar.options.locale;
+        let label_width = (1..=12)
+            .map(|month| text_width::width(locale.short_month_name(month)))
+            .max()
+            .unwrap_or(0)
+            .max("Total".len())
+            .max("Cost".len());
+
+        let monthly_costs = self.monthly_costs();
+        let mut columns: Vec<Column> = matrix
+            .iter()
+            .map(|(category, cells)| {
+                let total = SummaryCell {
+                    events: self.year_event_count(category),
+                    days: cells.iter().map(|cell| cell.days).sum(),
+                };
+                Column::new(
+                    category,
+                    self.category_style(category),
+                    cells.iter().map(cell_text).collect(),
+                    cell_text(&total),
+                    monthly_costs.map(|_| cost_text(self.category_cost(category))),
+                )
+            })
+            .collect();
+        if let Some(costs) = monthly_costs {
+            columns.push(Column::new(
+                "Cost",
+                Style::new(),
+                costs.iter().map(|&cost| cost_text(cost)).collect(),
+                cost_text(costs.iter().sum()),
+                Some(String::new()),
+            ));
+        }
+
+        write!(w, "{:<label_width$}", "")?;
+        for column in &columns {
+            let padding = " ".repeat(column.width - column.header.len());
+            write!(
+                w,
+                "  {}{}{}{}",
+                padding,
+                column.style.render(),
+                column.header,
+                column.style.render_reset()
+            )?;
+        }
+        writeln!(w)?;
+
+        for month in 1..=12 {
+            let mut line = text_width::pad_end(locale.short_month_name(month), label_width);
+            for column in &columns {
+                let width = column.width;
+                line.push_str(&format!("  {:>width$}", column.cells[month as usize - 1]));
+            }
+            writeln!(w, "{}", line.trim_end())?;
+        }
+
+        let mut line = format!("{:<label_width$}", "Total");
+        for column in &columns {
+            let width = column.width;
+            line.push_str(&format!("  {:>width$}", column.total));
+        }
+        writeln!(w, "{}", line.trim_end())?;
+
+        if monthly_costs.is_some() {
+            let mut line = format!("{:<label_width$}", "Cost");
+            for column in &columns {
+                let width = column.width;
+                let cost = column.cost.as_deref().unwrap_or("");
+                line.push_str(&format!("  {:>width$}", cost));
+            }
+            writeln!(w, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// Yearly cost of the events in a category
+    fn category_cost(&self, category: &str) -> f64 {
+        self.year_events()
+            .into_iter()
+            .filter(|event| self.categories(event).contains(&category))
+            .filter_map(|event| event.cost)
+            .sum()
+    }
+
+    /// Color columns are drawn in their own color; tags stay plain
+    fn category_style(&self, category: &str) -> Style {
+        match self.category {
+            SummaryCategory::Color => self.palette.annotation_style(Some(category)),
+            SummaryCategory::Tag => Style::new(),
+        }
+    }
+
+    /// Events of a category touching the calendar's year, each counted once
+    fn year_event_count(&self, category: &str) -> usize {
+        self.year_events()
+            .into_iter()
+            .filter(|event| self.categories(event).contains(&category))
+            .count()
+    }
+}
+
+/// One right-aligned column of the matrix
+struct Column<'a> {
+    header: &'a str,
+    style: Style,
+    cells: Vec<String>,
+    total: String,
+    /// Yearly cost, when costs are shown
+    cost: Option<String>,
+    width: usize,
+}
+
+impl<'a> Column<'a> {
+    fn new(
+        header: &'a str,
+        style: Style,
+        cells: Vec<String>,
+        total: String,
+        cost: Option<String>,
+    ) -> Self {
+        let width = cells
+            .iter()
+            .chain([&total])
+            .chain(&cost)
+            .map(String::len)
+            .chain([header.len()])
+            .max()
+            .unwrap_or(0);
+        Self {
+            header,
+            style,
+            cells,
+            total,
+            cost,
+            width,
+        }
+    }
+}
+
+/// Whole amounts without decimals, anything else with two; "-" for nothing
+fn cost_text(cost: f64) -> String {
+    if cost == 0.0 {
+        "-".to_string()
+    } else if cost.fract() == 0.0 {
+        format!("{:.0}", cost)
+    } else {
+        format!("{:.2}", cost)
+    }
+}
+
+/// "events/days", or "-" for an empty cell
+fn cell_text(cell: &SummaryCell) -> String {
+    if cell.events == 0 {
+        "-".to_string()
+    } else {
+        format!("{}/{}", cell.events, cell.days)
+    }
+}
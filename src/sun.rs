@@ -0,0 +1,212 @@
+//! Sunrise, sunset, solstices and equinoxes, from the NOAA solar position
+//! formulas; good to a minute or two away from the poles.
+
+use crate::models::{Event, EventKind};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+
+/// Julian Day of the Unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Sun's altitude at rise and set: its radius and atmospheric refraction
+const HORIZON_ZENITH: f64 = 90.833;
+
+/// Annotations below the colored dates, so they go first when cut short
+const PRIORITY: i32 = -1;
+
+/// Where the calendar is, and the sunrise and sunset times worth a note
+/// when the sun crosses them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Daylight {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub sunrise: Vec<NaiveTime>,
+    pub sunset: Vec<NaiveTime>,
+}
+
+impl Daylight {
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, String> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err(format!("invalid latitude {}, expected -90 to 90", latitude));
+        }
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!(
+                "invalid longitude {}, expected -180 to 180",
+                longitude
+            ));
+        }
+        Ok(Self {
+            latitude,
+            longitude,
+            sunrise: Vec::new(),
+            sunset: Vec::new(),
+        })
+    }
+
+    /// Sunrise and sunset on `date`, or `None` during polar day or night
+    pub fn sun_times(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let midnight = date.and_time(NaiveTime::MIN).and_utc();
+        // The sun's position at local solar noon
+        let noon = midnight + minutes(720.0 - 4.0 * self.longitude);
+        let sun = SunPosition::at(noon);
+
+        let latitude = self.latitude.to_radians();
+        let declination = sun.declination.to_radians();
+        let cos_hour_angle = HORIZON_ZENITH.to_radians().cos()
+            / (latitude.cos() * declination.cos())
+            - latitude.tan() * declination.tan();
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        let solar_noon = 720.0 - 4.0 * self.longitude - sun.equation_of_time;
+        Some((
+            midnight + minutes(solar_noon - 4.0 * hour_angle),
+            midnight + minutes(solar_noon + 4.0 * hour_angle),
+        ))
+    }
+
+    /// Solstices, equinoxes and the days sunrise or sunset crosses one of
+    /// the times, in `year`; `local` gives the clock time of an instant
+    pub fn events(&self, year: i32, local: impl Fn(DateTime<Utc>) -> NaiveDateTime) -> Vec<Event> {
+        let mut notes: Vec<(NaiveDate, String)> = seasons(year)
+            .into_iter()
+            .map(|(instant, name)| (local(instant).date(), name.to_string()))
+            .collect();
+
+        let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let times: Vec<Option<(NaiveTime, NaiveTime)>> = first
+            .pred_opt()
+            .unwrap()
+            .iter_days()
+            .take_while(|date| date.year() <= year)
+            .map(|date| {
+                self.sun_times(date)
+                    .map(|(rise, set)| (local(rise).time(), local(set).time()))
+            })
+            .collect();
+        for (date, pair) in first.iter_days().zip(times.windows(2)) {
+            let (Some(before), Some(after)) = (pair[0], pair[1]) else {
+                continue;
+            };
+            for (name, thresholds, before, after) in [
+                ("Sunrise", &self.sunrise, before.0, after.0),
+                ("Sunset", &self.sunset, before.1, after.1),
+            ] {
+                for threshold in thresholds {
+                    let crossing = match (before > *threshold, after > *threshold) {
+                        (false, true) => "after",
+                        (true, false) => "before",
+                        _ => continue,
+                    };
+                    notes.push((
+                        date,
+                        format!("{} {} {}", name, crossing, threshold.format("%H:%M")),
+                    ));
+                }
+            }
+        }
+
+        notes.sort_by_key(|(date, _)| *date);
+        notes
+            .into_iter()
+            .filter_map(|(date, description)| {
+                Event::builder(EventKind::Date, date)
+                    .description(description)
+                    .priority(PRIORITY)
+                    .build()
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+/// The equinoxes and solstices of `year`, named by their month so they
+/// read the same in both hemispheres; within a quarter of an hour
+pub fn seasons(year: i32) -> Vec<(DateTime<Utc>, &'static str)> {
+    [
+        (0.0, "March equinox"),
+        (90.0, "June solstice"),
+        (180.0, "September equinox"),
+        (270.0, "December solstice"),
+    ]
+    .into_iter()
+    .map(|(longitude, name)| (solar_term(year, longitude), name))
+    .collect()
+}
+
+/// When the sun reaches ecliptic `longitude` (0 at the March equinox) in
+/// `year`; within a quarter of an hour
+pub fn solar_term(year: i32, longitude: f64) -> DateTime<Utc> {
+    // The sun's longitude grows by about a degree a day, so search the days
+    // around where an even pace from the equinox would put it
+    let equinox = NaiveDate::from_ymd_opt(year, 3, 20)
+        .unwrap()
+        .and_time(NaiveTime::MIN)
+        .and_utc();
+    let guess = equinox + minutes(longitude / 360.0 * 365.2422 * 1440.0);
+    let (mut low, mut high) = (guess - TimeDelta::days(5), guess + TimeDelta::days(5));
+    while high - low > TimeDelta::seconds(1) {
+        let middle = low + (high - low) / 2;
+        let past = (SunPosition::at(middle).longitude - longitude + 540.0) % 360.0 - 180.0;
+        if past < 0.0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Where the sun is in the sky at an instant
+struct SunPosition {
+    /// Apparent ecliptic longitude, in degrees
+    longitude: f64,
+    /// In degrees
+    declination: f64,
+    /// How far apparent solar time runs ahead of mean solar time, in minutes
+    equation_of_time: f64,
+}
+
+impl SunPosition {
+    fn at(instant: DateTime<Utc>) -> Self {
+        let jd = instant.timestamp() as f64 / 86400.0 + UNIX_EPOCH_JD;
+        let t = (jd - 2451545.0) / 36525.0;
+
+        let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+        let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+        let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+        let m = mean_anomaly.to_radians();
+        let center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+            + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+            + (3.0 * m).sin() * 0.000289;
+        let omega = (125.04 - 1934.136 * t).to_radians();
+        let longitude =
+            (mean_longitude + center - 0.00569 - 0.00478 * omega.sin()).rem_euclid(360.0);
+
+        let mean_obliquity =
+            23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+        let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+        let declination = (obliquity.sin() * longitude.to_radians().sin())
+            .asin()
+            .to_degrees();
+
+        let y = (obliquity / 2.0).tan().powi(2);
+        let l0 = mean_longitude.to_radians();
+        let equation_of_time = 4.0
+            * (y * (2.0 * l0).sin() - 2.0 * eccentricity * m.sin()
+                + 4.0 * eccentricity * y * m.sin() * (2.0 * l0).cos()
+                - 0.5 * y * y * (4.0 * l0).sin()
+                - 1.25 * eccentricity * eccentricity * (2.0 * m).sin())
+            .to_degrees();
+
+        Self {
+            longitude,
+            declination,
+            equation_of_time,
+        }
+    }
+}
+
+fn minutes(minutes: f64) -> TimeDelta {
+    TimeDelta::seconds((minutes * 60.0).round() as i64)
+}
@@ -0,0 +1,69 @@
+//! Helpers for writing stable tests against rendered calendars.
+//!
+//! Enabled with the `testing` cargo feature.
+
+use crate::models::{Calendar, CalendarOptions, Event, EventKind, PastDateDisplay, WeekendDisplay};
+use chrono::NaiveDate;
+
+impl Calendar {
+    /// A small calendar with a few dates and ranges, rendered without
+    /// weekend dimming or strikethrough and with "today" pinned to January 1st
+    pub fn sample(year: i32) -> Calendar {
+        let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+
+        let options = CalendarOptions::builder()
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .today(date(1, 1))
+            .build();
+
+        let events = vec![
+            Event::builder(EventKind::Range, date(1, 1))
+                .end(date(1, 7))
+                .color("green")
+                .description("New Year Week"),
+            Event::builder(EventKind::Date, date(2, 14))
+                .color("red")
+                .description("Valentine's Day"),
+            Event::builder(EventKind::Date, date(7, 4))
+                .color("blue")
+                .description("Independence Day"),
+            Event::builder(EventKind::Date, date(10, 31)).description("Halloween"),
+            Event::builder(EventKind::Range, date(12, 20))
+                .end(date(12, 31))
+                .color("cyan")
+                .description("Holiday Break"),
+        ]
+        .into_iter()
+        .map(|builder| builder.build().unwrap())
+        .collect();
+
+        Calendar::new(year, options, events)
+    }
+}
+
+pub use crate::text_width::strip_ansi;
+
+/// Compare two renderings, ignoring any ANSI styling
+pub fn eq_ignoring_ansi(a: &str, b: &str) -> bool {
+    strip_ansi(a) == strip_ansi(b)
+}
+
+/// Assert two renderings match once ANSI styling is stripped
+#[track_caller]
+pub fn assert_eq_ignoring_ansi(actual: &str, expected: &str) {
+    let (actual, expected) = (strip_ansi(actual), strip_ansi(expected));
+    if actual != expected {
+        let line = actual
+            .lines()
+            .zip(expected.lines())
+            .position(|(a, e)| a != e)
+            .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+        panic!(
+            "renderings differ at line {}\n--- actual ---\n{}\n--- expected ---\n{}",
+            line + 1,
+            actual,
+            expected
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Text measured in terminal columns rather than characters: CJK and most
+//! emoji take two, combining marks none, and ANSI escape codes none.
+
+use unicode_width::UnicodeWidthChar;
+
+/// Columns `text` takes up in a terminal
+pub fn width(text: &str) -> usize {
+    visible_chars(text).map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// `text` without its ANSI escape codes (colors, effects)
+pub fn strip_ansi(text: &str) -> String {
+    visible_chars(text).collect()
+}
+
+/// The characters of `text` that show, skipping ANSI escape codes
+fn visible_chars(text: &str) -> impl Iterator<Item = char> + '_ {
+    let mut chars = text.chars();
+    std::iter::from_fn(move || loop {
+        let c = chars.next()?;
+        if c != '\x1b' {
+            return Some(c);
+        }
+        // CSI sequences run from "ESC [" to a final byte in '@'..='~'
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// `text` followed by spaces up to `columns`, like `{:<columns$}`
+pub fn pad_end(text: &str, columns: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(columns.saturating_sub(width(text)))
+    )
+}
+
+/// `text` after spaces up to `columns`, like `{:>columns$}`
+pub fn pad_start(text: &str, columns: usize) -> String {
+    format!(
+        "{}{}",
+        " ".repeat(columns.saturating_sub(width(text))),
+        text
+    )
+}
+
+/// `text` in the middle of `columns`, the odd space after it, like
+/// `{:^columns$}`
+pub fn center(text: &str, columns: usize) -> String {
+    let space = columns.saturating_sub(width(text));
+    format!(
+        "{}{}{}",
+        " ".repeat(space / 2),
+        text,
+        " ".repeat(space - space / 2)
+    )
+}
+
+/// The start of `text` that fits in `columns`, without escape codes; a wide
+/// character that would straddle the edge is left out
+pub fn truncate(text: &str, columns: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > columns {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// `line` split after its first `columns` columns, not counting escape codes
+pub fn split_at(line: &str, columns: usize) -> (&str, &str) {
+    let mut used = 0;
+    let mut in_escape = false;
+    for (i, c) in line.char_indices() {
+        if used >= columns {
+            return line.split_at(i);
+        }
+        match c {
+            '\x1b' => in_escape = true,
+            _ if in_escape => in_escape = !c.is_ascii_alphabetic(),
+            _ => used += c.width().unwrap_or(0),
+        }
+    }
+    (line, "")
+}
@@ -0,0 +1,271 @@
+use crate::error::{Error, Result};
+use crate::named_colors;
+use crate::paths;
+use crate::rendering::{ColorPalette, ColorValue};
+use anstyle::Effects;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the theme file in the config directory
+pub const THEME_FILE_NAME: &str = "theme.toml";
+
+/// A user's visual preferences, from `theme.toml` in the config directory:
+/// colors of their own, the effects marking today, past days and weekends,
+/// and the border glyphs. Kept apart from the calendar config, so a shared
+/// config renders in each user's own look.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    /// Colors usable by name in configs, before the built-in ones
+    pub colors: BTreeMap<String, ColorValue>,
+    /// Replaces today's underline
+    pub today: Option<Effects>,
+    /// Replaces the strikethrough of past days
+    pub past: Option<Effects>,
+    /// Replaces the dimming of weekends and non-working days
+    pub weekend: Option<Effects>,
+    pub borders: Borders,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTheme {
+    /// `name = "#rrggbb"`, or another color's name
+    #[serde(default)]
+    colors: BTreeMap<String, String>,
+    #[serde(default)]
+    today: Option<RawEffects>,
+    #[serde(default)]
+    past: Option<RawEffects>,
+    #[serde(default)]
+    weekend: Option<RawEffects>,
+    #[serde(default)]
+    borders: Option<RawBorders>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEffects {
+    effects: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBorders {
+    /// "light", "rounded", "heavy", "double" or "ascii"
+    #[serde(default)]
+    style: Option<String>,
+    #[serde(default)]
+    horizontal: Option<char>,
+    #[serde(default)]
+    vertical: Option<char>,
+    #[serde(default)]
+    top_left: Option<char>,
+    #[serde(default)]
+    top_right: Option<char>,
+    #[serde(default)]
+    bottom_left: Option<char>,
+    #[serde(default)]
+    bottom_right: Option<char>,
+    #[serde(default)]
+    left_tee: Option<char>,
+    #[serde(default)]
+    right_tee: Option<char>,
+    #[serde(default)]
+    top_tee: Option<char>,
+    #[serde(default)]
+    bottom_tee: Option<char>,
+    #[serde(default)]
+    cross: Option<char>,
+}
+
+impl Theme {
+    /// The theme file in the XDG config directory, or else the platform's,
+    /// if there is one
+    pub fn find() -> Option<PathBuf> {
+        [paths::xdg_config_dir(), paths::platform_config_dir()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(THEME_FILE_NAME))
+            .find(|path| path.exists())
+    }
+
+    /// The user's theme, or the default look without a theme file
+    pub fn load() -> Result<Self> {
+        match Self::find() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let raw: RawTheme = toml::from_str(&contents)
+            .map_err(|e| Error::parse(path.to_path_buf(), &contents, e))?;
+        let invalid =
+            |reason: String| Error::InvalidEntry(format!("theme {}: {}", path.display(), reason));
+
+        let mut colors = BTreeMap::new();
+        for (name, value) in &raw.colors {
+            let color = named_colors::parse_hex(value)
+                .map(ColorValue::from_rgb)
+                .or_else(|| ColorPalette::get_color_value(value))
+                .ok_or_else(|| invalid(format!("unknown color '{}' for {}", value, name)))?;
+            colors.insert(name.clone(), color);
+        }
+        let effects = |raw: &Option<RawEffects>| {
+            raw.as_ref()
+                .map(|raw| parse_effects(&raw.effects))
+                .transpose()
+                .map_err(invalid)
+        };
+
+        Ok(Theme {
+            colors,
+            today: effects(&raw.today)?,
+            past: effects(&raw.past)?,
+            weekend: effects(&raw.weekend)?,
+            borders: match &raw.borders {
+                Some(borders) => Borders::from_raw(borders).map_err(invalid)?,
+                None => Borders::default(),
+            },
+        })
+    }
+}
+
+/// The effects named in a list such as `["bold", "italic"]`; an empty list
+/// is no effect
+pub fn parse_effects(names: &[String]) -> std::result::Result<Effects, String> {
+    names.iter().try_fold(Effects::new(), |effects, name| {
+        let effect = match name.to_lowercase().as_str() {
+            "bold" => Effects::BOLD,
+            "dimmed" | "dim" => Effects::DIMMED,
+            "italic" => Effects::ITALIC,
+            "underline" => Effects::UNDERLINE,
+            "strikethrough" => Effects::STRIKETHROUGH,
+            "inverse" => Effects::INVERT,
+            _ => {
+                return Err(format!(
+                    "Invalid effect: '{}'. Use bold, dimmed, italic, underline, strikethrough or inverse",
+                    name
+                ))
+            }
+        };
+        Ok(effects | effect)
+    })
+}
+
+/// Glyphs the calendar's frame is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Borders {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    /// `├`, where a line meets the left side
+    pub left_tee: char,
+    pub right_tee: char,
+    pub top_tee: char,
+    pub bottom_tee: char,
+    pub cross: char,
+}
+
+impl Default for Borders {
+    /// The thin box-drawing lines the calendar is rendered with
+    fn default() -> Self {
+        Self::from_glyphs("─│┌┐└┘├┤┬┴┼")
+    }
+}
+
+impl Borders {
+    /// Borders from 11 glyphs, in the order of the fields
+    fn from_glyphs(glyphs: &str) -> Self {
+        let glyphs: Vec<char> = glyphs.chars().collect();
+        Self {
+            horizontal: glyphs[0],
+            vertical: glyphs[1],
+            top_left: glyphs[2],
+            top_right: glyphs[3],
+            bottom_left: glyphs[4],
+            bottom_right: glyphs[5],
+            left_tee: glyphs[6],
+            right_tee: glyphs[7],
+            top_tee: glyphs[8],
+            bottom_tee: glyphs[9],
+            cross: glyphs[10],
+        }
+    }
+
+    pub fn parse(style: &str) -> std::result::Result<Self, String> {
+        match style.to_lowercase().as_str() {
+            "light" => Ok(Self::default()),
+            "rounded" => Ok(Self::from_glyphs("─│╭╮╰╯├┤┬┴┼")),
+            "heavy" => Ok(Self::from_glyphs("━┃┏┓┗┛┣┫┳┻╋")),
+            "double" => Ok(Self::from_glyphs("═║╔╗╚╝╠╣╦╩╬")),
+            "ascii" => Ok(Self::from_glyphs("-|+++++++++")),
+            _ => Err(format!(
+                "Invalid border style: '{}'. Use light, rounded, heavy, double or ascii",
+                style
+            )),
+        }
+    }
+
+    fn from_raw(raw: &RawBorders) -> std::result::Result<Self, String> {
+        let preset = raw.style.as_deref().map(Self::parse).transpose()?;
+        let preset = preset.unwrap_or_default();
+        Ok(Self {
+            horizontal: raw.horizontal.unwrap_or(preset.horizontal),
+            vertical: raw.vertical.unwrap_or(preset.vertical),
+            top_left: raw.top_left.unwrap_or(preset.top_left),
+            top_right: raw.top_right.unwrap_or(preset.top_right),
+            bottom_left: raw.bottom_left.unwrap_or(preset.bottom_left),
+            bottom_right: raw.bottom_right.unwrap_or(preset.bottom_right),
+            left_tee: raw.left_tee.unwrap_or(preset.left_tee),
+            right_tee: raw.right_tee.unwrap_or(preset.right_tee),
+            top_tee: raw.top_tee.unwrap_or(preset.top_tee),
+            bottom_tee: raw.bottom_tee.unwrap_or(preset.bottom_tee),
+            cross: raw.cross.unwrap_or(preset.cross),
+        })
+    }
+
+    fn glyphs(&self) -> [char; 11] {
+        [
+            self.horizontal,
+            self.vertical,
+            self.top_left,
+            self.top_right,
+            self.bottom_left,
+            self.bottom_right,
+            self.left_tee,
+            self.right_tee,
+            self.top_tee,
+            self.bottom_tee,
+            self.cross,
+        ]
+    }
+
+    /// `text`, rendered with the default borders, redrawn with these
+    pub fn apply<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let light = Self::default();
+        if *self == light {
+            return Cow::Borrowed(text);
+        }
+        let light = light.glyphs();
+        let glyphs = self.glyphs();
+        Cow::Owned(
+            text.chars()
+                .map(|c| match light.iter().position(|&glyph| glyph == c) {
+                    Some(i) => glyphs[i],
+                    None => c,
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use crate::listing::{format_dates, DATES_WIDTH};
+use crate::models::{Calendar, Event, EventKind};
+use crate::rendering::{Background, ColorPalette};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::io::{self, Write};
+
+/// The next few events from today, each with a countdown
+pub struct Upcoming<'a> {
+    calendar: &'a Calendar,
+    count: usize,
+    /// Instant used for destination dates while traveling; the clock when unset
+    now: Option<DateTime<Utc>>,
+    palette: ColorPalette,
+}
+
+impl<'a> Upcoming<'a> {
+    pub fn new(calendar: &'a Calendar, count: usize) -> Self {
+        Self {
+            calendar,
+            count,
+            now: None,
+            palette: ColorPalette::new()
+                .with_contrast(calendar.options.contrast, Background::detect())
+                .with_theme_colors(calendar.options.theme.colors.clone())
+                .with_dim_factor(calendar.options.dim_factor),
+        }
+    }
+
+    pub fn render(&self) {
+        let mut out = io::stdout().lock();
+        self.render_to(&mut out)
+            .and_then(|_| out.flush())
+            .expect("failed printing to stdout");
+    }
+
+    /// Render without colors, for snapshots and plain-text consumers
+    pub fn render_to_string(&self) -> String {
+        let mut output = Vec::new();
+        Upcoming {
+            calendar: self.calendar,
+            count: self.count,
+            now: self.now,
+            palette: ColorPalette::disabled(),
+        }
+        .render_to(&mut output)
+        .expect("writing to a Vec cannot fail");
+        String::from_utf8(output).expect("upcoming events are valid UTF-8")
+    }
+
+    /// Force colors on or off, regardless of `NO_COLOR`
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.palette = self.palette.with_colors(enabled);
+        self
+    }
+
+    /// Pin the current instant, which decides the date at a travel destination
+    pub fn at(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Travel ranges underway today
+    pub fn active_travel(&self) -> Vec<&'a Event> {
+        let today = self.calendar.today();
+        self.calendar
+            .ranges
+            .iter()
+            .filter(|event| event.kind == EventKind::Travel && event.contains(today))
+            .collect()
+    }
+
+    /// Events that haven't ended yet, including ranges already underway,
+    /// soonest first
+    pub fn events(&self) -> Vec<&'a Event> {
+        let today = self.calendar.today();
+        self.calendar
+            .events()
+            .into_iter()
+            .filter(|event| event.end >= today)
+            .take(self.count)
+            .collect()
+    }
+
+    pub fn render_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let today = self.calendar.today();
+        let now = self.now.unwrap_or_else(Utc::now);
+        for travel in self.active_travel() {
+            if let (Some(timezone), Some(date)) = (travel.timezone, travel.destination_date(now)) {
+                writeln!(
+                    w,
+                    "Today: {} at home, {} in {} ({})",
+                    today.format("%Y-%m-%d"),
+                    date.format("%Y-%m-%d"),
+                    timezone,
+                    travel.utc_offset_label().unwrap_or_default()
+                )?;
+            }
+        }
+
+        let lines: Vec<(&Event, String)> = self
+            .events()
+            .into_iter()
+            .map(|event| (event, countdown(event, today)))
+            .collect();
+        let countdown_width = lines
+            .iter()
+            .map(|(_, countdown)| countdown.len())
+            .max()
+            .unwrap_or(0);
+
+        for (event, countdown) in lines {
+            let dates = format_dates(event);
+            let style = self.palette.annotation_style(event.color.as_deref());
+            let line = format!(
+                "{}{}{}{}  {:<countdown_width$}  {}",
+                style.render(),
+                dates,
+                style.render_reset(),
+                " ".repeat(DATES_WIDTH - dates.len()),
+                countdown,
+                event.description.as_deref().unwrap_or(""),
+            );
+            writeln!(w, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Time until `event` starts, or until it ends when already underway
+pub fn countdown(event: &Event, today: NaiveDate) -> String {
+    if event.start > today {
+        match (event.start - today).num_days() {
+            1 => "tomorrow".to_string(),
+            days => format!("in {} days", days),
+        }
+    } else if event.is_single_day() {
+        "today".to_string()
+    } else {
+        match (event.end - today).num_days() {
+            0 => "ends today".to_string(),
+            1 => "ends tomorrow".to_string(),
+            days => format!("ends in {} days", days),
+        }
+    }
+}
@@ -0,0 +1,643 @@
+use crate::cache;
+use crate::config::{parse_day_span, parse_month_day, CalendarConfig, ConfigFormat};
+use crate::error::line_col;
+use crate::expressions::Anchors;
+use crate::models::{DayNumbering, Gradient, PlainMarker, TodayMarker, WeekColumn, WeekStart};
+use crate::rendering::ColorPalette;
+use crate::secondary::SecondaryCalendar;
+use crate::theme::Theme;
+use chrono::Datelike;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, Table};
+
+/// A problem in a config file, with the 1-based line it was found on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Check a TOML config file's contents, returning every problem that would
+/// make an entry be skipped: unparseable dates, unknown colors, ranges ending
+/// before they start, dates listed twice, and so on. A TOML syntax or schema
+/// error stops the check at that error.
+pub fn validate(contents: &str) -> Vec<Problem> {
+    validate_as(contents, ConfigFormat::Toml)
+}
+
+/// Check a config file's contents in the given format. Only syntax and schema
+/// errors carry a line for YAML and JSON.
+pub fn validate_as(contents: &str, format: ConfigFormat) -> Vec<Problem> {
+    validate_with_theme(contents, format, &Theme::default())
+}
+
+/// Like [`validate_as`], also accepting the colors `theme` names
+pub fn validate_with_theme(contents: &str, format: ConfigFormat, theme: &Theme) -> Vec<Problem> {
+    let palette = ColorPalette::new().with_theme_colors(theme.colors.clone());
+    let mut skipped_lines = Vec::new();
+    let (config, document): (CalendarConfig, _) = match format {
+        ConfigFormat::Toml => {
+            let config = match toml::from_str(contents) {
+                Ok(config) => config,
+                Err(e) => {
+                    return vec![Problem {
+                        line: e.span().map(|span| line_col(contents, span.start).0),
+                        message: one_line(e.message()),
+                    }]
+                }
+            };
+            match ImDocument::parse(contents) {
+                Ok(document) => (config, Some(document)),
+                Err(e) => {
+                    return vec![Problem {
+                        line: e.span().map(|span| line_col(contents, span.start).0),
+                        message: one_line(e.message()),
+                    }]
+                }
+            }
+        }
+        ConfigFormat::Yaml => match serde_yaml::from_str(contents) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                let line = e.location().map(|location| location.line());
+                return vec![located_problem(&e, line)];
+            }
+        },
+        ConfigFormat::Json => match serde_json::from_str(contents) {
+            Ok(config) => (config, None),
+            Err(e) => return vec![located_problem(&e, Some(e.line()))],
+        },
+        ConfigFormat::Remind => {
+            let (config, skipped) = crate::remind::parse(contents);
+            skipped_lines = skipped;
+            (config, None)
+        }
+    };
+    let document = document.as_ref().map(|document| document.as_table());
+    let line = |span: Option<Range<usize>>| span.map(|span| line_col(contents, span.start).0);
+
+    let mut problems: Vec<Problem> = skipped_lines
+        .into_iter()
+        .map(|skipped| Problem {
+            line: Some(skipped.line),
+            message: skipped.message,
+        })
+        .collect();
+    let mut anchors_by_year = HashMap::new();
+    let mut anchors = |year: i32| -> Anchors {
+        anchors_by_year
+            .entry(year)
+            .or_insert_with(|| config.anchors(Some(year)))
+            .clone()
+    };
+
+    if let Err(e) = config.settings.contrast() {
+        let theme = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("theme"));
+        problems.push(Problem {
+            line: line(theme.and_then(Item::span)),
+            message: e.to_string(),
+        });
+    }
+    if let Some(Err(e)) = config
+        .settings
+        .today_marker
+        .as_deref()
+        .map(TodayMarker::parse)
+    {
+        let today_marker = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("today_marker"));
+        problems.push(Problem {
+            line: line(today_marker.and_then(Item::span)),
+            message: e,
+        });
+    }
+    if let Some(Err(e)) = config
+        .settings
+        .day_format
+        .as_deref()
+        .map(DayNumbering::parse)
+    {
+        let day_format = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("day_format"));
+        problems.push(Problem {
+            line: line(day_format.and_then(Item::span)),
+            message: e,
+        });
+    }
+    if let Some(Err(e)) = config
+        .settings
+        .plain_marker
+        .as_deref()
+        .map(PlainMarker::parse)
+    {
+        let plain_marker = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("plain_marker"));
+        problems.push(Problem {
+            line: line(plain_marker.and_then(Item::span)),
+            message: e,
+        });
+    }
+
+    if let Some(Err(e)) = config.settings.week_start.as_deref().map(WeekStart::parse) {
+        let week_start = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("week_start"));
+        problems.push(Problem {
+            line: line(week_start.and_then(Item::span)),
+            message: e,
+        });
+    }
+
+    if let Some(Err(e)) = config
+        .settings
+        .week_column
+        .as_deref()
+        .map(WeekColumn::parse)
+    {
+        let week_column = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("week_column"));
+        problems.push(Problem {
+            line: line(week_column.and_then(Item::span)),
+            message: e,
+        });
+    }
+
+    if let Some(Err(e)) = config
+        .settings
+        .secondary_calendar
+        .as_deref()
+        .map(SecondaryCalendar::parse)
+    {
+        let secondary_calendar = document
+            .and_then(|document| document.get("settings"))
+            .and_then(|settings| settings.get("secondary_calendar"));
+        problems.push(Problem {
+            line: line(secondary_calendar.and_then(Item::span)),
+            message: e,
+        });
+    }
+
+    let style = [
+        ("past", config.style.past_display().err()),
+        ("weekends", config.style.weekend_display().err()),
+        ("dim_factor", config.style.dim_factor().err()),
+    ];
+    for (key, error) in style {
+        if let Some(e) = error {
+            let item = document
+                .and_then(|document| document.get("style"))
+                .and_then(|style| style.get(key));
+            problems.push(Problem {
+                line: line(item.and_then(Item::span)),
+                message: e.to_string(),
+            });
+        }
+    }
+
+    for (key, message) in config.style.conflicts(&config.settings) {
+        let item = document
+            .and_then(|document| document.get("style"))
+            .and_then(|style| style.get(key));
+        problems.push(Problem {
+            line: line(item.and_then(Item::span)),
+            message,
+        });
+    }
+
+    if let Err(e) = config.timezone() {
+        let timezone = document.and_then(|document| document.get("timezone"));
+        problems.push(Problem {
+            line: line(timezone.and_then(Item::span)),
+            message: e.to_string(),
+        });
+    }
+
+    for (key, entries, name) in [
+        ("non_working", &config.non_working, "non-working days"),
+        (
+            "working_weekends",
+            &config.working_weekends,
+            "working weekend",
+        ),
+    ] {
+        let array = document
+            .and_then(|document| document.get(key))
+            .and_then(Item::as_array);
+        for (i, entry) in entries.iter().enumerate() {
+            if let Err(e) = in_any_year(YEARS, |year| parse_day_span(entry, year, &anchors(year))) {
+                let value = array.and_then(|array| array.get(i));
+                problems.push(Problem {
+                    line: line(value.and_then(|value| value.span())),
+                    message: format!("{} {}: {}", name, entry, e),
+                });
+            }
+        }
+    }
+
+    let range_tables = array_tables(document.and_then(|document| document.get("ranges")));
+    for (i, range) in config.ranges.iter().enumerate() {
+        let table = range_tables.get(i).copied();
+        let entry_line = line(table.and_then(Table::span));
+        let event = in_any_year(YEARS, |year| {
+            range.to_event(Some(year), &anchors(year), &config.categories)
+        });
+        if let Err(e) = event {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("range starting {}: {}", range.start, e),
+            });
+        }
+        if let Some(color) = &range.color {
+            if let Some(problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                problems.push(problem);
+            }
+        }
+        if let Some(Ok(Gradient::To(color))) = range.gradient.as_deref().map(Gradient::parse) {
+            if palette.color_value(&color).is_none() {
+                let gradient_line = table
+                    .and_then(|table| table.get("gradient"))
+                    .and_then(|item| line(item.span()));
+                problems.push(Problem {
+                    line: gradient_line.or(entry_line),
+                    message: format!("unknown gradient color '{}'", color),
+                });
+            }
+        }
+    }
+
+    let categories_table = document
+        .and_then(|document| document.get("categories"))
+        .and_then(Item::as_table_like);
+    for (tag, category) in &config.categories {
+        let table = categories_table
+            .and_then(|table| table.get(tag))
+            .and_then(Item::as_table);
+        let entry_line = line(table.and_then(Table::span));
+        if let Some(color) = &category.color {
+            if let Some(mut problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                problem.message = format!("category {}: {}", tag, problem.message);
+                problems.push(problem);
+            }
+        }
+    }
+
+    if let Some(daylight) = &config.daylight {
+        if let Err(e) = daylight.to_daylight() {
+            problems.push(Problem {
+                line: line(
+                    document
+                        .and_then(|document| document.get("daylight"))
+                        .and_then(Item::as_table)
+                        .and_then(Table::span),
+                ),
+                message: format!("daylight: {}", e),
+            });
+        }
+    }
+
+    // Bad [daylight] coordinates it borrows are reported above
+    if let (Some(weather), Ok(_)) = (&config.weather, config.daylight()) {
+        let ttl = weather.ttl.as_deref().map(cache::parse_ttl);
+        let problem = match (config.weather(), ttl) {
+            (Err(e), _) => Some(e.to_string()),
+            (_, Some(Err(e))) => Some(format!("weather: {}", e)),
+            _ => None,
+        };
+        if let Some(message) = problem {
+            problems.push(Problem {
+                line: line(
+                    document
+                        .and_then(|document| document.get("weather"))
+                        .and_then(Item::as_table)
+                        .and_then(Table::span),
+                ),
+                message,
+            });
+        }
+    }
+
+    if let Some(sprints) = &config.sprints {
+        let table = document
+            .and_then(|document| document.get("sprints"))
+            .and_then(Item::as_table);
+        let entry_line = line(table.and_then(Table::span));
+        // The year the sprints run in, if given
+        let years = given_year(&sprints.start);
+        if let Err(e) = in_any_year(years, |year| sprints.to_events(year, &anchors(year))) {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("sprints starting {}: {}", sprints.start, e),
+            });
+        }
+        for color in &sprints.colors {
+            if let Some(problem) = unknown_color(&palette, color, None, entry_line, &line) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if let Some(payperiods) = &config.payperiods {
+        let table = document
+            .and_then(|document| document.get("payperiods"))
+            .and_then(Item::as_table);
+        let entry_line = line(table.and_then(Table::span));
+        let years = given_year(&payperiods.payday);
+        if let Err(e) = in_any_year(years, |year| payperiods.to_events(year, &anchors(year))) {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("payperiods: {}", e),
+            });
+        }
+        let colors = payperiods.color.iter().chain(&payperiods.colors);
+        for color in colors {
+            if let Some(problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if let Some(shifts) = &config.shifts {
+        let table = document
+            .and_then(|document| document.get("shifts"))
+            .and_then(Item::as_table);
+        let entry_line = line(table.and_then(Table::span));
+        let years = given_year(&shifts.start);
+        if let Err(e) = in_any_year(years, |year| shifts.to_events(year, &anchors(year))) {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("shifts: {}", e),
+            });
+        }
+        let codes = table
+            .and_then(|table| table.get("codes"))
+            .and_then(Item::as_table);
+        for (code, shift) in &shifts.codes {
+            let code_table = codes
+                .and_then(|codes| codes.get(code))
+                .and_then(Item::as_table);
+            let code_line = line(code_table.and_then(Table::span)).or(entry_line);
+            if code.chars().count() != 1 {
+                problems.push(Problem {
+                    line: code_line,
+                    message: format!("shift code '{}' must be a single letter", code),
+                });
+            }
+            if let Some(problem) =
+                unknown_color(&palette, &shift.color, code_table, code_line, &line)
+            {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if let Some(labels) = &config.week_labels {
+        let table = document
+            .and_then(|document| document.get("week_labels"))
+            .and_then(Item::as_table);
+        let years = labels.start.as_deref().map_or(YEARS, given_year);
+        if let Err(e) = in_any_year(years, |year| labels.to_labels(year, &anchors(year))) {
+            problems.push(Problem {
+                line: line(table.and_then(Table::span)),
+                message: format!("week labels: {}", e),
+            });
+        }
+    }
+
+    let weekly_tables = array_tables(document.and_then(|document| document.get("weekly")));
+    for (i, weekly) in config.weekly.iter().enumerate() {
+        let table = weekly_tables.get(i).copied();
+        let entry_line = line(table.and_then(Table::span));
+        // The years the entry falls in, so its occurrences get checked
+        let years = weekly.from.as_deref().map_or(YEARS, given_year);
+        let events = in_any_year(years, |year| {
+            weekly.to_events(year, &anchors(year), &config.categories)
+        });
+        if let Err(e) = events {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("weekly event every {}: {}", weekly.every, e),
+            });
+        }
+        if let Some(color) = &weekly.detail.color {
+            if let Some(problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    let term_tables = array_tables(document.and_then(|document| document.get("terms")));
+    for (i, term) in config.terms.iter().enumerate() {
+        let table = term_tables.get(i).copied();
+        let entry_line = line(table.and_then(Table::span));
+        let years = given_year(&term.start);
+        if let Err(e) = in_any_year(years, |year| term.to_events(year, &anchors(year))) {
+            problems.push(Problem {
+                line: entry_line,
+                message: format!("term {}: {}", term.name, e),
+            });
+        }
+        if let Some(color) = &term.color {
+            if let Some(problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    let availability_tables =
+        array_tables(document.and_then(|document| document.get("availability")));
+    for (i, block) in config.availability.iter().enumerate() {
+        if let Err(e) = block.to_block() {
+            problems.push(Problem {
+                line: line(availability_tables.get(i).and_then(|table| table.span())),
+                message: format!("busy block on {}: {}", block.days, e),
+            });
+        }
+    }
+
+    let source_tables = array_tables(document.and_then(|document| document.get("sources")));
+    for (i, source) in config.sources.iter().enumerate() {
+        if let Some(Err(e)) = source.ttl.as_deref().map(cache::parse_ttl) {
+            problems.push(Problem {
+                line: line(source_tables.get(i).and_then(|table| table.span())),
+                message: format!("source {}: {}", source.url, e),
+            });
+        }
+    }
+    let google_tables = array_tables(document.and_then(|document| document.get("google")));
+    for (i, calendar) in config.google.iter().enumerate() {
+        if let Some(Err(e)) = calendar.ttl.as_deref().map(cache::parse_ttl) {
+            problems.push(Problem {
+                line: line(google_tables.get(i).and_then(|table| table.span())),
+                message: format!("Google calendar {}: {}", calendar.calendar, e),
+            });
+        }
+    }
+
+    if let Some(holidays) = &config.holidays {
+        let problem = holidays.use_api().err().or_else(|| {
+            let ttl = holidays.ttl.as_deref()?;
+            cache::parse_ttl(ttl).err()
+        });
+        if let Some(message) = problem {
+            let item = document.and_then(|document| document.get("holidays"));
+            problems.push(Problem {
+                line: line(item.and_then(Item::span)),
+                message: format!("holidays: {}", message),
+            });
+        }
+    }
+
+    let dates_table = document
+        .and_then(|document| document.get("dates"))
+        .and_then(Item::as_table_like);
+    let mut dates: Vec<(&String, Option<usize>)> = config
+        .dates
+        .keys()
+        .map(|date_str| {
+            let key_span = dates_table
+                .and_then(|table| table.get_key_value(date_str))
+                .and_then(|(key, _)| key.span());
+            (date_str, line(key_span))
+        })
+        .collect();
+    dates.sort_by_key(|(date_str, entry_line)| (*entry_line, (*date_str).clone()));
+
+    // Keys like "2024-3-5" and "2024-03-05" name the same day
+    let mut seen = HashMap::new();
+    for (date_str, entry_line) in dates {
+        let item = dates_table.and_then(|table| table.get(date_str));
+        // `[[dates."..."]]` lists several entries for the date
+        let entry_tables = array_tables(item);
+        for (i, detail) in config.dates[date_str].iter().enumerate() {
+            let table = item
+                .and_then(Item::as_table)
+                .or_else(|| entry_tables.get(i).copied());
+            let entry_line = entry_tables
+                .get(i)
+                .and_then(|table| line(table.span()))
+                .or(entry_line);
+            // Anniversaries only fall in the years since they started
+            let years = match detail.anniversary_date() {
+                Ok(Some(since)) => since.year()..since.year() + YEARS.len() as i32,
+                _ => YEARS,
+            };
+            let event = in_any_year(years, |year| {
+                detail.to_event(date_str, Some(year), &anchors(year), &config.categories)
+            });
+            if let Err(e) = event {
+                problems.push(Problem {
+                    line: entry_line,
+                    message: format!("date {}: {}", date_str, e),
+                });
+            }
+            if let Some(color) = &detail.color {
+                if let Some(problem) = unknown_color(&palette, color, table, entry_line, &line) {
+                    problems.push(problem);
+                }
+            }
+        }
+        if let Some(key) = parse_month_day(date_str) {
+            if let Some(first) = seen.insert(key, date_str) {
+                problems.push(Problem {
+                    line: entry_line,
+                    message: format!("date {} is the same day as {}", date_str, first),
+                });
+            }
+        }
+    }
+
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+/// A parse error whose message ends in its location, which is reported as
+/// the problem's line instead
+/// Years taking in every arrangement of weekdays and leap days, so an entry
+/// that resolves in none of them never will
+const YEARS: Range<i32> = 2000..2028;
+
+/// The year `date_str` names, or else every shape of year
+fn given_year(date_str: &str) -> Range<i32> {
+    match parse_month_day(date_str).and_then(|(year, _, _)| year) {
+        Some(year) => year..year + 1,
+        None => YEARS,
+    }
+}
+
+/// Fine if `check` passes in any of `years`, else the first year's error
+fn in_any_year<T, E>(
+    years: Range<i32>,
+    mut check: impl FnMut(i32) -> Result<T, E>,
+) -> Result<(), E> {
+    let mut first = None;
+    for year in years {
+        match check(year) {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                first.get_or_insert(e);
+            }
+        }
+    }
+    first.map_or(Ok(()), Err)
+}
+
+fn located_problem(error: &impl fmt::Display, line: Option<usize>) -> Problem {
+    let message = error.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+    Problem {
+        line,
+        message: one_line(message),
+    }
+}
+
+/// Parser messages can span lines, e.g. "invalid table header\nduplicate key"
+fn one_line(message: &str) -> String {
+    message.trim().lines().collect::<Vec<_>>().join(": ")
+}
+
+/// The tables of an array like `[[ranges]]`; inline arrays have no spans
+/// to report
+fn array_tables(item: Option<&Item>) -> Vec<&Table> {
+    match item.and_then(Item::as_array_of_tables) {
+        Some(tables) => tables.iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+fn unknown_color(
+    palette: &ColorPalette,
+    color: &str,
+    table: Option<&Table>,
+    entry_line: Option<usize>,
+    line: &impl Fn(Option<Range<usize>>) -> Option<usize>,
+) -> Option<Problem> {
+    if palette.color_value(color).is_some() {
+        return None;
+    }
+    let color_line = table
+        .and_then(|table| table.get("color"))
+        .and_then(|item| line(item.span()));
+    Some(Problem {
+        line: color_line.or(entry_line),
+        message: format!("unknown color '{}'", color),
+    })
+}
@@ -0,0 +1,387 @@
+//! Reading vdir collections, the directories of one `.ics` file per event
+//! that vdirsyncer syncs CalDAV calendars into and khal reads, so synced
+//! events show up next to the config's own.
+//!
+//! All-day and timed events become dates, or ranges when they span several
+//! days. Yearly events repeat as `MM-DD` dates and weekly ones become
+//! `[[weekly]]` entries; other repeating events only show their first
+//! occurrence. The collection's `color` file, as vdirsyncer writes it, colors
+//! its events.
+
+use crate::config::{CalendarConfig, RawDateDetail, RawDateRange, RawWeekly};
+use crate::named_colors;
+use crate::rendering::ColorPalette;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Color of ranges from a collection without a `color` file
+pub const DEFAULT_COLOR: &str = "light_blue";
+
+/// An event vdirsyncer wrote that can't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub path: PathBuf,
+    /// 1-based line of the property, after unfolding
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: line {}: {}",
+            self.path.display(),
+            self.line,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    Yearly,
+    /// Weekly on the given weekdays, until the last day when it ends
+    Weekly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VEvent {
+    pub summary: String,
+    pub start: NaiveDate,
+    /// Last day, included
+    pub end: NaiveDate,
+    /// Overrides the collection's color
+    pub color: Option<String>,
+    repeat: Option<Repeat>,
+    /// Weekdays of a weekly event, as in "mon,thu"
+    weekdays: Option<String>,
+    until: Option<NaiveDate>,
+}
+
+impl VEvent {
+    /// A one-off event from `start` to `end`
+    pub fn new(summary: String, start: NaiveDate, end: NaiveDate, color: Option<String>) -> Self {
+        Self {
+            summary,
+            start,
+            end: end.max(start),
+            color,
+            repeat: None,
+            weekdays: None,
+            until: None,
+        }
+    }
+}
+
+/// The events of one vdir collection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Collection {
+    pub events: Vec<VEvent>,
+    /// Nearest color to the collection's `color` file
+    pub color: Option<String>,
+    /// Tags every event gets, like
+    /// [`TIME_OFF_TAG`](crate::availability::TIME_OFF_TAG) on public holidays
+    pub tags: Vec<String>,
+}
+
+impl Collection {
+    /// Read every `.ics` file in `dir`, in name order
+    pub fn read(dir: &Path) -> Result<Self, ParseError> {
+        let io_error = |path: &Path, e: std::io::Error| ParseError {
+            path: path.to_path_buf(),
+            line: 0,
+            message: e.to_string(),
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| io_error(dir, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ics"))
+            .collect();
+        files.sort();
+
+        let mut events = Vec::new();
+        for path in files {
+            let contents = fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+            events.extend(parse(&contents).map_err(|(line, message)| ParseError {
+                path: path.clone(),
+                line,
+                message,
+            })?);
+        }
+        let color = fs::read_to_string(dir.join("color"))
+            .ok()
+            .and_then(|color| named_colors::parse_hex(color.trim()))
+            .map(|rgb| ColorPalette::nearest_color_name(rgb).to_string());
+        Ok(Self {
+            events,
+            color,
+            tags: Vec::new(),
+        })
+    }
+
+    /// Add the events to `config`, alongside any on the same dates
+    pub fn add_to(&self, config: &mut CalendarConfig) {
+        let iso = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+        for event in &self.events {
+            let color = event.color.as_ref().or(self.color.as_ref());
+            let detail = RawDateDetail {
+                description: event.summary.clone(),
+                color: color.cloned(),
+                tags: self.tags.clone(),
+                ..RawDateDetail::default()
+            };
+            match event.repeat {
+                Some(Repeat::Weekly) if event.start == event.end => {
+                    config.weekly.push(RawWeekly {
+                        every: event
+                            .weekdays
+                            .clone()
+                            .unwrap_or_else(|| event.start.weekday().to_string().to_lowercase()),
+                        from: Some(iso(event.start)),
+                        until: event.until.map(iso),
+                        detail,
+                    });
+                }
+                Some(Repeat::Yearly) if event.start == event.end => {
+                    let key = event.start.format("%m-%d").to_string();
+                    config.dates.entry(key).or_default().push(detail);
+                }
+                _ if event.start == event.end => {
+                    config
+                        .dates
+                        .entry(iso(event.start))
+                        .or_default()
+                        .push(detail);
+                }
+                _ => config.ranges.push(RawDateRange {
+                    start: iso(event.start),
+                    end: iso(event.end),
+                    color: Some(color.map_or(DEFAULT_COLOR, |color| color).to_string()),
+                    tag: None,
+                    description: Some(event.summary.clone()),
+                    kind: None,
+                    timezone: None,
+                    id: None,
+                    tags: self.tags.clone(),
+                    owner: None,
+                    cost: None,
+                    priority: None,
+                    layer: None,
+                    status: None,
+                    gradient: None,
+                    icon: None,
+                }),
+            }
+        }
+    }
+}
+
+/// The `VEVENT`s of an iCalendar file, skipping cancelled events and the
+/// changed occurrences of repeating ones. Errors carry their line.
+pub fn parse(contents: &str) -> Result<Vec<VEvent>, (usize, String)> {
+    // Lines starting with a space or tab continue the one before
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => last.push_str(rest),
+            _ => lines.push((i + 1, line.to_string())),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Vec<(usize, String)>> = None;
+    for (number, line) in lines {
+        match line.trim_end() {
+            "BEGIN:VEVENT" => current = Some(Vec::new()),
+            "END:VEVENT" => {
+                if let Some(event) = current.take().map(|props| event(&props)).transpose()? {
+                    events.extend(event);
+                }
+            }
+            _ => {
+                if let Some(props) = current.as_mut() {
+                    props.push((number, line));
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Parameters of a property, as in `TZID=Europe/Berlin`
+type Params<'a> = Vec<(&'a str, &'a str)>;
+
+/// Split "NAME;PARAM=VALUE:value" into the name, its parameters and value
+fn property(line: &str) -> Option<(String, Params<'_>, &str)> {
+    let (head, value) = line.split_once(':')?;
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_uppercase();
+    let params = parts.filter_map(|param| param.split_once('=')).collect();
+    Some((name, params, value))
+}
+
+/// A date or date-time value, and whether it had a time
+fn parse_value(params: &[(&str, &str)], value: &str) -> Result<(NaiveDate, bool), String> {
+    let invalid = || format!("invalid date '{}'", value);
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        return Ok((date, false));
+    }
+    let local = value.trim_end_matches('Z');
+    let time = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    let tzid = params
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("TZID"));
+    // UTC times are shown on the local day; times in a named zone on the
+    // day there
+    let date = if value.ends_with('Z') {
+        Utc.from_utc_datetime(&time)
+            .with_timezone(&Local)
+            .date_naive()
+    } else if let Some(tz) = tzid.and_then(|(_, tz)| tz.parse::<Tz>().ok()) {
+        tz.from_local_datetime(&time)
+            .earliest()
+            .map_or(time.date(), |time| time.date_naive())
+    } else {
+        time.date()
+    };
+    Ok((date, true))
+}
+
+fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => output.push(' '),
+            Some(c) => output.push(c),
+            None => {}
+        }
+    }
+    output
+}
+
+fn event(props: &[(usize, String)]) -> Result<Option<VEvent>, (usize, String)> {
+    let mut summary = String::new();
+    let mut start: Option<(NaiveDate, bool)> = None;
+    let mut end: Option<(NaiveDate, bool)> = None;
+    let mut days: Option<i64> = None;
+    let mut rule = None;
+    for (number, line) in props {
+        let Some((name, params, value)) = property(line) else {
+            continue;
+        };
+        let at_line = |message| (*number, message);
+        match name.as_str() {
+            "SUMMARY" => summary = unescape(value),
+            "DTSTART" => start = Some(parse_value(&params, value).map_err(at_line)?),
+            "DTEND" => end = Some(parse_value(&params, value).map_err(at_line)?),
+            "DURATION" => days = duration_days(value),
+            "RRULE" => rule = Some(value.to_string()),
+            "STATUS" if value.eq_ignore_ascii_case("CANCELLED") => return Ok(None),
+            "RECURRENCE-ID" => return Ok(None),
+            _ => {}
+        }
+    }
+    let Some((start, timed)) = start else {
+        let line = props.first().map_or(0, |(number, _)| *number);
+        return Err((line, "event without DTSTART".to_string()));
+    };
+    // All-day ends are exclusive; timed ones fall on the day they end
+    let end = match (end, days) {
+        (Some((end, false)), _) => end.pred_opt().unwrap_or(end),
+        (Some((end, true)), _) => end,
+        (None, Some(days)) if !timed => start + chrono::Duration::days((days - 1).max(0)),
+        (None, _) => start,
+    }
+    .max(start);
+
+    let mut event = VEvent::new(summary, start, end, None);
+    if let Some(rule) = rule {
+        repeat(&mut event, &rule);
+    }
+    Ok(Some(event))
+}
+
+/// Whole days of a duration such as "P1D" or "P2W", rounded up
+fn duration_days(value: &str) -> Option<i64> {
+    let value = value.strip_prefix('P')?;
+    let (date, time) = value.split_once('T').unwrap_or((value, ""));
+    let number = |unit: char| -> Option<i64> {
+        let end = date.find(unit)?;
+        let start = date[..end]
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        date[start..end].parse().ok()
+    };
+    let days = number('W').unwrap_or(0) * 7 + number('D').unwrap_or(0);
+    Some(days + i64::from(!time.is_empty()))
+}
+
+/// Apply the supported part of an RRULE: yearly without an end, and weekly
+/// with an optional end. Anything else keeps just the first occurrence.
+fn repeat(event: &mut VEvent, rule: &str) {
+    let parts: Vec<(&str, &str)> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .collect();
+    let get = |key: &str| {
+        parts
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| *value)
+    };
+    if get("INTERVAL").is_some_and(|interval| interval != "1") {
+        return;
+    }
+    match get("FREQ") {
+        Some("YEARLY") if get("UNTIL").is_none() && get("COUNT").is_none() => {
+            event.repeat = Some(Repeat::Yearly);
+        }
+        Some("WEEKLY") => {
+            let weekdays: Option<Vec<&str>> = get("BYDAY").map(|days| {
+                days.split(',')
+                    .filter_map(|day| match day.to_uppercase().as_str() {
+                        "MO" => Some("mon"),
+                        "TU" => Some("tue"),
+                        "WE" => Some("wed"),
+                        "TH" => Some("thu"),
+                        "FR" => Some("fri"),
+                        "SA" => Some("sat"),
+                        "SU" => Some("sun"),
+                        _ => None,
+                    })
+                    .collect()
+            });
+            let until = match (get("UNTIL"), get("COUNT")) {
+                (Some(until), _) => match parse_value(&[], until) {
+                    Ok((until, _)) => Some(until),
+                    Err(_) => return,
+                },
+                // A count only fixes the end when there's one a week
+                (None, Some(count)) if weekdays.as_ref().is_none_or(|days| days.len() <= 1) => {
+                    match count.parse::<i64>() {
+                        Ok(count) => Some(event.start + chrono::Duration::weeks(count - 1)),
+                        Err(_) => return,
+                    }
+                }
+                (None, Some(_)) => return,
+                (None, None) => None,
+            };
+            event.repeat = Some(Repeat::Weekly);
+            event.weekdays = weekdays.map(|days| days.join(","));
+            event.until = until;
+        }
+        _ => {}
+    }
+}
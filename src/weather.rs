@@ -0,0 +1,110 @@
+//! Short-range forecasts for `--weather`, from Open-Meteo's daily forecast,
+//! shown as a glyph before each of the coming days.
+
+use chrono::NaiveDate;
+
+/// Open-Meteo's daily forecast, with `{latitude}`, `{longitude}` and `{days}`
+/// placeholders
+pub const API_URL: &str = "https://api.open-meteo.com/v1/forecast?latitude={latitude}&longitude={longitude}&daily=weather_code,temperature_2m_max,temperature_2m_min,precipitation_probability_max&timezone=auto&forecast_days={days}";
+
+/// Days forecast when the config doesn't say
+pub const DEFAULT_DAYS: u32 = 7;
+
+/// Most days Open-Meteo forecasts
+pub const MAX_DAYS: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl Weather {
+    /// The weather of a WMO weather interpretation code, as Open-Meteo gives
+    /// them
+    pub fn from_code(code: u64) -> Self {
+        match code {
+            0 | 1 => Self::Clear,
+            45 | 48 => Self::Fog,
+            51..=67 | 80..=82 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            95..=99 => Self::Thunderstorm,
+            _ => Self::Cloudy,
+        }
+    }
+
+    /// Single-width marker shown next to the day
+    pub fn glyph(&self) -> char {
+        match self {
+            Self::Clear => '☀',
+            Self::Cloudy => '☁',
+            Self::Fog => '≡',
+            Self::Rain => '☂',
+            Self::Snow => '❄',
+            Self::Thunderstorm => '↯',
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Clear => "Clear",
+            Self::Cloudy => "Cloudy",
+            Self::Fog => "Fog",
+            Self::Rain => "Rain",
+            Self::Snow => "Snow",
+            Self::Thunderstorm => "Thunderstorm",
+        }
+    }
+}
+
+/// One day of a forecast
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayForecast {
+    pub date: NaiveDate,
+    pub weather: Weather,
+    /// Highest and lowest temperature, in °C
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    /// Chance of precipitation, in percent
+    pub precipitation: Option<u8>,
+}
+
+/// The days of an Open-Meteo daily forecast response, in date order
+pub fn from_response(response: &str) -> Result<Vec<DayForecast>, String> {
+    let response: serde_json::Value = serde_json::from_str(response)
+        .map_err(|e| format!("unexpected response from the weather API: {}", e))?;
+    if let Some(reason) = response["reason"].as_str() {
+        return Err(reason.to_string());
+    }
+    let daily = &response["daily"];
+    let dates = daily["time"]
+        .as_array()
+        .ok_or("unexpected response from the weather API: no daily forecast")?;
+    let mut days = Vec::new();
+    for (i, date) in dates.iter().enumerate() {
+        let Some(date) = date
+            .as_str()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        let Some(code) = daily["weather_code"][i].as_u64() else {
+            continue;
+        };
+        days.push(DayForecast {
+            date,
+            weather: Weather::from_code(code),
+            high: daily["temperature_2m_max"][i].as_f64(),
+            low: daily["temperature_2m_min"][i].as_f64(),
+            precipitation: daily["precipitation_probability_max"][i]
+                .as_u64()
+                .map(|chance| chance.min(100) as u8),
+        });
+    }
+    days.sort_by_key(|day| day.date);
+    Ok(days)
+}
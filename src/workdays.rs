@@ -0,0 +1,50 @@
+//! Business-day arithmetic: working days are weekdays, and weekend days
+//! listed in `working_weekends`, that are neither company non-working days
+//! nor covered by a date or range tagged
+//! [`TIME_OFF_TAG`](crate::availability::TIME_OFF_TAG), such as a public
+//! holiday or vacation.
+
+use crate::models::Calendar;
+use chrono::{Days, NaiveDate};
+
+impl Calendar {
+    /// Whether `date` is neither a weekend day, a non-working day nor time
+    /// off
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && !self.is_non_working(date) && !self.is_time_off(date)
+    }
+
+    /// Working days from `start` up to but not including `end`; negative
+    /// when `end` comes first
+    pub fn working_days_between(&self, start: NaiveDate, end: NaiveDate) -> i64 {
+        let (first, last, sign) = if start <= end {
+            (start, end, 1)
+        } else {
+            (end, start, -1)
+        };
+        let count = first
+            .iter_days()
+            .take_while(|date| *date < last)
+            .filter(|date| self.is_working_day(*date))
+            .count() as i64;
+        sign * count
+    }
+
+    /// The date `n` working days after `date`, or before it when `n` is
+    /// negative; `date` itself when `n` is 0
+    pub fn add_working_days(&self, date: NaiveDate, n: i64) -> NaiveDate {
+        let mut date = date;
+        let mut remaining = n.unsigned_abs();
+        while remaining > 0 {
+            date = if n > 0 {
+                date + Days::new(1)
+            } else {
+                date - Days::new(1)
+            };
+            if self.is_working_day(date) {
+                remaining -= 1;
+            }
+        }
+        date
+    }
+}
@@ -1,8 +1,49 @@
+use anstyle::{Effects, RgbColor};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use compact_calendar_cli::availability::DayAvailability;
+use compact_calendar_cli::cache::{self, Cache, CachePolicy};
+use compact_calendar_cli::config::{CalendarConfig, ConfigFormat};
+use compact_calendar_cli::datasets::{self, Datasets};
+use compact_calendar_cli::diff::{ConfigDiff, Difference};
+use compact_calendar_cli::dst;
+use compact_calendar_cli::editing;
+use compact_calendar_cli::export::{self, ExportFormat};
+use compact_calendar_cli::habits::HabitLog;
+use compact_calendar_cli::heatmap::Heatmap;
+use compact_calendar_cli::holidays::Country;
+use compact_calendar_cli::ics::IcsExport;
+use compact_calendar_cli::listing::EventList;
+use compact_calendar_cli::locale::Locale;
 use compact_calendar_cli::models::{
-    CalendarOptions, ColorMode, MonthFilter, PastDateDisplay, WeekStart, WeekendDisplay,
+    parse_icon, AnnotationOverflow, Calendar, CalendarOptions, CellWidth, ColorMode, ContrastMode,
+    DayNumbering, Direction, Event, EventKind, Gradient, Highlight, Layout, MonthFilter,
+    OverlapDisplay, PastDateDisplay, PlainMarker, Status, TodayMarker, WeekColumn, WeekStart,
+    WeekendDisplay,
 };
-use compact_calendar_cli::rendering::CalendarRenderer;
-use std::path::PathBuf;
+use compact_calendar_cli::moon::{self, MoonPhase};
+use compact_calendar_cli::named_colors;
+use compact_calendar_cli::pager::{self, Paging};
+use compact_calendar_cli::paths;
+use compact_calendar_cli::planning::Plan;
+use compact_calendar_cli::profile::Profile;
+use compact_calendar_cli::remind;
+use compact_calendar_cli::rendering::{
+    self as rendering, Background, CalendarRenderer, ColorPalette, FADE_STEPS, GRADIENT_STEPS,
+    HIGH_CONTRAST_RATIO, MAX_FADE_STEP,
+};
+use compact_calendar_cli::secondary::SecondaryCalendar;
+use compact_calendar_cli::sources;
+use compact_calendar_cli::summary::{Summary, SummaryCategory};
+use compact_calendar_cli::sun;
+use compact_calendar_cli::testing::{assert_eq_ignoring_ansi, strip_ansi};
+use compact_calendar_cli::text_width;
+use compact_calendar_cli::theme::Theme;
+use compact_calendar_cli::upcoming::Upcoming;
+use compact_calendar_cli::validation;
+use compact_calendar_cli::vdir;
+use compact_calendar_cli::weather::Weather;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 fn create_calendar_from_config(year: i32, config_path: &str) -> String {
     create_calendar_from_config_with_filter(year, config_path, MonthFilter::All)
@@ -13,14 +54,31 @@ fn create_calendar_from_config_with_filter(
     config_path: &str,
     month_filter: MonthFilter,
 ) -> String {
-    let config = compact_calendar_cli::load_config(&PathBuf::from(config_path));
-    let options = CalendarOptions {
-        week_start: WeekStart::Monday,
-        weekend_display: WeekendDisplay::Normal,
-        color_mode: ColorMode::Normal,
-        past_date_display: PastDateDisplay::Normal,
-        month_filter,
-    };
+    let config = compact_calendar_cli::load_config(&PathBuf::from(config_path)).unwrap();
+    let options = CalendarOptions::builder()
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(month_filter)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+
+    let renderer = CalendarRenderer::new(&calendar);
+    renderer.render_to_string()
+}
+
+fn create_calendar_with_today(
+    year: i32,
+    config_path: &str,
+    month_filter: MonthFilter,
+    today: NaiveDate,
+) -> String {
+    let config = compact_calendar_cli::load_config(&PathBuf::from(config_path)).unwrap();
+    let options = CalendarOptions::builder()
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(month_filter)
+        .today(today)
+        .build();
     let calendar = compact_calendar_cli::build_calendar(year, options, config);
 
     let renderer = CalendarRenderer::new(&calendar);
@@ -65,14 +123,13 @@ fn test_empty_2025() {
 
 #[test]
 fn test_sunday_start_2024() {
-    let config = compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"));
-    let options = CalendarOptions {
-        week_start: WeekStart::Sunday,
-        weekend_display: WeekendDisplay::Normal,
-        color_mode: ColorMode::Normal,
-        past_date_display: PastDateDisplay::Normal,
-        month_filter: MonthFilter::All,
-    };
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .week_start(WeekStart::Sunday)
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .build();
     let calendar = compact_calendar_cli::build_calendar(2024, options, config);
 
     let renderer = CalendarRenderer::new(&calendar);
@@ -125,10 +182,11 @@ fn test_single_month_february_2024_leap_year() {
 #[test]
 fn test_current_with_following_3_months_2026() {
     // This simulates January + 3 following months = Jan, Feb, Mar, Apr
-    let output = create_calendar_from_config_with_filter(
+    let output = create_calendar_with_today(
         2026,
         "tests/fixtures/empty.toml",
         MonthFilter::CurrentWithFollowing(3),
+        NaiveDate::from_ymd_opt(2026, 1, 15).unwrap(),
     );
     insta::assert_snapshot!(output);
 }
@@ -146,10 +204,3627 @@ fn test_single_month_with_events_march_2024() {
 #[test]
 fn test_three_months_with_quarters_2024() {
     // Test Q1: Jan, Feb, Mar
-    let output = create_calendar_from_config_with_filter(
+    let output = create_calendar_with_today(
         2024,
         "tests/fixtures/quarters.toml",
         MonthFilter::CurrentWithFollowing(2),
+        NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+    );
+    insta::assert_snapshot!(output);
+}
+
+// Cell width tests
+
+fn create_calendar_with_cell_width(year: i32, config_path: &str, cell_width: CellWidth) -> String {
+    let config = compact_calendar_cli::load_config(&PathBuf::from(config_path)).unwrap();
+    let options = CalendarOptions::builder()
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(3))
+        .cell_width(cell_width)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(year, options, config);
+
+    let renderer = CalendarRenderer::new(&calendar);
+    renderer.render_to_string()
+}
+
+#[test]
+fn test_compact_march_2024() {
+    let output =
+        create_calendar_with_cell_width(2024, "tests/fixtures/simple.toml", CellWidth::Compact);
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_wide_march_2024() {
+    let output =
+        create_calendar_with_cell_width(2024, "tests/fixtures/simple.toml", CellWidth::Wide);
+    insta::assert_snapshot!(output);
+}
+
+// Config serialization tests
+
+#[test]
+fn test_commented_toml_simple() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let output = config.to_commented_toml();
+
+    let reparsed: compact_calendar_cli::config::CalendarConfig = toml::from_str(&output).unwrap();
+    assert_eq!(
+        reparsed.parse_dates_for_year(2024).len(),
+        config.parse_dates_for_year(2024).len()
+    );
+    assert_eq!(
+        reparsed.parse_ranges_for_year(2024).len(),
+        config.parse_ranges_for_year(2024).len()
+    );
+
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_import_vdir_collection_as_commented_toml() {
+    let path = PathBuf::from("tests/fixtures/vdir/work");
+    let config = compact_calendar_cli::import_config(&path).unwrap();
+    let output = config.to_commented_toml();
+    assert!(output.contains("# March\n[[ranges]]"));
+
+    let single = compact_calendar_cli::import_config(&path.join("offsite.ics")).unwrap();
+    assert_eq!(single.ranges.len(), 1);
+    let yaml =
+        compact_calendar_cli::import_config(&PathBuf::from("tests/fixtures/simple.yaml")).unwrap();
+    assert_eq!(
+        yaml.to_commented_toml(),
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.yaml"))
+            .unwrap()
+            .to_commented_toml()
+    );
+    insta::assert_snapshot!(output);
+}
+
+// Error tests
+
+#[test]
+fn test_error_config_not_found() {
+    let err = compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/missing.toml"))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        compact_calendar_cli::Error::ConfigNotFound(_)
+    ));
+    insta::assert_snapshot!(err.to_string());
+}
+
+#[test]
+fn test_error_invalid_toml() {
+    let err = compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/invalid.toml"))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        compact_calendar_cli::Error::Parse {
+            location: Some((2, 1)),
+            ..
+        }
+    ));
+    insta::assert_snapshot!(err.to_string());
+}
+
+#[test]
+fn test_json_config_matches_toml() {
+    let list = |config| {
+        let calendar =
+            compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+        EventList::new(&calendar).render_to_string()
+    };
+    let toml = compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"));
+    let json = compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.json"));
+    assert_eq!(list(json.unwrap()), list(toml.unwrap()));
+
+    // An explicit format wins over the extension
+    let err = compact_calendar_cli::load_config_as(
+        &PathBuf::from("tests/fixtures/simple.toml"),
+        ConfigFormat::Json,
+    )
+    .unwrap_err();
+    assert!(matches!(err, compact_calendar_cli::Error::ParseJson { .. }));
+
+    let problems = validation::validate_as(
+        "{\n  \"ranges\": [\n    {\"start\": \"2024-01-01\"}\n  ]\n}\n",
+        ConfigFormat::Json,
+    );
+    assert_eq!(problems[0].to_string(), "line 3: missing field `end`");
+}
+
+#[test]
+fn test_yaml_config_matches_toml() {
+    let load = |path: &str| {
+        let config = compact_calendar_cli::load_config(&PathBuf::from(path)).unwrap();
+        let calendar =
+            compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+        EventList::new(&calendar).render_to_string()
+    };
+    assert_eq!(
+        load("tests/fixtures/simple.yaml"),
+        load("tests/fixtures/simple.toml")
+    );
+
+    let path = PathBuf::from("tests/fixtures/invalid.yaml");
+    let err = compact_calendar_cli::load_config(&path).unwrap_err();
+    assert!(matches!(err, compact_calendar_cli::Error::ParseYaml { .. }));
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let problems = validation::validate_as(&contents, ConfigFormat::from_path(&path));
+    assert_eq!(
+        problems[0].to_string(),
+        "line 2: ranges[0]: missing field `end`"
+    );
+}
+
+#[test]
+fn test_remind_file_translates_into_entries() {
+    let path = PathBuf::from("tests/fixtures/reminders.rem");
+    let config = compact_calendar_cli::load_config(&path).unwrap();
+    assert_eq!(config.non_working, ["12-26", "2025-08-18..2025-08-19"]);
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    insta::assert_snapshot!(strip_ansi(&EventList::new(&calendar).render_to_string()));
+
+    let contents = "REM 25 Dec MSG Ok\nREM Mon *2 MSG Odd\nREM 2025-03-14 MSG Taxes\n";
+    let problems = validation::validate_as(contents, ConfigFormat::Remind);
+    assert_eq!(problems[0].to_string(), "line 2: unsupported repeat '*2'");
+    // The unsupported line is skipped, and the rest still read
+    let (config, skipped) = remind::parse(contents);
+    assert_eq!(skipped.len(), 1);
+    let mut dates: Vec<&String> = config.dates.keys().collect();
+    dates.sort();
+    assert_eq!(dates, ["12-25", "2025-03-14"]);
+
+    // A delta past the calendar's range is skipped too, not a panic
+    let (config, skipped) = remind::parse(
+        "REM 2025-01-01 --4000000000 MSG Far\nREM Mon 1 Jun 2025 --4000000000 MSG Farther\n",
+    );
+    assert!(config.dates.is_empty());
+    let lines: Vec<usize> = skipped.iter().map(|skipped| skipped.line).collect();
+    assert_eq!(lines, [1, 2]);
+}
+
+#[test]
+fn test_vdir_collections_add_synced_events() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/vdir.toml")).unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    insta::assert_snapshot!(strip_ansi(&EventList::new(&calendar).render_to_string()));
+
+    let (line, message) = vdir::parse("BEGIN:VEVENT\nSUMMARY:Lunch\nEND:VEVENT\n").unwrap_err();
+    assert_eq!((line, message.as_str()), (2, "event without DTSTART"));
+}
+
+#[test]
+fn test_caldav_source_is_fetched_and_cached() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("caldav-source");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let served = dir.join("work.xml");
+    std::fs::copy("tests/fixtures/caldav_multistatus.xml", &served).unwrap();
+    let cache = Cache::new(dir.join("cache"));
+    let source: CalendarConfig = toml::from_str(&format!(
+        "[[sources]]\nurl = \"file://{}\"\ncolor = \"purple\"\n",
+        served.canonicalize().unwrap().display()
+    ))
+    .unwrap();
+    let source = &source.sources[0];
+
+    let list = |fetched: sources::Fetched| {
+        let mut config = CalendarConfig::default();
+        fetched.collection.add_to(&mut config);
+        let calendar =
+            compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+        strip_ansi(&EventList::new(&calendar).render_to_string())
+    };
+    let fetched = sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(fetched.warning, None);
+    let events = list(fetched);
+    insta::assert_snapshot!(events);
+
+    // The cached copy is used while fresh, and while the server is down
+    std::fs::remove_file(&served).unwrap();
+    let cached = sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(cached.warning, None);
+    assert_eq!(list(cached), events);
+    let stale = sources::fetch_with_cache(source, &cache, CachePolicy::Refresh).unwrap();
+    assert!(stale
+        .warning
+        .as_deref()
+        .unwrap()
+        .ends_with("using the cached copy"));
+    assert_eq!(list(stale), events);
+    let uncached = Cache::new(dir.join("empty"));
+    assert!(sources::fetch_with_cache(source, &uncached, CachePolicy::Fresh).is_err());
+}
+
+#[test]
+fn test_ics_subscription_is_fetched_and_cached() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ics-subscription");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let served = dir.join("basic.ics");
+    std::fs::copy("tests/fixtures/vdir/work/offsite.ics", &served).unwrap();
+    let config: CalendarConfig = toml::from_str(&format!(
+        "[[sources]]\nurl = \"file://{}\"\n",
+        served.canonicalize().unwrap().display()
+    ))
+    .unwrap();
+    let source = &config.sources[0];
+    assert!(sources::is_subscription(source));
+
+    let cache = Cache::new(dir.join("cache"));
+    let fetched = sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    let events = fetched.collection.events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].summary, "Team offsite, Lisbon");
+    std::fs::remove_file(&served).unwrap();
+    let cached = sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(cached.collection.events, events);
+}
+
+#[test]
+fn test_update_data_installs_verified_datasets() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("update-data");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dataset = r#"[{"date": "2031-01-01", "localName": "New Year's Day", "global": true},
+        {"date": "2031-07-04", "localName": "Independence Day", "global": true}]"#;
+    std::fs::write(dir.join("us.json"), dataset).unwrap();
+    let manifest = format!(
+        r#"{{"holidays": [
+            {{"country": "us", "url": "us.json", "sha256": "{}"}},
+            {{"country": "gb", "url": "us.json", "sha256": "{}"}}
+        ]}}"#,
+        datasets::sha256_hex(dataset.as_bytes()),
+        datasets::sha256_hex(b"something else")
+    );
+    std::fs::write(dir.join("manifest.json"), &manifest).unwrap();
+
+    let data = Datasets::new(dir.join("data"));
+    let url = format!(
+        "file://{}/manifest.json",
+        dir.canonicalize().unwrap().display()
+    );
+    // A manifest other than the one expected is refused outright
+    let refused = data.update(&url, &datasets::sha256_hex(b"another manifest"));
+    assert!(refused.is_err_and(|e| e.contains("doesn't match the expected")));
+    assert!(!data.path(Country::Us).exists());
+
+    let sha256 = datasets::sha256_hex(manifest.as_bytes());
+    let results = data.update(&url, &sha256).unwrap();
+    assert_eq!(
+        results[0],
+        Ok("holidays for us: 2 days from 2031 to 2031".to_string())
+    );
+    assert!(results[1]
+        .as_ref()
+        .is_err_and(|e| e.contains("doesn't match the expected")));
+    assert!(!data.path(Country::Gb).exists());
+
+    // The dataset wins for the years it covers, the rules for the others
+    let names = |year| {
+        let days = datasets::holidays_or_builtin(Some(&data), Country::Us, year);
+        days.into_iter().map(|(_, name)| name).collect::<Vec<_>>()
+    };
+    assert_eq!(names(2031), ["New Year's Day", "Independence Day"]);
+    assert_eq!(names(2030).len(), Country::Us.holidays(2030).len());
+}
+
+#[test]
+fn test_shipped_datasets_match_pinned_manifest() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+    let manifest = std::fs::read(data.join("manifest.json")).unwrap();
+    assert_eq!(datasets::sha256_hex(&manifest), datasets::MANIFEST_SHA256);
+
+    // Installing from the shipped manifest checks every dataset it lists
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("shipped-data");
+    let _ = std::fs::remove_dir_all(&dir);
+    let url = format!(
+        "file://{}/manifest.json",
+        data.canonicalize().unwrap().display()
+    );
+    let results = Datasets::new(&dir)
+        .update(&url, datasets::MANIFEST_SHA256)
+        .unwrap();
+    assert!(!results.is_empty());
+    for result in results {
+        assert!(result.is_ok(), "{:?}", result);
+    }
+}
+
+#[test]
+fn test_remote_config_is_cached_for_offline_use() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("remote-config");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let served = dir.join("team-calendar.yaml");
+    std::fs::copy("tests/fixtures/simple.yaml", &served).unwrap();
+    let url = format!("file://{}", served.canonicalize().unwrap().display());
+    let cache = Cache::new(dir.join("cache"));
+
+    let offline = sources::fetch_config_with_cache(&url, &cache, CachePolicy::Offline);
+    assert_eq!(
+        offline.unwrap_err(),
+        format!("{}: not cached yet, and offline", url)
+    );
+    let (cached, warning) =
+        sources::fetch_config_with_cache(&url, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(warning, None);
+    assert_eq!(ConfigFormat::from_path(&cached), ConfigFormat::Yaml);
+
+    std::fs::remove_file(&served).unwrap();
+    let (offline, warning) =
+        sources::fetch_config_with_cache(&url, &cache, CachePolicy::Offline).unwrap();
+    assert_eq!((offline.clone(), warning), (cached, None));
+    let list = |path: &Path| {
+        let config = compact_calendar_cli::load_config(&path.to_path_buf()).unwrap();
+        let calendar =
+            compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+        EventList::new(&calendar).render_to_string()
+    };
+    assert_eq!(
+        list(&offline),
+        list(Path::new("tests/fixtures/simple.yaml"))
+    );
+}
+
+#[test]
+fn test_holidays_from_api_are_cached_with_builtin_fallback() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("holiday-api");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(
+        "tests/fixtures/holidays_fr_2025.json",
+        dir.join("FR-2025.json"),
+    )
+    .unwrap();
+    let api = format!(
+        "file://{}/{{country}}-{{year}}.json",
+        dir.canonicalize().unwrap().display()
+    );
+    let config: CalendarConfig = toml::from_str(&format!(
+        "holidays = {{ country = \"fr\", url = \"{}\" }}\n",
+        api
+    ))
+    .unwrap();
+    let holidays = config.holidays.as_ref().unwrap();
+    let cache = Cache::new(dir.join("cache"));
+
+    let list = |fetched: sources::Fetched| {
+        let mut config = CalendarConfig::default();
+        fetched.collection.add_to(&mut config);
+        let calendar =
+            compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+        strip_ansi(&EventList::new(&calendar).render_to_string())
+    };
+    let fetched =
+        sources::fetch_holidays_with_cache(holidays, 2025, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(fetched.warning, None);
+    // Holidays are days off: Thursday May 8 is Victory in Europe Day
+    let mut config = CalendarConfig::default();
+    fetched.collection.add_to(&mut config);
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    assert!(!calendar.is_working_day(date(5, 8)));
+    assert_eq!(calendar.working_days_between(date(5, 5), date(5, 12)), 4);
+    let events = list(fetched);
+    insta::assert_snapshot!(events);
+
+    // Offline, the cached copy is used; without one, a country with built-in
+    // holidays gets those, and any other none
+    std::fs::remove_file(dir.join("FR-2025.json")).unwrap();
+    let offline =
+        sources::fetch_holidays_with_cache(holidays, 2025, &cache, CachePolicy::Offline).unwrap();
+    assert_eq!(list(offline), events);
+    assert!(
+        sources::fetch_holidays_with_cache(holidays, 2026, &cache, CachePolicy::Offline).is_err()
+    );
+    let germany: CalendarConfig = toml::from_str(&format!(
+        "[holidays]\ncountry = \"DE\"\nsource = \"api\"\nurl = \"{}\"\n",
+        api
+    ))
+    .unwrap();
+    let fallback = sources::fetch_holidays_with_cache(
+        germany.holidays.as_ref().unwrap(),
+        2025,
+        &cache,
+        CachePolicy::Fresh,
+    )
+    .unwrap();
+    assert!(fallback
+        .warning
+        .unwrap()
+        .ends_with("using the built-in holidays"));
+    assert_eq!(fallback.collection.events.len(), 9);
+
+    let problems = validation::validate("holidays = { country = \"FR\", source = \"builtin\" }\n");
+    assert_eq!(
+        problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["line 1: holidays: no built-in holidays for FR; use source = \"api\""]
+    );
+}
+
+#[test]
+fn test_cache_ttl_status_and_clear() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cache-status");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let served = dir.join("basic.ics");
+    std::fs::copy("tests/fixtures/vdir/work/offsite.ics", &served).unwrap();
+    let url = format!("file://{}", served.canonicalize().unwrap().display());
+    let config: CalendarConfig =
+        toml::from_str(&format!("[[sources]]\nurl = \"{}\"\nttl = \"0s\"\n", url)).unwrap();
+    let source = &config.sources[0];
+    let cache = Cache::new(dir.join("cache"));
+
+    assert_eq!(cache::parse_ttl("30m"), Ok(Duration::from_secs(30 * 60)));
+    assert_eq!(
+        cache::parse_ttl("1d"),
+        Ok(Duration::from_secs(24 * 60 * 60))
+    );
+    assert!(cache::parse_ttl("2 weeks").is_err());
+    assert!(cache::parse_ttl("999999999999999d").is_err());
+
+    sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    let config_url = format!(
+        "file://{}",
+        Path::new("tests/fixtures/simple.yaml")
+            .canonicalize()
+            .unwrap()
+            .display()
+    );
+    sources::fetch_config_with_cache(&config_url, &cache, CachePolicy::Fresh).unwrap();
+    // Google sign-ins are kept beside the cached calendars
+    let token = cache.path("google", "client-id", "token");
+    std::fs::create_dir_all(token.parent().unwrap()).unwrap();
+    std::fs::write(&token, "refresh-token").unwrap();
+
+    // A copy past its TTL is fetched again, and stands in when that fails
+    std::fs::remove_file(&served).unwrap();
+    let stale = sources::fetch_with_cache(source, &cache, CachePolicy::Fresh).unwrap();
+    assert!(stale.warning.is_some());
+
+    let entries = cache.entries();
+    let listed: Vec<(&str, &str)> = entries
+        .iter()
+        .map(|entry| (entry.kind.as_str(), entry.key.as_str()))
+        .collect();
+    assert_eq!(
+        listed,
+        [("configs", config_url.as_str()), ("sources", url.as_str())]
+    );
+    assert!(entries.iter().all(|entry| entry.size > 0));
+
+    assert_eq!(cache.clear().unwrap(), 2);
+    assert_eq!(cache.entries(), []);
+    assert!(token.exists());
+}
+
+#[cfg(feature = "google")]
+#[test]
+fn test_google_events_response() {
+    use compact_calendar_cli::google;
+    use compact_calendar_cli::vdir::Collection;
+
+    let response = std::fs::read_to_string("tests/fixtures/google_events.json").unwrap();
+    let collection = Collection {
+        events: google::events_from_response(&response).unwrap(),
+        color: Some("green".to_string()),
+        tags: Vec::new(),
+    };
+    let mut config = CalendarConfig::default();
+    collection.add_to(&mut config);
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    insta::assert_snapshot!(strip_ansi(&EventList::new(&calendar).render_to_string()));
+
+    let error = r#"{"error": {"code": 404, "message": "Not Found"}}"#;
+    assert_eq!(
+        google::events_from_response(error).unwrap_err(),
+        "Not Found"
+    );
+}
+
+// Locale tests
+
+#[test]
+fn test_locale_de_march_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let locale = Locale::De;
+    let options = CalendarOptions::builder()
+        .week_start(locale.week_start())
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(3))
+        .locale(locale)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+
+    let renderer = CalendarRenderer::new(&calendar);
+    insta::assert_snapshot!(renderer.render_to_string());
+}
+
+#[test]
+fn test_locale_en_us_starts_on_sunday() {
+    assert_eq!(Locale::parse("en_US.UTF-8"), Ok(Locale::EnUs));
+    assert_eq!(Locale::EnUs.week_start(), WeekStart::Sunday);
+    assert_eq!(Locale::parse("de-AT"), Ok(Locale::De));
+    assert!(Locale::parse("xx").is_err());
+
+    // The locale's day is only the default
+    assert_eq!(WeekStart::parse("Mon"), Ok(WeekStart::Monday));
+    assert_eq!(WeekStart::parse("sunday"), Ok(WeekStart::Sunday));
+    assert!(WeekStart::parse("sat").is_err());
+    let problems = validation::validate("[settings]\nlocale = \"en-US\"\nweek_start = \"tue\"\n");
+    assert_eq!(
+        problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["line 3: Invalid week start: 'tue'. Use mon or sun"]
+    );
+}
+
+#[test]
+fn test_locale_he_runs_right_to_left() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let locale = Locale::parse("he_IL.UTF-8").unwrap();
+    assert_eq!(locale.direction(), Direction::Rtl);
+    let options = CalendarOptions::builder()
+        .week_start(locale.week_start())
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(3))
+        .locale(locale)
+        .direction(locale.direction())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+
+    let renderer = CalendarRenderer::new(&calendar).with_colors(false);
+    let rendered = renderer.render_to_string();
+    // Watch mode's per-week rendering is mirrored the same way
+    let mut output = Vec::new();
+    renderer.layout().write_to(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), rendered);
+    assert_eq!(Direction::parse("RTL"), Ok(Direction::Rtl));
+    assert!(Direction::parse("up").is_err());
+    insta::assert_snapshot!(rendered);
+}
+
+#[test]
+fn test_locale_he_keeps_markers_in_their_cells() {
+    let render = |contrast, plain_marker| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"))
+                .unwrap();
+        let locale = Locale::He;
+        let options = CalendarOptions::builder()
+            .week_start(locale.week_start())
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .month_filter(MonthFilter::Single(2))
+            .locale(locale)
+            .direction(locale.direction())
+            .contrast(contrast)
+            .plain_marker(plain_marker)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        CalendarRenderer::new(&calendar)
+            .with_colors(false)
+            .render_to_string()
+    };
+    // Each day moves with its markers, whichever side of the number they are on
+    let high_contrast = render(ContrastMode::High, PlainMarker::None);
+    let symbols = render(ContrastMode::Normal, PlainMarker::Symbols);
+    insta::assert_snapshot!(format!("{}{}", high_contrast, symbols));
+}
+
+// High contrast tests
+
+#[test]
+fn test_high_contrast_march_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .weekend_display(WeekendDisplay::Normal)
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(3))
+        .contrast(ContrastMode::High)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+
+    let renderer = CalendarRenderer::new(&calendar);
+    insta::assert_snapshot!(renderer.render_to_string());
+}
+
+#[test]
+fn test_high_contrast_palette_meets_wcag() {
+    let names = [
+        "orange",
+        "yellow",
+        "green",
+        "blue",
+        "purple",
+        "red",
+        "cyan",
+        "gray",
+        "light_orange",
+        "light_yellow",
+        "light_green",
+        "light_blue",
+        "light_purple",
+        "light_red",
+        "light_cyan",
+    ];
+    for name in names {
+        let color = ColorPalette::get_color_value(name).unwrap().normal;
+        for (background, text) in [
+            (Background::Dark, RgbColor(0, 0, 0)),
+            (Background::Light, RgbColor(255, 255, 255)),
+        ] {
+            let adjusted = ColorPalette::high_contrast_color(color, background);
+            assert!(
+                ColorPalette::contrast_ratio(adjusted, text) >= HIGH_CONTRAST_RATIO,
+                "{} on {:?}",
+                name,
+                background
+            );
+        }
+    }
+    assert_eq!(Background::from_colorfgbg("0;15"), Some(Background::Light));
+    assert_eq!(Background::from_colorfgbg("15;0"), Some(Background::Dark));
+}
+
+// Testing helper tests
+
+#[test]
+fn test_sample_calendar_2024() {
+    let calendar = Calendar::sample(2024);
+    insta::assert_snapshot!(CalendarRenderer::new(&calendar).render_to_string());
+}
+
+#[test]
+fn test_list_sample_2024() {
+    let calendar = Calendar::sample(2024);
+    insta::assert_snapshot!(EventList::new(&calendar).render_to_string());
+}
+
+#[test]
+fn test_summary_simple_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    insta::assert_snapshot!(Summary::new(&calendar).render_to_string());
+}
+
+#[test]
+fn test_summary_by_tag_with_costs_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/budget.toml")).unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    insta::assert_snapshot!(Summary::new(&calendar)
+        .with_category(SummaryCategory::Tag)
+        .render_to_string());
+
+    // An entry's `tag` counts the same as its `tags`, and only once
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [dates."2024-05-15"]
+        description = "Flight"
+        tag = "travel"
+
+        [[ranges]]
+        start = "2024-05-20"
+        end = "2024-05-21"
+        description = "Meetup"
+        color = "blue"
+        tag = "travel"
+        tags = ["travel", "conference"]
+        "#,
+    )
+    .unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    let matrix = Summary::new(&calendar)
+        .with_category(SummaryCategory::Tag)
+        .matrix();
+    assert_eq!(
+        matrix.keys().copied().collect::<Vec<_>>(),
+        ["conference", "travel"]
+    );
+    let may = matrix["travel"][4];
+    assert_eq!((may.events, may.days), (2, 3));
+}
+
+#[test]
+fn test_changes_since_previous_config_march_2024() {
+    let calendar = |contents: &str| {
+        let config: CalendarConfig = toml::from_str(contents).unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(3))
+            .past_date_display(PastDateDisplay::Normal)
+            .build();
+        compact_calendar_cli::build_calendar(2024, options, config)
+    };
+    let before = calendar(
+        r#"
+        [[ranges]]
+        start = "2024-03-04"
+        end = "2024-03-08"
+        color = "blue"
+        description = "Sprint"
+
+        [dates."2024-03-15"]
+        description = "Demo"
+        color = "green"
+        "#,
+    );
+    let after = calendar(
+        r#"
+        [[ranges]]
+        start = "2024-03-04"
+        end = "2024-03-12"
+        color = "blue"
+        description = "Sprint"
+
+        [dates."2024-03-20"]
+        description = "Demo"
+        color = "green"
+        "#,
+    );
+
+    let changes = after.changes_since(&before);
+    let mut dates: Vec<_> = changes.dates.iter().map(|date| date.day()).collect();
+    dates.sort();
+    assert_eq!(dates, [9, 10, 11, 12, 15, 20]);
+    assert_eq!(changes.events.len(), 2);
+    assert!(after.changes_since(&after).events.is_empty());
+
+    insta::assert_snapshot!(CalendarRenderer::new(&after)
+        .with_changes(changes)
+        .render_to_string());
+}
+
+#[test]
+fn test_layout_update_matches_full_render() {
+    let calendar = |contents: &str| {
+        let config: CalendarConfig = toml::from_str(contents).unwrap();
+        let options = CalendarOptions::builder()
+            .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+            .build();
+        compact_calendar_cli::build_calendar(2024, options, config)
+    };
+    let before = calendar(
+        r#"
+        [[ranges]]
+        start = "2024-03-04"
+        end = "2024-03-08"
+        color = "blue"
+        description = "Sprint"
+
+        [dates."2024-06-14"]
+        description = "Demo"
+        "#,
+    );
+    let after = calendar(
+        r#"
+        [[ranges]]
+        start = "2024-03-04"
+        end = "2024-03-12"
+        color = "blue"
+        description = "Sprint"
+
+        [dates."2024-06-21"]
+        description = "Demo"
+        "#,
+    );
+    let rendered = |layout: &compact_calendar_cli::rendering::YearLayout| {
+        let mut output = Vec::new();
+        layout.write_to(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let mut layout = CalendarRenderer::new(&before).with_colors(true).layout();
+    let changes = after.changes_since(&before);
+    assert_eq!(changes.removed.len(), 2);
+    let renderer = CalendarRenderer::new(&after)
+        .with_colors(true)
+        .with_changes(changes.clone());
+    // Two weeks of the sprint and the weeks the demo moved between
+    assert_eq!(layout.update(&renderer, &changes), 4);
+    let mut full = Vec::new();
+    renderer.render_to(&mut full).unwrap();
+    assert_eq!(rendered(&layout), String::from_utf8(full).unwrap());
+
+    // Clearing the highlight redraws the same weeks
+    let renderer = CalendarRenderer::new(&after).with_colors(true);
+    assert_eq!(layout.update(&renderer, &changes), 4);
+    assert_eq!(rendered(&layout), rendered(&renderer.layout()));
+}
+
+#[test]
+fn test_travel_march_2024() {
+    insta::assert_snapshot!(create_calendar_from_config_with_filter(
+        2024,
+        "tests/fixtures/travel.toml",
+        MonthFilter::Single(3)
+    ));
+}
+
+#[test]
+fn test_list_approval_statuses_2024() {
+    let config = || {
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/approvals.toml")).unwrap()
+    };
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config());
+    insta::assert_snapshot!(EventList::new(&calendar).render_to_string());
+
+    // Only the requests still waiting on a manager, next to the plain plans
+    let options = CalendarOptions::builder()
+        .statuses(vec![Status::Requested])
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config());
+    let descriptions: Vec<_> = calendar
+        .events()
+        .iter()
+        .filter_map(|event| event.description.as_deref())
+        .collect();
+    assert_eq!(descriptions, ["Hiking trip", "Offsite"]);
+}
+
+#[test]
+fn test_approval_statuses_july_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/approvals.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(7))
+        .statuses(vec![Status::Requested, Status::Approved, Status::Rejected])
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(CalendarRenderer::new(&calendar).render_to_string());
+}
+
+#[test]
+fn test_categories_may_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/categories.toml"))
+            .unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(5))
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(CalendarRenderer::new(&calendar).render_to_string());
+
+    // Hidden categories are left off entirely
+    let colors: Vec<_> = calendar
+        .events()
+        .iter()
+        .map(|event| (event.category.as_deref(), event.color.as_deref()))
+        .collect();
+    assert_eq!(
+        colors,
+        [
+            (Some("vacation"), Some("green")),
+            (Some("conference"), Some("purple")),
+            (Some("conference"), Some("purple")),
+            (Some("vacation"), Some("cyan")),
+        ]
+    );
+}
+
+#[test]
+fn test_priority_orders_annotations_april_2024() {
+    let calendar = |options: CalendarOptions| {
+        let config: CalendarConfig = toml::from_str(
+            r#"
+            [[ranges]]
+            start = "2024-04-08"
+            end = "2024-04-12"
+            color = "blue"
+            description = "Sprint"
+            priority = -1
+
+            [dates."2024-04-09"]
+            description = "Standup"
+
+            [dates."2024-04-11"]
+            description = "Launch"
+            color = "red"
+            priority = 5
+            "#,
+        )
+        .unwrap();
+        compact_calendar_cli::build_calendar(2024, options, config)
+    };
+    let options = || {
+        CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(4))
+            .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+    };
+    insta::assert_snapshot!(CalendarRenderer::new(&calendar(options().build())).render_to_string());
+
+    // Low-priority events are left out entirely
+    let calendar = calendar(options().min_priority(0).build());
+    let descriptions: Vec<_> = calendar
+        .events()
+        .iter()
+        .filter_map(|event| event.description.as_deref())
+        .collect();
+    assert_eq!(descriptions, ["Standup", "Launch"]);
+}
+
+#[test]
+fn test_weekly_events_march_2024() {
+    let output = create_calendar_from_config_with_filter(
+        2024,
+        "tests/fixtures/weekly.toml",
+        MonthFilter::Single(3),
     );
     insta::assert_snapshot!(output);
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/weekly.toml")).unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(
+        reparsed.events_for_year(2024).len(),
+        config.events_for_year(2024).len()
+    );
+
+    let problems: Vec<String> = validation::validate(
+        "[[weekly]]\nevery = \"caturday\"\n\n[[weekly]]\nevery = \"fri\"\nfrom = \"2024-06-01\"\nuntil = \"2024-05-01\"\n",
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 1: weekly event every caturday: invalid weekday 'caturday'",
+            "line 4: weekly event every fri: weekly event ends before it starts",
+        ]
+    );
+}
+
+#[test]
+fn test_floating_dates_resolve_per_year() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/floating.toml")).unwrap();
+    let dates = |config: &CalendarConfig, year| {
+        let mut events = config.events_for_year(year);
+        events.sort_by_key(|event| event.start);
+        events
+            .iter()
+            .map(|event| (event.start.to_string(), event.kind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        dates(&config, 2024),
+        [
+            ("2024-05-09".to_string(), EventKind::Recurring),
+            ("2024-05-27".to_string(), EventKind::Recurring),
+            ("2024-09-02".to_string(), EventKind::Recurring),
+            ("2024-11-28".to_string(), EventKind::Recurring),
+            ("2025-12-24".to_string(), EventKind::Date),
+        ]
+    );
+    assert_eq!(
+        dates(&config, 2025),
+        [
+            ("2025-05-26".to_string(), EventKind::Recurring),
+            ("2025-05-29".to_string(), EventKind::Recurring),
+            ("2025-09-01".to_string(), EventKind::Recurring),
+            ("2025-11-27".to_string(), EventKind::Recurring),
+            ("2025-12-24".to_string(), EventKind::Date),
+        ]
+    );
+
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(dates(&reparsed, 2026), dates(&config, 2026));
+
+    let problems: Vec<String> = validation::validate(
+        "[dates.\"6th monday of may\"]\ndescription = \"Never\"\n\n[dates.x]\nnth_weekday = { n = 1, weekday = \"mon\", month = 13 }\n\n[dates.\"easter + 3x\"]\n",
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 1: date 6th monday of may: invalid date '6th monday of may'",
+            "line 4: date x: invalid month 13, expected 1 to 12",
+            "line 7: date easter + 3x: invalid date 'easter + 3x'",
+        ]
+    );
+    // Days that only some years have are fine, like a fifth Monday
+    for date in ["5th monday of march", "5th monday of february"] {
+        let contents = format!("[dates.\"{}\"]\ndescription = \"Some years\"\n", date);
+        assert_eq!(validation::validate(&contents), [], "{}", date);
+    }
+    assert!(validation::validate("non_working = [\"5th friday of march\"]\n").is_empty());
+}
+
+#[test]
+fn test_anchored_dates_move_with_their_anchor() {
+    let mut config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/anchors.toml")).unwrap();
+    let dates = |config: &CalendarConfig| {
+        let mut events = config.events_for_year(2025);
+        events.sort_by_key(|event| event.start);
+        events
+            .iter()
+            .map(|event| {
+                format!(
+                    "{} {} {}",
+                    event.start,
+                    event.end,
+                    event.description.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        dates(&config),
+        [
+            "2025-02-27 2025-02-27 Beta",
+            "2025-03-06 2025-03-06 Code freeze",
+            "2025-03-20 2025-03-20 Launch",
+            "2025-03-21 2025-03-27 Hypercare",
+        ]
+    );
+
+    // The launch slips two weeks, and everything after it follows
+    config.dates.get_mut("launch").unwrap()[0].date = Some("2025-04-03".to_string());
+    assert_eq!(
+        dates(&config),
+        [
+            "2025-03-13 2025-03-13 Beta",
+            "2025-03-20 2025-03-20 Code freeze",
+            "2025-04-03 2025-04-03 Launch",
+            "2025-04-04 2025-04-10 Hypercare",
+        ]
+    );
+
+    let problems: Vec<String> = validation::validate(
+        "[dates.a]\nid = \"a\"\ndate = \"b + 1d\"\n\n[dates.b]\nid = \"b\"\ndate = \"a - 1d\"\n\n[dates.c]\ndate = \"release - 2w\"\n",
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 1: date a: unknown or circular reference to id 'b'",
+            "line 5: date b: unknown or circular reference to id 'a'",
+            "line 9: date c: unknown or circular reference to id 'release'",
+        ]
+    );
+}
+
+#[test]
+fn test_anniversaries_count_years() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/anniversaries.toml"))
+            .unwrap();
+    let descriptions = |year| {
+        let mut events = config.events_for_year(year);
+        events.sort_by_key(|event| event.start);
+        events
+            .into_iter()
+            .map(|event| {
+                (
+                    event.start.to_string(),
+                    event.description.unwrap(),
+                    event.kind,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        descriptions(2025),
+        [
+            (
+                "2025-06-12".to_string(),
+                "Mom's birthday (35)".to_string(),
+                EventKind::Recurring
+            ),
+            (
+                "2025-09-05".to_string(),
+                "Married 10 years".to_string(),
+                EventKind::Recurring
+            ),
+        ]
+    );
+    // Nothing to celebrate before the wedding
+    assert_eq!(
+        descriptions(2014),
+        [(
+            "2014-06-12".to_string(),
+            "Mom's birthday (24)".to_string(),
+            EventKind::Recurring
+        )]
+    );
+
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.events_for_year(2014), config.events_for_year(2014));
+    assert_eq!(validation::validate(&config.to_commented_toml()), []);
+
+    // A leap day falls on February 28th in other years, or on its `leap_day`
+    let leap = |leap_day: &str| {
+        let config: CalendarConfig = toml::from_str(&format!(
+            "[dates.leap]\nanniversary = \"2000-02-29\"\ndescription = \"Birthday\"\n{}",
+            leap_day
+        ))
+        .unwrap();
+        [2024, 2025].map(|year| {
+            let event = &config.events_for_year(year)[0];
+            format!("{} {}", event.start, event.description.as_deref().unwrap())
+        })
+    };
+    assert_eq!(
+        leap(""),
+        ["2024-02-29 Birthday (24)", "2025-02-28 Birthday (25)"]
+    );
+    assert_eq!(
+        leap("leap_day = \"03-01\"\n"),
+        ["2024-02-29 Birthday (24)", "2025-03-01 Birthday (25)"]
+    );
+    let problems =
+        validation::validate("[dates.leap]\nanniversary = \"2000-02-29\"\nleap_day = \"02-30\"\n");
+    assert_eq!(
+        problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["line 1: date leap: invalid leap_day '02-30', expected \"02-28\" or \"03-01\""]
+    );
+}
+
+#[test]
+fn test_annotation_width_truncates_and_wraps() {
+    let calendar = |overflow| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/categories.toml"))
+                .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(5))
+            .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+            .annotation_width(30)
+            .annotation_overflow(overflow)
+            .build();
+        compact_calendar_cli::build_calendar(2024, options, config)
+    };
+
+    // Cut-off annotations are listed in full after the calendar
+    let truncated = calendar(AnnotationOverflow::Truncate);
+    let rendered = CalendarRenderer::new(&truncated).render_to_string();
+    insta::assert_snapshot!("annotation_width_truncate_may_2024", rendered);
+    let mut output = Vec::new();
+    CalendarRenderer::new(&truncated)
+        .with_colors(false)
+        .layout()
+        .write_to(&mut output)
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), rendered);
+
+    insta::assert_snapshot!(
+        "annotation_width_wrap_may_2024",
+        CalendarRenderer::new(&calendar(AnnotationOverflow::Wrap)).render_to_string()
+    );
+}
+
+#[test]
+fn test_legend() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/categories.toml"))
+            .unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(5))
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .legend(true)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(
+        "legend_categories_may_2024",
+        CalendarRenderer::new(&calendar).render_to_string()
+    );
+
+    // Without categories, descriptions say what a color means
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .legend(true)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let rendered = CalendarRenderer::new(&calendar).render_to_string();
+    insta::assert_snapshot!(
+        "legend_simple_2024",
+        &rendered[rendered.find("Legend:").unwrap()..]
+    );
+}
+
+#[test]
+fn test_validate_categories() {
+    let contents = r#"[categories.vacation]
+color = "mauve"
+
+[[ranges]]
+start = "2024-05-06"
+end = "2024-05-10"
+tag = "vacation"
+
+[[ranges]]
+start = "2024-06-03"
+end = "2024-06-07"
+tag = "offsite"
+"#;
+    let problems: Vec<String> = validation::validate(contents)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 2: category vacation: unknown color 'mauve'",
+            "line 9: range starting 2024-06-03: range has no color",
+        ]
+    );
+}
+
+#[test]
+fn test_ics_export_approvals_2024() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/approvals.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .statuses(vec![Status::Requested, Status::Approved, Status::Rejected])
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let ics = IcsExport::new(&calendar).render_to_string();
+    assert!(ics
+        .split_terminator("\r\n")
+        .all(|line| line.len() <= 75 && !line.contains('\n')));
+    insta::assert_snapshot!(ics.replace("\r\n", "\n"));
+}
+
+#[test]
+fn test_export_all_years_and_formats() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let years = export::parse_years("2024..2026").unwrap();
+    let formats = [ExportFormat::Text, ExportFormat::Ics];
+    let files = export::export_all(&config, &options, years, &formats);
+
+    let names: Vec<String> = files.iter().map(|file| file.file_name()).collect();
+    assert_eq!(
+        names,
+        [
+            "calendar-2024.txt",
+            "calendar-2024.ics",
+            "calendar-2025.txt",
+            "calendar-2025.ics",
+            "calendar-2026.txt",
+            "calendar-2026.ics",
+        ]
+    );
+    // Each file matches a render of its year on its own
+    for file in &files {
+        let calendar = Calendar::new(
+            file.year,
+            options.clone(),
+            config.events_for_year(file.year),
+        );
+        assert_eq!(file.contents, file.format.render(&calendar));
+    }
+
+    assert_eq!(export::parse_years("2025"), Ok(2025..=2025));
+    assert_eq!(export::parse_years("2024..=2025"), Ok(2024..=2025));
+    assert!(export::parse_years("2026..2024").is_err());
+    assert!(ExportFormat::parse("pdf").is_err());
+}
+
+#[test]
+fn test_export_only_family_events() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+[dates]
+"2024-03-05" = { description = "Birthday", color = "red", tag = "family" }
+"2024-04-10" = { description = "Review", color = "blue", tag = "work" }
+"2024-06-01" = { description = "Picnic", color = "green", tags = ["family", "outdoors"], owner = "sam" }
+"#,
+    )
+    .unwrap();
+    let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let summaries = |options: CalendarOptions| {
+        let files = export::export_all(&config, &options, 2024..=2024, &[ExportFormat::Ics]);
+        files[0]
+            .contents
+            .lines()
+            .filter_map(|line| line.strip_prefix("SUMMARY:"))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let family = CalendarOptions::builder()
+        .today(today)
+        .tags(vec!["family".to_string()])
+        .build();
+    assert_eq!(summaries(family), ["Birthday", "Picnic"]);
+    let sam = CalendarOptions::builder()
+        .today(today)
+        .owners(vec!["sam".to_string()])
+        .build();
+    assert_eq!(summaries(sam), ["Picnic"]);
+    let blue = CalendarOptions::builder()
+        .today(today)
+        .colors(vec!["blue".to_string()])
+        .build();
+    assert_eq!(summaries(blue), ["Review"]);
+    let spring = CalendarOptions::builder()
+        .today(today)
+        .from(NaiveDate::from_ymd_opt(2024, 3, 6).unwrap())
+        .to(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())
+        .build();
+    assert_eq!(summaries(spring), ["Review", "Picnic"]);
+}
+
+#[test]
+fn test_next_while_traveling() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/travel.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .today(NaiveDate::from_ymd_opt(2024, 3, 12).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let now = "2024-03-12T20:00:00Z".parse().unwrap();
+    let output = Upcoming::new(&calendar, 1).at(now).render_to_string();
+    assert_eq!(
+        output.lines().next(),
+        Some("Today: 2024-03-12 at home, 2024-03-13 in Asia/Tokyo (UTC+9)")
+    );
+}
+
+#[test]
+fn test_invalid_busy_block_is_warned_about() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("invalid-availability");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("calendar.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[availability]]
+days = "mon-fri"
+start = "09:00"
+end = "17:00"
+
+[[availability]]
+days = "sat"
+start = "14:00"
+end = "10:00"
+"#,
+    )
+    .unwrap();
+
+    let config = compact_calendar_cli::load_config(&path).unwrap();
+    assert_eq!(config.parse_availability().len(), 1);
+    assert_eq!(
+        config.warnings,
+        [format!(
+            "{:?} busy block on sat: busy block ends (10:00) before it starts (14:00); skipped",
+            path
+        )]
+    );
+}
+
+#[test]
+fn test_availability_free_runs() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/availability.toml"))
+            .unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+    assert_eq!(
+        calendar.availability(date(5, 13)),
+        DayAvailability::FreeEvening
+    );
+    assert_eq!(calendar.availability(date(5, 14)), DayAvailability::Busy);
+    assert_eq!(calendar.availability(date(5, 18)), DayAvailability::Free);
+    assert_eq!(calendar.availability(date(5, 7)), DayAvailability::Free);
+
+    assert_eq!(
+        calendar.free_runs(date(5, 1), date(5, 31), 3),
+        [(date(5, 4), date(5, 9))]
+    );
+    assert_eq!(calendar.free_runs(date(5, 13), date(5, 31), 2).len(), 2);
+}
+
+#[test]
+fn test_working_days_skip_weekends_and_time_off() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [dates."2024-12-25"]
+        description = "Christmas"
+        tags = ["off"]
+
+        [dates."2024-12-26"]
+        description = "Boxing Day"
+        tags = ["off"]
+
+        [[ranges]]
+        start = "2024-12-30"
+        end = "2024-12-31"
+        color = "green"
+        description = "Vacation"
+        tags = ["off"]
+        "#,
+    )
+    .unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+    assert!(calendar.is_working_day(date(12, 24)));
+    assert!(!calendar.is_working_day(date(12, 25)));
+    assert!(!calendar.is_working_day(date(12, 28)));
+    assert!(!calendar.is_working_day(date(12, 30)));
+
+    // Mon 12/23 up to Mon 12/30: only the 23rd, 24th and 27th
+    assert_eq!(calendar.working_days_between(date(12, 23), date(12, 30)), 3);
+    assert_eq!(
+        calendar.working_days_between(date(12, 30), date(12, 23)),
+        -3
+    );
+    assert_eq!(calendar.working_days_between(date(12, 2), date(12, 2)), 0);
+
+    assert_eq!(calendar.add_working_days(date(12, 24), 1), date(12, 27));
+    assert_eq!(
+        calendar.add_working_days(date(12, 27), 1),
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+    );
+    assert_eq!(calendar.add_working_days(date(12, 27), -2), date(12, 23));
+    assert_eq!(calendar.add_working_days(date(12, 28), 0), date(12, 28));
+    assert_eq!(
+        calendar.working_days_between(date(12, 2), calendar.add_working_days(date(12, 2), 10)),
+        10
+    );
+}
+
+#[test]
+fn test_conflicts_between_colored_events() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [[ranges]]
+        start = "2024-07-01"
+        end = "2024-07-14"
+        color = "blue"
+        description = "Vacation"
+
+        [[ranges]]
+        start = "2024-07-10"
+        end = "2024-07-20"
+        color = "purple"
+
+        [[ranges]]
+        start = "2024-07-01"
+        end = "2024-07-31"
+        color = "green"
+        description = "Sabbatical"
+        status = "rejected"
+
+        [dates."2024-07-04"]
+        description = "Independence Day"
+        color = "red"
+
+        [dates."2024-07-05"]
+        description = "Uncolored"
+        "#,
+    )
+    .unwrap();
+    let options = CalendarOptions::builder()
+        .statuses(vec![Status::Approved, Status::Rejected])
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let conflicts: Vec<String> = calendar
+        .conflicts()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        conflicts,
+        [
+            "2024-07-04: Vacation (blue) is hidden by Independence Day (red)",
+            "2024-07-10 to 2024-07-14: 2024-07-10 to 2024-07-20 (purple) is hidden by Vacation (blue)",
+        ]
+    );
+}
+
+#[test]
+fn test_layer_decides_overlapping_range_color() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [[ranges]]
+        start = "2024-10-01"
+        end = "2024-12-31"
+        color = "gray"
+        description = "Q4"
+
+        [[ranges]]
+        start = "2024-12-24"
+        end = "2024-12-26"
+        color = "red"
+        description = "Holidays"
+        layer = 1
+
+        [[ranges]]
+        start = "2024-12-26"
+        end = "2024-12-27"
+        color = "blue"
+        description = "Offsite"
+        "#,
+    )
+    .unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    let color = |day| {
+        calendar
+            .coloring_event(NaiveDate::from_ymd_opt(2024, 12, day).unwrap())
+            .and_then(|event| event.color.as_deref())
+    };
+    assert_eq!(color(23), Some("gray"));
+    assert_eq!(color(24), Some("red"));
+    assert_eq!(color(26), Some("red"));
+    // Same layer: the range listed first still wins
+    assert_eq!(color(27), Some("gray"));
+
+    let conflicts: Vec<String> = calendar
+        .conflicts()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        conflicts,
+        [
+            "2024-12-24 to 2024-12-26: Q4 (gray) is hidden by Holidays (red)",
+            "2024-12-26: Offsite (blue) is hidden by Holidays (red)",
+            "2024-12-26 to 2024-12-27: Offsite (blue) is hidden by Q4 (gray)",
+        ]
+    );
+}
+
+#[test]
+fn test_blend_overlaps_mixes_range_colors() {
+    assert_eq!(
+        ColorPalette::new()
+            .with_colors(true)
+            .get_blended_style(&["red", "blue"], false),
+        anstyle::Style::new().bg_color(Some(RgbColor(164, 153, 187).into()))
+    );
+
+    let render = |overlap_display| {
+        let config: CalendarConfig = toml::from_str(
+            r#"
+            [[ranges]]
+            start = "2024-07-01"
+            end = "2024-09-30"
+            color = "blue"
+            description = "Q3"
+
+            [[ranges]]
+            start = "2024-08-05"
+            end = "2024-08-09"
+            color = "red"
+            description = "Vacation"
+            "#,
+        )
+        .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(8))
+            .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+            .overlap_display(overlap_display)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        let mut output = Vec::new();
+        CalendarRenderer::new(&calendar)
+            .with_colors(true)
+            .render_to(&mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let blended = "48;2;164;153;187m";
+    assert!(!render(OverlapDisplay::Top).contains(blended));
+    // The five vacation days inside Q3
+    assert_eq!(render(OverlapDisplay::Blend).matches(blended).count(), 5);
+}
+
+#[test]
+fn test_next_sample_2024() {
+    let calendar = Calendar::sample(2024);
+    insta::assert_snapshot!(Upcoming::new(&calendar, 3).render_to_string());
+}
+
+#[test]
+fn test_colored_render_matches_plain_ignoring_ansi() {
+    let calendar = Calendar::sample(2024);
+    let mut colored = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut colored)
+        .unwrap();
+    let colored = String::from_utf8(colored).unwrap();
+    let plain = CalendarRenderer::new(&calendar).render_to_string();
+
+    assert!(colored.contains('\x1b'));
+    assert_eq_ignoring_ansi(&colored, &plain);
+    assert_eq!(strip_ansi("\x1b[4m\x1b[48;2;1;2;3m07\x1b[0m"), "07");
+}
+
+// Config editing tests
+
+#[test]
+fn test_alternate_weeks_shade_even_iso_weeks() {
+    let render = |week_start, alternate_weeks| {
+        let config: CalendarConfig = toml::from_str(
+            r#"
+            [dates."2024-01-10"]
+            description = "Colored"
+            color = "red"
+            "#,
+        )
+        .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(1))
+            .today(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+            .week_start(week_start)
+            .alternate_weeks(alternate_weeks)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        let mut output = Vec::new();
+        CalendarRenderer::new(&calendar)
+            .with_colors(true)
+            .render_to(&mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let shade = "48;2;48;48;48m";
+    assert!(!render(WeekStart::Monday, false).contains(shade));
+    // Weeks 2 and 4, less the colored day
+    assert_eq!(render(WeekStart::Monday, true).matches(shade).count(), 13);
+    // Rows from Sunday follow the ISO week of their Thursday
+    assert_eq!(render(WeekStart::Sunday, true).matches(shade).count(), 13);
+}
+
+#[test]
+fn test_starter_config_with_holidays() {
+    let holidays = Country::Us.holidays(2025);
+    assert_eq!(holidays.len(), 11);
+    assert!(holidays.contains(&(
+        NaiveDate::from_ymd_opt(2025, 11, 27).unwrap(),
+        "Thanksgiving"
+    )));
+    assert!(Country::De
+        .holidays(2025)
+        .contains(&(NaiveDate::from_ymd_opt(2025, 4, 21).unwrap(), "Ostermontag")));
+
+    let starter = CalendarConfig::starter(2025, &Country::Gb.holidays(2025));
+    assert_eq!(validation::validate(&starter), []);
+    insta::assert_snapshot!(starter);
+}
+
+#[test]
+fn test_lunar_holidays() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let china = Country::Cn.holidays(2025);
+    for holiday in [
+        (date(2025, 1, 29), "Spring Festival"),
+        (date(2025, 4, 4), "Qingming Festival"),
+        (date(2025, 5, 31), "Dragon Boat Festival"),
+        (date(2025, 10, 6), "Mid-Autumn Festival"),
+    ] {
+        assert!(china.contains(&holiday), "{:?}", holiday);
+    }
+    let korea = Country::Kr.holidays(2026);
+    assert!(korea.contains(&(date(2026, 2, 17), "Seollal")));
+    assert!(korea.contains(&(date(2026, 5, 24), "Buddha's Birthday")));
+    assert!(korea.contains(&(date(2026, 9, 25), "Chuseok")));
+    assert!(korea.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+}
+
+#[test]
+fn test_add_date_and_range_keep_comments() {
+    let contents = std::fs::read_to_string("tests/fixtures/quarters.toml").unwrap();
+    let contents =
+        editing::add_date(&contents, "2023-07-04", "Independence Day", Some("red")).unwrap();
+    let contents = editing::add_range(
+        &contents,
+        "2023-08-01",
+        "2023-08-14",
+        "cyan",
+        Some("Vacation"),
+    )
+    .unwrap();
+    insta::assert_snapshot!(contents);
+}
+
+#[test]
+fn test_config_settings() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/settings.toml")).unwrap();
+    assert!(config.settings.sunday && config.settings.work && !config.settings.wide);
+    assert_eq!(config.settings.locale.as_deref(), Some("de"));
+    assert_eq!(
+        config.settings.contrast().unwrap(),
+        Some(ContrastMode::High)
+    );
+
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.settings, config.settings);
+
+    let problems = validation::validate("[settings]\nsundy = true\n");
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].line, Some(2));
+    let problems = validation::validate("[settings]\ntheme = \"neon\"\n");
+    assert_eq!(
+        problems[0].to_string(),
+        "line 2: unknown theme 'neon', expected \"normal\" or \"high-contrast\""
+    );
+}
+
+#[test]
+fn test_plan_wizard_2024() {
+    let answers = "xx\nde\n30\nmon-fri 9-17 Work\nmon-fri 09:00-17:00 Work\n\n\
+                   2024-04-30 Tax return\n06-12 Birthday\n";
+    let mut prompts = Vec::new();
+    let plan = Plan::ask(2024, answers.as_bytes(), &mut prompts).unwrap();
+    let prompts = String::from_utf8(prompts).unwrap();
+    assert!(prompts.contains("Unknown country: 'xx'"));
+    assert!(prompts.contains("invalid time '9'"));
+    assert_eq!(plan.country, Some(Country::De));
+    assert_eq!(plan.vacation_days, Some(30));
+    assert_eq!(plan.commitments.len(), 1);
+    assert_eq!(plan.deadlines.len(), 2);
+
+    let config = plan.to_toml();
+    assert!(validation::validate(&config).is_empty());
+    insta::assert_snapshot!(config);
+}
+
+#[test]
+fn test_profile_load() {
+    let profile = Profile::load_from(&PathBuf::from("tests/fixtures/profiles/work.toml")).unwrap();
+    assert_eq!(
+        profile.config,
+        Some(PathBuf::from("tests/fixtures/profiles/../simple.toml"))
+    );
+    let theme_file = profile.theme_file.as_ref().unwrap();
+    assert_eq!(
+        theme_file,
+        &PathBuf::from("tests/fixtures/profiles/../theme.toml")
+    );
+    assert!(Theme::load_from(theme_file).is_ok());
+    let settings = &profile.settings;
+    assert_eq!(settings.contrast().unwrap(), Some(ContrastMode::High));
+    assert!(settings.sunday && settings.work && !settings.compact);
+    assert_eq!(settings.locale.as_deref(), Some("en-US"));
+
+    assert!(matches!(
+        Profile::load("../work"),
+        Err(compact_calendar_cli::Error::InvalidEntry(_))
+    ));
+}
+
+#[test]
+fn test_config_candidates_end_in_working_directory() {
+    let candidates = paths::config_candidates();
+    assert_eq!(
+        candidates.last(),
+        Some(&PathBuf::from(paths::CONFIG_FILE_NAME))
+    );
+    for (i, candidate) in candidates.iter().enumerate() {
+        assert!(candidate.ends_with(paths::CONFIG_FILE_NAME));
+        assert!(!candidates[..i].contains(candidate));
+    }
+}
+
+#[test]
+fn test_include_fragments() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/include/main.toml"))
+            .unwrap();
+    let calendar = compact_calendar_cli::build_calendar(2024, CalendarOptions::default(), config);
+    insta::assert_snapshot!(EventList::new(&calendar).render_to_string());
+
+    let err =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/include/cycle/a.toml"))
+            .unwrap_err();
+    match err {
+        compact_calendar_cli::Error::IncludeCycle(paths) => {
+            let names: Vec<_> = paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_str().unwrap())
+                .collect();
+            assert_eq!(names, ["a.toml", "b.toml", "a.toml"]);
+        }
+        err => panic!("expected an include cycle, got {}", err),
+    }
+}
+
+#[test]
+fn test_include_expands_environment_variables() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/include/env.toml"))
+            .unwrap();
+    assert_eq!(config.dates.len(), 2);
+
+    let config: CalendarConfig =
+        toml::from_str(r#"include = ["${COMPACT_CALENDAR_UNSET}/a.toml"]"#).unwrap();
+    let err = config.include_paths(Path::new(".")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "environment variable COMPACT_CALENDAR_UNSET in path '${COMPACT_CALENDAR_UNSET}/a.toml' is not set"
+    );
+}
+
+#[test]
+fn test_readonly_config_refuses_edits() {
+    let writable =
+        |path: &str, format| compact_calendar_cli::ensure_writable(&PathBuf::from(path), format);
+    let err = writable("tests/fixtures/readonly.toml", ConfigFormat::Toml).unwrap_err();
+    assert!(matches!(err, compact_calendar_cli::Error::ReadOnly(_)));
+    assert!(writable("tests/fixtures/simple.toml", ConfigFormat::Toml).is_ok());
+    assert!(writable("tests/fixtures/missing.toml", ConfigFormat::Toml).is_ok());
+
+    // A config that can't be loaded can't be shown to be writable, and is
+    // read as the format it was given as
+    let err = writable("tests/fixtures/readonly.toml", ConfigFormat::Json).unwrap_err();
+    assert!(matches!(err, compact_calendar_cli::Error::ParseJson { .. }));
+}
+
+#[test]
+fn test_add_rejects_invalid_entries() {
+    let err = editing::add_date("", "2023-13-01", "Bad month", None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid date '2023-13-01', expected YYYY-MM-DD or MM-DD"
+    );
+    let err = editing::add_date("", "07-04", "Bad color", Some("mauve")).unwrap_err();
+    assert_eq!(err.to_string(), "unknown color 'mauve'");
+    let err = editing::add_range("", "08-14", "08-01", "blue", None).unwrap_err();
+    assert_eq!(err.to_string(), "range end 08-01 is before its start 08-14");
+}
+
+#[test]
+fn test_add_to_a_date_that_has_entries() {
+    let contents = "# Holidays\n[dates.\"07-04\"]\ndescription = \"Independence Day\"\n\n[dates.\"12-25\"]\ndescription = \"Christmas\"\n";
+    let contents = editing::add_date(contents, "07-04", "Fireworks", Some("red")).unwrap();
+    let contents = editing::add_date(&contents, "07-04", "Barbecue", None).unwrap();
+    insta::assert_snapshot!(contents);
+
+    let config: CalendarConfig = toml::from_str(&contents).unwrap();
+    let descriptions: Vec<_> = config.dates["07-04"]
+        .iter()
+        .map(|detail| detail.description.as_str())
+        .collect();
+    assert_eq!(descriptions, ["Independence Day", "Fireworks", "Barbecue"]);
+    assert!(validation::validate(&contents).is_empty());
+}
+
+#[test]
+fn test_event_builder_validates() {
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+    let event = Event::builder(EventKind::Milestone, date(3, 15))
+        .id("launch")
+        .description("Launch")
+        .tags(["release", "q1"])
+        .owner("ops")
+        .build()
+        .unwrap();
+    assert!(event.is_single_day() && !event.is_range());
+
+    let err = Event::builder(EventKind::Range, date(3, 15))
+        .end(date(3, 1))
+        .color("blue")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "event ends before it starts");
+    assert!(Event::builder(EventKind::Milestone, date(3, 1))
+        .end(date(3, 2))
+        .build()
+        .is_err());
+    assert!(Event::builder(EventKind::Range, date(3, 1))
+        .end(date(3, 2))
+        .build()
+        .is_err());
+    assert!(Event::builder(EventKind::Date, date(3, 1))
+        .id("two words")
+        .build()
+        .is_err());
+    assert!(Event::builder(EventKind::Date, date(3, 1))
+        .tags(["a", "a"])
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_validate_reports_every_problem() {
+    let contents = r#"[dates."02-29"]
+description = "Leap day"
+
+[dates."2024-03-15"]
+description = "Launch"
+kind = "launch"
+
+[dates."2024-3-15"]
+description = "Launch again"
+color = "mauve"
+
+[[ranges]]
+start = "2024-06-07"
+end = "2024-06-01"
+color = "blue"
+
+[[ranges]]
+start = "2024-07-01"
+end = "2024-07-05"
+color = "green"
+kind = "travel"
+timezone = "Mars/Olympus_Mons"
+
+[[ranges]]
+start = "2024-13-01"
+end = "2024-13-05"
+color = "blue"
+"#;
+
+    let problems: Vec<String> = validation::validate(contents)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 4: date 2024-03-15: Invalid event kind: 'launch'. Use date, range, milestone, recurring or travel",
+            "line 8: date 2024-3-15 is the same day as 2024-03-15",
+            "line 10: unknown color 'mauve'",
+            "line 12: range starting 2024-06-07: event ends before it starts",
+            "line 17: range starting 2024-07-01: unknown timezone 'Mars/Olympus_Mons'",
+            "line 24: range starting 2024-13-01: invalid date '2024-13-01'",
+        ]
+    );
+
+    let problems = validation::validate("[dates.\"01-01\"]\ndescription = 1\n");
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].line, Some(2));
+}
+
+#[test]
+fn test_config_event_metadata() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [dates."2024-03-15"]
+        description = "Launch"
+        kind = "milestone"
+        id = "launch"
+        tags = ["release"]
+        owner = "ops"
+
+        [dates."12-25"]
+        description = "Christmas"
+
+        [[ranges]]
+        start = "2024-06-01"
+        end = "2024-06-07"
+        color = "blue"
+        tags = ["travel"]
+        "#,
+    )
+    .unwrap();
+
+    let calendar = Calendar::new(
+        2024,
+        CalendarOptions::default(),
+        config.events_for_year(2024),
+    );
+    let launch = &calendar.details[&NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()][0];
+    assert_eq!(launch.kind, EventKind::Milestone);
+    assert_eq!(launch.id.as_deref(), Some("launch"));
+    assert_eq!(launch.tags, ["release"]);
+    assert_eq!(launch.owner.as_deref(), Some("ops"));
+    let christmas = &calendar.details[&NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()][0];
+    assert_eq!(christmas.kind, EventKind::Recurring);
+    assert_eq!(calendar.ranges.len(), 1);
+    assert_eq!(calendar.ranges[0].tags, ["travel"]);
+
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.events_for_year(2024).len(), 3);
+    assert_eq!(
+        reparsed.dates["2024-03-15"][0].kind.as_deref(),
+        Some("milestone")
+    );
+}
+
+#[test]
+fn test_several_entries_on_one_date() {
+    let output = create_calendar_from_config_with_filter(
+        2025,
+        "tests/fixtures/shared_dates.toml",
+        MonthFilter::Single(3),
+    );
+    insta::assert_snapshot!("shared_dates_march_2025", output);
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/shared_dates.toml"))
+            .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.dates["2025-03-14"].len(), 2);
+    assert_eq!(reparsed.events_for_year(2025).len(), 3);
+
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    let pi_day = NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+    let descriptions: Vec<&str> = calendar
+        .details_on(pi_day)
+        .filter_map(|event| event.description.as_deref())
+        .collect();
+    assert_eq!(descriptions, ["Sam's birthday", "Tax filing deadline"]);
+    // The first colored entry fills the day
+    assert_eq!(
+        calendar
+            .coloring_event(pi_day)
+            .and_then(|event| event.color.as_deref()),
+        Some("purple")
+    );
+    let conflicts: Vec<String> = calendar
+        .conflicts()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        conflicts,
+        ["2025-03-14: Tax filing deadline (red) is hidden by Sam's birthday (purple)"]
+    );
+}
+
+#[test]
+fn test_highlights_color_dates_over_the_config() {
+    assert_eq!(
+        Highlight::parse("2024-08-15:red:Launch: v2").unwrap(),
+        Highlight {
+            date: NaiveDate::from_ymd_opt(2024, 8, 15).unwrap(),
+            color: "red".to_string(),
+            label: Some("Launch: v2".to_string()),
+        }
+    );
+    assert_eq!(
+        Highlight::parse("2024-08-20").unwrap().color,
+        Highlight::DEFAULT_COLOR
+    );
+    assert!(Highlight::parse("08-15").is_err());
+    assert!(Highlight::parse("2024-08-15:mauve").is_err());
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(8))
+        .past_date_display(PastDateDisplay::Normal)
+        .highlights(vec![
+            Highlight::parse("2024-08-01:red:Go/no-go").unwrap(),
+            Highlight::parse("2024-08-20").unwrap(),
+        ])
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let color = |day| {
+        calendar
+            .coloring_event(NaiveDate::from_ymd_opt(2024, 8, day).unwrap())
+            .and_then(|event| event.color.as_deref())
+    };
+    // The highlight wins over the config's "Product Launch" on the same day
+    assert_eq!(color(1), Some("red"));
+    assert_eq!(color(20), Some(Highlight::DEFAULT_COLOR));
+
+    let output = CalendarRenderer::new(&calendar).render_to_string();
+    assert!(output.contains("08/01 - Go/no-go, 08/01 - Product Launch"));
+    assert!(output.contains("│08/20\n"));
+}
+
+#[test]
+fn test_today_marker_styles() {
+    assert_eq!(TodayMarker::parse("Bold").unwrap(), TodayMarker::Bold);
+    assert_eq!(
+        TodayMarker::parse("light_blue").unwrap(),
+        TodayMarker::Background("light_blue".to_string())
+    );
+    assert_eq!(TodayMarker::parse("▶").unwrap(), TodayMarker::Glyph('▶'));
+    assert!(TodayMarker::parse("sparkles").is_err());
+
+    let render = |today_marker, cell_width, colors| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"))
+                .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(3))
+            .today(NaiveDate::from_ymd_opt(2024, 3, 13).unwrap())
+            .today_marker(today_marker)
+            .cell_width(cell_width)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        let mut output = Vec::new();
+        CalendarRenderer::new(&calendar)
+            .with_colors(colors)
+            .render_to(&mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    // The glyph shows without colors too
+    let output = render(TodayMarker::Glyph('▶'), CellWidth::Normal, false);
+    assert!(output.contains(" 12  ▶13   14 "));
+    let output = render(
+        TodayMarker::Background("blue".to_string()),
+        CellWidth::Normal,
+        true,
+    );
+    assert!(output.contains("\u{1b}[30m\u{1b}[48;2;89;194;255m13"));
+    // Compact cells have no room for the glyph, so today is underlined
+    let output = render(TodayMarker::Glyph('▶'), CellWidth::Compact, true);
+    assert!(!output.contains('▶'));
+    assert!(output.contains("\u{1b}[4m13"));
+}
+
+#[test]
+fn test_timezone_decides_today() {
+    let config: CalendarConfig = toml::from_str("timezone = \"Asia/Tokyo\"").unwrap();
+    assert_eq!(config.timezone().unwrap(), Some(chrono_tz::Asia::Tokyo));
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.timezone, config.timezone);
+
+    // 25 hours apart, so never on the same day
+    let today = |timezone| {
+        CalendarOptions::builder()
+            .timezone(timezone)
+            .build()
+            .today()
+    };
+    let days_apart =
+        (today(chrono_tz::Pacific::Kiritimati) - today(chrono_tz::Pacific::Pago_Pago)).num_days();
+    assert!((1..=2).contains(&days_apart));
+
+    // A pinned date still wins
+    let pinned = NaiveDate::from_ymd_opt(2024, 3, 13).unwrap();
+    let options = CalendarOptions::builder()
+        .today(pinned)
+        .timezone(chrono_tz::Pacific::Kiritimati)
+        .build();
+    assert_eq!(options.today(), pinned);
+
+    let problems: Vec<String> =
+        validation::validate("readonly = false\ntimezone = \"Mars/Olympus\"\n")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+    assert_eq!(problems, ["line 2: unknown timezone 'Mars/Olympus'"]);
+}
+
+#[test]
+fn test_faded_past_dates_darken_with_age() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .past_date_display(PastDateDisplay::Faded)
+        .weekend_display(WeekendDisplay::Normal)
+        .today(NaiveDate::from_ymd_opt(2024, 10, 16).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let mut output = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // Past dates are faded rather than struck through
+    assert!(!output.contains("\u{1b}[9m"));
+
+    let gray = |step| {
+        let shade = ((192 * (FADE_STEPS - step) + FADE_STEPS / 2) / FADE_STEPS) as u8;
+        format!("\u{1b}[38;2;{shade};{shade};{shade}m")
+    };
+    // October's past days fade one step, September's two, and the oldest
+    // months stop at the maximum
+    assert!(output.contains(&format!("{}14\u{1b}[0m", gray(1))));
+    assert!(output.contains(&format!("{}30\u{1b}[0m", gray(2))));
+    assert!(output.contains(&format!("{}02\u{1b}[0m", gray(MAX_FADE_STEP))));
+    // Today and the future are left alone
+    assert!(output.contains("\u{1b}[4m16\u{1b}[0m"));
+    assert!(output.contains(" 17 "));
+}
+
+#[test]
+fn test_non_working_days_are_dimmed_and_not_worked() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        non_working = ["12-24", "2024-12-27..2024-12-31"]
+
+        [dates."2024-12-25"]
+        description = "Christmas"
+        tags = ["off"]
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.non_working, config.non_working);
+    assert_eq!(
+        config.non_working_for_year(2025)[0],
+        (
+            NaiveDate::from_ymd_opt(2025, 12, 24).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 12, 24).unwrap()
+        )
+    );
+
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(12))
+        .past_date_display(PastDateDisplay::Normal)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    assert!(calendar.is_non_working(date(12, 24)));
+    assert!(!calendar.is_working_day(date(12, 24)));
+    assert!(calendar.is_working_day(date(12, 23)));
+    // Mon 12/23 to Fri 1/3: the 23rd, the 26th and 1/1 to 1/3
+    assert_eq!(
+        calendar.working_days_between(date(12, 23), NaiveDate::from_ymd_opt(2025, 1, 4).unwrap()),
+        5
+    );
+
+    let mut output = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(" 23   \u{1b}[2m24\u{1b}[0m"));
+    assert!(output.contains("\u{1b}[2m27\u{1b}[0m"));
+
+    let problems: Vec<String> =
+        validation::validate("non_working = [\"12-31..12-30\", \"someday\"]\n")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 1: non-working days 12-31..12-30: ends before it starts",
+            "line 1: non-working days someday: unknown or circular reference to id 'someday'",
+        ]
+    );
+}
+
+#[test]
+fn test_working_weekends_count_as_weekdays() {
+    // Spring Festival 2025: the Sunday before and the Saturday after are worked
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        working_weekends = ["2025-01-26", "2025-02-08"]
+        non_working = ["2025-01-28..2025-02-04"]
+
+        [dates."2025-01-26"]
+        description = "Make-up workday"
+        color = "orange"
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.working_weekends, config.working_weekends);
+
+    let options = CalendarOptions::builder()
+        .color_mode(ColorMode::Work)
+        .month_filter(MonthFilter::Single(1))
+        .past_date_display(PastDateDisplay::Normal)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2025, options, config);
+    let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    assert!(!calendar.is_weekend(date(1, 26)));
+    assert!(calendar.is_weekend(date(1, 25)));
+    assert!(calendar.is_working_day(date(2, 8)));
+    // Work mode still colors a worked Sunday
+    assert!(calendar.coloring_event(date(1, 26)).is_some());
+    // Fri 1/24 up to Mon 2/10: the 24th, 26th, 27th, 5th to 8th
+    assert_eq!(calendar.working_days_between(date(1, 24), date(2, 10)), 7);
+
+    let mut output = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // The worked Sunday is not dimmed, unlike the Saturday before it
+    assert!(output.contains("\u{1b}[2m25\u{1b}[0m"));
+    assert!(!output.contains("\u{1b}[2m26\u{1b}[0m"));
+}
+
+#[test]
+fn test_payperiods_mark_paydays_and_shade_periods() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [payperiods]
+        payday = "2024-12-27"
+        interval = "biweekly"
+        shade = true
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.payperiods, config.payperiods);
+
+    let events = config.parse_payperiods_for_year(2025);
+    let paydays: Vec<NaiveDate> = events
+        .iter()
+        .filter(|event| event.kind == EventKind::Date)
+        .map(|event| event.start)
+        .collect();
+    assert_eq!(paydays.len(), 26);
+    assert_eq!(paydays[0], date(2025, 1, 10));
+    // The period paid on December 27th runs into the new year
+    let first_period = events.iter().find(|event| event.is_range()).unwrap();
+    assert_eq!(first_period.start, date(2024, 12, 27));
+    assert_eq!(first_period.end, date(2025, 1, 9));
+
+    let paydays = |payday: &str, interval: &str| {
+        let config: CalendarConfig = toml::from_str(&format!(
+            "[payperiods]\npayday = \"{}\"\ninterval = \"{}\"\n",
+            payday, interval
+        ))
+        .unwrap();
+        config
+            .parse_payperiods_for_year(2025)
+            .into_iter()
+            .map(|event| event.start)
+            .collect::<Vec<_>>()
+    };
+    let semimonthly = paydays("2025-01-15", "semimonthly");
+    assert_eq!(semimonthly.len(), 24);
+    assert_eq!(semimonthly[2..4], [date(2025, 2, 15), date(2025, 2, 28)]);
+    assert_eq!(semimonthly[13], date(2025, 7, 31));
+    let monthly = paydays("2025-01-31", "monthly");
+    assert_eq!(monthly[1], date(2025, 2, 28));
+    assert_eq!(monthly[3], date(2025, 4, 30));
+
+    let problems: Vec<String> = validation::validate(
+        r#"
+[payperiods]
+payday = "2025-01-10"
+interval = "daily"
+"#,
+    )
+    .into_iter()
+    .map(|problem| problem.to_string())
+    .collect();
+    assert_eq!(problems.len(), 1);
+    assert!(
+        problems[0].contains("invalid pay interval 'daily'"),
+        "{:?}",
+        problems
+    );
+}
+
+#[test]
+fn test_shifts_expand_rotation_into_runs() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [shifts]
+        start = "2024-12-31"
+        pattern = "DDNN OOOO"
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.shifts, config.shifts);
+
+    let events = config.parse_shifts_for_year(2025);
+    let runs: Vec<(NaiveDate, NaiveDate, &str)> = events
+        .iter()
+        .take(3)
+        .map(|event| {
+            (
+                event.start,
+                event.end,
+                event.description.as_deref().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        runs,
+        [
+            (date(2024, 12, 31), date(2025, 1, 1), "Day shift"),
+            (date(2025, 1, 2), date(2025, 1, 3), "Night shift"),
+            (date(2025, 1, 8), date(2025, 1, 9), "Day shift"),
+        ]
+    );
+    // Two runs of work every eight days
+    assert_eq!(events.len(), 2 * 46);
+    assert_eq!(events[1].color.as_deref(), Some("blue"));
+
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [shifts]
+        start = "2025-01-01"
+        pattern = "X"
+
+        [shifts.codes.X]
+        name = "On call"
+        color = "red"
+        "#,
+    )
+    .unwrap();
+    let events = config.parse_shifts_for_year(2025);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].start, date(2025, 1, 1));
+
+    let problems: Vec<String> = validation::validate(
+        r#"
+[shifts]
+start = "2025-01-06"
+pattern = "DDXO"
+"#,
+    )
+    .into_iter()
+    .map(|problem| problem.to_string())
+    .collect();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("unknown shift 'X'"), "{:?}", problems);
+}
+
+#[test]
+fn test_sprints_generate_alternating_ranges() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [sprints]
+        start = "2024-12-30"
+        first = 42
+        colors = ["light_blue", "light_purple"]
+
+        [[ranges]]
+        start = "2025-01-20"
+        end = "2025-01-24"
+        color = "orange"
+        description = "Offsite"
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.sprints, config.sprints);
+
+    let events = config.parse_sprints_for_year(2025);
+    // Sprint 42 began in 2024 and still covers New Year's Day
+    let first = &events[0];
+    assert_eq!(first.start, NaiveDate::from_ymd_opt(2024, 12, 30).unwrap());
+    assert_eq!(first.end, NaiveDate::from_ymd_opt(2025, 1, 12).unwrap());
+    assert_eq!(first.description.as_deref(), Some("Sprint 42"));
+    assert_eq!(events[1].description.as_deref(), Some("Sprint 43"));
+    assert_eq!(events[1].color.as_deref(), Some("light_purple"));
+    assert_eq!(events[2].color.as_deref(), Some("light_blue"));
+    // The last one starts in December and runs into 2026
+    let last = events.last().unwrap();
+    assert_eq!(last.start, NaiveDate::from_ymd_opt(2025, 12, 29).unwrap());
+    assert_eq!(last.description.as_deref(), Some("Sprint 68"));
+
+    // Other ranges paint over the sprints
+    let calendar = Calendar::new(
+        2025,
+        CalendarOptions::default(),
+        config.events_for_year(2025),
+    );
+    let color = |month, day| {
+        calendar
+            .coloring_event(NaiveDate::from_ymd_opt(2025, month, day).unwrap())
+            .and_then(|event| event.color.clone())
+    };
+    assert_eq!(color(1, 22).as_deref(), Some("orange"));
+    assert_eq!(color(1, 13).as_deref(), Some("light_purple"));
+
+    let problems: Vec<String> = validation::validate(
+        r#"
+[sprints]
+start = "2025-01-06"
+weeks = 0
+colors = ["mauve"]
+"#,
+    )
+    .into_iter()
+    .map(|problem| problem.to_string())
+    .collect();
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].contains("at least a week"));
+    assert!(problems[1].contains("mauve"));
+}
+
+#[test]
+fn test_week_labels_replace_week_numbers() {
+    let output = create_calendar_from_config_with_filter(
+        2025,
+        "tests/fixtures/week_labels.toml",
+        MonthFilter::Single(3),
+    );
+    insta::assert_snapshot!("week_labels_march_2025", output);
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/week_labels.toml"))
+            .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.week_labels, config.week_labels);
+
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    let week = |month, day| {
+        let start = NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let dates: Vec<NaiveDate> = start.iter_days().take(7).collect();
+        calendar.week_label(&dates).map(str::to_string)
+    };
+    // The week before the first sprint keeps its number
+    assert_eq!(week(12, 30), None);
+    assert_eq!(week(1, 6).as_deref(), Some("S42"));
+    assert_eq!(week(1, 13).as_deref(), Some("S42"));
+    assert_eq!(week(1, 20).as_deref(), Some("S43"));
+    assert_eq!(week(3, 17).as_deref(), Some("Offsite"));
+
+    let problems = validation::validate(
+        r#"
+[week_labels]
+format = "S{n}"
+every = 0
+"#,
+    );
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].to_string().contains("at least a week"));
+}
+
+#[test]
+fn test_terms_shade_and_number_weeks() {
+    let output = create_calendar_from_config_with_filter(
+        2025,
+        "tests/fixtures/terms.toml",
+        MonthFilter::Single(10),
+    );
+    insta::assert_snapshot!("terms_october_2025", output);
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/terms.toml")).unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.terms, config.terms);
+
+    // Half term splits the autumn shading in two
+    let autumn: Vec<(NaiveDate, NaiveDate)> = config
+        .parse_terms_for_year(2025)
+        .into_iter()
+        .filter(|event| event.description.as_deref() == Some("Autumn term"))
+        .map(|event| (event.start, event.end))
+        .collect();
+    let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    assert_eq!(
+        autumn,
+        [(date(9, 3), date(10, 26)), (date(11, 1), date(12, 19))]
+    );
+
+    let calendar = compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config);
+    let week = |month, day| {
+        let dates: Vec<NaiveDate> = date(month, day).iter_days().take(7).collect();
+        calendar.week_label(&dates).map(str::to_string)
+    };
+    assert_eq!(week(9, 1).as_deref(), Some("T1W1"));
+    // The half-term week isn't counted
+    assert_eq!(week(10, 20).as_deref(), Some("T1W8"));
+    assert_eq!(week(10, 27), None);
+    assert_eq!(week(11, 3).as_deref(), Some("T1W9"));
+    assert_eq!(week(1, 6).as_deref(), Some("Spr 1"));
+
+    let problems = validation::validate(
+        r#"
+[[terms]]
+name = "Summer term"
+start = "2025-04-22"
+end = "2025-07-18"
+breaks = ["2025-08-01"]
+"#,
+    );
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].to_string().contains("outside the term"));
+}
+
+#[test]
+fn test_habit_log_ticks_off_days_and_counts_streaks() {
+    let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+    let contents = std::fs::read_to_string("tests/fixtures/habits.txt").unwrap();
+    let habits = HabitLog::parse(&contents).unwrap();
+    assert_eq!(
+        HabitLog::parse("done = [\"2025-01-01\", 2025-01-02]").unwrap(),
+        HabitLog::parse("2025-01-01\n2025-01-02\n").unwrap()
+    );
+    assert_eq!(
+        HabitLog::parse("2025-01-01\nyesterday\n").unwrap_err(),
+        "line 2: invalid date 'yesterday', expected YYYY-MM-DD"
+    );
+
+    // Today isn't done yet, so the streak still runs through yesterday
+    assert_eq!(habits.current_streak(date(21)), 3);
+    assert_eq!(habits.current_streak(date(20)), 3);
+    assert_eq!(habits.current_streak(date(22)), 0);
+    assert_eq!(habits.longest_streak(date(1), date(31)), 7);
+
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(1))
+        .today(date(21))
+        .habits(habits)
+        .build();
+    let calendar = Calendar::new(2025, options, Vec::new());
+    insta::assert_snapshot!(CalendarRenderer::new(&calendar).render_to_string());
+}
+
+#[test]
+fn test_git_heatmap_shades_days_by_commits() {
+    let repo = Path::new(env!("CARGO_TARGET_TMPDIR")).join("heatmap-repo");
+    let _ = std::fs::remove_dir_all(&repo);
+    std::fs::create_dir_all(&repo).unwrap();
+    let git = |args: &[&str], date: &str| {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    };
+    git(&["init", "-q"], "2025-01-01T12:00:00");
+    for date in [
+        "2025-03-03T09:00:00",
+        "2025-03-05T09:00:00",
+        "2025-03-05T10:00:00",
+        "2025-03-05T11:00:00",
+        "2025-03-05T12:00:00",
+        "2024-06-01T12:00:00",
+    ] {
+        git(&["commit", "-q", "--allow-empty", "-m", "Work"], date);
+    }
+
+    let heatmap = Heatmap::from_git(&repo, 2025).unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+    let (start, end) = (
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+    );
+    assert_eq!(heatmap.count(date(5)), 4);
+    assert_eq!(heatmap.level(date(3), start, end), Some(1));
+    assert_eq!(heatmap.level(date(4), start, end), None);
+    assert_eq!(heatmap.level(date(5), start, end), Some(4));
+    assert_eq!(heatmap.total(start, end), 5);
+    assert!(Heatmap::from_git(Path::new("tests/fixtures/missing"), 2025).is_err());
+
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(3))
+        .today(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .heatmap(heatmap)
+        .build();
+    let calendar = Calendar::new(2025, options, Vec::new());
+    let mut output = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // The quietest and busiest shades on a dark terminal
+    assert_eq!(output.matches("48;2;14;68;41m").count(), 1);
+    assert_eq!(output.matches("48;2;57;211;83m").count(), 1);
+    assert!(strip_ansi(&output).contains("Activity: 5 in 2025, busiest 03/05 with 4"));
+}
+
+#[test]
+fn test_github_contributions_response() {
+    let contents = std::fs::read_to_string("tests/fixtures/github_contributions.json").unwrap();
+    let heatmap = Heatmap::from_github_response(&contents).unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+    let (start, end) = (date(1), NaiveDate::from_ymd_opt(2025, 12, 31).unwrap());
+    assert_eq!(heatmap.total(start, end), 18);
+    assert_eq!(heatmap.busiest(start, end), Some((date(2), 8)));
+    assert_eq!(heatmap.level(date(1), start, end), Some(1));
+    assert_eq!(heatmap.level(date(6), start, end), Some(3));
+
+    assert_eq!(
+        Heatmap::from_github_response(
+            r#"{"data": {"user": null}, "errors": [{"message": "Could not resolve to a User with the login of 'nobody'."}]}"#
+        )
+        .unwrap_err(),
+        "GitHub: Could not resolve to a User with the login of 'nobody'."
+    );
+    assert_eq!(
+        Heatmap::from_github_response(r#"{"message": "Bad credentials"}"#).unwrap_err(),
+        "GitHub: Bad credentials"
+    );
+}
+
+#[test]
+fn test_weather_forecast_marks_the_coming_days() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("weather-forecast");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let served = dir.join("forecast.json");
+    std::fs::copy("tests/fixtures/open_meteo_forecast.json", &served).unwrap();
+    let config: CalendarConfig = toml::from_str(&format!(
+        "[daylight]\nlatitude = 48.86\nlongitude = 2.34\n\n[weather]\nurl = \"file://{}?lat={{latitude}}&days={{days}}\"\n",
+        served.canonicalize().unwrap().display()
+    ))
+    .unwrap();
+    let weather = config.weather().unwrap().unwrap();
+    assert_eq!(
+        (weather.latitude, weather.longitude),
+        (Some(48.86), Some(2.34))
+    );
+    let cache = Cache::new(dir.join("cache"));
+
+    let (forecast, warning) =
+        sources::fetch_forecast_with_cache(&weather, &cache, CachePolicy::Fresh).unwrap();
+    assert_eq!(warning, None);
+    assert_eq!(forecast.len(), 7);
+    assert_eq!(forecast[2].weather, Weather::Rain);
+    assert_eq!(forecast[2].precipitation, Some(85));
+    assert_eq!(forecast[3].high, Some(19.9));
+    std::fs::remove_file(&served).unwrap();
+    let (offline, _) =
+        sources::fetch_forecast_with_cache(&weather, &cache, CachePolicy::Offline).unwrap();
+    assert_eq!(offline, forecast);
+
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(6))
+        .today(NaiveDate::from_ymd_opt(2025, 6, 2).unwrap())
+        .moon(true)
+        .forecast(forecast)
+        .build();
+    let output = strip_ansi(
+        &CalendarRenderer::new(&Calendar::new(2025, options, Vec::new())).render_to_string(),
+    );
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn test_clock_changes_are_marked_in_the_timezone() {
+    let changes = |timezone: &str| -> Vec<String> {
+        dst::transitions(timezone.parse().unwrap(), 2025)
+            .iter()
+            .map(|transition| format!("{} {}", transition.date(), transition.description()))
+            .collect()
+    };
+    assert_eq!(
+        changes("America/New_York"),
+        [
+            "2025-03-09 Clocks go forward 1h (02:00 EST → 03:00 EDT)",
+            "2025-11-02 Clocks go back 1h (02:00 EDT → 01:00 EST)",
+        ]
+    );
+    // Southern summer time ends in April, and Lord Howe Island only moves
+    // its clocks by half an hour
+    assert_eq!(
+        changes("Australia/Lord_Howe"),
+        [
+            "2025-04-06 Clocks go back 30m (02:00 UTC+11:00 → 01:30 UTC+10:30)",
+            "2025-10-05 Clocks go forward 30m (02:00 UTC+10:30 → 02:30 UTC+11:00)",
+        ]
+    );
+    assert_eq!(changes("Asia/Tokyo"), Vec::<String>::new());
+
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(3))
+        .timezone("America/New_York".parse().unwrap())
+        .build();
+    let calendar = Calendar::new(2025, options, Vec::new());
+    let output = strip_ansi(&CalendarRenderer::new(&calendar).render_to_string());
+    assert!(output.contains(" »09 "), "{}", output);
+    assert!(output.contains("Clocks go forward 1h"), "{}", output);
+}
+
+#[test]
+fn test_leap_days_and_week_53_are_noted() {
+    let calendar = |year| {
+        let options = CalendarOptions::builder().leap_markers(true).build();
+        Calendar::new(year, options, Vec::new())
+    };
+    let notes = |year| -> Vec<String> {
+        calendar(year)
+            .events()
+            .into_iter()
+            .map(|event| {
+                format!(
+                    "{} {}",
+                    event.start,
+                    event.description.as_deref().unwrap_or_default()
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        [2024, 2025, 2026, 2020]
+            .map(|year| (calendar(year).is_leap_year(), calendar(year).is_long_year())),
+        [(true, false), (false, false), (false, true), (true, true)]
+    );
+    assert_eq!(notes(2024), ["2024-02-29 Leap day"]);
+    assert_eq!(notes(2025), Vec::<String>::new());
+    assert_eq!(notes(2026), ["2026-12-28 ISO week 53 of 2026"]);
+    assert_eq!(
+        notes(2020),
+        ["2020-02-29 Leap day", "2020-12-28 ISO week 53 of 2020"]
+    );
+    // Only when asked for
+    assert!(Calendar::new(2024, CalendarOptions::default(), Vec::new())
+        .events()
+        .is_empty());
+}
+
+#[test]
+fn test_moon_phases_mark_their_days() {
+    let phases = moon::phases(2025);
+    // Published times (UTC), which the computed ones are within minutes of
+    for (phase, published) in [
+        (MoonPhase::Full, "2025-01-13T22:27:00Z"),
+        (MoonPhase::New, "2025-01-29T12:36:00Z"),
+        (MoonPhase::Full, "2025-10-07T03:47:00Z"),
+        (MoonPhase::New, "2025-10-21T12:25:00Z"),
+    ] {
+        let published: DateTime<Utc> = published.parse().unwrap();
+        assert!(
+            phases.iter().any(|(instant, p)| *p == phase
+                && (*instant - published).num_seconds().abs() < 120),
+            "{:?} at {}",
+            phase,
+            published
+        );
+    }
+
+    let calendar = |timezone: &str| {
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(1))
+            .timezone(timezone.parse().unwrap())
+            .moon(true)
+            .build();
+        Calendar::new(2025, options, Vec::new())
+    };
+    // The late evening full moon in London is already the next day in Tokyo
+    let date = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+    assert_eq!(
+        calendar("Europe/London").moon_phase(date(13)),
+        Some(MoonPhase::Full)
+    );
+    assert_eq!(
+        calendar("Asia/Tokyo").moon_phase(date(14)),
+        Some(MoonPhase::Full)
+    );
+    assert_eq!(calendar("Asia/Tokyo").moon_phase(date(13)), None);
+
+    let output = CalendarRenderer::new(&calendar("Europe/London")).render_to_string();
+    assert!(output.contains("│○13   14"));
+    assert!(output.contains(" ●29 "));
+    // The new moon of December 30th shows in the first week too
+    assert!(output.contains("│●30   31"));
+    assert_eq!(
+        Calendar::new(2025, CalendarOptions::default(), Vec::new()).moon_phase(date(13)),
+        None
+    );
+}
+
+#[test]
+fn test_daylight_notes_seasons_and_sunset_crossings() {
+    // Published times (UTC)
+    let seasons = sun::seasons(2025);
+    for (i, published) in [
+        "2025-03-20T09:01:00Z",
+        "2025-06-21T02:42:00Z",
+        "2025-09-22T18:19:00Z",
+        "2025-12-21T15:03:00Z",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let published: DateTime<Utc> = published.parse().unwrap();
+        assert!(
+            (seasons[i].0 - published).num_minutes().abs() < 15,
+            "{:?}",
+            seasons[i]
+        );
+    }
+
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [daylight]
+        latitude = 51.5
+        longitude = -0.13
+        sunset = ["20:00"]
+        "#,
+    )
+    .unwrap();
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.daylight, config.daylight);
+    let daylight = config.daylight().unwrap().unwrap();
+
+    // London's midsummer sunset is at 21:21 BST
+    let (_, sunset) = daylight
+        .sun_times(NaiveDate::from_ymd_opt(2025, 6, 21).unwrap())
+        .unwrap();
+    let published: DateTime<Utc> = "2025-06-21T20:21:00Z".parse().unwrap();
+    assert!((sunset - published).num_seconds().abs() < 120, "{}", sunset);
+    // No sunset at all in the Arctic summer
+    let svalbard = sun::Daylight::new(78.2, 15.6).unwrap();
+    assert!(svalbard
+        .sun_times(NaiveDate::from_ymd_opt(2025, 6, 21).unwrap())
+        .is_none());
+
+    let options = CalendarOptions::builder()
+        .timezone("Europe/London".parse().unwrap())
+        .daylight(daylight)
+        .build();
+    let calendar = Calendar::new(2025, options, Vec::new());
+    let notes: Vec<String> = calendar
+        .events()
+        .into_iter()
+        .map(|event| {
+            format!(
+                "{} {}",
+                event.start,
+                event.description.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+    assert_eq!(
+        notes,
+        [
+            "2025-03-20 March equinox",
+            "2025-03-30 Clocks go forward 1h (01:00 GMT → 02:00 BST)",
+            "2025-04-17 Sunset after 20:00",
+            "2025-06-21 June solstice",
+            "2025-08-27 Sunset before 20:00",
+            "2025-09-22 September equinox",
+            "2025-10-26 Clocks go back 1h (02:00 BST → 01:00 GMT)",
+            "2025-12-21 December solstice",
+        ]
+    );
+    assert!(sun::Daylight::new(91.0, 0.0).is_err());
+}
+
+#[test]
+fn test_day_of_year_numbers() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(2))
+        .day_numbering(DayNumbering::DayOfYear)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(
+        "day_of_year_february_2024",
+        CalendarRenderer::new(&calendar).render_to_string()
+    );
+
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    assert_eq!(DayNumbering::DayOfYear.format(date(1, 1)), "001");
+    // A leap year ends on day 366
+    assert_eq!(DayNumbering::DayOfYear.format(date(12, 31)), "366");
+    assert_eq!(DayNumbering::DayOfMonth.format(date(12, 31)), "31");
+}
+
+#[test]
+fn test_day_format_widens_cells_to_fit() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let day_format = DayNumbering::parse("%m/%d").unwrap();
+    assert_eq!(day_format.width(), 5);
+    let options = CalendarOptions::builder()
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(2))
+        .day_numbering(day_format)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(
+        "day_format_february_2024",
+        CalendarRenderer::new(&calendar).render_to_string()
+    );
+
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    let unpadded = DayNumbering::parse("unpadded").unwrap();
+    assert_eq!(unpadded.format(date(3, 1)), " 1");
+    // Right-aligned to the longest weekday and month names
+    let named = DayNumbering::parse("%a %b %e").unwrap();
+    assert_eq!(named.width(), 10);
+    assert_eq!(named.format(date(3, 1)), "Fri Mar  1");
+    assert_eq!(
+        DayNumbering::parse("Day-Of-Year"),
+        Ok(DayNumbering::DayOfYear)
+    );
+    assert!(DayNumbering::parse("%Q").is_err());
+    assert!(DayNumbering::parse("dd").is_err());
+    // Times, zones and whitespace specifiers have nothing to show for a day
+    for format in ["%H", "%M:%S", "%z", "%Z", "%d%n", "%t%d"] {
+        assert!(DayNumbering::parse(format).is_err(), "{}", format);
+    }
+    let problems: Vec<String> = validation::validate("[settings]\nday_format = \"%d %H\"\n")
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("line 2: Invalid day format"));
+}
+
+#[test]
+fn test_week_column_shows_julian_day_or_iso_week_date() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    assert_eq!(WeekColumn::JulianDay.format(date(2000, 1, 1)), "2451545");
+    assert_eq!(
+        WeekColumn::IsoWeekDate.format(date(2024, 12, 30)),
+        "2025-W01-1"
+    );
+    assert_eq!(
+        WeekColumn::IsoWeekDate.format(date(2021, 1, 3)),
+        "2020-W53-7"
+    );
+    assert!(WeekColumn::parse("mjd").is_err());
+
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder()
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(1))
+        .week_column(WeekColumn::JulianDay)
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    insta::assert_snapshot!(
+        "julian_day_column_january_2024",
+        CalendarRenderer::new(&calendar).render_to_string()
+    );
+}
+
+#[test]
+fn test_secondary_calendar_month_starts() {
+    let starts = |calendar: SecondaryCalendar, year| {
+        calendar
+            .month_starts(year)
+            .into_iter()
+            .map(|(date, name)| format!("{} {}", date, name))
+            .collect::<Vec<String>>()
+    };
+    let hebrew = starts(SecondaryCalendar::Hebrew, 2025);
+    assert_eq!(hebrew.len(), 13);
+    assert!(hebrew.contains(&"2025-03-30 Nisan 5785".to_string()));
+    assert!(hebrew.contains(&"2025-09-23 Tishrei 5786".to_string()));
+    // 5784 was a leap year, with two Adars
+    let hebrew = starts(SecondaryCalendar::Hebrew, 2024);
+    assert!(hebrew.contains(&"2024-02-10 Adar I 5784".to_string()));
+    assert!(hebrew.contains(&"2024-03-11 Adar II 5784".to_string()));
+
+    let islamic = starts(SecondaryCalendar::Islamic, 2025);
+    assert!(islamic.contains(&"2025-03-01 Ramadan 1446".to_string()));
+    assert!(islamic.contains(&"2025-06-27 Muharram 1447".to_string()));
+
+    let chinese = starts(SecondaryCalendar::Chinese, 2025);
+    assert_eq!(chinese[0], "2025-01-29 Lunar New Year");
+    assert!(chinese.contains(&"2025-07-25 Lunar leap month 6".to_string()));
+    let chinese = starts(SecondaryCalendar::Chinese, 2024);
+    assert!(chinese.contains(&"2024-02-10 Lunar New Year".to_string()));
+    assert!(!chinese.iter().any(|month| month.contains("leap")));
+    // The Chinese calendar's 2033 has a rare leap 11th month
+    let chinese = starts(SecondaryCalendar::Chinese, 2033);
+    assert!(chinese.contains(&"2033-12-22 Lunar leap month 11".to_string()));
+
+    let options = CalendarOptions::builder()
+        .past_date_display(PastDateDisplay::Normal)
+        .month_filter(MonthFilter::Single(3))
+        .secondary_calendar(SecondaryCalendar::Islamic)
+        .build();
+    let calendar = Calendar::new(2025, options, Vec::new());
+    let output = CalendarRenderer::new(&calendar).render_to_string();
+    assert!(output.contains("03/01 - Ramadan 1446"));
+    assert!(output.contains("03/31 - Shawwal 1446"));
+    assert!(SecondaryCalendar::parse("mayan").is_err());
+}
+
+#[test]
+fn test_horizontal_layout_runs_months_across() {
+    let render = |cell_width, month_filter| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"))
+                .unwrap();
+        let options = CalendarOptions::builder()
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .cell_width(cell_width)
+            .month_filter(month_filter)
+            .layout(Layout::Horizontal)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2025, options, config);
+        strip_ansi(&CalendarRenderer::new(&calendar).render_to_string())
+    };
+    insta::assert_snapshot!(render(CellWidth::Normal, MonthFilter::All));
+    // Widened to fit the title
+    let march = render(CellWidth::Compact, MonthFilter::Single(3));
+    assert!(march.contains("│COMPACT CALENDAR 2025│"));
+    assert!(march.contains("│Sa│01 08 15 22 29    │"));
+    assert!(march.contains("│  │Mar               │"));
+    assert_eq!(Layout::parse("Horizontal"), Ok(Layout::Horizontal));
+    assert!(Layout::parse("diagonal").is_err());
+}
+
+#[test]
+fn test_grid_layout_blocks_months_by_row() {
+    let render = |cell_width, month_filter, columns| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"))
+                .unwrap();
+        let options = CalendarOptions::builder()
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .cell_width(cell_width)
+            .month_filter(month_filter)
+            .layout(Layout::Grid { columns })
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2025, options, config);
+        strip_ansi(&CalendarRenderer::new(&calendar).render_to_string())
+    };
+    insta::assert_snapshot!(render(CellWidth::Normal, MonthFilter::All, 3));
+    let five = render(CellWidth::Compact, MonthFilter::All, 5);
+    assert!(five.contains("│       January                February                March                  April                   May          │"));
+    // The last row leaves room for the missing blocks
+    let width = five.lines().next().unwrap().chars().count();
+    assert!(five
+        .lines()
+        .filter(|line| line.ends_with('│'))
+        .all(|line| line.chars().count() == width));
+    assert!(five.contains("│ 24 25 26 27 28 29 30   29 30 31   "));
+    let february = render(CellWidth::Compact, MonthFilter::Single(2), 3);
+    assert!(february.contains("│ COMPACT CALENDAR 2025 │"));
+    assert!(february.contains("│ 24 25 26 27 28        │"));
+    assert_eq!(
+        Layout::parse("grid"),
+        Ok(Layout::Grid {
+            columns: Layout::GRID_COLUMNS
+        })
+    );
+}
+
+#[test]
+fn test_heatmap_layout_shades_a_cell_per_day() {
+    let config =
+        compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml")).unwrap();
+    let options = CalendarOptions::builder().layout(Layout::Heatmap).build();
+    let calendar = compact_calendar_cli::build_calendar(2025, options, config);
+    insta::assert_snapshot!(strip_ansi(
+        &CalendarRenderer::new(&calendar).render_to_string()
+    ));
+
+    // Activity from --git or --github takes the place of the events
+    let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+    let heatmap = Heatmap::new([(date(1, 1), 1), (date(1, 2), 4)].into_iter().collect());
+    let options = CalendarOptions::builder()
+        .layout(Layout::Heatmap)
+        .month_filter(MonthFilter::Single(1))
+        .heatmap(heatmap)
+        .build();
+    let output = strip_ansi(
+        &CalendarRenderer::new(&Calendar::new(2025, options, Vec::new())).render_to_string(),
+    );
+    assert!(output.contains("│Wed│░ · · · ·  "), "{}", output);
+    assert!(output.contains("│Thu│█ · · · ·  "), "{}", output);
+    assert!(output.contains("Activity: 5 in 2025, busiest 01/02 with 4"));
+    assert_eq!(Layout::parse("heatmap"), Ok(Layout::Heatmap));
+}
+
+#[test]
+fn test_compare_renders_two_calendars_side_by_side() {
+    let render = |config_path: &str| {
+        let config = compact_calendar_cli::load_config(&PathBuf::from(config_path)).unwrap();
+        let options = CalendarOptions::builder()
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .month_filter(MonthFilter::Single(5))
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        format!(
+            "{}\n{}",
+            config_path,
+            CalendarRenderer::new(&calendar).render_to_string()
+        )
+    };
+    let output = rendering::side_by_side(
+        &render("tests/fixtures/simple.toml"),
+        &render("tests/fixtures/categories.toml"),
+    );
+    insta::assert_snapshot!(output);
+
+    // Colors don't count towards the width, and a longer right side goes on
+    let colored = rendering::side_by_side("\x1b[31mab\x1b[0m\nabcd", "x\ny\nz");
+    assert_eq!(colored, "\x1b[31mab\x1b[0m     x\nabcd   y\n       z\n");
+}
+
+#[test]
+fn test_diff_reports_added_removed_and_changed_events() {
+    let calendar = |config_path: &str| {
+        let config = compact_calendar_cli::load_config(&PathBuf::from(config_path)).unwrap();
+        compact_calendar_cli::build_calendar(2025, CalendarOptions::default(), config)
+    };
+    let before = calendar("tests/fixtures/team_before.toml");
+    let after = calendar("tests/fixtures/team_after.toml");
+    let diff = ConfigDiff::new(&before, &after);
+    insta::assert_snapshot!(diff.render_to_string());
+
+    // The moved range is paired up by its description
+    let moved = diff
+        .differences()
+        .iter()
+        .find(|difference| difference.event().description.as_deref() == Some("Ana away"));
+    assert!(matches!(moved, Some(Difference::Changed { before, .. })
+        if before.start == NaiveDate::from_ymd_opt(2025, 7, 7).unwrap()));
+    // Days that changed color are highlighted in the calendar
+    let changed_day = NaiveDate::from_ymd_opt(2025, 5, 2).unwrap();
+    assert!(diff.changes().dates.contains(&changed_day));
+
+    let unchanged = ConfigDiff::new(&before, &calendar("tests/fixtures/team_before.toml"));
+    assert!(unchanged.is_empty());
+    assert_eq!(unchanged.render_to_string(), "No differences\n");
+}
+
+#[test]
+fn test_remaining_starts_at_the_current_week() {
+    let render = |year, layout| {
+        let config =
+            compact_calendar_cli::load_config(&PathBuf::from("tests/fixtures/simple.toml"))
+                .unwrap();
+        let options = CalendarOptions::builder()
+            .weekend_display(WeekendDisplay::Normal)
+            .past_date_display(PastDateDisplay::Normal)
+            .today(NaiveDate::from_ymd_opt(2025, 10, 15).unwrap())
+            .remaining(true)
+            .layout(layout)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(year, options, config);
+        CalendarRenderer::new(&calendar).render_to_string()
+    };
+    insta::assert_snapshot!(render(2025, Layout::Vertical));
+    // The first week is named for its month in the other layouts too
+    assert!(render(2025, Layout::Horizontal).contains("│   │October        November "));
+    // Other years are shown whole
+    assert!(render(2026, Layout::Vertical).contains("W01 January"));
+}
+
+#[test]
+fn test_paging_modes_and_pager_command() {
+    assert_eq!(Paging::parse("Always"), Ok(Paging::Always));
+    assert!(Paging::parse("sometimes").is_err());
+    assert!(Paging::Always.pages("one line"));
+    assert!(!Paging::Never.pages(&"line\n".repeat(1000)));
+
+    let command = |pager| {
+        let (program, args) = pager::pager_command(pager);
+        format!("{} {}", program, args.join(" "))
+    };
+    assert_eq!(command(None), "less -R");
+    assert_eq!(command(Some("  ")), "less -R");
+    assert_eq!(command(Some("/usr/bin/less -S")), "/usr/bin/less -S -R");
+    assert_eq!(command(Some("less -r")), "less -r");
+    assert_eq!(command(Some("more")), "more ");
+}
+
+#[test]
+fn test_gradient_ranges_shade_from_start_to_end() {
+    let palette = ColorPalette::new().with_colors(true);
+    let background = |step| {
+        palette
+            .get_gradient_style("blue", &Gradient::Shade, step, false)
+            .get_bg_color()
+    };
+    assert_eq!(background(0), Some(RgbColor(144, 214, 255).into()));
+    assert_eq!(
+        background(GRADIENT_STEPS),
+        Some(RgbColor(59, 129, 170).into())
+    );
+
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [[ranges]]
+        start = "2024-07-01"
+        end = "2024-09-30"
+        color = "blue"
+        gradient = "red"
+        description = "Q3"
+        "#,
+    )
+    .unwrap();
+    let options = CalendarOptions::builder()
+        .weekend_display(WeekendDisplay::Normal)
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let mut output = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // Blue on the first day, red on the last, and shades between
+    assert!(output.contains("48;2;89;194;255m01"));
+    assert!(output.contains("48;2;240;113;120m30"));
+    let shades: std::collections::HashSet<&str> = output
+        .split("\x1b[48;2;")
+        .skip(1)
+        .filter_map(|cell| cell.split('m').next())
+        .collect();
+    assert!(shades.len() > 40);
+
+    let problems: Vec<String> = validation::validate(
+        "[[ranges]]\nstart = \"2024-07-01\"\nend = \"2024-09-30\"\ncolor = \"blue\"\ngradient = \"mauve\"\n",
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+    assert_eq!(problems, ["line 5: unknown gradient color 'mauve'"]);
+    assert!(Event::builder(
+        EventKind::Date,
+        NaiveDate::from_ymd_opt(2024, 7, 1).unwrap()
+    )
+    .gradient(Gradient::Shade)
+    .build()
+    .is_err());
+}
+
+#[test]
+fn test_css_color_names_resolve_to_rgb() {
+    let tomato = ColorPalette::get_color_value("tomato").unwrap();
+    assert_eq!(tomato.normal, RgbColor(255, 99, 71));
+    assert_eq!(tomato.dimmed, RgbColor(179, 69, 50));
+    assert_eq!(
+        ColorPalette::get_color_value("SteelBlue").unwrap().normal,
+        RgbColor(70, 130, 180)
+    );
+    // The palette's own names keep their shades
+    assert_eq!(
+        ColorPalette::get_color_value("orange").unwrap().normal,
+        RgbColor(255, 143, 64)
+    );
+    assert!(ColorPalette::get_color_value("mauve").is_none());
+    assert_eq!(named_colors::CSS_COLORS.len(), 148);
+
+    let problems = validation::validate(
+        "[[ranges]]\nstart = \"2024-07-01\"\nend = \"2024-07-05\"\ncolor = \"rebeccapurple\"\n",
+    );
+    assert!(problems.is_empty());
+}
+
+#[test]
+fn test_theme_file_sets_colors_effects_and_borders() {
+    let theme = Theme::load_from(Path::new("tests/fixtures/theme.toml")).unwrap();
+    assert_eq!(theme.colors["brand"].normal, RgbColor(51, 102, 255));
+    assert_eq!(theme.colors["vacation"].normal, RgbColor(255, 99, 71));
+    assert_eq!(theme.today, Some(Effects::BOLD | Effects::INVERT));
+    assert_eq!(theme.past, Some(Effects::new()));
+    assert_eq!(theme.weekend, None);
+    assert_eq!(theme.borders.top_left, '╭');
+    assert_eq!(theme.borders.vertical, '┃');
+
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [[ranges]]
+        start = "2024-05-06"
+        end = "2024-05-10"
+        color = "brand"
+        "#,
+    )
+    .unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(5))
+        .today(NaiveDate::from_ymd_opt(2024, 5, 15).unwrap())
+        .theme(theme.clone())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let plain = CalendarRenderer::new(&calendar).render_to_string();
+    assert!(plain.starts_with("╭────"));
+    assert!(plain.contains("┃W01 May"));
+    assert!(!plain.contains('│'));
+
+    let mut colored = Vec::new();
+    CalendarRenderer::new(&calendar)
+        .with_colors(true)
+        .render_to(&mut colored)
+        .unwrap();
+    let colored = String::from_utf8(colored).unwrap();
+    assert!(colored.contains("\x1b[48;2;51;102;255m06"));
+    // Past days are no longer struck through; today is bold and inverted
+    assert!(!colored.contains("\x1b[9m"));
+    assert!(colored.contains("\x1b[1m\x1b[7m15"));
+
+    let contents = "[[ranges]]\nstart = \"2024-05-06\"\nend = \"2024-05-10\"\ncolor = \"brand\"\n";
+    assert_eq!(validation::validate(contents).len(), 1);
+    assert!(validation::validate_with_theme(contents, ConfigFormat::Toml, &theme).is_empty());
+}
+
+#[test]
+fn test_config_style() {
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [style]
+        dim_factor = 0.5
+        past = "fade"
+        weekends = "normal"
+        annotation_width = 30
+        "#,
+    )
+    .unwrap();
+    let settings = config.style.settings(&config.settings).unwrap();
+    assert!(settings.fade_past && settings.no_dim_weekends);
+    assert!(!settings.no_strikethrough_past);
+    assert_eq!(settings.annotation_width, Some(30));
+    assert_eq!(config.style.dim_factor().unwrap(), Some(0.5));
+
+    let reparsed: CalendarConfig = toml::from_str(&config.to_commented_toml()).unwrap();
+    assert_eq!(reparsed.style, config.style);
+
+    // Dimmed colors are the normal ones at the dim factor
+    let dimmed = |factor| {
+        ColorPalette::new()
+            .with_colors(true)
+            .with_dim_factor(factor)
+            .get_style("blue", true)
+            .get_bg_color()
+    };
+    assert_eq!(dimmed(None), Some(RgbColor(62, 136, 179).into()));
+    assert_eq!(dimmed(Some(0.5)), Some(RgbColor(45, 97, 128).into()));
+
+    let problems: Vec<String> =
+        validation::validate("[style]\npast = \"blur\"\ndim_factor = 1.5\n")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 2: unknown past style 'blur', expected \"strike\", \"fade\" or \"none\"",
+            "line 3: dim_factor 1.5 is not between 0 and 1",
+        ]
+    );
+
+    // [settings] wins where the two disagree, and validate says so
+    let both = "[settings]\nfade_past = true\nannotation_width = 20\n\n\
+                [style]\npast = \"none\"\nweekends = \"normal\"\nannotation_width = 30\n";
+    let config: CalendarConfig = toml::from_str(both).unwrap();
+    let settings = config.style.settings(&config.settings).unwrap();
+    assert!(!settings.fade_past && !settings.no_strikethrough_past);
+    assert!(settings.no_dim_weekends);
+    assert_eq!(settings.annotation_width, Some(20));
+    let problems: Vec<String> = validation::validate(both)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "line 6: [style] past = \"none\" disagrees with [settings] fade_past = true, which wins",
+            "line 8: [style] annotation_width = 30 disagrees with [settings] annotation_width = 20, which wins",
+        ]
+    );
+}
+
+#[test]
+fn test_event_icons_mark_days_and_annotations() {
+    assert_eq!(parse_icon("✈️"), Ok('✈'));
+    assert!(parse_icon("ab").is_err());
+    assert!(parse_icon("").is_err());
+
+    let config: CalendarConfig = toml::from_str(
+        r#"
+        [dates.2024-03-15]
+        description = "Flight"
+        color = "blue"
+        icon = "✈️"
+
+        [[ranges]]
+        start = "2024-03-20"
+        end = "2024-03-22"
+        color = "green"
+        description = "Retreat"
+        icon = "★"
+        "#,
+    )
+    .unwrap();
+    let options = CalendarOptions::builder()
+        .month_filter(MonthFilter::Single(3))
+        .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .build();
+    let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+    let output = CalendarRenderer::new(&calendar).render_to_string();
+    assert!(output.contains("14  ✈15   16   17 │✈ 03/15 - Flight"));
+    assert!(output.contains("19  ★20  ★21  ★22   23 "));
+    assert!(output.contains("│★ 03/20 to 03/22 - Retreat"));
+}
+
+#[test]
+fn test_plain_markers_tell_colored_days_apart_without_colors() {
+    let render = |plain_marker, cell_width| {
+        let config: CalendarConfig = toml::from_str(
+            r#"
+            [dates.2024-03-15]
+            description = "Flight"
+            color = "blue"
+
+            [[ranges]]
+            start = "2024-03-20"
+            end = "2024-03-22"
+            color = "green"
+            description = "Retreat"
+            "#,
+        )
+        .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(3))
+            .today(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+            .plain_marker(plain_marker)
+            .cell_width(cell_width)
+            .legend(true)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        CalendarRenderer::new(&calendar).render_to_string()
+    };
+
+    let symbols = render(PlainMarker::Symbols, CellWidth::Normal);
+    assert!(symbols.contains("14  *15   16   17 │* 03/15 - Flight"));
+    assert!(symbols.contains("19  +20  +21  +22   23 "));
+    assert!(symbols.contains("│+ 03/20 to 03/22 - Retreat"));
+    assert!(symbols.contains("  * blue   Flight\n  + green  Retreat\n"));
+
+    let brackets = render(PlainMarker::Brackets, CellWidth::Normal);
+    assert!(brackets.contains("14  [15]  16 "));
+    assert!(brackets.contains("│03/15 - Flight"));
+    assert!(render(PlainMarker::Star, CellWidth::Normal).contains("19  *20  *21  *22   23 "));
+    assert!(render(PlainMarker::None, CellWidth::Normal).contains("19   20   21   22   23 "));
+    // Compact cells keep a column for the marks
+    let compact = render(PlainMarker::Symbols, CellWidth::Compact);
+    assert!(compact.contains(" Mo   Tu "));
+    assert!(compact.contains("14  *15   16   17 │* 03/15 - Flight"));
+    assert!(render(PlainMarker::None, CellWidth::Compact).contains(" Mo Tu "));
+    assert_eq!(PlainMarker::parse("Brackets"), Ok(PlainMarker::Brackets));
+    assert!(PlainMarker::parse("dots").is_err());
+}
+
+#[test]
+fn test_wide_characters_keep_the_box_aligned() {
+    assert_eq!(text_width::width("🎉20"), 4);
+    assert_eq!(text_width::width("\x1b[1m日本\x1b[0m"), 4);
+    assert_eq!(text_width::strip_ansi("\x1b[1m日本\x1b[0m"), "日本");
+    assert_eq!(text_width::pad_end("日本", 6), "日本  ");
+    assert_eq!(text_width::center("日本", 7), " 日本  ");
+    assert_eq!(text_width::truncate("日本語", 5), "日本");
+
+    for cell_width in [CellWidth::Normal, CellWidth::Wide] {
+        let config: CalendarConfig = toml::from_str(
+            r#"
+            [dates.2024-03-15]
+            description = "Party"
+            color = "blue"
+            icon = "🎉"
+
+            [week_labels.weeks]
+            "2024-03-18" = "春休み"
+            "#,
+        )
+        .unwrap();
+        let options = CalendarOptions::builder()
+            .month_filter(MonthFilter::Single(3))
+            .cell_width(cell_width)
+            .build();
+        let calendar = compact_calendar_cli::build_calendar(2024, options, config);
+        let output = CalendarRenderer::new(&calendar).render_to_string();
+        assert!(output.contains("🎉15"), "{}", output);
+        assert!(output.contains("│春休み"), "{}", output);
+        // Every row of the box ends in the same column
+        let width = text_width::width(output.lines().next().unwrap());
+        for line in output.lines().take_while(|line| !line.is_empty()) {
+            let (row, _) = text_width::split_at(line, width);
+            assert!(row.ends_with(['│', '┐', '┤', '┘']), "{}", output);
+        }
+    }
 }